@@ -0,0 +1,124 @@
+//! Deserializes records out of a simulated slow memory mapping, once straight
+//! from the slice and once through a `BlockCachedReader`.
+//!
+//! Every access to the mapping stalls for `WAIT`, like a read from external
+//! flash with its command and wait states. Reading from the slice is one
+//! access per number, reading through the cache one access per line. The
+//! records are all numbers, as strings are borrowed from the mapping by both.
+//!
+//! Run with `cargo bench --bench block_cached`.
+
+extern crate bincode;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use std::time::{Duration, Instant};
+
+use bincode::arrayvec::ArrayVec;
+use bincode::{BincodeRead, BlockCachedReader, DefaultOptions, Deserializer, Result, SliceReader};
+use serde::Deserialize;
+
+/// How long a single access to the mapping stalls.
+const WAIT: Duration = Duration::from_nanos(200);
+
+const RECORDS: usize = 256;
+const ROUNDS: usize = 20;
+
+#[derive(Serialize, Deserialize)]
+struct Reading {
+    sensor: u16,
+    flags: u8,
+    value: f32,
+    stamp: u32,
+}
+
+/// Charges `WAIT` for every access the reader it wraps makes to the mapping.
+struct Flash<R> {
+    reader: R,
+    reads: usize,
+    charged: usize,
+    /// The accesses made so far, given the reader and the number of reads.
+    accesses: fn(&R, usize) -> usize,
+}
+
+impl<R> Flash<R> {
+    fn new(reader: R, accesses: fn(&R, usize) -> usize) -> Flash<R> {
+        Flash { reader, reads: 0, charged: 0, accesses }
+    }
+
+    fn charge(&mut self) {
+        self.reads += 1;
+        let accesses = (self.accesses)(&self.reader, self.reads);
+        while self.charged < accesses {
+            let start = Instant::now();
+            while start.elapsed() < WAIT {}
+            self.charged += 1;
+        }
+    }
+}
+
+impl<'storage, R: BincodeRead<'storage>> BincodeRead<'storage> for Flash<R> {
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        let value = self.reader.forward_read_str(length, visitor);
+        self.charge();
+        value
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        let value = self.reader.forward_read_bytes(length, visitor);
+        self.charge();
+        value
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        let result = self.reader.read_exact(buf);
+        self.charge();
+        result
+    }
+
+    fn remaining_hint(&self) -> Option<usize> {
+        self.reader.remaining_hint()
+    }
+}
+
+/// Deserializes every record, returning the number of accesses it took.
+fn run<'storage, R: BincodeRead<'storage>>(flash: &mut Flash<R>) -> usize {
+    let mut deserializer = Deserializer::new(&mut *flash, DefaultOptions::new());
+    for _ in 0..RECORDS {
+        Reading::deserialize(&mut deserializer).unwrap();
+    }
+    flash.charged
+}
+
+fn bench<F: FnMut() -> usize>(name: &str, mut f: F) {
+    let start = Instant::now();
+    let mut accesses = 0;
+    for _ in 0..ROUNDS {
+        accesses = f();
+    }
+    let per_round = start.elapsed() / ROUNDS as u32;
+    println!("{:<24} {:>6} accesses {:>10.1?} per {} records", name, accesses, per_round, RECORDS);
+}
+
+fn main() {
+    let mut mapping: ArrayVec<[u8; 4096]> = ArrayVec::new();
+    for i in 0..RECORDS {
+        let reading = Reading { sensor: i as u16, flags: 1, value: i as f32 / 4.0, stamp: 1000 + i as u32 };
+        bincode::serialize_into(&mut mapping, &reading).unwrap();
+    }
+
+    bench("SliceReader", || run(&mut Flash::new(SliceReader::new(&mapping), |_, reads| reads)));
+    bench("BlockCachedReader<32>", || {
+        run(&mut Flash::new(BlockCachedReader::<32>::new(&mapping), |r, _| r.line_fetches()))
+    });
+    bench("BlockCachedReader<64>", || {
+        run(&mut Flash::new(BlockCachedReader::<64>::new(&mapping), |r, _| r.line_fetches()))
+    });
+}
@@ -0,0 +1,58 @@
+//! Serializes and deserializes a struct of every number type, under the
+//! default options and under a byte limit with big-endian numbers.
+//!
+//! Run with `cargo bench --bench numbers`.
+
+extern crate bincode;
+#[macro_use]
+extern crate serde_derive;
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use bincode::arrayvec::ArrayVec;
+use bincode::{DefaultOptions, Options, OptionsExt};
+
+const ROUNDS: u32 = 1_000_000;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Telemetry {
+    a: u8,
+    b: i8,
+    c: u16,
+    d: i16,
+    e: u32,
+    f: i32,
+    g: u64,
+    h: i64,
+    i: f32,
+    j: f64,
+}
+
+fn bench<O: Options, F: Fn() -> O>(name: &str, options: F) {
+    let value = Telemetry { a: 1, b: -2, c: 3, d: -4, e: 5, f: -6, g: 7, h: -8, i: 9.5, j: -10.25 };
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let mut buf: ArrayVec<[u8; 64]> = ArrayVec::new();
+        bincode::serialize_into_with_options(&mut buf, black_box(&value), options()).unwrap();
+        black_box(&buf);
+    }
+    let serialize = start.elapsed() / ROUNDS;
+
+    let mut buf: ArrayVec<[u8; 64]> = ArrayVec::new();
+    bincode::serialize_into_with_options(&mut buf, &value, options()).unwrap();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let decoded: Telemetry = bincode::deserialize_with_options(black_box(&buf), options()).unwrap();
+        black_box(decoded);
+    }
+    let deserialize = start.elapsed() / ROUNDS;
+
+    println!("{:<12} serialize {:>8.1?}  deserialize {:>8.1?}", name, serialize, deserialize);
+}
+
+fn main() {
+    bench("default", || DefaultOptions::new().with_no_limit());
+    bench("limited, BE", || DefaultOptions::new().with_limit(64).with_big_endian());
+}
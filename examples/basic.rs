@@ -2,7 +2,8 @@
 extern crate serde_derive;
 extern crate bincode;
 
-use bincode::{deserialize, serialize};
+use bincode::arrayvec::ArrayVec;
+use bincode::{deserialize, serialize_into};
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 struct Entity {
@@ -16,7 +17,8 @@ struct World(Vec<Entity>);
 fn main() {
     let world = World(vec![Entity { x: 0.0, y: 4.0 }, Entity { x: 10.0, y: 20.5 }]);
 
-    let encoded: Vec<u8> = serialize(&world).unwrap();
+    let mut encoded: ArrayVec<[u8; 64]> = ArrayVec::new();
+    serialize_into(&mut encoded, &world).unwrap();
 
     // 8 bytes for the length of the vector (usize), 4 bytes per float.
     assert_eq!(encoded.len(), 8 + 4 * 4);
@@ -0,0 +1,52 @@
+//! Times decoding a struct with a 64-byte hash, as a plain array and through
+//! `bincode::byte_array`. Run with `cargo run --release --example byte_array_bench`.
+
+#[macro_use]
+extern crate serde_derive;
+extern crate bincode;
+
+use std::hint::black_box;
+use std::time::Instant;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Plain {
+    height: u64,
+    hash: ([u8; 32], [u8; 32]),
+    nonce: u32,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Fast {
+    height: u64,
+    #[serde(with = "bincode::byte_array")]
+    hash: [u8; 64],
+    nonce: u32,
+}
+
+const ROUNDS: u32 = 1_000_000;
+
+fn time<F: FnMut()>(name: &str, mut f: F) {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    println!("{:>10}: {:?} per decode", name, start.elapsed() / ROUNDS);
+}
+
+fn main() {
+    let value = Fast {
+        height: 800_000,
+        hash: [0x5A; 64],
+        nonce: 42,
+    };
+    let mut buf = [0; 8 + 64 + 4];
+    bincode::serialize_into_slice(&value, &mut buf).unwrap();
+
+    // Serde only implements arrays up to 32 elements, hence the pair.
+    time("plain", || {
+        black_box(bincode::deserialize::<Plain>(black_box(&buf)).unwrap());
+    });
+    time("byte_array", || {
+        black_box(bincode::deserialize::<Fast>(black_box(&buf)).unwrap());
+    });
+}
@@ -0,0 +1,42 @@
+//! A hand-rolled header, a serde body and a trailing checksum, all read
+//! from one `SliceReader`.
+
+#[macro_use]
+extern crate serde_derive;
+extern crate bincode;
+extern crate byteorder;
+
+use bincode::arrayvec::ArrayVec;
+use bincode::{deserialize_from_custom, serialize_into_with_crc, BincodeRead, CrcReader, SliceReader};
+use byteorder::LittleEndian;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Body<'a> {
+    id: u32,
+    name: &'a str,
+}
+
+fn main() {
+    let body = Body { id: 7, name: "sensor" };
+
+    // Magic and version by hand, then the body followed by its CRC-32.
+    let mut encoded: ArrayVec<[u8; 64]> = ArrayVec::new();
+    encoded.extend(b"PKT\x01".iter().cloned());
+    serialize_into_with_crc(&mut encoded, &body).unwrap();
+
+    let mut reader = SliceReader::new(&encoded);
+
+    let mut magic = [0; 4];
+    reader.forward_read_exact(&mut magic).unwrap();
+    assert_eq!(&magic, b"PKT\x01");
+
+    // Passing `&mut reader` leaves the cursor with us once serde is done.
+    let mut crc = CrcReader::new(&mut reader);
+    let decoded: Body = deserialize_from_custom(&mut crc).unwrap();
+    let actual = crc.crc();
+
+    let expected = reader.read_u32::<LittleEndian>().unwrap();
+    assert_eq!(expected, actual);
+    assert_eq!(reader.remaining(), 0);
+    assert_eq!(decoded, body);
+}
@@ -0,0 +1,109 @@
+//! Serde adapters for `ArrayVec` and `ArrayString` fields, for use with
+//! `#[serde(with = "...")]`.
+//!
+//! `bytes` encodes an `ArrayVec<[u8; N]>` exactly like a `&[u8]`, and
+//! `string` encodes an `ArrayString` exactly like a `&str`. Decoding a value
+//! longer than the capacity fails instead of truncating it.
+//!
+//! ```rust
+//! extern crate bincode;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! use bincode::arrayvec::{ArrayString, ArrayVec};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Frame {
+//!     #[serde(with = "bincode::arrays::bytes")]
+//!     key: ArrayVec<[u8; 32]>,
+//!     #[serde(with = "bincode::arrays::string")]
+//!     name: ArrayString<[u8; 16]>,
+//! }
+//! # fn main() {}
+//! ```
+
+/// Encodes an `ArrayVec<[u8; N]>` as bytes.
+pub mod bytes {
+    use core::fmt;
+
+    use arrayvec::{Array, ArrayVec};
+    use serde;
+    use serde::de::Error;
+
+    /// Serializes `value` like a `&[u8]`.
+    pub fn serialize<A, S>(value: &ArrayVec<A>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        A: Array<Item = u8>,
+        S: serde::Serializer,
+    {
+        serializer.serialize_bytes(value)
+    }
+
+    /// Deserializes bytes, failing if there are more than fit.
+    pub fn deserialize<'de, A, D>(deserializer: D) -> Result<ArrayVec<A>, D::Error>
+    where
+        A: Array<Item = u8>,
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor<A>(::core::marker::PhantomData<A>);
+
+        impl<'de, A: Array<Item = u8>> serde::de::Visitor<'de> for Visitor<A> {
+            type Value = ArrayVec<A>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "at most {} bytes", ArrayVec::<A>::new().capacity())
+            }
+
+            fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<ArrayVec<A>, E> {
+                let mut vec = ArrayVec::new();
+                if v.len() > vec.capacity() {
+                    return Err(E::invalid_length(v.len(), &self));
+                }
+                vec.extend(v.iter().cloned());
+                Ok(vec)
+            }
+        }
+
+        deserializer.deserialize_bytes(Visitor(::core::marker::PhantomData))
+    }
+}
+
+/// Encodes an `ArrayString` as a string.
+pub mod string {
+    use core::fmt;
+
+    use arrayvec::{Array, ArrayString};
+    use serde;
+    use serde::de::Error;
+
+    /// Serializes `value` like a `&str`.
+    pub fn serialize<A, S>(value: &ArrayString<A>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        A: Array<Item = u8> + Copy,
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(value)
+    }
+
+    /// Deserializes a string, failing if it is longer than fits.
+    pub fn deserialize<'de, A, D>(deserializer: D) -> Result<ArrayString<A>, D::Error>
+    where
+        A: Array<Item = u8> + Copy,
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor<A>(::core::marker::PhantomData<A>);
+
+        impl<'de, A: Array<Item = u8> + Copy> serde::de::Visitor<'de> for Visitor<A> {
+            type Value = ArrayString<A>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a string of at most {} bytes", ArrayString::<A>::new().capacity())
+            }
+
+            fn visit_str<E: Error>(self, v: &str) -> Result<ArrayString<A>, E> {
+                ArrayString::from(v).map_err(|_| E::invalid_length(v.len(), &self))
+            }
+        }
+
+        deserializer.deserialize_str(Visitor(::core::marker::PhantomData))
+    }
+}
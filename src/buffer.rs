@@ -0,0 +1,164 @@
+use arrayvec::{Array, ArrayVec};
+use serde;
+
+use {config, Config, ErrorKind, Result};
+
+/// A serializer built once around an `ArrayVec` and reused for every message
+/// written into it.
+///
+/// The configuration is kept for the lifetime of the serializer, so a stream
+/// of values doesn't set it up again for each one. The size limit applies to
+/// every value on its own, not to the buffer as a whole.
+///
+/// ```rust
+/// extern crate bincode;
+/// use bincode::arrayvec::ArrayVec;
+/// use bincode::BufferSerializer;
+///
+/// fn main() {
+///     let mut buf: ArrayVec<[u8; 16]> = ArrayVec::new();
+///     let mut serializer = BufferSerializer::new(&mut buf);
+///
+///     let mut sent = Vec::new();
+///     for reading in 0..4u32 {
+///         let len = serializer.serialize(&(reading, reading * 10)).unwrap();
+///         assert_eq!(len, 8);
+///         sent.push(serializer.as_slice().to_vec());
+///         serializer.reset();
+///     }
+///
+///     assert_eq!(sent[3], [3, 0, 0, 0, 30, 0, 0, 0]);
+/// }
+/// ```
+///
+/// Bytes the serializer doesn't produce, like a protocol header, can be written
+/// in between with `write_raw` and friends, or into the buffer directly.
+/// Nothing marks where they are, so the decoding side has to read them back
+/// the same way:
+///
+/// ```rust
+/// extern crate bincode;
+/// #[macro_use]
+/// extern crate serde_derive;
+/// use bincode::arrayvec::ArrayVec;
+/// use bincode::BufferSerializer;
+///
+/// #[derive(Serialize)]
+/// struct Reading {
+///     sensor: u8,
+///     value: i16,
+/// }
+///
+/// fn main() {
+///     let mut buf: ArrayVec<[u8; 16]> = ArrayVec::new();
+///     let mut serializer = BufferSerializer::new(&mut buf);
+///
+///     serializer.write_raw(&[0xB1, 0x7E]).unwrap();
+///     serializer.serialize_with_crc(&Reading { sensor: 2, value: -1 }).unwrap();
+///     serializer.write_u16_raw(41).unwrap();
+///
+///     let frame = serializer.into_inner();
+///     assert_eq!(frame.len(), 2 + 3 + 4 + 2);
+///     assert_eq!(&frame[..5], &[0xB1, 0x7E, 2, 0xFF, 0xFF]);
+///     assert_eq!(&frame[9..], &[41, 0]);
+/// }
+/// ```
+pub struct BufferSerializer<'a, A: 'a + Array<Item = u8>> {
+    buffer: &'a mut ArrayVec<A>,
+    config: Config,
+}
+
+impl<'a, A: Array<Item = u8>> BufferSerializer<'a, A> {
+    /// Creates a serializer that appends to `buffer` using the default configuration.
+    pub fn new(buffer: &'a mut ArrayVec<A>) -> BufferSerializer<'a, A> {
+        BufferSerializer::with_config(buffer, config())
+    }
+
+    /// Creates a serializer that appends to `buffer` using `config`.
+    pub fn with_config(buffer: &'a mut ArrayVec<A>, config: Config) -> BufferSerializer<'a, A> {
+        BufferSerializer { buffer, config }
+    }
+
+    /// Appends `value` to the buffer, returning the number of bytes it took up.
+    ///
+    /// If the serialization fails for any reason, the buffer is truncated back to its
+    /// length before the call, like with `serialize_into`.
+    #[inline(always)]
+    pub fn serialize<T: ?Sized + serde::Serialize>(&mut self, value: &T) -> Result<usize> {
+        self.config.serialize_into(self.buffer, value)
+    }
+
+    /// Appends `value` to the buffer followed by the CRC-32 (IEEE) of its bytes, like
+    /// `Config::serialize_into_with_crc`. Returns the number of bytes it took up,
+    /// checksum included.
+    #[inline(always)]
+    pub fn serialize_with_crc<T: ?Sized + serde::Serialize>(&mut self, value: &T) -> Result<usize> {
+        self.config.serialize_into_with_crc(self.buffer, value)
+    }
+
+    /// Appends `bytes` to the buffer as they are, outside of any value.
+    ///
+    /// Raw bytes don't count towards the size limit, and it's up to the caller to
+    /// skip or read them back when decoding. If they don't all fit, `BufferTooSmall`
+    /// is returned and nothing is written.
+    pub fn write_raw(&mut self, bytes: &[u8]) -> Result<()> {
+        self.check_room(bytes.len())?;
+        self.config.write_raw(self.buffer, bytes)
+    }
+
+    /// Appends `n` in the configured endianness, like `write_raw`.
+    pub fn write_u16_raw(&mut self, n: u16) -> Result<()> {
+        self.check_room(2)?;
+        self.config.write_u16_raw(self.buffer, n)
+    }
+
+    /// Appends `n` in the configured endianness, like `write_raw`.
+    pub fn write_u32_raw(&mut self, n: u32) -> Result<()> {
+        self.check_room(4)?;
+        self.config.write_u32_raw(self.buffer, n)
+    }
+
+    /// Appends `n` in the configured endianness, like `write_raw`.
+    pub fn write_u64_raw(&mut self, n: u64) -> Result<()> {
+        self.check_room(8)?;
+        self.config.write_u64_raw(self.buffer, n)
+    }
+
+    fn check_room(&self, needed: usize) -> Result<()> {
+        let available = self.buffer.capacity() - self.buffer.len();
+        if needed > available {
+            return Err(ErrorKind::BufferTooSmall {
+                needed: needed as u64,
+                available,
+                written: 0,
+            });
+        }
+        Ok(())
+    }
+
+    /// Empties the buffer, ready for the next message.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// The bytes written since the last `reset`, or since the serializer was created
+    /// if the buffer started out empty.
+    pub fn as_slice(&self) -> &[u8] {
+        self.buffer
+    }
+
+    /// Borrows the buffer.
+    pub fn get_ref(&self) -> &ArrayVec<A> {
+        self.buffer
+    }
+
+    /// Borrows the buffer mutably, e.g. to write a header by hand between values.
+    pub fn get_mut(&mut self) -> &mut ArrayVec<A> {
+        self.buffer
+    }
+
+    /// Unwraps the buffer the serializer was writing into.
+    pub fn into_inner(self) -> &'a mut ArrayVec<A> {
+        self.buffer
+    }
+}
@@ -0,0 +1,84 @@
+//! Fixed-size byte arrays read in one go, for use with `#[serde(with)]`.
+//!
+//! A `[u8; N]` is encoded as its `N` bytes, without a length prefix, exactly
+//! like the plain array. Serde decodes a plain array one element at a time,
+//! though, so a 64-byte hash costs 64 visitor calls. Fields marked with
+//! `#[serde(with = "bincode::byte_array")]` are instead copied out of the
+//! input as a single range.
+//!
+//! ```rust
+//! extern crate bincode;
+//! #[macro_use]
+//! extern crate serde_derive;
+//!
+//! #[derive(Serialize, Deserialize, PartialEq, Debug)]
+//! struct Block {
+//!     height: u32,
+//!     #[serde(with = "bincode::byte_array")]
+//!     hash: [u8; 64],
+//! }
+//!
+//! fn main() {
+//!     let block = Block { height: 7, hash: [0xAB; 64] };
+//!     let mut buf = [0; 68];
+//!     assert_eq!(bincode::serialize_into_slice(&block, &mut buf).unwrap(), 4 + 64);
+//!     assert_eq!(bincode::deserialize::<Block>(&buf).unwrap(), block);
+//!     // Same bytes as the plain array.
+//!     assert_eq!(bincode::deserialize::<(u32, [u8; 32], [u8; 32])>(&buf).unwrap().1, [0xAB; 32]);
+//! }
+//! ```
+
+use core::fmt;
+
+use serde;
+use serde::de::Error as DeError;
+use serde::ser::SerializeTupleStruct;
+
+/// The name the deserializer recognizes to read the array as one range
+/// instead of decoding a tuple struct.
+pub(crate) const TOKEN: &str = "$bincode::private::ByteArray";
+
+/// Serializes `bytes` like a plain `[u8; N]`.
+pub fn serialize<S: serde::Serializer, const N: usize>(bytes: &[u8; N], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut tuple = serializer.serialize_tuple_struct(TOKEN, N)?;
+    for byte in bytes {
+        tuple.serialize_field(byte)?;
+    }
+    tuple.end()
+}
+
+/// Deserializes a `[u8; N]` encoded like a plain array.
+pub fn deserialize<'de, D: serde::Deserializer<'de>, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error> {
+    struct Visitor<const N: usize>;
+
+    impl<'de, const N: usize> serde::de::Visitor<'de> for Visitor<N> {
+        type Value = [u8; N];
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "{} bytes", N)
+        }
+
+        fn visit_bytes<E: DeError>(self, v: &[u8]) -> Result<[u8; N], E> {
+            if v.len() != N {
+                return Err(E::invalid_length(v.len(), &self));
+            }
+            let mut bytes = [0; N];
+            bytes.copy_from_slice(v);
+            Ok(bytes)
+        }
+
+        // Deserializers that don't know the token hand over the elements.
+        fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<[u8; N], A::Error> {
+            let mut bytes = [0; N];
+            for (i, byte) in bytes.iter_mut().enumerate() {
+                *byte = match seq.next_element()? {
+                    Some(byte) => byte,
+                    None => return Err(A::Error::invalid_length(i, &self)),
+                };
+            }
+            Ok(bytes)
+        }
+    }
+
+    deserializer.deserialize_tuple_struct(TOKEN, N, Visitor::<N>)
+}
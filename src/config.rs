@@ -2,7 +2,11 @@ use super::internal::{Bounded, Infinite, SizeLimit};
 use arrayvec::{Array, ArrayVec};
 use byteorder::{BigEndian, ByteOrder, LittleEndian, NativeEndian};
 use core::marker::PhantomData;
-use error::Result;
+use de::read::{BincodeRead, DynReader};
+use error::{ErrorKind, Result};
+use flavors::{InputFlavor, OutputFlavor};
+use iter::DeserializeIter;
+use ser::spans::FieldSpan;
 use serde;
 use SerializerAcceptor;
 // use de::read::BincodeRead;
@@ -10,32 +14,188 @@ use SerializerAcceptor;
 use self::EndianOption::*;
 use self::LimitOption::*;
 
-struct DefaultOptions(Infinite);
+/// The options a `Config` starts out with, for building a `Serializer` or
+/// `Deserializer` directly. Limits and endianness are chosen with the
+/// `OptionsExt` methods, as they are part of the options' type.
+pub struct DefaultOptions {
+    limit: Infinite,
+    length: LengthEncoding,
+    str_filter: StrFilter,
+    none_tag: u8,
+    some_tag: u8,
+    align: bool,
+    lossy_utf8: bool,
+}
+
+/// A function applied to every deserialized string before it is handed to
+/// the visitor. See `Config::str_filter`.
+pub type StrFilter = fn(&str) -> &str;
 
-pub(crate) trait Options {
+/// The settings a `Serializer` or `Deserializer` is generic over, see
+/// `DefaultOptions` and `OptionsExt`.
+///
+/// It can be implemented for option types of your own. Only `limit` is
+/// required; every other method defaults to what `DefaultOptions::new()`
+/// does. A set of options is used for a single (de)serialization, which
+/// takes it by value, so it has to be built anew for every call.
+///
+/// The encoding is decided by the options alone: for the bytes to read back,
+/// the reading side has to use options that return the same values.
+///
+/// ```rust
+/// extern crate bincode;
+/// extern crate byteorder;
+/// use bincode::arrayvec::ArrayVec;
+/// use bincode::{Bounded, Direction, ErrorKind, LengthEncoding, Options};
+///
+/// /// Big-endian, short length prefixes and at most 16 bytes per message.
+/// struct Telemetry {
+///     limit: Bounded,
+/// }
+///
+/// impl Telemetry {
+///     fn new() -> Telemetry {
+///         Telemetry { limit: Bounded(16) }
+///     }
+/// }
+///
+/// impl Options for Telemetry {
+///     type Limit = Bounded;
+///     type Endian = byteorder::BigEndian;
+///
+///     fn limit(&mut self) -> &mut Bounded {
+///         &mut self.limit
+///     }
+///
+///     fn length_encoding(&self) -> LengthEncoding {
+///         LengthEncoding::U16
+///     }
+/// }
+///
+/// fn main() {
+///     let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+///     bincode::serialize_into_with_options(&mut buf, &(0x0102u16, "hi"), Telemetry::new()).unwrap();
+///     assert_eq!(&buf[..], &[0x01, 0x02, 0x00, 0x02, b'h', b'i']);
+///
+///     let value: (u16, &str) = bincode::deserialize_with_options(&buf, Telemetry::new()).unwrap();
+///     assert_eq!(value, (0x0102, "hi"));
+///
+///     let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+///     match bincode::serialize_into_with_options(&mut buf, "far too long for it", Telemetry::new()) {
+///         Err(ErrorKind::SizeLimit(Direction::Serialize)) => {}
+///         other => panic!("{:?}", other),
+///     }
+/// }
+/// ```
+pub trait Options {
+    /// The byte limit, counted down as bytes are read or written.
     type Limit: SizeLimit + 'static;
+    /// The byte order of numbers and length prefixes.
     type Endian: ByteOrder + 'static;
 
+    /// The byte limit for the rest of the (de)serialization. It is updated in
+    /// place, so it has to be stored in the options rather than created anew
+    /// on every call.
+    ///
+    /// Deserializing counts every byte read against it. Serializing checks the
+    /// whole value up front against a clone of it, and only if
+    /// `SizeLimit::limit` returns `Some`.
     fn limit(&mut self) -> &mut Self::Limit;
+
+    /// How the lengths of sequences, maps, strings and byte arrays are
+    /// encoded. See `Config::length_encoding`.
+    fn length_encoding(&self) -> LengthEncoding {
+        LengthEncoding::U64
+    }
+
+    /// Applied to every deserialized string. See `Config::str_filter`.
+    fn str_filter(&self) -> StrFilter {
+        ::str_filters::identity
+    }
+
+    /// The tag byte of `None`. See `Config::none_tag`.
+    fn none_tag(&self) -> u8 {
+        0
+    }
+
+    /// The tag byte of `Some`, which has to differ from `none_tag`, otherwise
+    /// (de)serializing an `Option` fails with `EqualOptionTags`. See
+    /// `Config::some_tag`.
+    fn some_tag(&self) -> u8 {
+        1
+    }
+
+    /// Whether numbers are aligned to their size. See `Config::align`.
+    fn align(&self) -> bool {
+        false
+    }
+
+    /// Whether invalid UTF-8 is accepted rather than rejected when
+    /// deserializing. See `Config::lossy_utf8`.
+    fn lossy_utf8(&self) -> bool {
+        false
+    }
 }
 
-pub(crate) trait OptionsExt: Options + Sized {
+/// The tags of `None` and `Some` in `options`, or `EqualOptionTags` if they
+/// can't be told apart.
+pub(crate) fn option_tags<O: Options>(options: &O) -> Result<(u8, u8)> {
+    let (none, some) = (options.none_tag(), options.some_tag());
+    if none == some {
+        return Err(ErrorKind::EqualOptionTags);
+    }
+    Ok((none, some))
+}
+
+/// Adapters changing the byte limit or endianness of a set of `Options`.
+///
+/// They can be stacked in any order; an outer adapter replaces what an inner
+/// one of the same kind set, and leaves everything else as it was.
+///
+/// ```rust
+/// extern crate bincode;
+/// use bincode::arrayvec::ArrayVec;
+/// use bincode::{Bounded, DefaultOptions, OptionsExt};
+///
+/// fn main() {
+///     let a = DefaultOptions::new().with_big_endian().with_size_limit(Bounded(4));
+///     let b = DefaultOptions::new().with_limit(100).with_limit(4).with_big_endian();
+///
+///     let mut x: ArrayVec<[u8; 8]> = ArrayVec::new();
+///     let mut y: ArrayVec<[u8; 8]> = ArrayVec::new();
+///     bincode::serialize_into_with_options(&mut x, &1u32, a).unwrap();
+///     bincode::serialize_into_with_options(&mut y, &1u32, b).unwrap();
+///     assert_eq!(x, y);
+/// }
+/// ```
+pub trait OptionsExt: Options + Sized {
+    /// Removes the byte limit.
     fn with_no_limit(self) -> WithOtherLimit<Self, Infinite> {
         WithOtherLimit::new(self, Infinite)
     }
 
+    /// Limits the number of bytes read or written to `limit`.
     fn with_limit(self, limit: u64) -> WithOtherLimit<Self, Bounded> {
         WithOtherLimit::new(self, Bounded(limit))
     }
 
+    /// Replaces the byte limit with `limit`, e.g. `Bounded(n)` or a `SizeLimit`
+    /// of your own.
+    fn with_size_limit<L: SizeLimit + 'static>(self, limit: L) -> WithOtherLimit<Self, L> {
+        WithOtherLimit::new(self, limit)
+    }
+
+    /// Reads and writes numbers little-endian.
     fn with_little_endian(self) -> WithOtherEndian<Self, LittleEndian> {
         WithOtherEndian::new(self)
     }
 
+    /// Reads and writes numbers big-endian.
     fn with_big_endian(self) -> WithOtherEndian<Self, BigEndian> {
         WithOtherEndian::new(self)
     }
 
+    /// Reads and writes numbers in the machine's endianness.
     fn with_native_endian(self) -> WithOtherEndian<Self, NativeEndian> {
         WithOtherEndian::new(self)
     }
@@ -49,13 +209,64 @@ impl<'a, O: Options> Options for &'a mut O {
     fn limit(&mut self) -> &mut Self::Limit {
         (*self).limit()
     }
+
+    #[inline(always)]
+    fn length_encoding(&self) -> LengthEncoding {
+        (**self).length_encoding()
+    }
+
+    #[inline(always)]
+    fn str_filter(&self) -> StrFilter {
+        (**self).str_filter()
+    }
+
+    #[inline(always)]
+    fn none_tag(&self) -> u8 {
+        (**self).none_tag()
+    }
+
+    #[inline(always)]
+    fn some_tag(&self) -> u8 {
+        (**self).some_tag()
+    }
+
+    #[inline(always)]
+    fn align(&self) -> bool {
+        (**self).align()
+    }
+
+    #[inline(always)]
+    fn lossy_utf8(&self) -> bool {
+        (**self).lossy_utf8()
+    }
 }
 
 impl<T: Options> OptionsExt for T {}
 
 impl DefaultOptions {
-    fn new() -> DefaultOptions {
-        DefaultOptions(Infinite)
+    /// The default options: no limit, little-endian, `u64` length prefixes.
+    pub fn new() -> DefaultOptions {
+        DefaultOptions::from_config(&Config::new())
+    }
+
+    /// Takes every setting of `config` except the byte limit and endianness,
+    /// which have to be added with `OptionsExt`.
+    pub fn from_config(config: &Config) -> DefaultOptions {
+        DefaultOptions {
+            limit: Infinite,
+            length: config.length,
+            str_filter: config.str_filter,
+            none_tag: config.none_tag,
+            some_tag: config.some_tag,
+            align: config.align,
+            lossy_utf8: config.lossy_utf8,
+        }
+    }
+}
+
+impl Default for DefaultOptions {
+    fn default() -> DefaultOptions {
+        DefaultOptions::new()
     }
 }
 
@@ -65,7 +276,37 @@ impl Options for DefaultOptions {
 
     #[inline(always)]
     fn limit(&mut self) -> &mut Infinite {
-        &mut self.0
+        &mut self.limit
+    }
+
+    #[inline(always)]
+    fn length_encoding(&self) -> LengthEncoding {
+        self.length
+    }
+
+    #[inline(always)]
+    fn str_filter(&self) -> StrFilter {
+        self.str_filter
+    }
+
+    #[inline(always)]
+    fn none_tag(&self) -> u8 {
+        self.none_tag
+    }
+
+    #[inline(always)]
+    fn some_tag(&self) -> u8 {
+        self.some_tag
+    }
+
+    #[inline(always)]
+    fn align(&self) -> bool {
+        self.align
+    }
+
+    #[inline(always)]
+    fn lossy_utf8(&self) -> bool {
+        self.lossy_utf8
     }
 }
 
@@ -82,12 +323,163 @@ enum EndianOption {
     Native,
 }
 
+/// How the length prefix of sequences, maps, strings and byte arrays is encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LengthEncoding {
+    /// A `u16` in the configured endianness.
+    U16,
+    /// A `u32` in the configured endianness.
+    U32,
+    /// A `u64` in the configured endianness.
+    U64,
+    /// A LEB128 varint: seven bits per byte, least significant first, with
+    /// the top bit set on every byte but the last. Lengths below 128 take a
+    /// single byte, the largest ten. Varints are never padded by `align`, and
+    /// sequences and maps of unknown length can't be serialized with them,
+    /// as the room for the length isn't known up front.
+    Varint,
+}
+
+/// The most bytes an encoded length takes up, a `u64` as a varint.
+pub(crate) const MAX_LEN_WIDTH: usize = 10;
+
+impl LengthEncoding {
+    /// The largest length that can be encoded.
+    pub fn max_len(self) -> u64 {
+        match self {
+            LengthEncoding::U16 => u16::MAX as u64,
+            LengthEncoding::U32 => u32::MAX as u64,
+            LengthEncoding::U64 | LengthEncoding::Varint => u64::MAX,
+        }
+    }
+
+    /// Converts `len` for encoding, failing if it can't be represented.
+    pub(crate) fn check_len(self, len: usize) -> Result<u64> {
+        let len = len as u64;
+        if len > self.max_len() {
+            return Err(ErrorKind::LengthOverflow {
+                len,
+                max: self.max_len(),
+            });
+        }
+        Ok(len)
+    }
+
+    /// Encodes `len` into `buf`, returning the prefix to write. Nothing
+    /// should be written if this fails.
+    pub(crate) fn encode_len<E: ByteOrder>(self, len: usize, buf: &mut [u8; MAX_LEN_WIDTH]) -> Result<&[u8]> {
+        let len = self.check_len(len)?;
+        match self {
+            LengthEncoding::U16 => E::write_u16(buf, len as u16),
+            LengthEncoding::U32 => E::write_u32(buf, len as u32),
+            LengthEncoding::U64 => E::write_u64(buf, len),
+            LengthEncoding::Varint => {
+                let mut rest = len;
+                for byte in buf.iter_mut() {
+                    *byte = rest as u8 & 0x7F;
+                    rest >>= 7;
+                    if rest == 0 {
+                        break;
+                    }
+                    *byte |= 0x80;
+                }
+            }
+        }
+        Ok(&buf[..self.encoded_width(len) as usize])
+    }
+
+    /// The number of bytes an encoded length takes up, or for `Varint` the
+    /// most it can take.
+    pub fn width(self) -> u64 {
+        match self {
+            LengthEncoding::U16 => 2,
+            LengthEncoding::U32 => 4,
+            LengthEncoding::U64 => 8,
+            LengthEncoding::Varint => MAX_LEN_WIDTH as u64,
+        }
+    }
+
+    /// The number of bytes `len` takes up once encoded.
+    pub(crate) fn encoded_width(self, len: u64) -> u64 {
+        match self {
+            LengthEncoding::Varint => {
+                let bits = 64 - len.leading_zeros() as u64;
+                ::core::cmp::max(1, bits.div_ceil(7))
+            }
+            _ => self.width(),
+        }
+    }
+
+    /// What an encoded length is aligned to with `align`. Varints are never
+    /// padded.
+    pub(crate) fn alignment(self) -> u64 {
+        match self {
+            LengthEncoding::Varint => 1,
+            _ => self.width(),
+        }
+    }
+}
+
+/// The number of bytes `Config::align` pads with before a `width` byte item
+/// that would start `pos` bytes into the message.
+pub(crate) fn padding(pos: u64, width: u64) -> u64 {
+    pos.next_multiple_of(width) - pos
+}
+
+/// Decodes a `LengthEncoding::Varint` length a byte at a time, rejecting any
+/// that isn't in its shortest form.
+pub(crate) struct VarintDecoder {
+    len: u64,
+    read: usize,
+}
+
+impl VarintDecoder {
+    pub(crate) fn new() -> VarintDecoder {
+        VarintDecoder { len: 0, read: 0 }
+    }
+
+    /// Adds the next byte, returning the length once it is complete.
+    pub(crate) fn push(&mut self, byte: u8) -> Result<Option<usize>> {
+        let i = self.read;
+        self.read += 1;
+        // The last byte only has room for the top bit of a `u64`.
+        if i == MAX_LEN_WIDTH - 1 && byte > 1 {
+            return Err(ErrorKind::InvalidVarintEncoding);
+        }
+        self.len |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 != 0 {
+            return Ok(None);
+        }
+        // A zero after the first byte adds nothing and could be left out.
+        if byte == 0 && i > 0 {
+            return Err(ErrorKind::InvalidVarintEncoding);
+        }
+        if self.len > usize::MAX as u64 {
+            return Err(ErrorKind::LengthOverflow {
+                len: self.len,
+                max: usize::MAX as u64,
+            });
+        }
+        Ok(Some(self.len as usize))
+    }
+}
+
 /// A configuration builder whose options Bincode will use
 /// while serializing and deserializing.
 ///
 /// ### Options
 /// Endianness: The endianness with which multi-byte integers will be read/written.  *default: little endian*
 /// Limit: The maximum number of bytes that will be read/written in a bincode serialize/deserialize. *default: unlimited*
+/// Length encoding: How the lengths of sequences, maps, strings and byte arrays are written. *default: u64*
+/// String filter: A function applied to strings as they are deserialized. *default: identity*
+/// Option tags: The bytes written before `None` and `Some`. *default: 0 and 1*
+/// Pad byte: The byte that fills fixed-size slots after a padded record. *default: 0x00*
+/// Alignment: Whether multi-byte numbers are padded to their natural alignment. *default: off*
+/// Lossy UTF-8: Whether invalid strings are repaired instead of rejected. *default: off*
+///
+/// Every combination of these options decodes exactly what it encodes, and `serialized_size`
+/// agrees with the bytes written. The test suite checks a sample of them; the ignored
+/// `config_matrix_all_tags` test checks every pair of `Option` tags.
 ///
 /// ### Byte Limit Details
 /// The purpose of byte-limiting is to prevent Denial-Of-Service attacks whereby malicious attackers get bincode
@@ -95,17 +487,53 @@ enum EndianOption {
 ///
 /// When a byte limit is set, bincode will return `Err` on any deserialization that goes over the limit, or any
 /// serialization that goes over the limit.
+///
+/// ### Constants
+/// A `Config` is `Copy` and its setters are `const`, so a protocol's configuration can be a constant:
+///
+/// ```rust
+/// extern crate bincode;
+/// use bincode::Config;
+///
+/// const WIRE: Config = {
+///     let mut config = bincode::config();
+///     config.big_endian().limit(64);
+///     config
+/// };
+///
+/// fn main() {
+///     let mut buf = [0; 4];
+///     WIRE.serialize_into_slice(&1u32, &mut buf).unwrap();
+///     assert_eq!(buf, [0, 0, 0, 1]);
+/// }
+/// ```
+///
+/// ### Options types
+/// The byte limit and endianness are chosen at run time here and turned into a concrete `Options`
+/// type on every call. To pick the type up front instead, e.g. for a `Serializer` or `Deserializer`
+/// or the `_with_options` functions, start from `DefaultOptions::from_config` and add the limit and
+/// endianness with `OptionsExt`.
+#[derive(Clone, Copy)]
 pub struct Config {
     limit: LimitOption,
     endian: EndianOption,
+    length: LengthEncoding,
+    str_filter: StrFilter,
+    none_tag: u8,
+    some_tag: u8,
+    pad: u8,
+    align: bool,
+    lossy_utf8: bool,
 }
 
-pub(crate) struct WithOtherLimit<O: Options, L: SizeLimit> {
+/// `Options` with their byte limit replaced, see `OptionsExt`.
+pub struct WithOtherLimit<O: Options, L: SizeLimit> {
     _options: O,
     pub(crate) new_limit: L,
 }
 
-pub(crate) struct WithOtherEndian<O: Options, E: ByteOrder> {
+/// `Options` with their endianness replaced, see `OptionsExt`.
+pub struct WithOtherEndian<O: Options, E: ByteOrder> {
     options: O,
     _endian: PhantomData<E>,
 }
@@ -124,7 +552,7 @@ impl<O: Options, E: ByteOrder> WithOtherEndian<O, E> {
     #[inline(always)]
     pub(crate) fn new(options: O) -> WithOtherEndian<O, E> {
         WithOtherEndian {
-            options: options,
+            options,
             _endian: PhantomData,
         }
     }
@@ -138,6 +566,36 @@ impl<O: Options, E: ByteOrder + 'static> Options for WithOtherEndian<O, E> {
     fn limit(&mut self) -> &mut O::Limit {
         self.options.limit()
     }
+
+    #[inline(always)]
+    fn length_encoding(&self) -> LengthEncoding {
+        self.options.length_encoding()
+    }
+
+    #[inline(always)]
+    fn str_filter(&self) -> StrFilter {
+        self.options.str_filter()
+    }
+
+    #[inline(always)]
+    fn none_tag(&self) -> u8 {
+        self.options.none_tag()
+    }
+
+    #[inline(always)]
+    fn some_tag(&self) -> u8 {
+        self.options.some_tag()
+    }
+
+    #[inline(always)]
+    fn align(&self) -> bool {
+        self.options.align()
+    }
+
+    #[inline(always)]
+    fn lossy_utf8(&self) -> bool {
+        self.options.lossy_utf8()
+    }
 }
 
 impl<O: Options, L: SizeLimit + 'static> Options for WithOtherLimit<O, L> {
@@ -147,34 +605,64 @@ impl<O: Options, L: SizeLimit + 'static> Options for WithOtherLimit<O, L> {
     fn limit(&mut self) -> &mut L {
         &mut self.new_limit
     }
+
+    #[inline(always)]
+    fn length_encoding(&self) -> LengthEncoding {
+        self._options.length_encoding()
+    }
+
+    #[inline(always)]
+    fn str_filter(&self) -> StrFilter {
+        self._options.str_filter()
+    }
+
+    #[inline(always)]
+    fn none_tag(&self) -> u8 {
+        self._options.none_tag()
+    }
+
+    #[inline(always)]
+    fn some_tag(&self) -> u8 {
+        self._options.some_tag()
+    }
+
+    #[inline(always)]
+    fn align(&self) -> bool {
+        self._options.align()
+    }
+
+    #[inline(always)]
+    fn lossy_utf8(&self) -> bool {
+        self._options.lossy_utf8()
+    }
 }
 
 macro_rules! config_map {
     ($self:expr, $opts:ident => $call:expr) => {
         match ($self.limit, $self.endian) {
             (Unlimited, Little) => {
-                let $opts = DefaultOptions::new().with_no_limit().with_little_endian();
+                let $opts = DefaultOptions::from_config(&$self).with_no_limit().with_little_endian();
                 $call
             }
             (Unlimited, Big) => {
-                let $opts = DefaultOptions::new().with_no_limit().with_big_endian();
+                let $opts = DefaultOptions::from_config(&$self).with_no_limit().with_big_endian();
                 $call
             }
             (Unlimited, Native) => {
-                let $opts = DefaultOptions::new().with_no_limit().with_native_endian();
+                let $opts = DefaultOptions::from_config(&$self).with_no_limit().with_native_endian();
                 $call
             }
 
             (Limited(l), Little) => {
-                let $opts = DefaultOptions::new().with_limit(l).with_little_endian();
+                let $opts = DefaultOptions::from_config(&$self).with_limit(l).with_little_endian();
                 $call
             }
             (Limited(l), Big) => {
-                let $opts = DefaultOptions::new().with_limit(l).with_big_endian();
+                let $opts = DefaultOptions::from_config(&$self).with_limit(l).with_big_endian();
                 $call
             }
             (Limited(l), Native) => {
-                let $opts = DefaultOptions::new().with_limit(l).with_native_endian();
+                let $opts = DefaultOptions::from_config(&$self).with_limit(l).with_native_endian();
                 $call
             }
         }
@@ -183,24 +671,31 @@ macro_rules! config_map {
 
 impl Config {
     #[inline(always)]
-    pub(crate) fn new() -> Config {
+    pub(crate) const fn new() -> Config {
         Config {
             limit: LimitOption::Unlimited,
             endian: EndianOption::Little,
+            length: LengthEncoding::U64,
+            str_filter: ::str_filters::identity,
+            none_tag: 0,
+            some_tag: 1,
+            pad: 0,
+            align: false,
+            lossy_utf8: false,
         }
     }
 
     /// Sets the byte limit to be unlimited.
     /// This is the default.
     #[inline(always)]
-    pub fn no_limit(&mut self) -> &mut Self {
+    pub const fn no_limit(&mut self) -> &mut Self {
         self.limit = LimitOption::Unlimited;
         self
     }
 
     /// Sets the byte limit to `limit`.
     #[inline(always)]
-    pub fn limit(&mut self, limit: u64) -> &mut Self {
+    pub const fn limit(&mut self, limit: u64) -> &mut Self {
         self.limit = LimitOption::Limited(limit);
         self
     }
@@ -208,30 +703,108 @@ impl Config {
     /// Sets the endianness to little-endian
     /// This is the default.
     #[inline(always)]
-    pub fn little_endian(&mut self) -> &mut Self {
+    pub const fn little_endian(&mut self) -> &mut Self {
         self.endian = EndianOption::Little;
         self
     }
 
     /// Sets the endianness to big-endian
     #[inline(always)]
-    pub fn big_endian(&mut self) -> &mut Self {
+    pub const fn big_endian(&mut self) -> &mut Self {
         self.endian = EndianOption::Big;
         self
     }
 
     /// Sets the endianness to the the machine-native endianness
     #[inline(always)]
-    pub fn native_endian(&mut self) -> &mut Self {
+    pub const fn native_endian(&mut self) -> &mut Self {
         self.endian = EndianOption::Native;
         self
     }
 
-    // /// Serializes a serializable object into a `Vec` of bytes using this configuration
-    // #[inline(always)]
-    // pub fn serialize<T: ?Sized + serde::Serialize>(&self, t: &T) -> Result<Vec<u8>> {
-    //     config_map!(self, opts => ::internal::serialize(t, opts))
-    // }
+    /// Sets how length prefixes are encoded.
+    /// The default is `LengthEncoding::U64`.
+    #[inline(always)]
+    pub const fn length_encoding(&mut self, length: LengthEncoding) -> &mut Self {
+        self.length = length;
+        self
+    }
+
+    /// Sets a function that every deserialized string passes through before it
+    /// reaches the visitor, e.g. `str_filters::trim_nul`.
+    /// The default is `str_filters::identity`.
+    ///
+    /// The filter must return a subslice of its input, so borrowed strings still
+    /// borrow from the input buffer and nothing is allocated.
+    #[inline(always)]
+    pub const fn str_filter(&mut self, filter: StrFilter) -> &mut Self {
+        self.str_filter = filter;
+        self
+    }
+
+    /// Sets the tag byte that marks an `Option` as `None`, for peers that don't
+    /// use bincode's `0`. Any tag other than this one or the `some_tag` is
+    /// rejected with `InvalidTagEncoding`.
+    /// The default is `0`.
+    ///
+    /// The two tags have to differ. If they don't, (de)serializing an `Option`
+    /// fails with `EqualOptionTags`; the tags are only checked then, so they
+    /// can be swapped one at a time.
+    #[inline(always)]
+    pub const fn none_tag(&mut self, tag: u8) -> &mut Self {
+        self.none_tag = tag;
+        self
+    }
+
+    /// Sets the tag byte that marks an `Option` as `Some`, e.g. `0x01` for a
+    /// peer whose `None` is `0xFF`. See `none_tag`.
+    /// The default is `1`.
+    #[inline(always)]
+    pub const fn some_tag(&mut self, tag: u8) -> &mut Self {
+        self.some_tag = tag;
+        self
+    }
+
+    /// Sets the byte that `serialize_padded` fills slots with and that
+    /// `deserialize_padded` expects after the record, e.g. `0xFF` for erased
+    /// NOR flash.
+    /// The default is `0x00`.
+    #[inline(always)]
+    pub const fn pad_byte(&mut self, pad: u8) -> &mut Self {
+        self.pad = pad;
+        self
+    }
+
+    /// Pads every multi-byte number with zero bytes so that it starts at a multiple of its
+    /// own size, counted from the start of the message, e.g. for a C parser that reads
+    /// `u32` and `f32` fields in place. Length prefixes and enum tags are numbers too and
+    /// are aligned the same way. The deserializer skips the same padding.
+    /// The default is `false`.
+    ///
+    /// This changes the encoding, so both sides have to agree on it.
+    #[inline(always)]
+    pub const fn align(&mut self, align: bool) -> &mut Self {
+        self.align = align;
+        self
+    }
+
+    /// Accepts strings that aren't valid UTF-8 instead of failing with
+    /// `InvalidUtf8Encoding`. With the `alloc` feature invalid sequences are replaced
+    /// with U+FFFD, otherwise the string is cut short at the first one. Either way the
+    /// whole encoded string is consumed, so the fields after it still line up.
+    /// The default is `false`.
+    #[inline(always)]
+    pub const fn lossy_utf8(&mut self, lossy: bool) -> &mut Self {
+        self.lossy_utf8 = lossy;
+        self
+    }
+
+    /// Serializes a serializable object into a `Vec` of bytes using this configuration
+    #[cfg(feature = "alloc")]
+    #[inline(always)]
+    pub fn serialize_to_vec<T: ?Sized + serde::Serialize>(&self, t: &T) -> Result<::alloc::vec::Vec<u8>> {
+        config_map!(self, opts => ::internal::serialize_to_vec(t, opts))
+    }
 
     /// Returns the size that an object would be if serialized using Bincode with this configuration
     #[inline(always)]
@@ -239,20 +812,215 @@ impl Config {
         config_map!(self, opts => ::internal::serialized_size(t, opts))
     }
 
-    /// Serializes an object directly into a `Writer` using this configuration
+    /// Returns the size that an object would be if serialized using Bincode with this
+    /// configuration, or `None` if that is more than `max` bytes. Measuring stops as soon
+    /// as `max` is passed, so this is cheap even for large values.
+    #[inline(always)]
+    pub fn serialized_size_bounded<T: ?Sized + serde::Serialize>(&self, t: &T, max: u64) -> Result<Option<u64>> {
+        config_map!(self, opts => ::internal::serialized_size_bounded(t, max, opts))
+    }
+
+    /// Returns the number of bytes every encoded `T` takes up with this configuration,
+    /// or `None` if that depends on the value. See `stride::encoded_stride`.
+    #[inline(always)]
+    pub fn encoded_stride<T: serde::Deserialize<'static>>(&self) -> Option<usize> {
+        config_map!(self, opts => ::stride::encoded_stride_with_options::<T, _>(opts))
+    }
+
+    /// Serializes an object into a new `ArrayVec` using this configuration.
     ///
-    /// If the serialization would take more bytes than allowed by the size limit, an error
-    /// is returned and *no bytes* will be written into the `Writer`
+    /// Running out of room is reported as `BufferTooSmall`, like with `serialize_into`.
+    #[inline(always)]
+    pub fn serialize<A, T: ?Sized + serde::Serialize>(&self, t: &T) -> Result<ArrayVec<A>>
+    where
+        A: Array<Item = u8>,
+    {
+        let mut buf = ArrayVec::new();
+        self.serialize_into(&mut buf, t)?;
+        Ok(buf)
+    }
+
+    /// Serializes an object directly into a `Writer` using this configuration,
+    /// returning the number of bytes it appended.
+    ///
+    /// If the serialization fails for any reason, the `Writer` is truncated back to its
+    /// length before the call, so it only ever gains complete values. If it ran out of
+    /// room, `BufferTooSmall` reports how many bytes the value needs; that is only measured
+    /// once the value turned out not to fit.
     #[inline(always)]
     pub fn serialize_into<A, T: ?Sized + serde::Serialize>(
         &self,
         w: &mut ArrayVec<A>,
         t: &T,
-    ) -> Result<()>
+    ) -> Result<usize>
     where
         A: Array<Item = u8>,
     {
-        config_map!(self, opts => ::internal::serialize_into(w, t, opts))
+        config_map!(self, opts => ::internal::serialize_into_array(&mut *w, t, opts))
+    }
+
+    /// Serializes each of `items` into `w` back to back using this configuration, stopping at
+    /// the first one that doesn't fit. Returns the number of items written.
+    ///
+    /// Running out of room is not an error, and the item that didn't fit leaves no bytes
+    /// behind, so `w` always ends with a complete item. Any other error is returned after
+    /// the items before it have been written.
+    pub fn encode_many<'t, A, T, I>(&self, items: I, w: &mut ArrayVec<A>) -> Result<usize>
+    where
+        A: Array<Item = u8>,
+        T: 't + ?Sized + serde::Serialize,
+        I: IntoIterator<Item = &'t T>,
+    {
+        let mut count = 0;
+        for item in items {
+            match self.serialize_into(w, item) {
+                Ok(_) => count += 1,
+                Err(ErrorKind::BufferTooSmall { .. }) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(count)
+    }
+
+    /// Serializes an object directly into a `Writer` using this configuration, after
+    /// checking that it fits in the `Writer`'s remaining capacity. Returns the number of
+    /// bytes it appended.
+    ///
+    /// If it doesn't fit, `BufferTooSmall` is returned and *no bytes* will be written.
+    #[inline(always)]
+    pub fn serialize_into_checked<A, T: ?Sized + serde::Serialize>(
+        &self,
+        w: &mut ArrayVec<A>,
+        t: &T,
+    ) -> Result<usize>
+    where
+        A: Array<Item = u8>,
+    {
+        config_map!(self, opts => ::internal::serialize_into_checked(w, t, opts))
+    }
+
+    /// Serializes an object directly into a `Writer` using this configuration, recording
+    /// where each field of the outermost struct was written, e.g. to patch single fields
+    /// of the encoding in place later. Returns the number of bytes appended and the number
+    /// of fields.
+    ///
+    /// The first `spans.len()` fields are recorded in order; if there are more, the count
+    /// says so. Offsets are into the `Writer`, counting the bytes it already held. A
+    /// field's span covers everything written for it, nested values and padding included.
+    /// If the outermost value isn't a struct, or a newtype around one, nothing is recorded.
+    ///
+    /// If the serialization fails for any reason, the `Writer` is truncated back to its
+    /// length before the call, and `spans` may have been partly filled.
+    ///
+    /// ```rust
+    /// extern crate bincode;
+    /// #[macro_use]
+    /// extern crate serde_derive;
+    /// use bincode::arrayvec::ArrayVec;
+    /// use bincode::FieldSpan;
+    ///
+    /// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    /// struct State<'a> {
+    ///     name: &'a str,
+    ///     counter: u32,
+    ///     active: bool,
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+    ///     let mut spans = [FieldSpan::default(); 4];
+    ///     let state = State { name: "pump", counter: 1, active: true };
+    ///     let (len, fields) = bincode::config().serialize_into_with_spans(&mut buf, &state, &mut spans).unwrap();
+    ///     assert_eq!((len, fields), (17, 3));
+    ///     assert_eq!(spans[1], FieldSpan { name: "counter", offset: 12, len: 4 });
+    ///
+    ///     // Patch `counter` without encoding the rest again.
+    ///     let counter = spans[1];
+    ///     bincode::serialize_into_slice(&2u32, &mut buf[counter.offset..counter.offset + counter.len]).unwrap();
+    ///     let patched: State = bincode::deserialize(&buf).unwrap();
+    ///     assert_eq!(patched, State { name: "pump", counter: 2, active: true });
+    /// }
+    /// ```
+    #[inline(always)]
+    pub fn serialize_into_with_spans<A, T: ?Sized + serde::Serialize>(
+        &self,
+        w: &mut ArrayVec<A>,
+        t: &T,
+        spans: &mut [FieldSpan],
+    ) -> Result<(usize, usize)>
+    where
+        A: Array<Item = u8>,
+    {
+        config_map!(self, opts => ::internal::serialize_into_with_spans(w, t, spans, opts))
+    }
+
+    /// Serializes as much of an object as fits into a `Writer` using this configuration,
+    /// e.g. for diagnostic logs where part of a record beats none. Returns the number of
+    /// bytes appended and whether the object was cut short.
+    ///
+    /// A cut-off object leaves the `Writer` full, holding the start of its encoding up to
+    /// the last byte that fit; strings and byte arrays can be cut in the middle. Errors
+    /// other than running out of room are returned as usual, and the `Writer` is truncated
+    /// back to its length before the call. Sequences and maps of unknown length fail with
+    /// `SequenceMustHaveLength`, as a cut-off one would leave a length prefix that's wrong.
+    #[inline(always)]
+    pub fn serialize_into_lossy<A, T: ?Sized + serde::Serialize>(
+        &self,
+        w: &mut ArrayVec<A>,
+        t: &T,
+    ) -> Result<(usize, bool)>
+    where
+        A: Array<Item = u8>,
+    {
+        let start = w.len();
+        let result = config_map!(self, opts => ::internal::serialize_into_lossy(&mut *w, t, opts));
+        match result {
+            Ok(truncated) => Ok((w.len() - start, truncated)),
+            Err(e) => {
+                w.truncate(start);
+                Err(e)
+            }
+        }
+    }
+
+    /// Serializes an object directly into a `Writer` using this configuration, followed by
+    /// the CRC-32 (IEEE) of its bytes in the configured endianness. Returns the number of
+    /// bytes it appended, checksum included.
+    ///
+    /// The checksum is updated as the bytes are written, so the payload is only walked
+    /// once. If the serialization fails for any reason, the `Writer` is truncated back to
+    /// its length before the call.
+    #[inline(always)]
+    pub fn serialize_into_with_crc<A, T: ?Sized + serde::Serialize>(
+        &self,
+        w: &mut ArrayVec<A>,
+        t: &T,
+    ) -> Result<usize>
+    where
+        A: Array<Item = u8>,
+    {
+        let start = w.len();
+        let result = config_map!(self, opts => ::internal::serialize_into_with_crc(&mut *w, t, opts));
+        match result {
+            Ok(()) => Ok(w.len() - start),
+            Err(e) => {
+                w.truncate(start);
+                Err(e)
+            }
+        }
+    }
+
+    /// Serializes an object through an `OutputFlavor` using this configuration,
+    /// returning the number of bytes the flavor produced.
+    ///
+    /// If the serialization would take more bytes than allowed by the size limit, an error
+    /// is returned and *no bytes* will be written into the flavor.
+    #[inline(always)]
+    pub fn serialize_with_flavor<F, T: ?Sized + serde::Serialize>(&self, f: F, t: &T) -> Result<usize>
+    where
+        F: OutputFlavor,
+    {
+        config_map!(self, opts => ::internal::serialize_with_flavor(f, t, opts))
     }
 
     // /// Deserializes a slice of bytes into an instance of `T` using this configuration
@@ -261,40 +1029,405 @@ impl Config {
         config_map!(self, opts => ::internal::deserialize(bytes, opts))
     }
 
-    // /// TODO: document
-    // #[doc(hidden)]
-    // #[inline(always)]
-    // pub fn deserialize_in_place<'a, R, T>(&self, reader: R, place: &mut T) -> Result<()>
-    // where
-    //     R: BincodeRead<'a>,
-    //     T: serde::de::Deserialize<'a>,
-    // {
-    //     config_map!(self, opts => ::internal::deserialize_in_place(reader, opts, place))
-    // }
+    /// Deserializes a slice of bytes through a `DeserializeSeed` using this configuration,
+    /// see `bincode::deserialize_seed`.
+    #[inline(always)]
+    pub fn deserialize_seed<'a, S: serde::de::DeserializeSeed<'a>>(&self, seed: S, bytes: &'a [u8]) -> Result<S::Value> {
+        config_map!(self, opts => ::internal::deserialize_seed(seed, bytes, opts))
+    }
 
-    // /// Deserializes an object directly from a `Read`er using this configuration
-    // ///
-    // /// If this returns an `Error`, `reader` may be in an invalid state.
-    // #[inline(always)]
-    // pub fn deserialize_from<R: Read, T: serde::de::DeserializeOwned>(
-    //     &self,
-    //     reader: R,
-    // ) -> Result<T> {
-    //     config_map!(self, opts => ::internal::deserialize_from(reader, opts))
-    // }
+    /// Reads the `u32` at the start of `bytes` in this configuration's endianness without
+    /// deserializing anything, e.g. the tag of a message to decide how to decode the rest.
+    #[inline(always)]
+    pub fn peek_tag(&self, bytes: &[u8]) -> Result<u32> {
+        config_map!(self, opts => ::internal::peek_tag(bytes, opts))
+    }
 
-    // /// Deserializes an object from a custom `BincodeRead`er using the default configuration.
-    // /// It is highly recommended to use `deserialize_from` unless you need to implement
-    // /// `BincodeRead` for performance reasons.
-    // ///
-    // /// If this returns an `Error`, `reader` may be in an invalid state.
-    // #[inline(always)]
-    // pub fn deserialize_from_custom<'a, R: BincodeRead<'a>, T: serde::de::DeserializeOwned>(
-    //     &self,
-    //     reader: R,
-    // ) -> Result<T> {
-    //     config_map!(self, opts => ::internal::deserialize_from_custom(reader, opts))
-    // }
+    /// Appends `version` as a `u32` in this configuration's endianness, followed by `value`,
+    /// to `buf` using this configuration, returning the number of bytes appended.
+    ///
+    /// The value is serialized as if it started the buffer, so it can be decoded on its own
+    /// from the bytes after the version, e.g. by `decode_versioned_with`. If this fails, `buf`
+    /// is left as it was.
+    #[inline(always)]
+    pub fn encode_versioned<A, T: ?Sized + serde::Serialize>(
+        &self,
+        version: u32,
+        value: &T,
+        buf: &mut ArrayVec<A>,
+    ) -> Result<usize>
+    where
+        A: Array<Item = u8>,
+    {
+        config_map!(self, opts => ::internal::encode_versioned(version, value, &mut *buf, opts))
+    }
+
+    /// Decodes a value written by `encode_versioned` using this configuration, returning its
+    /// version alongside it.
+    ///
+    /// Input too short for the version fails with `UnexpectedEof`, like a truncated value.
+    #[inline(always)]
+    pub fn decode_versioned<'a, T: serde::Deserialize<'a>>(&self, bytes: &'a [u8]) -> Result<(u32, T)> {
+        config_map!(self, opts => ::internal::decode_versioned(bytes, opts))
+    }
+
+    /// Reads the version written by `encode_versioned` using this configuration and hands it
+    /// to `f` with the bytes after it, so each version can be decoded its own way.
+    ///
+    /// Input too short for the version fails with `UnexpectedEof` without calling `f`.
+    ///
+    /// ```rust
+    /// extern crate bincode;
+    /// #[macro_use]
+    /// extern crate serde_derive;
+    /// use bincode::arrayvec::ArrayVec;
+    /// use bincode::ErrorKind;
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct SettingsV1 {
+    ///     rate: u16,
+    /// }
+    ///
+    /// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    /// struct Settings {
+    ///     rate: u32,
+    ///     gain: i8,
+    /// }
+    ///
+    /// fn main() {
+    ///     let config = bincode::config();
+    ///     let mut buf: ArrayVec<[u8; 16]> = ArrayVec::new();
+    ///     config.encode_versioned(1, &SettingsV1 { rate: 9600 }, &mut buf).unwrap();
+    ///     assert_eq!(&buf[..], &[1, 0, 0, 0, 0x80, 0x25]);
+    ///
+    ///     let settings = config
+    ///         .decode_versioned_with(&buf, |version, rest| match version {
+    ///             1 => {
+    ///                 let old: SettingsV1 = config.deserialize(rest)?;
+    ///                 Ok(Settings { rate: old.rate as u32, gain: 0 })
+    ///             }
+    ///             2 => config.deserialize(rest),
+    ///             version => Err(ErrorKind::InvalidTagEncoding(version as usize)),
+    ///         })
+    ///         .unwrap();
+    ///     assert_eq!(settings, Settings { rate: 9600, gain: 0 });
+    /// }
+    /// ```
+    #[inline(always)]
+    pub fn decode_versioned_with<'a, F, R>(&self, bytes: &'a [u8], f: F) -> Result<R>
+    where
+        F: FnOnce(u32, &'a [u8]) -> Result<R>,
+    {
+        config_map!(self, opts => ::internal::decode_versioned_with(bytes, f, opts))
+    }
+
+    /// Deserializes an instance of `T` written by `serialize_into_with_crc` using this
+    /// configuration. The CRC-32 is computed over the bytes as they are decoded, then
+    /// compared against the 4 bytes following the value, failing with `ChecksumMismatch`.
+    ///
+    /// Corruption that makes the value itself undecodable, e.g. in a length prefix, is
+    /// reported as that error instead.
+    #[inline(always)]
+    pub fn deserialize_with_crc<'a, T: serde::Deserialize<'a>>(&self, bytes: &'a [u8]) -> Result<T> {
+        config_map!(self, opts => ::internal::deserialize_with_crc(bytes, opts))
+    }
+
+    /// Serializes an object into the start of `out` using this configuration, returning the
+    /// number of bytes written.
+    ///
+    /// If `out` is too short, `BufferTooSmall` is returned and the contents of `out` are
+    /// unspecified.
+    #[inline(always)]
+    pub fn serialize_into_slice<T: ?Sized + serde::Serialize>(&self, t: &T, out: &mut [u8]) -> Result<usize> {
+        config_map!(self, opts => ::internal::serialize_into_slice(out, t, opts))
+    }
+
+    /// Overwrites the `T` encoded at `offset` in `buf` with `value` using this configuration,
+    /// e.g. to bump a sequence number in an encoded packet without encoding the rest again.
+    ///
+    /// The value currently at `offset` is decoded to find out how many bytes it takes, and
+    /// `value` has to encode to exactly as many, or `PatchLengthMismatch` is returned.
+    /// Either way nothing is written unless the whole patch succeeds. With `align`, padding
+    /// is counted from `offset`, so it has to be a multiple of 8 from the start of the
+    /// message.
+    #[inline(always)]
+    pub fn patch<T>(&self, buf: &mut [u8], offset: usize, value: &T) -> Result<()>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        config_map!(self, opts => ::internal::patch(buf, offset, value, opts))
+    }
+
+    /// Serializes an object into a callback using this configuration, returning the number of
+    /// bytes passed to it. See `flavors::Sink`.
+    ///
+    /// If the serialization would take more bytes than allowed by the size limit, an error
+    /// is returned and the callback is never called. An error from the callback stops the
+    /// serialization and is returned as is.
+    #[inline(always)]
+    pub fn serialize_with_sink<T: ?Sized + serde::Serialize, F>(&self, t: &T, sink: F) -> Result<usize>
+    where
+        F: FnMut(&[u8]) -> Result<()>,
+    {
+        self.serialize_with_flavor(::flavors::Sink::new(sink), t)
+    }
+
+    /// Serializes an object into an `io::Write`r using this configuration, returning the
+    /// number of bytes written.
+    ///
+    /// If the serialization would take more bytes than allowed by the size limit, an error
+    /// is returned and *no bytes* will be written into the writer.
+    #[cfg(feature = "std")]
+    #[inline(always)]
+    pub fn serialize_into_writer<W, T: ?Sized + serde::Serialize>(&self, w: W, t: &T) -> Result<usize>
+    where
+        W: ::std::io::Write,
+    {
+        self.serialize_with_flavor(::flavors::IoWriter::new(w), t)
+    }
+
+    /// Serializes an object through an `OutputFlavor`, then fills the rest of a `slot_size`
+    /// byte slot with the pad byte, returning the number of bytes the flavor produced.
+    ///
+    /// If the object doesn't fit in the slot, `SizeLimit(Direction::Serialize)` is returned
+    /// and *no bytes* will be written into the flavor.
+    #[inline(always)]
+    pub fn serialize_padded<F, T: ?Sized + serde::Serialize>(&self, f: F, t: &T, slot_size: usize) -> Result<usize>
+    where
+        F: OutputFlavor,
+    {
+        let pad = self.pad;
+        config_map!(self, opts => ::internal::serialize_padded(f, t, slot_size, pad, opts))
+    }
+
+    /// Deserializes an instance of `T` from the first `slot_size` bytes of `slot`, failing
+    /// with `DirtyPadding` unless every byte after the record is the pad byte.
+    ///
+    /// This catches torn writes, where a partial record is followed by stale data.
+    #[inline(always)]
+    pub fn deserialize_padded<'a, T: serde::Deserialize<'a>>(&self, slot: &'a [u8], slot_size: usize) -> Result<T> {
+        let pad = self.pad;
+        config_map!(self, opts => ::internal::deserialize_padded(slot, slot_size, pad, opts))
+    }
+
+    /// Deserializes a slice of bytes into an instance of `T`, failing with `TrailingBytes`
+    /// if any bytes are left over after it.
+    ///
+    /// Use this when `bytes` should hold exactly one value, to catch framing bugs that
+    /// `deserialize` lets through by ignoring whatever follows the value.
+    #[inline(always)]
+    pub fn deserialize_exact<'a, T: serde::Deserialize<'a>>(&self, bytes: &'a [u8]) -> Result<T> {
+        config_map!(self, opts => ::internal::deserialize_exact(bytes, opts))
+    }
+
+    /// Serializes an object through a borrowed `OutputFlavor`, returning the number of bytes
+    /// written
+    #[inline(always)]
+    pub(crate) fn serialize_prefix<F, T: ?Sized + serde::Serialize>(&self, f: &mut F, t: &T) -> Result<usize>
+    where
+        F: OutputFlavor,
+    {
+        config_map!(self, opts => ::internal::serialize_prefix(f, t, opts))
+    }
+
+    /// Writes a length prefix through a borrowed `OutputFlavor`, returning the number of bytes
+    /// written
+    #[inline(always)]
+    pub(crate) fn serialize_len<F: OutputFlavor>(&self, f: &mut F, len: usize) -> Result<usize> {
+        config_map!(self, opts => ::internal::serialize_len(f, len, opts))
+    }
+
+    /// Appends `bytes` to `w` through a `Serializer`, see `Serializer::write_raw`
+    #[inline(always)]
+    pub(crate) fn write_raw<A: Array<Item = u8>>(&self, w: &mut ArrayVec<A>, bytes: &[u8]) -> Result<()> {
+        config_map!(self, opts => ::ser::Serializer::new(&mut *w, opts).write_raw(bytes))
+    }
+
+    /// Appends `n` to `w` through a `Serializer`, see `Serializer::write_u16_raw`
+    #[inline(always)]
+    pub(crate) fn write_u16_raw<A: Array<Item = u8>>(&self, w: &mut ArrayVec<A>, n: u16) -> Result<()> {
+        config_map!(self, opts => ::ser::Serializer::new(&mut *w, opts).write_u16_raw(n))
+    }
+
+    /// Appends `n` to `w` through a `Serializer`, see `Serializer::write_u32_raw`
+    #[inline(always)]
+    pub(crate) fn write_u32_raw<A: Array<Item = u8>>(&self, w: &mut ArrayVec<A>, n: u32) -> Result<()> {
+        config_map!(self, opts => ::ser::Serializer::new(&mut *w, opts).write_u32_raw(n))
+    }
+
+    /// Appends `n` to `w` through a `Serializer`, see `Serializer::write_u64_raw`
+    #[inline(always)]
+    pub(crate) fn write_u64_raw<A: Array<Item = u8>>(&self, w: &mut ArrayVec<A>, n: u64) -> Result<()> {
+        config_map!(self, opts => ::ser::Serializer::new(&mut *w, opts).write_u64_raw(n))
+    }
+
+    /// Deserializes an instance of `T` from the start of `bytes`, returning it together with
+    /// the number of bytes consumed. Anything after the value is left alone.
+    ///
+    /// To also know how far decoding got when it fails, deserialize from a `SliceReader`
+    /// with `deserialize_from_custom` and ask it for `bytes_read`.
+    #[inline(always)]
+    pub fn deserialize_prefix<'a, T: serde::Deserialize<'a>>(&self, bytes: &'a [u8]) -> Result<(T, usize)> {
+        config_map!(self, opts => ::internal::deserialize_prefix(bytes, opts))
+    }
+
+    /// Deserializes an instance of `T` from the start of `bytes`, returning it together with
+    /// the bytes after it, so values packed back to back can be decoded one after the other.
+    ///
+    /// If the input ends partway through the value, `UnexpectedEof` is returned rather than
+    /// a tail.
+    #[inline(always)]
+    pub fn deserialize_partial<'a, T: serde::Deserialize<'a>>(&self, bytes: &'a [u8]) -> Result<(T, &'a [u8])> {
+        config_map!(self, opts => ::internal::deserialize_partial(bytes, opts))
+    }
+
+    /// Returns an iterator over values of type `T` encoded back to back in `bytes`, using
+    /// this configuration. See `DeserializeIter`.
+    pub fn deserialize_iter<'a, T: serde::Deserialize<'a>>(&self, bytes: &'a [u8]) -> DeserializeIter<'a, T> {
+        DeserializeIter::with_config(bytes, *self)
+    }
+
+    /// Decodes the values of type `T` encoded back to back in `bytes` using this configuration
+    /// and adds them to `out`, returning how many were added.
+    ///
+    /// The values before one that fails to decode are added before the error is returned.
+    pub fn decode_many<'a, T, E>(&self, bytes: &'a [u8], out: &mut E) -> Result<usize>
+    where
+        T: serde::Deserialize<'a>,
+        E: Extend<T>,
+    {
+        let mut count = 0;
+        for value in self.deserialize_iter(bytes) {
+            out.extend(Some(value?));
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Reads a length prefix from the start of `bytes`, returning it together with the number
+    /// of bytes consumed
+    #[inline(always)]
+    pub(crate) fn deserialize_len(&self, bytes: &[u8]) -> Result<(usize, usize)> {
+        config_map!(self, opts => ::internal::deserialize_len(bytes, opts))
+    }
+
+    /// Appends `t` to `w` as a frame using this configuration, see `frame::encode`
+    #[inline(always)]
+    pub(crate) fn encode_frame<A, T: ?Sized + serde::Serialize>(&self, t: &T, w: &mut ArrayVec<A>) -> Result<usize>
+    where
+        A: Array<Item = u8>,
+    {
+        config_map!(self, opts => ::frame::encode_with_options(t, &mut *w, opts))
+    }
+
+    /// Decodes the frame at the start of `bytes` using this configuration, see
+    /// `frame::decode`
+    #[inline(always)]
+    pub(crate) fn decode_frame<'a, T: serde::Deserialize<'a>>(&self, bytes: &'a [u8]) -> Result<(T, &'a [u8])> {
+        config_map!(self, opts => ::frame::decode_with_options(bytes, opts))
+    }
+
+    /// Appends `magic` and then `t` as a frame to `w` using this configuration, see
+    /// `frame::encode_with_magic`
+    #[inline(always)]
+    pub(crate) fn encode_frame_with_magic<A, T: ?Sized + serde::Serialize>(
+        &self,
+        magic: [u8; ::frame::MAGIC_LEN],
+        t: &T,
+        w: &mut ArrayVec<A>,
+    ) -> Result<usize>
+    where
+        A: Array<Item = u8>,
+    {
+        config_map!(self, opts => ::frame::encode_with_magic_and_options(magic, t, &mut *w, opts))
+    }
+
+    /// Decodes the frame after `magic` at the start of `bytes` using this configuration, see
+    /// `frame::decode_with_magic`
+    #[inline(always)]
+    pub(crate) fn decode_frame_with_magic<'a, T: serde::Deserialize<'a>>(
+        &self,
+        magic: [u8; ::frame::MAGIC_LEN],
+        bytes: &'a [u8],
+    ) -> Result<(T, &'a [u8])> {
+        config_map!(self, opts => ::frame::decode_with_magic_and_options(magic, bytes, opts))
+    }
+
+    /// Writes `t` to `f` as a frame of a stream using this configuration, see
+    /// `stream::write_frame`
+    #[inline(always)]
+    pub(crate) fn write_stream_frame<F, T: ?Sized + serde::Serialize>(&self, f: &mut F, t: &T) -> Result<usize>
+    where
+        F: OutputFlavor,
+    {
+        config_map!(self, opts => ::stream::write_frame_with_options(f, t, opts))
+    }
+
+    /// Decodes the next message of `stream` using this configuration, see
+    /// `StreamDeserializer::try_next`
+    #[inline(always)]
+    pub(crate) fn next_stream_frame<T, A>(&self, stream: &mut ::stream::StreamDeserializer<T, A>) -> Result<Option<T>>
+    where
+        T: serde::de::DeserializeOwned,
+        A: Array<Item = u8>,
+    {
+        config_map!(self, opts => stream.try_next_with_options(opts))
+    }
+
+    /// Deserializes an instance of `T` from the payload of an `InputFlavor` using this
+    /// configuration
+    #[inline(always)]
+    pub fn deserialize_with_flavor<'a, F, T>(&self, f: F) -> Result<T>
+    where
+        F: InputFlavor<'a>,
+        T: serde::Deserialize<'a>,
+    {
+        config_map!(self, opts => ::internal::deserialize_with_flavor(f, opts))
+    }
+
+    /// Deserializes a slice of bytes into an existing `T` using this configuration, see
+    /// `bincode::deserialize_in_place`.
+    #[inline(always)]
+    pub fn deserialize_in_place<'a, T: serde::Deserialize<'a>>(&self, bytes: &'a [u8], place: &mut T) -> Result<()> {
+        config_map!(self, opts => ::internal::deserialize_in_place(bytes, place, opts))
+    }
+
+    /// Deserializes an object directly from a `Read`er using this configuration
+    ///
+    /// If this returns an `Error`, `reader` may be in an invalid state.
+    #[cfg(feature = "std")]
+    #[inline(always)]
+    pub fn deserialize_from<R: ::std::io::Read, T: serde::de::DeserializeOwned>(
+        &self,
+        reader: R,
+    ) -> Result<T> {
+        config_map!(self, opts => ::internal::deserialize_from(reader, opts))
+    }
+
+    /// Deserializes an object from a custom `BincodeRead`er using the default configuration.
+    /// It is highly recommended to use `deserialize_from` unless you need to implement
+    /// `BincodeRead` for performance reasons.
+    ///
+    /// Pass `&mut reader` to keep reading from the same position afterwards,
+    /// for example to mix hand-written header fields with serde-decoded bodies.
+    ///
+    /// If this returns an `Error`, `reader` may be in an invalid state.
+    #[inline(always)]
+    pub fn deserialize_from_custom<'a, R: BincodeRead<'a>, T: serde::de::Deserialize<'a>>(
+        &self,
+        reader: R,
+    ) -> Result<T> {
+        config_map!(self, opts => ::internal::deserialize_from_custom(reader, opts))
+    }
+
+    /// Deserializes an object from one of the built-in readers using this configuration,
+    /// without making the caller generic over the reader type.
+    ///
+    /// If this returns an `Error`, `reader` may be in an invalid state.
+    #[inline(always)]
+    pub fn deserialize_dyn<'a, T: serde::de::Deserialize<'a>>(&self, reader: &mut DynReader<'a>) -> Result<T> {
+        config_map!(self, opts => ::internal::deserialize_from_custom(&mut *reader, opts))
+    }
 
     // /// Executes the acceptor with a serde::Deserializer instance.
     // /// NOT A PART OF THE STABLE PUBLIC API
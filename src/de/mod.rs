@@ -1,14 +1,16 @@
-use ::config::Options;
+use ::config::{option_tags, padding, Options, VarintDecoder};
+
+use byteorder::ByteOrder;
 
 use serde;
 use serde::de::IntoDeserializer;
 use serde::de::Error as DeError;
-use ::{Error, ErrorKind, Result};
+use ::{Direction, Error, ErrorKind, Result};
 use ::internal::SizeLimit;
 use self::read::BincodeRead;
 
 pub mod read;
-use self::read::SliceReader;
+use self::read::{Checkpoint, SliceReader};
 
 // struct Cursor<'a> {
 //     pos: usize,
@@ -26,40 +28,137 @@ use self::read::SliceReader;
 //     }
 // }
 
-/// A Deserializer that reads bytes from a buffer.
+/// A Deserializer that reads bytes from a `BincodeRead`er.
 ///
-/// This struct should rarely be used.
-/// In most cases, prefer the `deserialize_from` function.
+/// In most cases, prefer the `deserialize` functions; this is for driving
+/// serde by hand, e.g. to decode several values with one deserializer or to
+/// wrap it in an adapter.
 ///
 /// The ByteOrder that is chosen will impact the endianness that
 /// is used to read integers out of the reader.
 ///
-/// ```rust,ignore
-/// let d = Deserializer::new(&mut some_reader, SizeLimit::new());
-/// serde::Deserialize::deserialize(&mut deserializer);
-/// let bytes_read = d.bytes_read();
+/// The constructors, `end`, `into_reader` and the `serde::Deserializer` impl
+/// are part of the stable API.
+///
+/// ```rust
+/// extern crate bincode;
+/// extern crate serde;
+/// use bincode::{DefaultOptions, Deserializer, OptionsExt};
+/// use serde::Deserialize;
+///
+/// fn main() {
+///     let bytes = [1, 0, 0, 2, 0, 0, 0];
+///     let mut d = Deserializer::from_slice(&bytes, DefaultOptions::new().with_big_endian());
+///     assert_eq!(u16::deserialize(&mut d).unwrap(), 256);
+///     assert_eq!(u8::deserialize(&mut d).unwrap(), 0);
+///     assert_eq!(u32::deserialize(&mut d).unwrap(), 0x0200_0000);
+///     assert_eq!(d.end().unwrap(), 7);
+/// }
 /// ```
-pub(crate) struct Deserializer<'a, O: Options>{
-    reader: SliceReader<'a>,
+pub struct Deserializer<R, O: Options>{
+    reader: R,
     options: O,
+    pos: u64,
 }
 
-impl<'a, 'de, O: Options> Deserializer<'a, O> {
-    /// Creates a new Deserializer with a given `Read`er and a size_limit.
-    pub(crate) fn new(r: SliceReader<'a>, options: O) -> Deserializer<'a, O> {
+impl<'de, R: BincodeRead<'de>, O: Options> Deserializer<R, O> {
+    /// Creates a new Deserializer with a given `BincodeRead`er and options.
+    pub fn new(r: R, options: O) -> Deserializer<R, O> {
         Deserializer {
             reader: r,
-            options: options,
+            options,
+            pos: 0,
         }
     }
 
+    /// Fails if any input is left after the values deserialized so far,
+    /// returning the number of bytes they took up otherwise.
+    pub fn end(&mut self) -> Result<u64> {
+        self.reader.end()?;
+        Ok(self.pos)
+    }
+
+    /// Unwraps the reader, which is positioned after the last value read.
+    pub fn into_reader(self) -> R {
+        self.reader
+    }
+
     fn read_bytes(&mut self, count: u64) -> Result<()> {
-        self.options.limit().add(count)
+        // Report running out of input before the size limit, and don't
+        // charge the limit for bytes that aren't there.
+        if let Some(remaining) = self.reader.remaining_hint() {
+            if count > remaining as u64 {
+                return Err(ErrorKind::UnexpectedEof {
+                    remaining,
+                    budget: self.options.limit().limit(),
+                    needed: Some((count - remaining as u64) as usize),
+                });
+            }
+        }
+        self.options.limit().add(count, Direction::Deserialize)?;
+        self.pos += count;
+        Ok(())
+    }
+
+    /// Reads a fixed number of bytes, e.g. for a number.
+    #[inline(always)]
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut buf = [0; N];
+        self.read_number(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Fills `buf` with a number, after the padding in front of it. Numbers
+    /// of every width share this, and decode the bytes on their own with
+    /// nothing but the endianness.
+    fn read_number(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.skip_padding(buf.len() as u64)?;
+        self.read_bytes(buf.len() as u64)?;
+        self.reader.read_exact(buf)
+    }
+
+    /// Skips the padding the serializer writes before a `width` byte number
+    /// if alignment is enabled.
+    fn skip_padding(&mut self, width: u64) -> Result<()> {
+        if self.options.align() && !self.pos.is_multiple_of(width) {
+            let padding = padding(self.pos, width);
+            self.read_bytes(padding)?;
+            self.reader.read_exact(&mut [0; 16][..padding as usize])?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn read_len(&mut self) -> Result<usize> {
+        use config::LengthEncoding;
+
+        match self.options.length_encoding() {
+            LengthEncoding::U16 => Ok(O::Endian::read_u16(&self.read_array::<2>()?) as usize),
+            LengthEncoding::U32 => Ok(O::Endian::read_u32(&self.read_array::<4>()?) as usize),
+            LengthEncoding::U64 => serde::Deserialize::deserialize(&mut *self),
+            LengthEncoding::Varint => {
+                let mut decoder = VarintDecoder::new();
+                loop {
+                    let [byte] = self.read_array::<1>()?;
+                    if let Some(len) = decoder.push(byte)? {
+                        return Ok(len);
+                    }
+                }
+            }
+        }
     }
 
-    fn read_type<T>(&mut self) -> Result<()> {
-        use core::mem::size_of;
-        self.read_bytes(size_of::<T>() as u64)
+    fn deserialize_nul_str<V>(&mut self, max: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let len = match self.reader.find_byte(0, max.saturating_add(1))? {
+            Some(len) => len,
+            None => return Err(ErrorKind::MissingNulTerminator { max }),
+        };
+        self.read_bytes(len as u64 + 1)?;
+        let value = self.reader.forward_read_str(len, visitor)?;
+        self.reader.read_exact(&mut [0; 1])?;
+        Ok(value)
     }
 
     // fn read_vec(&mut self) -> Result<Vec<u8>> {
@@ -74,21 +173,74 @@ impl<'a, 'de, O: Options> Deserializer<'a, O> {
     // }
 }
 
+impl<'de, O: Options> Deserializer<SliceReader<'de>, O> {
+    /// Creates a new Deserializer reading from the start of `bytes`.
+    pub fn from_slice(bytes: &'de [u8], options: O) -> Deserializer<SliceReader<'de>, O> {
+        Deserializer::new(SliceReader::new(bytes), options)
+    }
+
+    /// The number of input bytes not consumed yet.
+    pub(crate) fn remaining(&self) -> usize {
+        self.reader.remaining()
+    }
+
+    /// The number of input bytes consumed so far, including by a read that failed
+    /// on the contents of the bytes rather than on running out of them.
+    pub fn bytes_read(&self) -> usize {
+        self.reader.bytes_read()
+    }
+
+    /// Marks the current position, so a speculative decode can be undone with
+    /// `rollback`.
+    ///
+    /// Unlike `SliceReader::checkpoint`, the marker also holds what the size
+    /// limit has been charged so far and the position `align` pads from, so
+    /// one deserializer can be reused across attempts. Only deserializers
+    /// reading from a slice offer this; readers like `IoReader` can't rewind.
+    pub fn checkpoint(&mut self) -> Marker<'de, O::Limit> {
+        Marker {
+            reader: self.reader.checkpoint(),
+            limit: self.options.limit().clone(),
+            pos: self.pos,
+        }
+    }
+
+    /// Returns to a position marked by `checkpoint` on this deserializer,
+    /// undoing the reads and size limit charges since.
+    ///
+    /// Fails with `ForeignCheckpoint`, leaving the deserializer as it was, if
+    /// the marker was taken on a different input.
+    pub fn rollback(&mut self, marker: Marker<'de, O::Limit>) -> Result<()> {
+        self.reader.rollback(marker.reader)?;
+        *self.options.limit() = marker.limit;
+        self.pos = marker.pos;
+        Ok(())
+    }
+}
+
+/// A position in a `Deserializer`, see `Deserializer::checkpoint`.
+#[derive(Clone)]
+pub struct Marker<'storage, L> {
+    reader: Checkpoint<'storage>,
+    limit: L,
+    pos: u64,
+}
+
 macro_rules! impl_nums {
     ($ty:ty, $dser_method:ident, $visitor_method:ident, $reader_method:ident) => {
         #[inline]
         fn $dser_method<V>(self, visitor: V) -> Result<V::Value>
             where V: serde::de::Visitor<'de>,
         {
-            try!(self.read_type::<$ty>());
-            let value = try!(self.reader.$reader_method::<O::Endian>());
-            visitor.$visitor_method(value)
+            let buf = try!(self.read_array::<{ ::core::mem::size_of::<$ty>() }>());
+            visitor.$visitor_method(O::Endian::$reader_method(&buf))
         }
     }
 }
 
-impl<'de, 'a, O> serde::Deserializer<'de> for &'a mut Deserializer<'de, O>
+impl<'de, 'a, R, O> serde::Deserializer<'de> for &'a mut Deserializer<R, O>
 where
+    R: BincodeRead<'de>,
     O: Options,
 {
     type Error = Error;
@@ -135,7 +287,7 @@ where
             V: serde::de::Visitor<'de>
         {
             let _ = visitor;
-            Err(DeError::custom("u128 is not supported. Enable the `i128` feature of `bincode`"))
+            Err(ErrorKind::U128Unsupported)
         }
 
         #[cfg(not(feature = "i128"))]
@@ -144,7 +296,7 @@ where
             V: serde::de::Visitor<'de>
         {
             let _ = visitor;
-            Err(DeError::custom("i128 is not supported. Enable the `i128` feature of `bincode`"))
+            Err(ErrorKind::U128Unsupported)
         }
     }
 
@@ -153,13 +305,8 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        try!(self.read_type::<u8>());
-        if self.reader.slice.is_empty() {
-            return Err(ErrorKind::SizeLimit);
-        }
-        let value = self.reader.slice[0];
-        self.reader.slice = &self.reader.slice[1..];
-        visitor.visit_u8(value)
+        try!(self.read_bytes(1));
+        visitor.visit_u8(try!(self.reader.read_u8()))
     }
 
     #[inline]
@@ -167,13 +314,8 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        try!(self.read_type::<i8>());
-        if self.reader.slice.is_empty() {
-            return Err(ErrorKind::SizeLimit);
-        }
-        let value = self.reader.slice[0];
-        self.reader.slice = &self.reader.slice[1..];
-        visitor.visit_i8(value as i8)
+        try!(self.read_bytes(1));
+        visitor.visit_i8(try!(self.reader.read_i8()))
     }
 
     fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
@@ -195,6 +337,7 @@ where
 
         // Look at the first byte to see how many bytes must be read
         let _ = try!(self.reader.read_exact(&mut buf[..1]));
+        self.pos += 1;
         let width = utf8_char_width(buf[0]);
         if width == 1 {
             return visitor.visit_char(buf[0] as char);
@@ -206,6 +349,7 @@ where
         if self.reader.read_exact(&mut buf[1..width]).is_err() {
             return Err(error());
         }
+        self.pos += width as u64 - 1;
 
         let res = try!(
             str::from_utf8(&buf[..width])
@@ -220,9 +364,76 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        let len: usize = try!(serde::Deserialize::deserialize(&mut *self));
+        struct Filtered<V> {
+            visitor: V,
+            filter: ::StrFilter,
+        }
+
+        impl<'de, V: serde::de::Visitor<'de>> serde::de::Visitor<'de> for Filtered<V> {
+            type Value = V::Value;
+
+            fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                self.visitor.expecting(formatter)
+            }
+
+            fn visit_str<E: DeError>(self, v: &str) -> ::core::result::Result<V::Value, E> {
+                self.visitor.visit_str((self.filter)(v))
+            }
+
+            fn visit_borrowed_str<E: DeError>(self, v: &'de str) -> ::core::result::Result<V::Value, E> {
+                self.visitor.visit_borrowed_str((self.filter)(v))
+            }
+        }
+
+        /// Reads the string as bytes and repairs it if it isn't valid UTF-8.
+        struct Lossy<V>(Filtered<V>);
+
+        #[cfg(not(feature = "alloc"))]
+        impl<V> Lossy<V> {
+            /// The longest valid prefix of `v`.
+            fn prefix(v: &[u8], valid_up_to: usize) -> &str {
+                match ::core::str::from_utf8(&v[..valid_up_to]) {
+                    Ok(s) => s,
+                    Err(_) => "",
+                }
+            }
+        }
+
+        impl<'de, V: serde::de::Visitor<'de>> serde::de::Visitor<'de> for Lossy<V> {
+            type Value = V::Value;
+
+            fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                self.0.expecting(formatter)
+            }
+
+            fn visit_bytes<E: DeError>(self, v: &[u8]) -> ::core::result::Result<V::Value, E> {
+                match ::core::str::from_utf8(v) {
+                    Ok(s) => self.0.visit_str(s),
+                    #[cfg(feature = "alloc")]
+                    Err(_) => self.0.visit_str(&::alloc::string::String::from_utf8_lossy(v)),
+                    #[cfg(not(feature = "alloc"))]
+                    Err(e) => self.0.visit_str(Self::prefix(v, e.valid_up_to())),
+                }
+            }
+
+            fn visit_borrowed_bytes<E: DeError>(self, v: &'de [u8]) -> ::core::result::Result<V::Value, E> {
+                match ::core::str::from_utf8(v) {
+                    Ok(s) => self.0.visit_borrowed_str(s),
+                    #[cfg(feature = "alloc")]
+                    Err(_) => self.0.visit_str(&::alloc::string::String::from_utf8_lossy(v)),
+                    #[cfg(not(feature = "alloc"))]
+                    Err(e) => self.0.visit_borrowed_str(Self::prefix(v, e.valid_up_to())),
+                }
+            }
+        }
+
+        let len = try!(self.read_len());
         try!(self.read_bytes(len as u64));
-        self.reader.forward_read_str(len, visitor)
+        let filter = self.options.str_filter();
+        if self.options.lossy_utf8() {
+            return self.reader.forward_read_bytes(len, Lossy(Filtered { visitor, filter }));
+        }
+        self.reader.forward_read_str(len, Filtered { visitor, filter })
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
@@ -236,11 +447,22 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        let len: usize = try!(serde::Deserialize::deserialize(&mut *self));
+        let len = try!(self.read_len());
         try!(self.read_bytes(len as u64));
         self.reader.forward_read_bytes(len, visitor)
     }
 
+    #[cfg(feature = "alloc")]
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let len = try!(self.read_len());
+        try!(self.read_bytes(len as u64));
+        visitor.visit_byte_buf(try!(self.reader.get_byte_buffer(len)))
+    }
+
+    #[cfg(not(feature = "alloc"))]
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
@@ -257,8 +479,8 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        impl<'de, 'a, O> serde::de::EnumAccess<'de> for &'a mut Deserializer<'de, O>
-        where O: Options {
+        impl<'de, 'a, R: 'a, O> serde::de::EnumAccess<'de> for &'a mut Deserializer<R, O>
+        where R: BincodeRead<'de>, O: Options {
             type Error = Error;
             type Variant = Self;
 
@@ -278,17 +500,17 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        struct Access<'a, 'de: 'a, O: Options + 'a> {
-            deserializer: &'a mut Deserializer<'de, O>,
+        struct Access<'a, R: 'a, O: Options + 'a> {
+            deserializer: &'a mut Deserializer<R, O>,
             len: usize,
         }
 
         impl<
             'de,
             'a,
-            'b: 'a,
+            R: BincodeRead<'de> + 'a,
             O: Options,
-        > serde::de::SeqAccess<'de> for Access<'a, 'de, O> {
+        > serde::de::SeqAccess<'de> for Access<'a, R, O> {
             type Error = Error;
 
             fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
@@ -322,11 +544,14 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
+        let (none, some) = option_tags(&self.options)?;
         let value: u8 = try!(serde::de::Deserialize::deserialize(&mut *self));
-        match value {
-            0 => visitor.visit_none(),
-            1 => visitor.visit_some(&mut *self),
-            v => Err(ErrorKind::InvalidTagEncoding(v as usize).into()),
+        if value == none {
+            visitor.visit_none()
+        } else if value == some {
+            visitor.visit_some(&mut *self)
+        } else {
+            Err(ErrorKind::InvalidTagEncoding(value as usize).into())
         }
     }
 
@@ -334,7 +559,7 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        let len = try!(serde::Deserialize::deserialize(&mut *self));
+        let len = try!(self.read_len());
 
         self.deserialize_tuple(len, visitor)
     }
@@ -343,17 +568,17 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        struct Access<'a, 'de: 'a, O: Options + 'a> {
-            deserializer: &'a mut Deserializer<'de, O>,
+        struct Access<'a, R: 'a, O: Options + 'a> {
+            deserializer: &'a mut Deserializer<R, O>,
             len: usize,
         }
 
         impl<
             'de,
             'a,
-            'b: 'a,
+            R: BincodeRead<'de> + 'a,
             O: Options,
-        > serde::de::MapAccess<'de> for Access<'a, 'de, O> {
+        > serde::de::MapAccess<'de> for Access<'a, R, O> {
             type Error = Error;
 
             fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
@@ -388,7 +613,7 @@ where
             }
         }
 
-        let len = try!(serde::Deserialize::deserialize(&mut *self));
+        let len = try!(self.read_len());
 
         visitor.visit_map(Access {
             deserializer: self,
@@ -416,6 +641,7 @@ where
         Err(Error::custom(message))
     }
 
+    #[inline(always)]
     fn deserialize_newtype_struct<V>(self, _name: &str, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
@@ -432,13 +658,20 @@ where
 
     fn deserialize_tuple_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         len: usize,
         visitor: V,
     ) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
+        if name == ::nul_str::TOKEN {
+            return self.deserialize_nul_str(len, visitor);
+        }
+        if name == ::byte_array::TOKEN {
+            self.read_bytes(len as u64)?;
+            return self.reader.forward_read_bytes(len, visitor);
+        }
         self.deserialize_tuple(len, visitor)
     }
 
@@ -455,8 +688,8 @@ where
     }
 }
 
-impl<'de, 'a, O> serde::de::VariantAccess<'de> for &'a mut Deserializer<'de, O>
-where O: Options{
+impl<'de, 'a, R, O> serde::de::VariantAccess<'de> for &'a mut Deserializer<R, O>
+where R: BincodeRead<'de>, O: Options{
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
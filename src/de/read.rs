@@ -1,11 +1,43 @@
-// use std::io;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "embedded-io")]
+use arrayvec::{Array, ArrayVec};
+use byteorder::ByteOrder;
 use error::{Result, ErrorKind};
 use serde;
 
+macro_rules! impl_read_nums {
+    ($ty:ty, $reader_method:ident) => {
+        #[inline]
+        fn $reader_method<E: ByteOrder>(&mut self) -> Result<$ty> {
+            let mut buf = [0; ::core::mem::size_of::<$ty>()];
+            self.read_exact(&mut buf)?;
+            Ok(E::$reader_method(&buf))
+        }
+    }
+}
+
 /// An optional Read trait for advanced Bincode usage.
 ///
 /// It is highly recommended to use bincode with `io::Read` or `&[u8]` before
 /// implementing a custom `BincodeRead`.
+///
+/// ### Borrowing
+/// Readers over memory that outlives them hand strings and byte slices to the
+/// visitor with `visit_borrowed_str` and `visit_borrowed_bytes`, so `&str`,
+/// `&[u8]` and `#[serde(borrow)]` `Cow` fields point into the input:
+///
+/// - `SliceReader` and `BlockCachedReader` always borrow.
+/// - `ChainedSliceReader` borrows unless the value straddles the seam between
+///   its two slices, in which case a `Cow` is owned and a `&str` fails.
+/// - `DynReader` and `CrcReader` borrow whenever the reader they wrap does, and
+///   `dyn ObjectRead` whenever it hands out a `Chunk::Borrowed`.
+/// - `ScratchReader`, `IoReader`, `BufReader` and `EmbeddedIoReader` read into
+///   buffers of their own and never borrow.
+///
+/// A string that `Config::lossy_utf8` had to repair is always owned. Note that
+/// serde only borrows a `Cow` that is a field marked `#[serde(borrow)]`; a
+/// `Cow` deserialized on its own always comes out owned.
 pub trait BincodeRead<'storage> {
     /// Forwards reading `length` bytes of a string on to the serde reader.
     fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
@@ -13,75 +45,290 @@ pub trait BincodeRead<'storage> {
         V: serde::de::Visitor<'storage>;
 
     /// Return the first `length` bytes of the internal byte buffer.
-    // fn get_byte_buffer(&mut self, length: usize) -> R.esult<Vec<u8>>;
+    ///
+    /// The default implementation copies them out with `read_exact`. Readers
+    /// that already hold the bytes in a `Vec` of their own can hand it over
+    /// instead.
+    #[cfg(feature = "alloc")]
+    fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
+        let mut buf = ::alloc::vec![0; length];
+        self.read_exact(&mut buf)?;
+        Ok(buf)
+    }
 
     /// Forwards reading `length` bytes on to the serde reader.
     fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'storage>;
+
+    /// Fills `buf` with the next `buf.len()` bytes.
+    ///
+    /// The default implementation goes through `forward_read_bytes`.
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        struct CopyVisitor<'a>(&'a mut [u8]);
+
+        impl<'a, 'de> serde::de::Visitor<'de> for CopyVisitor<'a> {
+            type Value = ();
+
+            fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                formatter.write_str("bytes")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> ::core::result::Result<(), E> {
+                self.0.copy_from_slice(v);
+                Ok(())
+            }
+        }
+
+        let length = buf.len();
+        self.forward_read_bytes(length, CopyVisitor(buf))
+    }
+
+    /// Copies the next `out.len()` bytes straight into `out`, for fixed-size
+    /// fields like keys that don't need a visitor.
+    ///
+    /// The default implementation is `read_exact`, so readers only need to
+    /// override that one.
+    #[inline]
+    fn forward_read_exact(&mut self, out: &mut [u8]) -> Result<()> {
+        self.read_exact(out)
+    }
+
+    /// The number of bytes left in the input, if the reader knows it.
+    fn remaining_hint(&self) -> Option<usize> {
+        None
+    }
+
+    /// Consumes the next `n` bytes without looking at them, for hopping over
+    /// blocks the caller doesn't want to interpret.
+    ///
+    /// Fails with `UnexpectedEof` if fewer than `n` bytes are left. The default
+    /// reads them in small pieces, so it may have consumed some by then.
+    fn skip_bytes(&mut self, n: usize) -> Result<()> {
+        let mut buf = [0; 64];
+        let mut left = n;
+        while left > 0 {
+            let len = ::core::cmp::min(left, buf.len());
+            self.read_exact(&mut buf[..len])?;
+            left -= len;
+        }
+        Ok(())
+    }
+
+    /// Fails with `TrailingBytes` unless the input is used up, for checking
+    /// that a value spanned all of it.
+    ///
+    /// Readers that know how much is left report exactly that. Otherwise the
+    /// default tries to read one more byte, consuming it if there is one and
+    /// reporting `TrailingBytes(1)`, as more may follow.
+    fn end(&mut self) -> Result<()> {
+        match self.remaining_hint() {
+            Some(0) => Ok(()),
+            Some(n) => Err(ErrorKind::TrailingBytes(n)),
+            None => match self.read_u8() {
+                Ok(_) => Err(ErrorKind::TrailingBytes(1)),
+                Err(ErrorKind::UnexpectedEof { .. }) => Ok(()),
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    /// Returns the offset of the first `byte` within the next `max` bytes,
+    /// without consuming anything. Readers that can't look ahead keep the
+    /// default, which fails with `LookAheadUnsupported`.
+    fn find_byte(&self, byte: u8, max: usize) -> Result<Option<usize>> {
+        let _ = (byte, max);
+        Err(ErrorKind::LookAheadUnsupported)
+    }
+
+    /// Reads a single byte.
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8> {
+        let mut buf = [0; 1];
+        self.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    /// Reads a single byte as an `i8`.
+    #[inline]
+    fn read_i8(&mut self) -> Result<i8> {
+        self.read_u8().map(|byte| byte as i8)
+    }
+
+    impl_read_nums!(u16, read_u16);
+    impl_read_nums!(u32, read_u32);
+    impl_read_nums!(u64, read_u64);
+    impl_read_nums!(i16, read_i16);
+    impl_read_nums!(i32, read_i32);
+    impl_read_nums!(i64, read_i64);
+    impl_read_nums!(f32, read_f32);
+    impl_read_nums!(f64, read_f64);
+    #[cfg(feature = "i128")]
+    impl_read_nums!(u128, read_u128);
+    #[cfg(feature = "i128")]
+    impl_read_nums!(i128, read_i128);
+}
+
+impl<'a, 'storage, R: BincodeRead<'storage> + ?Sized> BincodeRead<'storage> for &'a mut R {
+    #[inline(always)]
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        (**self).forward_read_str(length, visitor)
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline(always)]
+    fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
+        (**self).get_byte_buffer(length)
+    }
+
+    #[inline(always)]
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        (**self).forward_read_bytes(length, visitor)
+    }
+
+    #[inline(always)]
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        (**self).read_exact(buf)
+    }
+
+    #[inline(always)]
+    fn forward_read_exact(&mut self, out: &mut [u8]) -> Result<()> {
+        (**self).forward_read_exact(out)
+    }
+
+    #[inline(always)]
+    fn read_u8(&mut self) -> Result<u8> {
+        (**self).read_u8()
+    }
+
+    #[inline(always)]
+    fn remaining_hint(&self) -> Option<usize> {
+        (**self).remaining_hint()
+    }
+
+    #[inline(always)]
+    fn skip_bytes(&mut self, n: usize) -> Result<()> {
+        (**self).skip_bytes(n)
+    }
+
+    #[inline(always)]
+    fn end(&mut self) -> Result<()> {
+        (**self).end()
+    }
+
+    #[inline(always)]
+    fn find_byte(&self, byte: u8, max: usize) -> Result<Option<usize>> {
+        (**self).find_byte(byte, max)
+    }
 }
 
 /// A BincodeRead implementation for byte slices
 /// NOT A PART OF THE STABLE PUBLIC API
 #[doc(hidden)]
 pub struct SliceReader<'storage> {
-    pub slice: &'storage [u8],
+    slice: &'storage [u8],
+    len: usize,
+}
+
+/// A position in a `SliceReader`, see `SliceReader::checkpoint`.
+#[derive(Clone, Copy, Debug)]
+pub struct Checkpoint<'storage> {
+    slice: &'storage [u8],
 }
 
 /// A BincodeRead implementation for io::Readers
 /// NOT A PART OF THE STABLE PUBLIC API
-// #[doc(hidden)]
-// pub struct IoReader<R> {
-//     reader: R,
-//     temp_buffer: Vec<u8>,
-// }
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub struct IoReader<R> {
+    reader: R,
+    temp_buffer: Vec<u8>,
+}
 
 impl<'storage> SliceReader<'storage> {
     /// Constructs a slice reader
     pub fn new(bytes: &'storage [u8]) -> SliceReader<'storage> {
-        SliceReader { slice: bytes }
+        SliceReader {
+            slice: bytes,
+            len: bytes.len(),
+        }
     }
-}
 
-// impl<R> IoReader<R> {
-//     /// Constructs an IoReadReader
-//     pub fn new(r: R) -> IoReader<R> {
-//         IoReader {
-//             reader: r,
-//             temp_buffer: vec![],
-//         }
-//     }
-// }
+    /// The number of bytes not consumed yet.
+    pub fn remaining(&self) -> usize {
+        self.slice.len()
+    }
+
+    /// The input not consumed yet. After a failed read this starts right
+    /// after the last byte the deserializer took, so callers can skip ahead
+    /// and carry on from there.
+    pub fn remaining_slice(&self) -> &'storage [u8] {
+        self.slice
+    }
+
+    /// The number of bytes consumed so far. A read that fails for lack of input
+    /// doesn't consume anything.
+    pub fn bytes_read(&self) -> usize {
+        self.len - self.slice.len()
+    }
+
+    /// Returns the next `n` bytes without consuming them.
+    pub fn peek(&self, n: usize) -> Result<&'storage [u8]> {
+        if n > self.slice.len() {
+            return Err(self.unexpected_eof(n));
+        }
+        Ok(&self.slice[..n])
+    }
+
+    /// Returns the next `u32` in the byte order `E` without consuming it.
+    pub fn peek_u32<E: ByteOrder>(&self) -> Result<u32> {
+        self.peek(4).map(E::read_u32)
+    }
 
-impl<'a> SliceReader<'a> {
-    pub fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
-        let len = buf.len();
-        if len > self.slice.len() {
-            return Err(ErrorKind::SizeLimit);
+    /// Marks the current position, so a speculative read can be undone with
+    /// `rollback`.
+    ///
+    /// Each `deserialize_from_custom` call starts its own size limit, so
+    /// rolling back the reader and deserializing again starts from a clean
+    /// slate. A `Deserializer` reused across attempts has to be rolled back
+    /// with `Deserializer::checkpoint` instead, which also restores its limit.
+    /// Readers that can't rewind, like `IoReader`, don't offer this.
+    pub fn checkpoint(&self) -> Checkpoint<'storage> {
+        Checkpoint { slice: self.slice }
+    }
+
+    /// Returns to a position marked by `checkpoint` on this reader.
+    ///
+    /// Fails with `ForeignCheckpoint`, leaving the reader as it was, if the
+    /// checkpoint was taken on a different input.
+    pub fn rollback(&mut self, checkpoint: Checkpoint<'storage>) -> Result<()> {
+        // Every position in the same input ends where it does.
+        let end = |slice: &[u8]| slice.as_ptr() as usize + slice.len();
+        if end(checkpoint.slice) != end(self.slice) || checkpoint.slice.len() > self.len {
+            return Err(ErrorKind::ForeignCheckpoint);
         }
-        buf.copy_from_slice(&self.slice[..len]);
-        self.slice = &self.slice[buf.len()..];
+        self.slice = checkpoint.slice;
         Ok(())
     }
 }
 
-macro_rules! impl_read_nums {
-    ($ty:ty, $reader_method:ident) => {
-        #[inline]
-        pub fn $reader_method<E: ::byteorder::ByteOrder>(&mut self) -> Result<$ty> {
-            let size = ::core::mem::size_of::<$ty>();
-            if size > self.slice.len() {
-                return Err(ErrorKind::SizeLimit);
-            }
-            let value = E::$reader_method(&self.slice);
-            self.slice = &self.slice[size..];
-            Ok(value)
+#[cfg(feature = "std")]
+impl<R> IoReader<R> {
+    /// Constructs an IoReadReader
+    pub fn new(r: R) -> IoReader<R> {
+        IoReader {
+            reader: r,
+            temp_buffer: Vec::new(),
         }
     }
 }
 
-
-
 // impl<'storage> io::Read for SliceReader<'storage> {
 //     #[inline(always)]
 //     fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
@@ -93,34 +340,48 @@ macro_rules! impl_read_nums {
 //     }
 // }
 
-// impl<R: io::Read> io::Read for IoReader<R> {
-//     #[inline(always)]
-//     fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
-//         self.reader.read(out)
-//     }
-//     #[inline(always)]
-//     fn read_exact(&mut self, out: &mut [u8]) -> io::Result<()> {
-//         self.reader.read_exact(out)
-//     }
-// }
+#[cfg(feature = "std")]
+impl<R: ::std::io::Read> ::std::io::Read for IoReader<R> {
+    #[inline(always)]
+    fn read(&mut self, out: &mut [u8]) -> ::std::io::Result<usize> {
+        self.reader.read(out)
+    }
+    #[inline(always)]
+    fn read_exact(&mut self, out: &mut [u8]) -> ::std::io::Result<()> {
+        self.reader.read_exact(out)
+    }
+}
 
 impl<'storage> SliceReader<'storage> {
-
-    impl_read_nums!(u16, read_u16);
-    impl_read_nums!(u32, read_u32);
-    impl_read_nums!(u64, read_u64);
-    impl_read_nums!(i16, read_i16);
-    impl_read_nums!(i32, read_i32);
-    impl_read_nums!(i64, read_i64);
-    impl_read_nums!(f32, read_f32);
-    impl_read_nums!(f64, read_f64);
+    #[inline(always)]
+    fn unexpected_eof(&self, length: usize) -> ErrorKind {
+        ErrorKind::UnexpectedEof {
+            remaining: self.slice.len(),
+            budget: None,
+            needed: Some(length - self.slice.len()),
+        }
+    }
 
     #[inline(always)]
-    fn unexpected_eof() -> ErrorKind {
-        ErrorKind::SizeLimit
+    fn take(&mut self, length: usize) -> Result<&'storage [u8]> {
+        if length > self.slice.len() {
+            return Err(self.unexpected_eof(length));
+        }
+        let (taken, rest) = split_prefix(self.slice, length);
+        self.slice = rest;
+        Ok(taken)
     }
 }
 
+#[cfg(feature = "unchecked-slices")]
+use unsafe_ops::split_prefix;
+
+#[cfg(not(feature = "unchecked-slices"))]
+#[inline(always)]
+fn split_prefix(slice: &[u8], len: usize) -> (&[u8], &[u8]) {
+    slice.split_at(len)
+}
+
 impl<'storage> BincodeRead<'storage> for SliceReader<'storage> {
     #[inline(always)]
     fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
@@ -128,94 +389,1009 @@ impl<'storage> BincodeRead<'storage> for SliceReader<'storage> {
         V: serde::de::Visitor<'storage>,
     {
         use ErrorKind;
-        if length > self.slice.len() {
-            return Err(SliceReader::unexpected_eof());
-        }
-
-        let string = match ::core::str::from_utf8(&self.slice[..length]) {
+        let bytes = self.take(length)?;
+        let string = match ::core::str::from_utf8(bytes) {
             Ok(s) => s,
             Err(e) => return Err(ErrorKind::InvalidUtf8Encoding(e).into()),
         };
-        let r = visitor.visit_borrowed_str(string);
-        self.slice = &self.slice[length..];
-        r
+        visitor.visit_borrowed_str(string)
     }
 
-    // #[inline(always)]
-    // fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
-    //     if length > self.slice.len() {
-    //         return Err(SliceReader::unexpected_eof());
-    //     }
+    #[cfg(feature = "alloc")]
+    #[inline(always)]
+    fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
+        Ok(self.take(length)?.to_vec())
+    }
+
+    #[inline(always)]
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        let bytes = self.take(length)?;
+        visitor.visit_borrowed_bytes(bytes)
+    }
+
+    #[inline(always)]
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        let bytes = self.take(buf.len())?;
+        buf.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
 
-    //     let r = &self.slice[..length];
-    //     self.slice = &self.slice[length..];
-    //     Ok(r.to_vec())
-    // }
+    #[inline(always)]
+    fn skip_bytes(&mut self, n: usize) -> Result<()> {
+        self.take(n).map(|_| ())
+    }
 
     #[inline(always)]
+    fn remaining_hint(&self) -> Option<usize> {
+        Some(self.slice.len())
+    }
+
+    fn find_byte(&self, byte: u8, max: usize) -> Result<Option<usize>> {
+        Ok(self.slice.iter().take(max).position(|&b| b == byte))
+    }
+}
+
+/// A BincodeRead implementation for memory-mapped storage where every access
+/// is expensive, such as external flash behind wait states.
+///
+/// Primitives are copied out of a `LINE`-byte cache that is refilled one
+/// aligned line at a time, so a run of small reads costs a single bulk access
+/// per line. Strings and byte slices are still borrowed straight from the
+/// mapping.
+pub struct BlockCachedReader<'storage, const LINE: usize> {
+    mapping: &'storage [u8],
+    pos: usize,
+    line: [u8; LINE],
+    line_start: usize,
+    line_len: usize,
+    fetches: usize,
+}
+
+impl<'storage, const LINE: usize> BlockCachedReader<'storage, LINE> {
+    const NONZERO_LINE: () = assert!(LINE > 0, "cache line size must not be zero");
+
+    /// Constructs a reader over `mapping`. `LINE` must not be zero, which is
+    /// checked at compile time:
+    ///
+    /// ```compile_fail
+    /// let reader = bincode::BlockCachedReader::<0>::new(&[1, 2, 3]);
+    /// ```
+    pub fn new(mapping: &'storage [u8]) -> BlockCachedReader<'storage, LINE> {
+        let () = Self::NONZERO_LINE;
+        BlockCachedReader {
+            mapping,
+            pos: 0,
+            line: [0; LINE],
+            line_start: 0,
+            line_len: 0,
+            fetches: 0,
+        }
+    }
+
+    /// The number of bytes consumed so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// The number of bytes not consumed yet.
+    pub fn remaining(&self) -> usize {
+        self.mapping.len() - self.pos
+    }
+
+    /// The number of lines pulled into the cache so far.
+    pub fn line_fetches(&self) -> usize {
+        self.fetches
+    }
+
+    fn unexpected_eof(&self, length: usize) -> ErrorKind {
+        ErrorKind::UnexpectedEof {
+            remaining: self.remaining(),
+            budget: None,
+            needed: Some(length - self.remaining()),
+        }
+    }
+
+    fn fetch_line(&mut self) {
+        let start = self.pos - self.pos % LINE;
+        let len = ::core::cmp::min(LINE, self.mapping.len() - start);
+        self.line[..len].copy_from_slice(&self.mapping[start..start + len]);
+        self.line_start = start;
+        self.line_len = len;
+        self.fetches += 1;
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'storage [u8]> {
+        if length > self.remaining() {
+            return Err(self.unexpected_eof(length));
+        }
+        let bytes = &self.mapping[self.pos..self.pos + length];
+        self.pos += length;
+        Ok(bytes)
+    }
+}
+
+impl<'storage, const LINE: usize> BincodeRead<'storage> for BlockCachedReader<'storage, LINE> {
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        let bytes = self.take(length)?;
+        let string = match ::core::str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(e) => return Err(ErrorKind::InvalidUtf8Encoding(e)),
+        };
+        visitor.visit_borrowed_str(string)
+    }
+
     fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'storage>,
     {
-        if length > self.slice.len() {
-            return Err(SliceReader::unexpected_eof());
+        let bytes = self.take(length)?;
+        visitor.visit_borrowed_bytes(bytes)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        if buf.len() > self.remaining() {
+            return Err(self.unexpected_eof(buf.len()));
         }
+        let mut filled = 0;
+        while filled < buf.len() {
+            if self.pos < self.line_start || self.pos >= self.line_start + self.line_len {
+                self.fetch_line();
+            }
+            let offset = self.pos - self.line_start;
+            let n = ::core::cmp::min(self.line_len - offset, buf.len() - filled);
+            buf[filled..filled + n].copy_from_slice(&self.line[offset..offset + n]);
+            filled += n;
+            self.pos += n;
+        }
+        Ok(())
+    }
 
-        let r = visitor.visit_borrowed_bytes(&self.slice[..length]);
-        self.slice = &self.slice[length..];
-        r
+    fn remaining_hint(&self) -> Option<usize> {
+        Some(self.remaining())
     }
+
+    fn find_byte(&self, byte: u8, max: usize) -> Result<Option<usize>> {
+        Ok(self.mapping[self.pos..].iter().take(max).position(|&b| b == byte))
+    }
+}
+
+/// A BincodeRead implementation for input that is split in two, like the
+/// contents of a ring buffer that wrapped around.
+///
+/// Anything that lies entirely within one of the slices is borrowed from it.
+/// A string or byte slice that crosses from `head` into `tail` is copied into
+/// a 64-byte buffer on the stack instead, and visited as `visit_str` or
+/// `visit_bytes`. If it's longer than that, `SeamTooLong` is returned.
+pub struct ChainedSliceReader<'storage> {
+    head: &'storage [u8],
+    tail: &'storage [u8],
 }
 
-// impl<R> IoReader<R>
-// where
-//     R: io::Read,
-// {
-//     fn fill_buffer(&mut self, length: usize) -> Result<()> {
-//         let current_length = self.temp_buffer.len();
-//         if length > current_length {
-//             self.temp_buffer.reserve_exact(length - current_length);
-//         }
+const SEAM_SCRATCH: usize = 64;
 
-//         unsafe {
-//             self.temp_buffer.set_len(length);
-//         }
+impl<'storage> ChainedSliceReader<'storage> {
+    /// Constructs a reader over `head` followed by `tail`.
+    pub fn new(head: &'storage [u8], tail: &'storage [u8]) -> ChainedSliceReader<'storage> {
+        let mut reader = ChainedSliceReader { head, tail };
+        reader.advance(0);
+        reader
+    }
 
-//         self.reader.read_exact(&mut self.temp_buffer)?;
-//         Ok(())
-//     }
-// }
+    /// The number of bytes not consumed yet.
+    pub fn remaining(&self) -> usize {
+        self.head.len() + self.tail.len()
+    }
 
-// impl<R> BincodeRead<'static> for IoReader<R>
-// where
-//     R: io::Read,
-// {
-//     fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
-//     where
-//         V: serde::de::Visitor<'static>,
-//     {
-//         self.fill_buffer(length)?;
-
-//         let string = match ::std::str::from_utf8(&self.temp_buffer[..]) {
-//             Ok(s) => s,
-//             Err(e) => return Err(::ErrorKind::InvalidUtf8Encoding(e).into()),
-//         };
-
-//         let r = visitor.visit_str(string);
-//         r
-//     }
+    fn unexpected_eof(&self, length: usize) -> ErrorKind {
+        ErrorKind::UnexpectedEof {
+            remaining: self.remaining(),
+            budget: None,
+            needed: Some(length - self.remaining()),
+        }
+    }
 
-//     fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
-//         self.fill_buffer(length)?;
-//         Ok(::std::mem::replace(&mut self.temp_buffer, Vec::new()))
-//     }
+    /// Consumes `length` bytes of `head`, moving on to `tail` once it's used up.
+    fn advance(&mut self, length: usize) {
+        self.head = &self.head[length..];
+        if self.head.is_empty() {
+            self.head = ::core::mem::take(&mut self.tail);
+        }
+    }
 
-//     fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
-//     where
-//         V: serde::de::Visitor<'static>,
-//     {
-//         self.fill_buffer(length)?;
-//         let r = visitor.visit_bytes(&self.temp_buffer[..]);
-//         r
-//     }
-// }
+    /// Returns the next `length` bytes if they don't cross the seam.
+    fn take_contiguous(&mut self, length: usize) -> Result<Option<&'storage [u8]>> {
+        if length > self.remaining() {
+            return Err(self.unexpected_eof(length));
+        }
+        if length > self.head.len() {
+            if length > SEAM_SCRATCH {
+                return Err(ErrorKind::SeamTooLong {
+                    len: length,
+                    max: SEAM_SCRATCH,
+                });
+            }
+            return Ok(None);
+        }
+        let bytes = &self.head[..length];
+        self.advance(length);
+        Ok(Some(bytes))
+    }
+}
+
+impl<'storage> BincodeRead<'storage> for ChainedSliceReader<'storage> {
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        if let Some(bytes) = self.take_contiguous(length)? {
+            return match ::core::str::from_utf8(bytes) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(e) => Err(ErrorKind::InvalidUtf8Encoding(e)),
+            };
+        }
+        let mut scratch = [0; SEAM_SCRATCH];
+        self.read_exact(&mut scratch[..length])?;
+        match ::core::str::from_utf8(&scratch[..length]) {
+            Ok(s) => visitor.visit_str(s),
+            Err(e) => Err(ErrorKind::InvalidUtf8Encoding(e)),
+        }
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        if let Some(bytes) = self.take_contiguous(length)? {
+            return visitor.visit_borrowed_bytes(bytes);
+        }
+        let mut scratch = [0; SEAM_SCRATCH];
+        self.read_exact(&mut scratch[..length])?;
+        visitor.visit_bytes(&scratch[..length])
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        if buf.len() > self.remaining() {
+            return Err(self.unexpected_eof(buf.len()));
+        }
+        let n = ::core::cmp::min(self.head.len(), buf.len());
+        buf[..n].copy_from_slice(&self.head[..n]);
+        self.advance(n);
+        let rest = buf.len() - n;
+        buf[n..].copy_from_slice(&self.head[..rest]);
+        self.advance(rest);
+        Ok(())
+    }
+
+    fn remaining_hint(&self) -> Option<usize> {
+        Some(self.remaining())
+    }
+
+    fn find_byte(&self, byte: u8, max: usize) -> Result<Option<usize>> {
+        Ok(self.head.iter().chain(self.tail).take(max).position(|&b| b == byte))
+    }
+}
+
+/// A BincodeRead implementation that pulls its input through a callback, for
+/// sources that hand out a few bytes at a time like a UART FIFO or a
+/// decompressor.
+///
+/// `fill` is asked to write into the free part of the caller's `scratch`
+/// buffer and return how many bytes it wrote. Returning `0` means the input
+/// has ended, and returning more than the length of the buffer it was given
+/// fails with `FillOverrun`. `fill` may supply more than the value needs. Whatever is left
+/// over stays in `buffered`.
+///
+/// Strings and byte slices have to fit in `scratch` as a whole, otherwise
+/// `ScratchTooSmall` is returned and the reader is left as it was. They are
+/// visited as `visit_str` and `visit_bytes`, so types that borrow from the
+/// input, like `&str`, can't be deserialized from it. `NulStr` only finds
+/// its terminator among the bytes already buffered.
+pub struct ScratchReader<'scratch, F> {
+    fill: F,
+    scratch: &'scratch mut [u8],
+    pos: usize,
+    end: usize,
+}
+
+impl<'scratch, F: FnMut(&mut [u8]) -> Result<usize>> ScratchReader<'scratch, F> {
+    /// Constructs a reader that buffers `fill`'s output in `scratch`, which must not
+    /// be empty.
+    pub fn new(scratch: &'scratch mut [u8], fill: F) -> ScratchReader<'scratch, F> {
+        assert!(!scratch.is_empty(), "scratch buffer must not be empty");
+        ScratchReader {
+            fill,
+            scratch,
+            pos: 0,
+            end: 0,
+        }
+    }
+
+    /// The bytes buffered but not consumed yet.
+    pub fn buffered(&self) -> &[u8] {
+        &self.scratch[self.pos..self.end]
+    }
+
+    /// Buffers more input, of which `missing` more bytes are needed.
+    fn pull(&mut self, missing: usize) -> Result<()> {
+        let room = self.scratch.len() - self.end;
+        let n = (self.fill)(&mut self.scratch[self.end..])?;
+        if n > room {
+            return Err(ErrorKind::FillOverrun { written: n, room });
+        }
+        if n == 0 {
+            return Err(ErrorKind::UnexpectedEof {
+                remaining: self.end - self.pos,
+                budget: None,
+                needed: Some(missing),
+            });
+        }
+        self.end += n;
+        Ok(())
+    }
+
+    /// Makes sure the next `length` bytes are buffered, in one piece.
+    fn fill_to(&mut self, length: usize) -> Result<&[u8]> {
+        if length > self.scratch.len() {
+            return Err(ErrorKind::ScratchTooSmall {
+                len: length,
+                max: self.scratch.len(),
+            });
+        }
+        if self.pos + length > self.scratch.len() {
+            self.scratch.copy_within(self.pos..self.end, 0);
+            self.end -= self.pos;
+            self.pos = 0;
+        }
+        while self.end - self.pos < length {
+            let missing = length - (self.end - self.pos);
+            self.pull(missing)?;
+        }
+        let bytes = &self.scratch[self.pos..self.pos + length];
+        self.pos += length;
+        Ok(bytes)
+    }
+}
+
+impl<'scratch, F: FnMut(&mut [u8]) -> Result<usize>> BincodeRead<'static> for ScratchReader<'scratch, F> {
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        match ::core::str::from_utf8(self.fill_to(length)?) {
+            Ok(s) => visitor.visit_str(s),
+            Err(e) => Err(ErrorKind::InvalidUtf8Encoding(e)),
+        }
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        visitor.visit_bytes(self.fill_to(length)?)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            if self.pos == self.end {
+                self.pos = 0;
+                self.end = 0;
+                self.pull(buf.len() - filled)?;
+            }
+            let n = ::core::cmp::min(self.end - self.pos, buf.len() - filled);
+            buf[filled..filled + n].copy_from_slice(&self.scratch[self.pos..self.pos + n]);
+            filled += n;
+            self.pos += n;
+        }
+        Ok(())
+    }
+
+    fn find_byte(&self, byte: u8, max: usize) -> Result<Option<usize>> {
+        Ok(self.buffered().iter().take(max).position(|&b| b == byte))
+    }
+}
+
+/// Wraps another reader and keeps a CRC-32 (IEEE) of every byte read through
+/// it, including strings and byte slices handed to the visitor.
+pub struct CrcReader<R> {
+    inner: R,
+    crc: u32,
+}
+
+impl<R> CrcReader<R> {
+    /// Wraps `inner`.
+    pub fn new(inner: R) -> CrcReader<R> {
+        CrcReader { inner, crc: 0 }
+    }
+
+    /// The checksum of everything read so far.
+    pub fn crc(&self) -> u32 {
+        self.crc
+    }
+
+    /// Unwraps the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Adds whatever the reader hands over to the checksum before passing it on.
+struct CrcVisitor<'a, V> {
+    visitor: V,
+    crc: &'a mut u32,
+}
+
+impl<'a, 'de, V: serde::de::Visitor<'de>> serde::de::Visitor<'de> for CrcVisitor<'a, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        self.visitor.expecting(formatter)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> ::core::result::Result<V::Value, E> {
+        *self.crc = ::flavors::crc32(*self.crc, v.as_bytes());
+        self.visitor.visit_str(v)
+    }
+
+    fn visit_borrowed_str<E: serde::de::Error>(self, v: &'de str) -> ::core::result::Result<V::Value, E> {
+        *self.crc = ::flavors::crc32(*self.crc, v.as_bytes());
+        self.visitor.visit_borrowed_str(v)
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> ::core::result::Result<V::Value, E> {
+        *self.crc = ::flavors::crc32(*self.crc, v);
+        self.visitor.visit_bytes(v)
+    }
+
+    fn visit_borrowed_bytes<E: serde::de::Error>(self, v: &'de [u8]) -> ::core::result::Result<V::Value, E> {
+        *self.crc = ::flavors::crc32(*self.crc, v);
+        self.visitor.visit_borrowed_bytes(v)
+    }
+}
+
+impl<'storage, R: BincodeRead<'storage>> BincodeRead<'storage> for CrcReader<R> {
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        let crc = &mut self.crc;
+        self.inner.forward_read_str(length, CrcVisitor { visitor, crc })
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        let crc = &mut self.crc;
+        self.inner.forward_read_bytes(length, CrcVisitor { visitor, crc })
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.inner.read_exact(buf)?;
+        self.crc = ::flavors::crc32(self.crc, buf);
+        Ok(())
+    }
+
+    fn remaining_hint(&self) -> Option<usize> {
+        self.inner.remaining_hint()
+    }
+
+    fn find_byte(&self, byte: u8, max: usize) -> Result<Option<usize>> {
+        self.inner.find_byte(byte, max)
+    }
+}
+
+/// One of the built-in readers, chosen at runtime.
+///
+/// `BincodeRead` has generic methods and can't be used as a trait object, so
+/// code that has to accept more than one kind of reader without becoming
+/// generic itself takes a `DynReader` and calls `deserialize_dyn`.
+///
+/// Dispatch costs one `match` per read: every primitive, length prefix,
+/// string and byte slice branches on the variant before going to the
+/// wrapped reader. The visitor side is unaffected.
+pub enum DynReader<'storage> {
+    /// A contiguous slice.
+    Slice(SliceReader<'storage>),
+    /// Memory-mapped storage read through 64-byte cache lines.
+    BlockCached(BlockCachedReader<'storage, 64>),
+    /// Two slices read back to back, copying up to 64 bytes across the seam.
+    Chained(ChainedSliceReader<'storage>),
+}
+
+impl<'storage> DynReader<'storage> {
+    /// The number of bytes not consumed yet.
+    pub fn remaining(&self) -> usize {
+        match *self {
+            DynReader::Slice(ref reader) => reader.remaining(),
+            DynReader::BlockCached(ref reader) => reader.remaining(),
+            DynReader::Chained(ref reader) => reader.remaining(),
+        }
+    }
+}
+
+impl<'storage> BincodeRead<'storage> for DynReader<'storage> {
+    #[inline]
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        match *self {
+            DynReader::Slice(ref mut reader) => reader.forward_read_str(length, visitor),
+            DynReader::BlockCached(ref mut reader) => reader.forward_read_str(length, visitor),
+            DynReader::Chained(ref mut reader) => reader.forward_read_str(length, visitor),
+        }
+    }
+
+    #[inline]
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        match *self {
+            DynReader::Slice(ref mut reader) => reader.forward_read_bytes(length, visitor),
+            DynReader::BlockCached(ref mut reader) => reader.forward_read_bytes(length, visitor),
+            DynReader::Chained(ref mut reader) => reader.forward_read_bytes(length, visitor),
+        }
+    }
+
+    #[inline]
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        match *self {
+            DynReader::Slice(ref mut reader) => reader.read_exact(buf),
+            DynReader::BlockCached(ref mut reader) => reader.read_exact(buf),
+            DynReader::Chained(ref mut reader) => reader.read_exact(buf),
+        }
+    }
+
+    #[inline]
+    fn remaining_hint(&self) -> Option<usize> {
+        Some(self.remaining())
+    }
+
+    #[inline]
+    fn find_byte(&self, byte: u8, max: usize) -> Result<Option<usize>> {
+        match *self {
+            DynReader::Slice(ref reader) => reader.find_byte(byte, max),
+            DynReader::BlockCached(ref reader) => reader.find_byte(byte, max),
+            DynReader::Chained(ref reader) => reader.find_byte(byte, max),
+        }
+    }
+}
+
+/// The bytes `ObjectRead::read_chunk` hands to its callback.
+pub enum Chunk<'storage, 'a> {
+    /// Borrowed from the input, so they can outlive the read.
+    Borrowed(&'storage [u8]),
+    /// Only valid until the callback returns.
+    Transient(&'a [u8]),
+}
+
+/// An object-safe counterpart of `BincodeRead`, for readers picked at runtime
+/// and stored as `&mut dyn ObjectRead<'storage>`.
+///
+/// Every `BincodeRead` implements it, and `dyn ObjectRead` implements
+/// `BincodeRead` in turn, so a `&mut dyn ObjectRead` can be passed to
+/// `deserialize_from_custom`. Only reads through the trait object pay for the
+/// indirect calls; concrete readers are used as before.
+pub trait ObjectRead<'storage> {
+    /// Reads `length` bytes and calls `sink` with them exactly once.
+    fn read_chunk(
+        &mut self,
+        length: usize,
+        sink: &mut dyn for<'a> FnMut(Chunk<'storage, 'a>) -> Result<()>,
+    ) -> Result<()>;
+
+    /// Fills `buf` with the next `buf.len()` bytes.
+    fn read_into(&mut self, buf: &mut [u8]) -> Result<()>;
+
+    /// See `BincodeRead::remaining_hint`.
+    fn size_hint(&self) -> Option<usize>;
+
+    /// See `BincodeRead::find_byte`.
+    fn scan_for(&self, byte: u8, max: usize) -> Result<Option<usize>>;
+}
+
+impl<'storage, R: BincodeRead<'storage>> ObjectRead<'storage> for R {
+    fn read_chunk(
+        &mut self,
+        length: usize,
+        sink: &mut dyn for<'a> FnMut(Chunk<'storage, 'a>) -> Result<()>,
+    ) -> Result<()> {
+        struct ChunkVisitor<'s, 'f> {
+            sink: &'f mut dyn for<'a> FnMut(Chunk<'s, 'a>) -> Result<()>,
+        }
+
+        impl<'s, 'f> serde::de::Visitor<'s> for ChunkVisitor<'s, 'f> {
+            type Value = Result<()>;
+
+            fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                formatter.write_str("bytes")
+            }
+
+            fn visit_borrowed_bytes<E: serde::de::Error>(self, v: &'s [u8]) -> ::core::result::Result<Result<()>, E> {
+                Ok((self.sink)(Chunk::Borrowed(v)))
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> ::core::result::Result<Result<()>, E> {
+                Ok((self.sink)(Chunk::Transient(v)))
+            }
+        }
+
+        self.forward_read_bytes(length, ChunkVisitor { sink })?
+    }
+
+    #[inline]
+    fn read_into(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.read_exact(buf)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        self.remaining_hint()
+    }
+
+    #[inline]
+    fn scan_for(&self, byte: u8, max: usize) -> Result<Option<usize>> {
+        self.find_byte(byte, max)
+    }
+}
+
+impl<'storage, 'r> dyn ObjectRead<'storage> + 'r {
+    /// Runs `visit` on the next `length` bytes, passing back what it returns.
+    fn visit_chunk<V, F>(&mut self, length: usize, visitor: V, mut visit: F) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+        F: FnMut(V, Chunk<'storage, '_>) -> Result<V::Value>,
+    {
+        let mut visitor = Some(visitor);
+        let mut value = None;
+        self.read_chunk(length, &mut |chunk| {
+            if let Some(visitor) = visitor.take() {
+                value = Some(visit(visitor, chunk)?);
+            }
+            Ok(())
+        })?;
+        // Only reachable if `read_chunk` never called back.
+        value.ok_or(ErrorKind::Serde)
+    }
+}
+
+impl<'storage, 'r> BincodeRead<'storage> for dyn ObjectRead<'storage> + 'r {
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        self.visit_chunk(length, visitor, |visitor, chunk| match chunk {
+            Chunk::Borrowed(bytes) => match ::core::str::from_utf8(bytes) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(e) => Err(ErrorKind::InvalidUtf8Encoding(e)),
+            },
+            Chunk::Transient(bytes) => match ::core::str::from_utf8(bytes) {
+                Ok(s) => visitor.visit_str(s),
+                Err(e) => Err(ErrorKind::InvalidUtf8Encoding(e)),
+            },
+        })
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'storage>,
+    {
+        self.visit_chunk(length, visitor, |visitor, chunk| match chunk {
+            Chunk::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Chunk::Transient(bytes) => visitor.visit_bytes(bytes),
+        })
+    }
+
+    #[inline]
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.read_into(buf)
+    }
+
+    #[inline]
+    fn remaining_hint(&self) -> Option<usize> {
+        self.size_hint()
+    }
+
+    #[inline]
+    fn find_byte(&self, byte: u8, max: usize) -> Result<Option<usize>> {
+        self.scan_for(byte, max)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> IoReader<R>
+where
+    R: ::std::io::Read,
+{
+    fn fill_buffer(&mut self, length: usize) -> Result<()> {
+        // `length` comes from the input, so the buffer only grows as bytes
+        // actually arrive, at most doubling at a time. A bogus length fails
+        // with `UnexpectedEof` instead of allocating all of it up front.
+        self.temp_buffer.clear();
+        let mut filled = 0;
+        while filled < length {
+            if filled == self.temp_buffer.len() {
+                let grow = ::core::cmp::min(length - filled, ::core::cmp::max(IO_CHUNK, filled));
+                self.temp_buffer.resize(filled + grow, 0);
+            }
+            filled += read_some(&mut self.reader, &mut self.temp_buffer[filled..], filled, length)?;
+        }
+        Ok(())
+    }
+}
+
+/// How much `IoReader` reads at first when filling its scratch buffer.
+#[cfg(feature = "std")]
+const IO_CHUNK: usize = 4096;
+
+/// Reads at least one byte into `buf`, retrying on `Interrupted`, for a read
+/// of `length` bytes of which `filled` have arrived.
+#[cfg(feature = "std")]
+fn read_some<R: ::std::io::Read>(reader: &mut R, buf: &mut [u8], filled: usize, length: usize) -> Result<usize> {
+    loop {
+        match reader.read(buf) {
+            Ok(0) => {
+                return Err(ErrorKind::UnexpectedEof {
+                    remaining: filled,
+                    budget: None,
+                    needed: Some(length - filled),
+                })
+            }
+            Ok(n) => return Ok(n),
+            Err(ref e) if e.kind() == ::std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Strings and byte slices are read into a scratch buffer that is reused
+/// between reads, so they are only ever visited as `visit_str` and
+/// `visit_bytes`. Types that borrow from the input, like `&str`, can't be
+/// deserialized from it.
+#[cfg(feature = "std")]
+impl<R> BincodeRead<'static> for IoReader<R>
+where
+    R: ::std::io::Read,
+{
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.fill_buffer(length)?;
+
+        let string = match ::core::str::from_utf8(&self.temp_buffer[..]) {
+            Ok(s) => s,
+            Err(e) => return Err(ErrorKind::InvalidUtf8Encoding(e)),
+        };
+
+        visitor.visit_str(string)
+    }
+
+    fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
+        self.fill_buffer(length)?;
+        Ok(::core::mem::take(&mut self.temp_buffer))
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.fill_buffer(length)?;
+        visitor.visit_bytes(&self.temp_buffer[..])
+    }
+
+    #[inline(always)]
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let length = buf.len();
+            filled += read_some(&mut self.reader, &mut buf[filled..], filled, length)?;
+        }
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<()> {
+        let mut probe = [0; 1];
+        loop {
+            match self.reader.read(&mut probe) {
+                Ok(0) => return Ok(()),
+                Ok(_) => return Err(ErrorKind::TrailingBytes(1)),
+                Err(ref e) if e.kind() == ::std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// A BincodeRead implementation for `bytes::Buf`s, like a chain of `Bytes`
+/// chunks handed out by a network stack.
+///
+/// Strings and byte slices that lie within the current chunk are visited
+/// straight from it. Ones that span several chunks are first gathered with
+/// `Buf::copy_to_bytes`. Either way they are only ever visited as `visit_str`
+/// and `visit_bytes`, so types that borrow from the input, like `&str`, can't
+/// be deserialized from it.
+///
+/// `NulStr` only finds its terminator if it's in the current chunk.
+#[cfg(feature = "bytes")]
+pub struct BufReader<B> {
+    buf: B,
+}
+
+#[cfg(feature = "bytes")]
+impl<B: ::bytes::Buf> BufReader<B> {
+    /// Constructs a reader over `buf`.
+    pub fn new(buf: B) -> BufReader<B> {
+        BufReader { buf }
+    }
+
+    /// Unwraps the `Buf`, with whatever wasn't consumed still in it.
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
+
+    fn unexpected_eof(&self, length: usize) -> ErrorKind {
+        ErrorKind::UnexpectedEof {
+            remaining: self.buf.remaining(),
+            budget: None,
+            needed: Some(length - self.buf.remaining()),
+        }
+    }
+
+    fn check_remaining(&self, length: usize) -> Result<()> {
+        if length > self.buf.remaining() {
+            return Err(self.unexpected_eof(length));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<B: ::bytes::Buf> BincodeRead<'static> for BufReader<B> {
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.check_remaining(length)?;
+        let chunk = self.buf.chunk();
+        if chunk.len() >= length {
+            let value = match ::core::str::from_utf8(&chunk[..length]) {
+                Ok(s) => visitor.visit_str(s),
+                Err(e) => Err(ErrorKind::InvalidUtf8Encoding(e)),
+            };
+            self.buf.advance(length);
+            return value;
+        }
+        let bytes = self.buf.copy_to_bytes(length);
+        match ::core::str::from_utf8(&bytes) {
+            Ok(s) => visitor.visit_str(s),
+            Err(e) => Err(ErrorKind::InvalidUtf8Encoding(e)),
+        }
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.check_remaining(length)?;
+        if self.buf.chunk().len() >= length {
+            let value = visitor.visit_bytes(&self.buf.chunk()[..length]);
+            self.buf.advance(length);
+            return value;
+        }
+        let bytes = self.buf.copy_to_bytes(length);
+        visitor.visit_bytes(&bytes)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.check_remaining(buf.len())?;
+        self.buf.copy_to_slice(buf);
+        Ok(())
+    }
+
+    fn remaining_hint(&self) -> Option<usize> {
+        Some(self.buf.remaining())
+    }
+
+    fn find_byte(&self, byte: u8, max: usize) -> Result<Option<usize>> {
+        Ok(self.buf.chunk().iter().take(max).position(|&b| b == byte))
+    }
+}
+
+/// A BincodeRead implementation for `embedded_io::Read`ers, e.g. a flash
+/// driver, that reads only as much as the value needs.
+///
+/// Strings and byte slices are read into a fixed scratch buffer of type `A`
+/// and visited as `visit_str` and `visit_bytes`, so types that borrow from
+/// the input, like `&str`, can't be deserialized from it. One that's longer
+/// than the scratch buffer is reported as `ScratchTooSmall`.
+///
+/// A driver error is returned as `ErrorKind::EmbeddedIo` with its kind, and
+/// the input ending early as `UnexpectedEof`.
+#[cfg(feature = "embedded-io")]
+pub struct EmbeddedIoReader<R, A: Array<Item = u8>> {
+    reader: R,
+    scratch: ArrayVec<A>,
+}
+
+#[cfg(feature = "embedded-io")]
+impl<R: ::embedded_io::Read, A: Array<Item = u8>> EmbeddedIoReader<R, A> {
+    /// Wraps `reader`.
+    pub fn new(reader: R) -> EmbeddedIoReader<R, A> {
+        EmbeddedIoReader {
+            reader,
+            scratch: ArrayVec::new(),
+        }
+    }
+
+    /// Unwraps the reader, positioned right after the last byte read.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn fill_scratch(&mut self, length: usize) -> Result<()> {
+        if length > self.scratch.capacity() {
+            return Err(ErrorKind::ScratchTooSmall {
+                len: length,
+                max: self.scratch.capacity(),
+            });
+        }
+        self.scratch.clear();
+        self.scratch.extend(::core::iter::repeat_n(0, length));
+        read_exact_embedded(&mut self.reader, &mut self.scratch)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+fn read_exact_embedded<R: ::embedded_io::Read>(reader: &mut R, buf: &mut [u8]) -> Result<()> {
+    use embedded_io::ReadExactError;
+
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(()),
+        Err(ReadExactError::UnexpectedEof) => Err(ErrorKind::UnexpectedEof {
+            remaining: 0,
+            budget: None,
+            needed: None,
+        }),
+        Err(ReadExactError::Other(e)) => Err(::flavors::embedded_io_error(e)),
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<R: ::embedded_io::Read, A: Array<Item = u8>> BincodeRead<'static> for EmbeddedIoReader<R, A> {
+    fn forward_read_str<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.fill_scratch(length)?;
+        match ::core::str::from_utf8(&self.scratch) {
+            Ok(s) => visitor.visit_str(s),
+            Err(e) => Err(ErrorKind::InvalidUtf8Encoding(e)),
+        }
+    }
+
+    fn forward_read_bytes<V>(&mut self, length: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'static>,
+    {
+        self.fill_scratch(length)?;
+        visitor.visit_bytes(&self.scratch)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        read_exact_embedded(&mut self.reader, buf)
+    }
+}
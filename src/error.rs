@@ -1,6 +1,8 @@
 use arrayvec::CapacityError;
 use core::fmt;
 use core::str::Utf8Error;
+#[cfg(feature = "std")]
+use std::io;
 
 use serde;
 
@@ -13,10 +15,28 @@ pub type Error = ErrorKind;
 /// The kind of error that can be produced during a serialization or deserialization.
 #[derive(Debug)]
 pub enum ErrorKind {
-    // /// If the error stems from the reader/writer that is being used
-    // /// during (de)serialization, that error will be stored and returned here.
-    // Io(io::Error),
+    /// If the error stems from the reader/writer that is being used
+    /// during (de)serialization, that error will be stored and returned here.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// Returned if an `embedded_io::Write`r fails, with the kind of error it reported.
+    #[cfg(feature = "embedded-io")]
+    EmbeddedIo(::embedded_io::ErrorKind),
     Fmt(fmt::Error),
+    /// Returned if the output buffer is too small for the serialized value.
+    /// For `serialize_into_slice`, whatever was written to the slice before
+    /// running out is garbage; `serialize_into` and `serialize_into_checked`
+    /// leave the `ArrayVec` as it was.
+    BufferTooSmall {
+        /// The number of bytes the value serializes to.
+        needed: u64,
+        /// The room left in the output buffer.
+        available: usize,
+        /// The number of bytes written before running out of room, whether or
+        /// not they were taken back out again. Zero if the value was measured
+        /// up front and nothing was written.
+        written: usize,
+    },
     /// Returned if the deserializer attempts to deserialize a string that is not valid utf8
     InvalidUtf8Encoding(Utf8Error),
     /// Returned if the deserializer attempts to deserialize a bool that was
@@ -27,18 +47,264 @@ pub enum ErrorKind {
     /// Returned if the deserializer attempts to deserialize the tag of an enum that is
     /// not in the expected ranges
     InvalidTagEncoding(usize),
+    /// Returned if a varint length prefix takes more bytes than it needs to,
+    /// or doesn't fit into a `u64`, see `LengthEncoding::Varint`.
+    InvalidVarintEncoding,
     /// Serde has a deserialize_any method that lets the format hint to the
     /// object which route to take in deserializing.
     DeserializeAnyNotSupported,
     /// If (de)serializing a message takes more than the provided size limit, this
-    /// error is returned.
-    SizeLimit,
-    /// Bincode can not encode sequences of unknown length (like iterators).
+    /// error is returned, along with which of the two it was.
+    SizeLimit(Direction),
+    /// Returned if the input ends before the value does. When the input was
+    /// also too short for the size limit, this is reported instead of `SizeLimit`.
+    UnexpectedEof {
+        /// The number of bytes left in the input.
+        remaining: usize,
+        /// What was left of the size limit, if one was configured and known.
+        budget: Option<u64>,
+        /// How many more bytes the read that failed needed, if known. Later
+        /// reads may need more on top of that.
+        needed: Option<usize>,
+    },
+    /// Returned if a string or byte slice crossing the seam of a
+    /// `ChainedSliceReader` is too long to be copied into its scratch buffer.
+    SeamTooLong {
+        /// The length of the string or byte slice.
+        len: usize,
+        /// The most that can be copied.
+        max: usize,
+    },
+    /// Returned if a string or byte slice is too long for the scratch buffer
+    /// of an `EmbeddedIoReader` or `ScratchReader`, or a frame is too long for
+    /// the buffer of a `StreamDeserializer`.
+    ScratchTooSmall {
+        /// The length of the string, byte slice or frame.
+        len: usize,
+        /// The capacity of the scratch buffer, or the room for a frame.
+        max: usize,
+    },
+    /// Returned if no NUL terminator was found within the maximum length of
+    /// a `NulStr`.
+    MissingNulTerminator {
+        /// The longest string that was allowed.
+        max: usize,
+    },
+    /// Bincode can only encode sequences and maps of unknown length (like
+    /// iterators) into outputs that can fill in the length afterwards, see
+    /// `OutputFlavor::rewrite`.
     SequenceMustHaveLength,
+    /// Returned if a `u128` or `i128` is (de)serialized without the `i128`
+    /// feature enabled.
+    U128Unsupported,
     // /// A custom error message from Serde.
     // Custom(String),
     CapacityError(CapacityError<u8>),
     Serde,
+    /// Returned if a length is too large for the configured `LengthEncoding`.
+    LengthOverflow {
+        /// The length that was to be encoded.
+        len: u64,
+        /// The largest length the configured encoding can represent.
+        max: u64,
+    },
+    /// Returned if bytes were left over after deserializing a value
+    /// that was expected to span the whole input.
+    TrailingBytes(usize),
+    /// Returned if the padding after a fixed-size record holds anything other
+    /// than the configured pad byte.
+    DirtyPadding {
+        /// The offset into the slot of the first unexpected byte.
+        offset: usize,
+        /// The byte found there.
+        found: u8,
+    },
+    /// Returned if an input could be deserialized using more than one of the
+    /// candidate configurations.
+    AmbiguousEncoding,
+    /// Returned if the callback of a `Chunked` flavor failed to send a packet.
+    FlushFailed,
+    /// Returned if a COBS-encoded frame is malformed.
+    InvalidCobsEncoding,
+    /// Returned if the length prefix of a `frame` doesn't match the size of
+    /// the value behind it, along with the length the prefix claimed.
+    InvalidFrameLength(u32),
+    /// Returned if a frame doesn't start with the magic number it was
+    /// expected to, see `frame::decode_with_magic`.
+    MagicMismatch {
+        /// The magic number that was expected.
+        expected: [u8; 4],
+        /// The bytes found in its place.
+        found: [u8; 4],
+    },
+    /// Returned if a patched value doesn't encode to exactly as many bytes as
+    /// the value it was to replace.
+    PatchLengthMismatch {
+        /// The length of the value in the buffer.
+        region: usize,
+        /// The length of the new value.
+        encoded: u64,
+    },
+    /// Returned if the checksum stored with the data does not match the
+    /// checksum computed over it.
+    ChecksumMismatch {
+        /// The checksum stored with the data.
+        expected: u32,
+        /// The checksum computed over the data.
+        actual: u32,
+    },
+    /// Returned if the callback of a `ScratchReader` claims to have written
+    /// more bytes than there was room for.
+    FillOverrun {
+        /// The number of bytes the callback claimed to have written.
+        written: usize,
+        /// The room it was given.
+        room: usize,
+    },
+    /// Returned if a `SliceReader` is rolled back to a `Checkpoint` taken on
+    /// a different input.
+    ForeignCheckpoint,
+    /// Returned if a `NulStr` is read from a reader that can't look ahead for
+    /// its terminator, see `BincodeRead::find_byte`.
+    LookAheadUnsupported,
+    /// Returned if an `Option` is (de)serialized with the same tag configured
+    /// for `None` and `Some`, see `Config::none_tag`.
+    EqualOptionTags,
+    /// Returned if an input is to be matched against an empty list of
+    /// candidate configurations.
+    NoCandidates,
+}
+
+impl ErrorKind {
+    /// A number identifying the kind of error, for logging it without
+    /// formatting anything. Codes are stable; new kinds of errors get new
+    /// codes and `0` is never used, so it can stand for success.
+    ///
+    /// | Code | Error |
+    /// |------|-------|
+    /// | 1 | `Io` |
+    /// | 2 | `EmbeddedIo` |
+    /// | 3 | `Fmt` |
+    /// | 4 | `BufferTooSmall` |
+    /// | 5 | `InvalidUtf8Encoding`, an invalid byte sequence |
+    /// | 6 | `InvalidUtf8Encoding`, a sequence cut off at the end |
+    /// | 7 | `InvalidBoolEncoding` |
+    /// | 8 | `InvalidCharEncoding` |
+    /// | 9 | `InvalidTagEncoding` |
+    /// | 10 | `DeserializeAnyNotSupported` |
+    /// | 11 | `SizeLimit(Direction::Serialize)` |
+    /// | 12 | `SizeLimit(Direction::Deserialize)` |
+    /// | 13 | `UnexpectedEof` |
+    /// | 14 | `SeamTooLong` |
+    /// | 15 | `ScratchTooSmall` |
+    /// | 16 | `MissingNulTerminator` |
+    /// | 17 | `SequenceMustHaveLength` |
+    /// | 18 | `U128Unsupported` |
+    /// | 19 | `CapacityError` |
+    /// | 20 | `Serde` |
+    /// | 21 | `LengthOverflow` |
+    /// | 22 | `TrailingBytes` |
+    /// | 23 | `DirtyPadding` |
+    /// | 24 | `AmbiguousEncoding` |
+    /// | 25 | `FlushFailed` |
+    /// | 26 | `InvalidCobsEncoding` |
+    /// | 27 | `InvalidFrameLength` |
+    /// | 28 | `MagicMismatch` |
+    /// | 29 | `PatchLengthMismatch` |
+    /// | 30 | `ChecksumMismatch` |
+    /// | 31 | `InvalidVarintEncoding` |
+    /// | 32 | `FillOverrun` |
+    /// | 33 | `ForeignCheckpoint` |
+    /// | 34 | `LookAheadUnsupported` |
+    /// | 35 | `EqualOptionTags` |
+    /// | 36 | `NoCandidates` |
+    pub fn code(&self) -> u8 {
+        match self {
+            #[cfg(feature = "std")]
+            ErrorKind::Io(_) => 1,
+            #[cfg(feature = "embedded-io")]
+            ErrorKind::EmbeddedIo(_) => 2,
+            ErrorKind::Fmt(_) => 3,
+            ErrorKind::BufferTooSmall { .. } => 4,
+            ErrorKind::InvalidUtf8Encoding(e) => match e.error_len() {
+                Some(_) => 5,
+                None => 6,
+            },
+            ErrorKind::InvalidBoolEncoding(_) => 7,
+            ErrorKind::InvalidCharEncoding => 8,
+            ErrorKind::InvalidTagEncoding(_) => 9,
+            ErrorKind::DeserializeAnyNotSupported => 10,
+            ErrorKind::SizeLimit(Direction::Serialize) => 11,
+            ErrorKind::SizeLimit(Direction::Deserialize) => 12,
+            ErrorKind::UnexpectedEof { .. } => 13,
+            ErrorKind::SeamTooLong { .. } => 14,
+            ErrorKind::ScratchTooSmall { .. } => 15,
+            ErrorKind::MissingNulTerminator { .. } => 16,
+            ErrorKind::SequenceMustHaveLength => 17,
+            ErrorKind::U128Unsupported => 18,
+            ErrorKind::CapacityError(_) => 19,
+            ErrorKind::Serde => 20,
+            ErrorKind::LengthOverflow { .. } => 21,
+            ErrorKind::TrailingBytes(_) => 22,
+            ErrorKind::DirtyPadding { .. } => 23,
+            ErrorKind::AmbiguousEncoding => 24,
+            ErrorKind::FlushFailed => 25,
+            ErrorKind::InvalidCobsEncoding => 26,
+            ErrorKind::InvalidFrameLength(_) => 27,
+            ErrorKind::MagicMismatch { .. } => 28,
+            ErrorKind::PatchLengthMismatch { .. } => 29,
+            ErrorKind::ChecksumMismatch { .. } => 30,
+            ErrorKind::InvalidVarintEncoding => 31,
+            ErrorKind::FillOverrun { .. } => 32,
+            ErrorKind::ForeignCheckpoint => 33,
+            ErrorKind::LookAheadUnsupported => 34,
+            ErrorKind::EqualOptionTags => 35,
+            ErrorKind::NoCandidates => 36,
+        }
+    }
+
+    /// The error with the given `code`, see `code`, if it carries nothing
+    /// but its kind. Codes of errors that hold more, like the length of an
+    /// `UnexpectedEof`, and unknown codes give `None`.
+    pub fn from_code(code: u8) -> Option<ErrorKind> {
+        Some(match code {
+            3 => ErrorKind::Fmt(fmt::Error),
+            8 => ErrorKind::InvalidCharEncoding,
+            10 => ErrorKind::DeserializeAnyNotSupported,
+            11 => ErrorKind::SizeLimit(Direction::Serialize),
+            12 => ErrorKind::SizeLimit(Direction::Deserialize),
+            17 => ErrorKind::SequenceMustHaveLength,
+            18 => ErrorKind::U128Unsupported,
+            20 => ErrorKind::Serde,
+            24 => ErrorKind::AmbiguousEncoding,
+            25 => ErrorKind::FlushFailed,
+            26 => ErrorKind::InvalidCobsEncoding,
+            31 => ErrorKind::InvalidVarintEncoding,
+            33 => ErrorKind::ForeignCheckpoint,
+            34 => ErrorKind::LookAheadUnsupported,
+            35 => ErrorKind::EqualOptionTags,
+            36 => ErrorKind::NoCandidates,
+            _ => return None,
+        })
+    }
+}
+
+/// Whether an error happened while serializing or deserializing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Encoding a value, or measuring its encoded size.
+    Serialize,
+    /// Decoding a value.
+    Deserialize,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Direction::Serialize => write!(fmt, "serializing"),
+            Direction::Deserialize => write!(fmt, "deserializing"),
+        }
+    }
 }
 
 // impl StdError for ErrorKind {
@@ -75,11 +341,23 @@ pub enum ErrorKind {
 //     }
 // }
 
-// impl From<io::Error> for Error {
-//     fn from(err: io::Error) -> Error {
-//         ErrorKind::Io(err).into()
-//     }
-// }
+#[cfg(feature = "std")]
+impl ::std::error::Error for ErrorKind {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ErrorKind::Io(err) => Some(err),
+            ErrorKind::InvalidUtf8Encoding(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        ErrorKind::Io(err).into()
+    }
+}
 
 impl From<fmt::Error> for Error {
     fn from(err: fmt::Error) -> Error {
@@ -96,22 +374,106 @@ impl From<CapacityError<u8>> for Error {
 impl fmt::Display for ErrorKind {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            // ErrorKind::Io(ref ioerr) => write!(fmt, "io error: {}", ioerr),
-            ErrorKind::InvalidUtf8Encoding(e) => write!(fmt, "{}: {}", self, e),
+            #[cfg(feature = "std")]
+            ErrorKind::Io(ioerr) => write!(fmt, "io error: {}", ioerr),
+            #[cfg(feature = "embedded-io")]
+            ErrorKind::EmbeddedIo(kind) => write!(fmt, "io error: {:?}", kind),
+            ErrorKind::InvalidUtf8Encoding(e) => write!(fmt, "string is not valid utf8: {}", e),
             ErrorKind::InvalidBoolEncoding(b) => {
-                write!(fmt, "{}, expected 0 or 1, found {}", self, b)
+                write!(fmt, "invalid u8 while decoding bool, expected 0 or 1, found {}", b)
+            }
+            ErrorKind::InvalidCharEncoding => write!(fmt, "char is not valid"),
+            ErrorKind::InvalidTagEncoding(tag) => write!(fmt, "tag for enum is not valid, found {}", tag),
+            ErrorKind::InvalidVarintEncoding => write!(fmt, "invalid varint length"),
+            ErrorKind::UnexpectedEof { remaining, budget, needed } => {
+                write!(fmt, "unexpected end of input with {} bytes left", remaining)?;
+                if let Some(budget) = budget {
+                    write!(fmt, " and {} bytes of the size limit left", budget)?;
+                }
+                match needed {
+                    Some(needed) => write!(fmt, ", {} more needed", needed),
+                    None => Ok(()),
+                }
+            }
+            ErrorKind::SeamTooLong { len, max } => write!(
+                fmt,
+                "{} bytes cross the seam of the input but only {} can be copied",
+                len, max
+            ),
+            ErrorKind::ScratchTooSmall { len, max } => write!(
+                fmt,
+                "{} bytes don't fit in a scratch buffer of {} bytes",
+                len, max
+            ),
+            ErrorKind::MissingNulTerminator { max } => {
+                write!(fmt, "no NUL terminator within {} bytes", max)
+            }
+            ErrorKind::SequenceMustHaveLength => write!(
+                fmt,
+                "bincode can only encode sequences and maps that have a knowable size ahead of time"
+            ),
+            ErrorKind::U128Unsupported => write!(
+                fmt,
+                "u128 and i128 are not supported, enable the `i128` feature of `bincode`"
+            ),
+            ErrorKind::SizeLimit(direction) => {
+                write!(fmt, "the size limit was reached while {}", direction)
             }
-            ErrorKind::InvalidCharEncoding => write!(fmt, "{}", self),
-            ErrorKind::InvalidTagEncoding(tag) => write!(fmt, "{}, found {}", self, tag),
-            ErrorKind::SequenceMustHaveLength => write!(fmt, "{}", self),
-            ErrorKind::SizeLimit => write!(fmt, "{}", self),
             ErrorKind::DeserializeAnyNotSupported => write!(
                 fmt,
                 "Bincode does not support the serde::Deserializer::deserialize_any method"
             ),
             ErrorKind::CapacityError(c) => write!(fmt, "{}", c),
+            ErrorKind::BufferTooSmall { needed, available, written } => write!(
+                fmt,
+                "output buffer too small, {} bytes needed but only {} available ({} written)",
+                needed, available, written
+            ),
             ErrorKind::Fmt(f) => write!(fmt, "{}", f),
             ErrorKind::Serde => write!(fmt, "Serde error"),
+            ErrorKind::LengthOverflow { len, max } => write!(
+                fmt,
+                "length {} exceeds the maximum of {} for the configured length encoding",
+                len, max
+            ),
+            ErrorKind::TrailingBytes(n) => write!(fmt, "{} trailing bytes after the value", n),
+            ErrorKind::DirtyPadding { offset, found } => write!(
+                fmt,
+                "padding byte at offset {} is {:#04x}, not the pad byte",
+                offset, found
+            ),
+            ErrorKind::AmbiguousEncoding => {
+                write!(fmt, "input is valid under more than one configuration")
+            }
+            ErrorKind::FlushFailed => write!(fmt, "failed to send a packet"),
+            ErrorKind::InvalidCobsEncoding => write!(fmt, "invalid COBS encoding"),
+            ErrorKind::InvalidFrameLength(len) => {
+                write!(fmt, "frame length {} doesn't match the value in it", len)
+            }
+            ErrorKind::MagicMismatch { expected, found } => write!(
+                fmt,
+                "frame starts with {:02x?} instead of the magic number {:02x?}",
+                found, expected
+            ),
+            ErrorKind::PatchLengthMismatch { region, encoded } => write!(
+                fmt,
+                "patch of {} bytes doesn't match the {} bytes it replaces",
+                encoded, region
+            ),
+            ErrorKind::ChecksumMismatch { expected, actual } => write!(
+                fmt,
+                "checksum mismatch, expected {:#010x}, found {:#010x}",
+                expected, actual
+            ),
+            ErrorKind::FillOverrun { written, room } => write!(
+                fmt,
+                "fill callback claimed {} bytes but was given room for {}",
+                written, room
+            ),
+            ErrorKind::ForeignCheckpoint => write!(fmt, "checkpoint belongs to a different input"),
+            ErrorKind::LookAheadUnsupported => write!(fmt, "the reader can't look ahead for a terminator"),
+            ErrorKind::EqualOptionTags => write!(fmt, "None and Some have the same tag"),
+            ErrorKind::NoCandidates => write!(fmt, "no configurations to try"),
         }
     }
 }
@@ -0,0 +1,58 @@
+//! Method-call syntax for the top-level functions.
+//!
+//! `BincodeExt` and `BincodeDeExt` are implemented for every `Serialize` and
+//! `Deserialize` type, so bringing them into scope is all it takes:
+//!
+//! ```rust
+//! extern crate bincode;
+//! use bincode::arrayvec::ArrayVec;
+//! use bincode::{BincodeDeExt, BincodeExt};
+//!
+//! fn main() {
+//!     let msg = (7u16, "status");
+//!
+//!     let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+//!     let len = msg.to_bincode_into(&mut buf).unwrap();
+//!     assert_eq!(len as u64, msg.bincode_size().unwrap());
+//!
+//!     let decoded = <(u16, &str)>::from_bincode(&buf).unwrap();
+//!     assert_eq!(decoded, msg);
+//! }
+//! ```
+//!
+//! Both use the default configuration. Go through `config()` for anything
+//! else.
+
+use serde;
+
+use flavors::OutputFlavor;
+use Result;
+
+/// Serialization methods for every `Serialize` type.
+pub trait BincodeExt: serde::Serialize {
+    /// Serializes `self` through `flavor`, returning the number of bytes the
+    /// flavor produced. See `serialize_with_flavor`.
+    #[inline]
+    fn to_bincode_into<F: OutputFlavor>(&self, flavor: F) -> Result<usize> {
+        ::serialize_with_flavor(flavor, self)
+    }
+
+    /// Returns the number of bytes `self` serializes to. See `serialized_size`.
+    #[inline]
+    fn bincode_size(&self) -> Result<u64> {
+        ::serialized_size(self)
+    }
+}
+
+impl<T: ?Sized + serde::Serialize> BincodeExt for T {}
+
+/// Deserialization methods for every `Deserialize` type.
+pub trait BincodeDeExt<'a>: serde::Deserialize<'a> {
+    /// Deserializes an instance of `Self` from `bytes`. See `deserialize`.
+    #[inline]
+    fn from_bincode(bytes: &'a [u8]) -> Result<Self> {
+        ::deserialize(bytes)
+    }
+}
+
+impl<'a, T: serde::Deserialize<'a>> BincodeDeExt<'a> for T {}
@@ -0,0 +1,574 @@
+//! Hashes of a type's shape, for checking that two sides agree on a schema.
+//!
+//! `fingerprint::<T>()` traces how `T` deserializes, hashing which kinds of
+//! data it asks for instead of reading any: the width and signedness of every
+//! number, where strings, bytes, options, sequences and maps are, how many
+//! fields each struct and tuple has, and how many variants each enum has,
+//! along with the shape of every variant. No value is involved, so both ends
+//! of a link compute the same fingerprint for the same type. Names of types,
+//! fields and variants are left out, so renaming a field keeps the
+//! fingerprint, while reordering or retyping one changes it.
+//!
+//! ```rust
+//! extern crate bincode;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! use bincode::fingerprint::fingerprint;
+//!
+//! #[derive(Deserialize)]
+//! struct Reading {
+//!     sensor: u8,
+//!     value: Option<f32>,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct ReadingV2 {
+//!     sensor: u16,
+//!     value: Option<f32>,
+//! }
+//!
+//! fn main() {
+//!     assert_eq!(fingerprint::<Reading>(), fingerprint::<Reading>());
+//!     assert_ne!(fingerprint::<Reading>(), fingerprint::<ReadingV2>());
+//! }
+//! ```
+//!
+//! Describing every variant takes running `T`'s `Deserialize` impl several
+//! times, each run taking variants that weren't described yet. Recursive
+//! types are cut off where a struct or enum shows up inside itself: from
+//! there on, options are `None`, sequences and maps empty, and enums take a
+//! variant that isn't already being described.
+//!
+//! Numbers are traced as `1` and strings and bytes as empty, so types that
+//! reject those, as well as types that need `deserialize_any`, types nesting
+//! more than 32 structs and enums deep and types with more than 32 enums or
+//! 256 variants in all, get a fingerprint marked as incomplete. It is still
+//! the same on both ends, but doesn't tell such types apart as well.
+//!
+//! The hash is 64-bit FNV-1a over a fixed description of the shape, so it is
+//! the same on every platform.
+
+use core::any::type_name;
+use core::mem::size_of;
+
+use arrayvec::ArrayVec;
+use serde;
+use serde::de::{DeserializeSeed, IntoDeserializer, Visitor};
+
+use {Direction, Error, ErrorKind, Result};
+
+/// Returns a hash of the shape of `T`, see the module documentation.
+pub fn fingerprint<'de, T: serde::Deserialize<'de>>() -> u64 {
+    let mut tracer = Tracer {
+        enums: ArrayVec::new(),
+        variants: ArrayVec::new(),
+        stack: ArrayVec::new(),
+        frames: ArrayVec::new(),
+        recursing: 0,
+        progress: false,
+    };
+
+    // Every run but the last describes a variant or completes one, so this
+    // ends well before the limit.
+    let mut result = tracer.run::<T>();
+    for _ in 0..2 * MAX_VARIANTS {
+        if result.is_err() || !tracer.progress {
+            break;
+        }
+        result = tracer.run::<T>();
+    }
+
+    let mut hash = FNV_OFFSET;
+    match result {
+        Ok(shape) => hash = fnv(hash, &shape.to_le_bytes()),
+        // A type that can't be traced still has a shape up to where it
+        // failed; mark it so it doesn't collide with a complete one.
+        Err(_) => hash = fnv(hash, b"!"),
+    }
+    for entry in &tracer.enums {
+        hash = fnv(hash, b"E");
+        hash = fnv(hash, &(entry.count as u32).to_le_bytes());
+        for variant in &tracer.variants[entry.first..entry.first + entry.count] {
+            hash = match variant.hash {
+                Some(shape) => fnv(hash, &shape.to_le_bytes()),
+                None => fnv(hash, b"?"),
+            };
+        }
+    }
+    hash
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+const MAX_DEPTH: usize = 32;
+const MAX_ENUMS: usize = 32;
+const MAX_VARIANTS: usize = 256;
+
+fn fnv(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// The error for a type too big to trace. It never leaves the module.
+fn too_big() -> Error {
+    ErrorKind::SizeLimit(Direction::Deserialize)
+}
+
+/// An enum seen while tracing, numbered in the order it was first seen.
+#[derive(Clone, Copy)]
+struct EnumEntry {
+    /// Tells enums apart; it's the type of their visitor, which differs
+    /// between instances of a generic enum.
+    key: &'static str,
+    /// Where its variants start in `Tracer::variants`.
+    first: usize,
+    count: usize,
+}
+
+#[derive(Clone, Copy)]
+struct VariantEntry {
+    /// The hash of its contents, once described.
+    hash: Option<u64>,
+    /// Whether every enum in its contents is fully described as well.
+    complete: bool,
+}
+
+/// A struct or enum being traced.
+#[derive(Clone, Copy)]
+struct Level {
+    key: &'static str,
+    variant: Option<usize>,
+    /// Whether it was already being traced further up.
+    repeated: bool,
+}
+
+/// The hash of a variant being described, or of the whole type at the bottom.
+#[derive(Clone, Copy)]
+struct Frame {
+    hash: u64,
+    /// Set for a variant that was described before, whose contents are only
+    /// visited to reach enums in it.
+    muted: bool,
+    /// Whether an enum in it isn't fully described yet.
+    incomplete: bool,
+}
+
+struct Tracer {
+    enums: ArrayVec<[EnumEntry; MAX_ENUMS]>,
+    variants: ArrayVec<[VariantEntry; MAX_VARIANTS]>,
+    stack: ArrayVec<[Level; MAX_DEPTH]>,
+    frames: ArrayVec<[Frame; MAX_DEPTH]>,
+    /// The number of levels on the stack that are `repeated`.
+    recursing: usize,
+    /// Whether the current run described or completed a variant.
+    progress: bool,
+}
+
+impl Tracer {
+    fn run<'de, T: serde::Deserialize<'de>>(&mut self) -> Result<u64> {
+        self.stack.clear();
+        self.frames.clear();
+        self.recursing = 0;
+        self.progress = false;
+        self.push_frame(false)?;
+        T::deserialize(&mut *self)?;
+        Ok(self.frames[0].hash)
+    }
+
+    fn feed(&mut self, bytes: &[u8]) {
+        if let Some(frame) = self.frames.last_mut() {
+            if !frame.muted {
+                frame.hash = fnv(frame.hash, bytes);
+            }
+        }
+    }
+
+    fn tag(&mut self, tag: u8) {
+        self.feed(&[tag]);
+    }
+
+    /// A tag followed by a count, e.g. the width of a number or the number of
+    /// fields of a struct.
+    fn tag_n(&mut self, tag: u8, n: u32) {
+        self.tag(tag);
+        self.feed(&n.to_le_bytes());
+    }
+
+    fn push_frame(&mut self, muted: bool) -> Result<()> {
+        let frame = Frame {
+            hash: FNV_OFFSET,
+            muted,
+            incomplete: false,
+        };
+        self.frames.try_push(frame).map_err(|_| too_big())
+    }
+
+    fn enter(&mut self, key: &'static str, variant: Option<usize>) -> Result<()> {
+        let repeated = self.stack.iter().any(|level| level.key == key);
+        self.stack
+            .try_push(Level { key, variant, repeated })
+            .map_err(|_| too_big())?;
+        if repeated {
+            self.recursing += 1;
+        }
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        if let Some(level) = self.stack.pop() {
+            if level.repeated {
+                self.recursing -= 1;
+            }
+        }
+    }
+
+    /// The number of elements to give a sequence or map: one, unless inside
+    /// a recursive type.
+    fn elements(&mut self) -> usize {
+        if self.recursing > 0 {
+            self.tag(b'r');
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Returns the number of the enum with `key`, registering it if it's new.
+    fn register(&mut self, key: &'static str, count: usize) -> Result<usize> {
+        if let Some(index) = self.enums.iter().position(|entry| entry.key == key) {
+            return Ok(index);
+        }
+        if self.variants.len() + count > MAX_VARIANTS {
+            return Err(too_big());
+        }
+        let entry = EnumEntry {
+            key,
+            first: self.variants.len(),
+            count,
+        };
+        self.enums.try_push(entry).map_err(|_| too_big())?;
+        for _ in 0..count {
+            self.variants.push(VariantEntry {
+                hash: None,
+                complete: false,
+            });
+        }
+        Ok(self.enums.len() - 1)
+    }
+
+    fn is_complete(&self, entry: EnumEntry) -> bool {
+        self.variants[entry.first..entry.first + entry.count]
+            .iter()
+            .all(|variant| variant.hash.is_some() && variant.complete)
+    }
+
+    /// Picks the variant to take: the first one not described yet, then the
+    /// first one with enums in it left to describe. Inside itself, an enum
+    /// takes the first variant not already being traced, to end the recursion.
+    fn choose(&self, entry: EnumEntry, recursive: bool) -> usize {
+        if recursive {
+            return (0..entry.count)
+                .find(|&i| {
+                    !self
+                        .stack
+                        .iter()
+                        .any(|level| level.key == entry.key && level.variant == Some(i))
+                })
+                .unwrap_or(0);
+        }
+        let variants = &self.variants[entry.first..entry.first + entry.count];
+        variants
+            .iter()
+            .position(|variant| variant.hash.is_none())
+            .or_else(|| variants.iter().position(|variant| !variant.complete))
+            .unwrap_or(0)
+    }
+}
+
+macro_rules! trace_nums {
+    ($ty:ty, $method:ident, $visit:ident, $tag:expr) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            self.tag_n($tag, size_of::<$ty>() as u32);
+            // One rather than zero, so `NonZero` types take it.
+            visitor.$visit(1)
+        }
+    };
+}
+
+impl<'de> serde::Deserializer<'de> for &mut Tracer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(ErrorKind::DeserializeAnyNotSupported)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.tag(b'b');
+        visitor.visit_bool(false)
+    }
+
+    trace_nums!(u8, deserialize_u8, visit_u8, b'u');
+    trace_nums!(u16, deserialize_u16, visit_u16, b'u');
+    trace_nums!(u32, deserialize_u32, visit_u32, b'u');
+    trace_nums!(u64, deserialize_u64, visit_u64, b'u');
+    trace_nums!(u128, deserialize_u128, visit_u128, b'u');
+    trace_nums!(i8, deserialize_i8, visit_i8, b'i');
+    trace_nums!(i16, deserialize_i16, visit_i16, b'i');
+    trace_nums!(i32, deserialize_i32, visit_i32, b'i');
+    trace_nums!(i64, deserialize_i64, visit_i64, b'i');
+    trace_nums!(i128, deserialize_i128, visit_i128, b'i');
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.tag_n(b'f', 4);
+        visitor.visit_f32(1.0)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.tag_n(b'f', 8);
+        visitor.visit_f64(1.0)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.tag(b'c');
+        visitor.visit_char('1')
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.tag(b's');
+        visitor.visit_borrowed_str("")
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.tag(b'y');
+        visitor.visit_borrowed_bytes(&[])
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.tag(b'o');
+        if self.recursing > 0 {
+            self.tag(b'r');
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.tag(b'0');
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        // Adds nothing to the shape, but can still hold itself.
+        self.enter(type_name::<V>(), None)?;
+        let value = visitor.visit_newtype_struct(&mut *self)?;
+        self.leave();
+        Ok(value)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.tag(b'[');
+        let left = self.elements();
+        let value = visitor.visit_seq(Elements { tracer: &mut *self, left })?;
+        self.tag(b'.');
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        self.tag_n(b'(', len as u32);
+        let value = visitor.visit_seq(Elements { tracer: &mut *self, left: len })?;
+        self.tag(b'.');
+        Ok(value)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value> {
+        self.enter(type_name::<V>(), None)?;
+        let value = self.deserialize_tuple(len, visitor)?;
+        self.leave();
+        Ok(value)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.tag(b'{');
+        let left = self.elements();
+        let value = visitor.visit_map(Elements { tracer: &mut *self, left })?;
+        self.tag(b'.');
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.enter(type_name::<V>(), None)?;
+        self.tag_n(b'<', fields.len() as u32);
+        let value = visitor.visit_seq(Elements {
+            tracer: &mut *self,
+            left: fields.len(),
+        })?;
+        self.tag(b'.');
+        self.leave();
+        Ok(value)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let key = type_name::<V>();
+        if variants.is_empty() {
+            return Err(ErrorKind::InvalidTagEncoding(0));
+        }
+        let number = self.register(key, variants.len())?;
+        let entry = self.enums[number];
+        let recursive = self.stack.iter().any(|level| level.key == key);
+        let index = self.choose(entry, recursive);
+        let described = self.variants[entry.first + index].hash.is_some();
+
+        // The enum is described once on its own, so here it's just its number.
+        self.tag_n(b'E', number as u32);
+        self.push_frame(described)?;
+        self.enter(key, Some(index))?;
+        let value = visitor.visit_enum(Variant {
+            tracer: &mut *self,
+            index: index as u32,
+        })?;
+        self.leave();
+        let frame = self.frames.pop().expect("pushed above");
+
+        let variant = &mut self.variants[entry.first + index];
+        if !described {
+            variant.hash = Some(frame.hash);
+            self.progress = true;
+        }
+        if !frame.incomplete && !variant.complete {
+            variant.complete = true;
+            self.progress = true;
+        }
+        // An enum inside itself is finished by the level that holds it.
+        if !recursive && !self.is_complete(entry) {
+            if let Some(parent) = self.frames.last_mut() {
+                parent.incomplete = true;
+            }
+        }
+        Ok(value)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(ErrorKind::DeserializeAnyNotSupported)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(ErrorKind::DeserializeAnyNotSupported)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// The elements of a sequence, tuple or struct, or the entries of a map.
+struct Elements<'a> {
+    tracer: &'a mut Tracer,
+    left: usize,
+}
+
+impl<'de, 'a> serde::de::SeqAccess<'de> for Elements<'a> {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.tracer).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+impl<'de, 'a> serde::de::MapAccess<'de> for Elements<'a> {
+    type Error = Error;
+
+    fn next_key_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.tracer).map(Some)
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value> {
+        self.left -= 1;
+        seed.deserialize(&mut *self.tracer)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+/// The variant an enum takes.
+struct Variant<'a> {
+    tracer: &'a mut Tracer,
+    index: u32,
+}
+
+impl<'de, 'a> serde::de::EnumAccess<'de> for Variant<'a> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self)> {
+        let index: serde::de::value::U32Deserializer<Error> = self.index.into_deserializer();
+        let value = seed.deserialize(index)?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 'a> serde::de::VariantAccess<'de> for Variant<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        self.tracer.tag(b'0');
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value> {
+        seed.deserialize(&mut *self.tracer)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        serde::Deserializer::deserialize_tuple(&mut *self.tracer, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        self.tracer.tag_n(b'<', fields.len() as u32);
+        let value = visitor.visit_seq(Elements {
+            tracer: &mut *self.tracer,
+            left: fields.len(),
+        })?;
+        self.tracer.tag(b'.');
+        Ok(value)
+    }
+}
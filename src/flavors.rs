@@ -0,0 +1,688 @@
+//! Composable transforms for the byte stream on its way out of the serializer
+//! and on its way into the deserializer.
+//!
+//! An `OutputFlavor` receives the bytes produced by the serializer. Flavors
+//! wrap other flavors, so transforms stack: `Crc32<Cobs<Slice>>` appends a
+//! checksum to the payload, COBS-encodes payload and checksum together and
+//! writes the result into a byte slice. Bytes flow from the outermost flavor
+//! towards the innermost one.
+//!
+//! An `InputFlavor` undoes the same stack before decoding. It is built the
+//! same way around the raw input and yields the payload the deserializer
+//! should see.
+//!
+//! ```rust
+//! extern crate bincode;
+//! use bincode::flavors::{Cobs, Crc32, Slice};
+//! use bincode::{deserialize_with_flavor, serialize_with_flavor};
+//!
+//! fn main() {
+//!     let mut buf = [0u8; 32];
+//!     let len = serialize_with_flavor(Crc32::new(Cobs::new(Slice::new(&mut buf))), &(1u32, 0u8))
+//!         .unwrap();
+//!
+//!     let decoded: (u32, u8) =
+//!         deserialize_with_flavor(Crc32::new(Cobs::new(Slice::new(&mut buf[..len])))).unwrap();
+//!     assert_eq!(decoded, (1, 0));
+//! }
+//! ```
+
+use arrayvec::{Array, ArrayVec, CapacityError};
+use byteorder::{ByteOrder, LittleEndian};
+
+use {ErrorKind, Result};
+
+/// A destination for serialized bytes.
+///
+/// The serializer is generic over this trait, so any sink that implements it
+/// can be written into directly: only `try_push` and `finalize` are required,
+/// and `write` can be overridden when the sink copies slices faster than
+/// single bytes.
+pub trait OutputFlavor {
+    /// Appends a single byte to the output.
+    fn try_push(&mut self, byte: u8) -> Result<()>;
+
+    /// Appends all of `bytes` to the output.
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        for &byte in bytes {
+            self.try_push(byte)?;
+        }
+        Ok(())
+    }
+
+    /// Writes out anything the flavor is still holding on to and returns the
+    /// number of bytes in the final output.
+    fn finalize(self) -> Result<usize>;
+
+    /// Whether bytes written earlier can still be changed with `rewrite`.
+    /// Sequences and maps of unknown length, e.g. from `serialize_seq(None)`,
+    /// can only be serialized into flavors that can, as their length prefix is
+    /// filled in once the last element is written.
+    fn can_rewrite(&self) -> bool {
+        false
+    }
+
+    /// Overwrites bytes written earlier with `bytes`, starting `back` bytes
+    /// before the end of the output. Only called if `can_rewrite` returns
+    /// `true`, and never reaching past the end.
+    fn rewrite(&mut self, back: usize, bytes: &[u8]) -> Result<()> {
+        let _ = (back, bytes);
+        Err(ErrorKind::SequenceMustHaveLength)
+    }
+}
+
+/// A source of bytes to be deserialized.
+pub trait InputFlavor<'de> {
+    /// Strips this flavor's transform from the input and returns the payload.
+    fn into_payload(self) -> Result<&'de mut [u8]>;
+}
+
+impl<'a, A: Array<Item = u8>> OutputFlavor for &'a mut ArrayVec<A> {
+    #[inline(always)]
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        ArrayVec::try_push(*self, byte).map_err(Into::into)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        // One capacity check for the whole slice, then a plain copy.
+        let room = self.capacity() - self.len();
+        if bytes.len() > room {
+            return Err(CapacityError::new(bytes[room]).into());
+        }
+        self.extend(bytes.iter().cloned());
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<usize> {
+        Ok(self.len())
+    }
+
+    fn can_rewrite(&self) -> bool {
+        true
+    }
+
+    fn rewrite(&mut self, back: usize, bytes: &[u8]) -> Result<()> {
+        let start = self.len() - back;
+        self[start..start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<'a, const N: usize> OutputFlavor for &'a mut ::heapless::Vec<u8, N> {
+    #[inline(always)]
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        ::heapless::Vec::push(*self, byte).map_err(|byte| CapacityError::new(byte).into())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        let room = self.capacity() - self.len();
+        if bytes.len() > room {
+            return Err(CapacityError::new(bytes[room]).into());
+        }
+        // Can't fail, there's room for all of `bytes`.
+        let _ = self.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<usize> {
+        Ok(self.len())
+    }
+
+    fn can_rewrite(&self) -> bool {
+        true
+    }
+
+    fn rewrite(&mut self, back: usize, bytes: &[u8]) -> Result<()> {
+        let start = self.len() - back;
+        self[start..start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> OutputFlavor for &'a mut ::alloc::vec::Vec<u8> {
+    #[inline(always)]
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        self.push(byte);
+        Ok(())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<usize> {
+        Ok(self.len())
+    }
+
+    fn can_rewrite(&self) -> bool {
+        true
+    }
+
+    fn rewrite(&mut self, back: usize, bytes: &[u8]) -> Result<()> {
+        let start = self.len() - back;
+        self[start..start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// Writes into, or reads from, a plain byte slice.
+pub struct Slice<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Slice<'a> {
+    /// Wraps `buf`. Output starts at the beginning of the slice.
+    pub fn new(buf: &'a mut [u8]) -> Slice<'a> {
+        Slice { buf, pos: 0 }
+    }
+
+    /// The number of bytes written so far.
+    pub(crate) fn written(&self) -> usize {
+        self.pos
+    }
+}
+
+impl<'a> OutputFlavor for Slice<'a> {
+    #[inline(always)]
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        if self.pos == self.buf.len() {
+            return Err(CapacityError::new(byte).into());
+        }
+        self.buf[self.pos] = byte;
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        if bytes.len() > self.buf.len() - self.pos {
+            return Err(CapacityError::new(bytes[self.buf.len() - self.pos]).into());
+        }
+        self.buf[self.pos..][..bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<usize> {
+        Ok(self.pos)
+    }
+
+    fn can_rewrite(&self) -> bool {
+        true
+    }
+
+    fn rewrite(&mut self, back: usize, bytes: &[u8]) -> Result<()> {
+        let start = self.pos - back;
+        self.buf[start..start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl<'a> InputFlavor<'a> for Slice<'a> {
+    fn into_payload(self) -> Result<&'a mut [u8]> {
+        Ok(self.buf)
+    }
+}
+
+/// Writes across several byte slices in turn, moving on to the next one when
+/// the current one is full.
+///
+/// The flavor is used by reference, so the fill of each slice can be read
+/// back after serializing:
+///
+/// ```rust
+/// extern crate bincode;
+/// use bincode::flavors::Scatter;
+///
+/// fn main() {
+///     let (mut first, mut second) = ([0u8; 6], [0u8; 6]);
+///     let mut segments = [&mut first[..], &mut second[..]];
+///     let mut scatter = Scatter::new(&mut segments);
+///     assert_eq!(bincode::serialize_with_flavor(&mut scatter, &(1u32, 2u32)).unwrap(), 8);
+///     assert_eq!((scatter.fill(0), scatter.fill(1)), (6, 2));
+/// }
+/// ```
+pub struct Scatter<'a, 'b: 'a> {
+    segments: &'a mut [&'b mut [u8]],
+    segment: usize,
+    pos: usize,
+    written: usize,
+    room: usize,
+}
+
+impl<'a, 'b> Scatter<'a, 'b> {
+    /// Wraps `segments`. Output starts at the beginning of the first one.
+    pub fn new(segments: &'a mut [&'b mut [u8]]) -> Scatter<'a, 'b> {
+        let room = segments.iter().map(|s| s.len()).sum();
+        Scatter {
+            segments,
+            segment: 0,
+            pos: 0,
+            written: 0,
+            room,
+        }
+    }
+
+    /// The number of bytes written into the segment at `index`.
+    pub fn fill(&self, index: usize) -> usize {
+        if index < self.segment {
+            self.segments[index].len()
+        } else if index == self.segment {
+            self.pos
+        } else {
+            0
+        }
+    }
+}
+
+impl<'s, 'a, 'b> OutputFlavor for &'s mut Scatter<'a, 'b> {
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        self.write(&[byte])
+    }
+
+    fn write(&mut self, mut bytes: &[u8]) -> Result<()> {
+        if bytes.len() > self.room {
+            return Err(CapacityError::new(bytes[self.room]).into());
+        }
+        self.room -= bytes.len();
+        self.written += bytes.len();
+        while !bytes.is_empty() {
+            let segment = &mut self.segments[self.segment][self.pos..];
+            let n = segment.len().min(bytes.len());
+            segment[..n].copy_from_slice(&bytes[..n]);
+            bytes = &bytes[n..];
+            if n == segment.len() {
+                self.segment += 1;
+                self.pos = 0;
+            } else {
+                self.pos += n;
+            }
+        }
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<usize> {
+        Ok(self.written)
+    }
+}
+
+/// Passes output to a callback as it is produced, without buffering it.
+///
+/// The callback is called once for every contiguous run of bytes the
+/// serializer writes: a number, a length prefix, a whole string or byte
+/// payload, or a single tag byte.
+pub struct Sink<F> {
+    sink: F,
+    written: usize,
+}
+
+impl<F: FnMut(&[u8]) -> Result<()>> Sink<F> {
+    /// Wraps `sink`.
+    pub fn new(sink: F) -> Sink<F> {
+        Sink { sink, written: 0 }
+    }
+}
+
+impl<F: FnMut(&[u8]) -> Result<()>> OutputFlavor for Sink<F> {
+    #[inline(always)]
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        self.write(&[byte])
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        (self.sink)(bytes)?;
+        self.written += bytes.len();
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<usize> {
+        Ok(self.written)
+    }
+}
+
+/// Collects output into packets of `N` bytes and hands each one to a
+/// callback as soon as it is full, e.g. for a link with a small MTU.
+///
+/// The flavor is used by reference, so any number of values can be streamed
+/// through it. `finish` sends the last, partly filled packet. If the callback
+/// fails, serializing stops with `ErrorKind::FlushFailed` and the callback's
+/// error can be taken back with `take_error`.
+///
+/// ```rust
+/// extern crate bincode;
+/// use bincode::flavors::Chunked;
+///
+/// fn main() {
+///     let mut packets = Vec::new();
+///     let mut chunked = Chunked::<_, (), 4>::new(|packet: &[u8]| {
+///         packets.push(packet.to_vec());
+///         Ok(())
+///     });
+///     bincode::serialize_with_flavor(&mut chunked, &(1u32, 2u16)).unwrap();
+///     assert_eq!(chunked.finish().unwrap(), 6);
+///     drop(chunked);
+///     assert_eq!(packets, [vec![1, 0, 0, 0], vec![2, 0]]);
+/// }
+/// ```
+pub struct Chunked<F, E, const N: usize> {
+    buf: [u8; N],
+    len: usize,
+    total: usize,
+    flush: F,
+    error: Option<E>,
+}
+
+impl<F: FnMut(&[u8]) -> ::core::result::Result<(), E>, E, const N: usize> Chunked<F, E, N> {
+    const NONZERO_PACKET: () = assert!(N > 0, "packets must hold at least one byte");
+
+    /// Wraps `flush`, which is called with every packet. `N` must not be
+    /// zero, which is checked at compile time:
+    ///
+    /// ```compile_fail
+    /// let chunked = bincode::flavors::Chunked::<_, (), 0>::new(|_: &[u8]| Ok(()));
+    /// ```
+    pub fn new(flush: F) -> Chunked<F, E, N> {
+        let () = Self::NONZERO_PACKET;
+        Chunked {
+            buf: [0; N],
+            len: 0,
+            total: 0,
+            flush,
+            error: None,
+        }
+    }
+
+    /// Sends the bytes that don't fill a whole packet yet, returning the number
+    /// of bytes written since the flavor was created.
+    pub fn finish(&mut self) -> Result<usize> {
+        self.send()?;
+        Ok(self.total)
+    }
+
+    /// Takes the error the callback returned, if it failed.
+    pub fn take_error(&mut self) -> Option<E> {
+        self.error.take()
+    }
+
+    fn send(&mut self) -> Result<()> {
+        if self.len == 0 {
+            return Ok(());
+        }
+        if let Err(e) = (self.flush)(&self.buf[..self.len]) {
+            self.error = Some(e);
+            return Err(ErrorKind::FlushFailed);
+        }
+        self.len = 0;
+        Ok(())
+    }
+}
+
+impl<'a, F, E, const N: usize> OutputFlavor for &'a mut Chunked<F, E, N>
+where
+    F: FnMut(&[u8]) -> ::core::result::Result<(), E>,
+{
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        self.write(&[byte])
+    }
+
+    fn write(&mut self, mut bytes: &[u8]) -> Result<()> {
+        while !bytes.is_empty() {
+            let n = (N - self.len).min(bytes.len());
+            self.buf[self.len..][..n].copy_from_slice(&bytes[..n]);
+            self.len += n;
+            self.total += n;
+            bytes = &bytes[n..];
+            if self.len == N {
+                self.send()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<usize> {
+        Ok(self.total)
+    }
+}
+
+/// Writes into an `io::Write`r, e.g. a `TcpStream` or a `File`.
+///
+/// Bytes are passed on as they are produced, often one at a time, so
+/// unbuffered writers should be wrapped in a `BufWriter`. The writer is
+/// flushed when the flavor is finalized.
+#[cfg(feature = "std")]
+pub struct IoWriter<W> {
+    writer: W,
+    written: usize,
+}
+
+#[cfg(feature = "std")]
+impl<W: ::std::io::Write> IoWriter<W> {
+    /// Wraps `writer`.
+    pub fn new(writer: W) -> IoWriter<W> {
+        IoWriter { writer, written: 0 }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: ::std::io::Write> OutputFlavor for IoWriter<W> {
+    #[inline(always)]
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        self.write(&[byte])
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes)?;
+        self.written += bytes.len();
+        Ok(())
+    }
+
+    fn finalize(mut self) -> Result<usize> {
+        self.writer.flush()?;
+        Ok(self.written)
+    }
+}
+
+/// Writes into an `embedded_io::Write`r, e.g. a UART or flash driver.
+///
+/// Bytes are passed on as they are produced, blocking until the driver has
+/// taken all of them. A driver error is returned as `ErrorKind::EmbeddedIo`
+/// with the kind of error it reported. The writer is flushed when the flavor
+/// is finalized.
+#[cfg(feature = "embedded-io")]
+pub struct EmbeddedIoWriter<W> {
+    writer: W,
+    written: usize,
+}
+
+#[cfg(feature = "embedded-io")]
+impl<W: ::embedded_io::Write> EmbeddedIoWriter<W> {
+    /// Wraps `writer`.
+    pub fn new(writer: W) -> EmbeddedIoWriter<W> {
+        EmbeddedIoWriter { writer, written: 0 }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+pub(crate) fn embedded_io_error<E: ::embedded_io::Error>(err: E) -> ErrorKind {
+    ErrorKind::EmbeddedIo(err.kind())
+}
+
+#[cfg(feature = "embedded-io")]
+impl<W: ::embedded_io::Write> OutputFlavor for EmbeddedIoWriter<W> {
+    #[inline(always)]
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        self.write(&[byte])
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes).map_err(embedded_io_error)?;
+        self.written += bytes.len();
+        Ok(())
+    }
+
+    fn finalize(mut self) -> Result<usize> {
+        self.writer.flush().map_err(embedded_io_error)?;
+        Ok(self.written)
+    }
+}
+
+/// Consistent Overhead Byte Stuffing.
+///
+/// On output the payload is encoded so that it contains no zero bytes and a
+/// single `0x00` delimiter is appended. On input the frame is decoded in
+/// place up to the first delimiter (or the end of the input).
+pub struct Cobs<F> {
+    inner: F,
+    run: [u8; 254],
+    run_len: usize,
+}
+
+impl<F> Cobs<F> {
+    /// Wraps `inner`.
+    pub fn new(inner: F) -> Cobs<F> {
+        Cobs {
+            inner,
+            run: [0; 254],
+            run_len: 0,
+        }
+    }
+}
+
+impl<F: OutputFlavor> Cobs<F> {
+    fn flush_run(&mut self) -> Result<()> {
+        self.inner.try_push(self.run_len as u8 + 1)?;
+        self.inner.write(&self.run[..self.run_len])?;
+        self.run_len = 0;
+        Ok(())
+    }
+}
+
+impl<F: OutputFlavor> OutputFlavor for Cobs<F> {
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        if byte == 0 {
+            return self.flush_run();
+        }
+        self.run[self.run_len] = byte;
+        self.run_len += 1;
+        if self.run_len == self.run.len() {
+            self.flush_run()?;
+        }
+        Ok(())
+    }
+
+    fn finalize(mut self) -> Result<usize> {
+        self.flush_run()?;
+        self.inner.try_push(0)?;
+        self.inner.finalize()
+    }
+}
+
+impl<'de, F: InputFlavor<'de>> InputFlavor<'de> for Cobs<F> {
+    fn into_payload(self) -> Result<&'de mut [u8]> {
+        let buf = self.inner.into_payload()?;
+        let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+
+        let mut read = 0;
+        let mut write = 0;
+        while read < end {
+            let code = buf[read] as usize;
+            read += 1;
+            if read + code - 1 > end {
+                return Err(ErrorKind::InvalidCobsEncoding);
+            }
+            for _ in 1..code {
+                buf[write] = buf[read];
+                write += 1;
+                read += 1;
+            }
+            if code != 0xFF && read < end {
+                buf[write] = 0;
+                write += 1;
+            }
+        }
+
+        Ok(&mut buf[..write])
+    }
+}
+
+/// Appends a CRC-32 (IEEE) of the payload, stored little-endian.
+///
+/// On input the trailing four bytes are checked against the checksum of
+/// everything before them, failing with `ErrorKind::ChecksumMismatch`.
+pub struct Crc32<F> {
+    inner: F,
+    crc: u32,
+}
+
+impl<F> Crc32<F> {
+    /// Wraps `inner`.
+    pub fn new(inner: F) -> Crc32<F> {
+        Crc32 {
+            inner,
+            crc: 0,
+        }
+    }
+
+    /// Unwraps the inner flavor and the checksum of everything written so far,
+    /// without appending it.
+    pub(crate) fn into_parts(self) -> (F, u32) {
+        (self.inner, self.crc)
+    }
+}
+
+impl<F: OutputFlavor> OutputFlavor for Crc32<F> {
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        self.write(&[byte])
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.crc = crc32(self.crc, bytes);
+        self.inner.write(bytes)
+    }
+
+    fn finalize(mut self) -> Result<usize> {
+        let mut buf = [0; 4];
+        LittleEndian::write_u32(&mut buf, self.crc);
+        self.inner.write(&buf)?;
+        self.inner.finalize()
+    }
+}
+
+impl<'de, F: InputFlavor<'de>> InputFlavor<'de> for Crc32<F> {
+    fn into_payload(self) -> Result<&'de mut [u8]> {
+        let buf = self.inner.into_payload()?;
+        if buf.len() < 4 {
+            return Err(ErrorKind::UnexpectedEof {
+                remaining: buf.len(),
+                budget: None,
+                needed: Some(4 - buf.len()),
+            });
+        }
+        let (payload, checksum) = buf.split_at_mut(buf.len() - 4);
+        let expected = LittleEndian::read_u32(checksum);
+        let actual = crc32(0, payload);
+        if expected != actual {
+            return Err(ErrorKind::ChecksumMismatch {
+                expected,
+                actual,
+            });
+        }
+        Ok(payload)
+    }
+}
+
+/// Continues the CRC-32 (IEEE) `crc` over `bytes`. Start from `0`.
+pub(crate) fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
@@ -0,0 +1,314 @@
+//! Self-delimiting messages: a `u32` length prefix followed by the encoded
+//! value.
+//!
+//! The prefix is written in the configured endianness and always takes four
+//! bytes, whatever the `LengthEncoding`; frames read by a `StreamDeserializer`
+//! use the length encoding instead, see `stream::write_frame`.
+//!
+//! `decode` checks the prefix against the bytes at hand before decoding
+//! anything. A frame that isn't all there yet fails with `UnexpectedEof`,
+//! whose `needed` says how many more bytes to wait for, while a prefix that
+//! doesn't match the value behind it fails with `InvalidFrameLength`.
+//!
+//! ```rust
+//! extern crate bincode;
+//! use bincode::arrayvec::ArrayVec;
+//! use bincode::{frame, ErrorKind};
+//!
+//! fn main() {
+//!     let mut buf: ArrayVec<[u8; 64]> = ArrayVec::new();
+//!     frame::encode(&(7u8, "seven"), &mut buf).unwrap();
+//!     frame::encode(&(8u8, "eight"), &mut buf).unwrap();
+//!     assert_eq!(&buf[..4], &[14, 0, 0, 0]);
+//!
+//!     let (first, rest): ((u8, &str), _) = frame::decode(&buf).unwrap();
+//!     assert_eq!(first, (7, "seven"));
+//!     match frame::decode::<(u8, &str)>(&rest[..10]) {
+//!         Err(ErrorKind::UnexpectedEof { needed: Some(8), .. }) => {}
+//!         other => panic!("{:?}", other),
+//!     }
+//!     let (second, rest): ((u8, &str), _) = frame::decode(rest).unwrap();
+//!     assert_eq!(second, (8, "eight"));
+//!     assert!(rest.is_empty());
+//! }
+//! ```
+//!
+//! A frame can also start with a 4-byte magic number, so a reader that lost
+//! track of the frames, e.g. after corrupted input, can find the next one with
+//! `scan_for_magic`. `decode_with_magic` checks the magic number before
+//! anything else, failing with `MagicMismatch`.
+//!
+//! ```rust
+//! extern crate bincode;
+//! use bincode::arrayvec::ArrayVec;
+//! use bincode::frame;
+//!
+//! const MAGIC: [u8; 4] = *b"BNC1";
+//!
+//! fn main() {
+//!     let mut buf: ArrayVec<[u8; 64]> = ArrayVec::new();
+//!     buf.extend([0xFF, 0x42, 0x00].iter().cloned());
+//!     frame::encode_with_magic(MAGIC, &(7u8, "seven"), &mut buf).unwrap();
+//!
+//!     assert!(frame::decode_with_magic::<(u8, &str)>(MAGIC, &buf).is_err());
+//!     let start = frame::scan_for_magic(MAGIC, &buf).unwrap();
+//!     assert_eq!(start, 3);
+//!     let (value, rest): ((u8, &str), _) = frame::decode_with_magic(MAGIC, &buf[start..]).unwrap();
+//!     assert_eq!(value, (7, "seven"));
+//!     assert!(rest.is_empty());
+//! }
+//! ```
+
+use arrayvec::{Array, ArrayVec};
+use byteorder::ByteOrder;
+use serde;
+
+use {Config, DefaultOptions, ErrorKind, Options, Result};
+
+/// The number of bytes the length prefix takes up.
+pub const PREFIX_LEN: usize = 4;
+
+/// The number of bytes a magic number takes up.
+pub const MAGIC_LEN: usize = 4;
+
+/// Appends `value` to `buf` as a frame using the default configuration,
+/// returning the number of bytes appended, prefix included.
+///
+/// If this fails, `buf` is left as it was.
+pub fn encode<A, T: ?Sized>(value: &T, buf: &mut ArrayVec<A>) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+{
+    encode_with_options(value, buf, DefaultOptions::new())
+}
+
+/// Appends `value` to `buf` as a frame using `config`, see `encode`.
+pub fn encode_with_config<A, T: ?Sized>(value: &T, buf: &mut ArrayVec<A>, config: &Config) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+{
+    config.encode_frame(value, buf)
+}
+
+/// Appends `value` to `buf` as a frame using `options`, see `encode`.
+pub fn encode_with_options<A, T, O>(value: &T, buf: &mut ArrayVec<A>, options: O) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: ?Sized + serde::Serialize,
+    O: Options,
+{
+    let start = buf.len();
+    let available = buf.capacity() - start;
+    if available < PREFIX_LEN {
+        return Err(ErrorKind::BufferTooSmall {
+            needed: PREFIX_LEN as u64 + ::internal::serialized_size(value, options)?,
+            available,
+            written: 0,
+        });
+    }
+
+    // Reserve the prefix and patch it once the length is known.
+    buf.extend([0; PREFIX_LEN].iter().cloned());
+    let len = match ::internal::serialize_into_array(&mut *buf, value, options) {
+        Ok(len) => len,
+        Err(e) => {
+            buf.truncate(start);
+            return Err(match e {
+                ErrorKind::BufferTooSmall { needed, written, .. } => ErrorKind::BufferTooSmall {
+                    needed: PREFIX_LEN as u64 + needed,
+                    available,
+                    written: PREFIX_LEN + written,
+                },
+                e => e,
+            });
+        }
+    };
+    if len as u64 > u32::MAX as u64 {
+        buf.truncate(start);
+        return Err(ErrorKind::LengthOverflow {
+            len: len as u64,
+            max: u32::MAX as u64,
+        });
+    }
+    O::Endian::write_u32(&mut buf[start..start + PREFIX_LEN], len as u32);
+    Ok(PREFIX_LEN + len)
+}
+
+/// Decodes the frame at the start of `bytes` using the default configuration,
+/// returning the value and the bytes after the frame.
+pub fn decode<'a, T>(bytes: &'a [u8]) -> Result<(T, &'a [u8])>
+where
+    T: serde::Deserialize<'a>,
+{
+    decode_with_options(bytes, DefaultOptions::new())
+}
+
+/// Decodes the frame at the start of `bytes` using `config`, see `decode`.
+pub fn decode_with_config<'a, T>(bytes: &'a [u8], config: &Config) -> Result<(T, &'a [u8])>
+where
+    T: serde::Deserialize<'a>,
+{
+    config.decode_frame(bytes)
+}
+
+/// Decodes the frame at the start of `bytes` using `options`, see `decode`.
+pub fn decode_with_options<'a, T, O>(bytes: &'a [u8], mut options: O) -> Result<(T, &'a [u8])>
+where
+    T: serde::Deserialize<'a>,
+    O: Options,
+{
+    if bytes.len() < PREFIX_LEN {
+        return Err(ErrorKind::UnexpectedEof {
+            remaining: bytes.len(),
+            budget: None,
+            needed: Some(PREFIX_LEN - bytes.len()),
+        });
+    }
+    let len = ::internal::peek_tag(bytes, &mut options)?;
+    let body = &bytes[PREFIX_LEN..];
+    if len as u64 > body.len() as u64 {
+        return Err(ErrorKind::UnexpectedEof {
+            remaining: body.len(),
+            budget: None,
+            needed: Some(len as usize - body.len()),
+        });
+    }
+
+    let (payload, rest) = body.split_at(len as usize);
+    match ::internal::deserialize_prefix(payload, options) {
+        Ok((value, used)) if used == payload.len() => Ok((value, rest)),
+        // The value ends before the frame does, or runs past it.
+        Ok(_) | Err(ErrorKind::UnexpectedEof { .. }) => Err(ErrorKind::InvalidFrameLength(len)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Appends `magic` and then `value` as a frame to `buf` using the default
+/// configuration, returning the number of bytes appended, magic number and
+/// prefix included.
+///
+/// If this fails, `buf` is left as it was.
+pub fn encode_with_magic<A, T: ?Sized>(magic: [u8; MAGIC_LEN], value: &T, buf: &mut ArrayVec<A>) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+{
+    encode_with_magic_and_options(magic, value, buf, DefaultOptions::new())
+}
+
+/// Appends `magic` and then `value` as a frame to `buf` using `config`, see
+/// `encode_with_magic`.
+pub fn encode_with_magic_and_config<A, T: ?Sized>(
+    magic: [u8; MAGIC_LEN],
+    value: &T,
+    buf: &mut ArrayVec<A>,
+    config: &Config,
+) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+{
+    config.encode_frame_with_magic(magic, value, buf)
+}
+
+/// Appends `magic` and then `value` as a frame to `buf` using `options`, see
+/// `encode_with_magic`.
+pub fn encode_with_magic_and_options<A, T, O>(
+    magic: [u8; MAGIC_LEN],
+    value: &T,
+    buf: &mut ArrayVec<A>,
+    options: O,
+) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: ?Sized + serde::Serialize,
+    O: Options,
+{
+    let start = buf.len();
+    let available = buf.capacity() - start;
+    if available < MAGIC_LEN {
+        return Err(ErrorKind::BufferTooSmall {
+            needed: (MAGIC_LEN + PREFIX_LEN) as u64 + ::internal::serialized_size(value, options)?,
+            available,
+            written: 0,
+        });
+    }
+
+    buf.extend(magic.iter().cloned());
+    match encode_with_options(value, buf, options) {
+        Ok(len) => Ok(MAGIC_LEN + len),
+        Err(e) => {
+            buf.truncate(start);
+            Err(match e {
+                ErrorKind::BufferTooSmall { needed, written, .. } => ErrorKind::BufferTooSmall {
+                    needed: MAGIC_LEN as u64 + needed,
+                    available,
+                    written: MAGIC_LEN + written,
+                },
+                e => e,
+            })
+        }
+    }
+}
+
+/// Decodes the frame after the magic number at the start of `bytes` using the
+/// default configuration, returning the value and the bytes after the frame.
+///
+/// Fails with `MagicMismatch` if `bytes` doesn't start with `magic`, without
+/// looking at the rest.
+pub fn decode_with_magic<'a, T>(magic: [u8; MAGIC_LEN], bytes: &'a [u8]) -> Result<(T, &'a [u8])>
+where
+    T: serde::Deserialize<'a>,
+{
+    decode_with_magic_and_options(magic, bytes, DefaultOptions::new())
+}
+
+/// Decodes the frame after the magic number at the start of `bytes` using
+/// `config`, see `decode_with_magic`.
+pub fn decode_with_magic_and_config<'a, T>(magic: [u8; MAGIC_LEN], bytes: &'a [u8], config: &Config) -> Result<(T, &'a [u8])>
+where
+    T: serde::Deserialize<'a>,
+{
+    config.decode_frame_with_magic(magic, bytes)
+}
+
+/// Decodes the frame after the magic number at the start of `bytes` using
+/// `options`, see `decode_with_magic`.
+pub fn decode_with_magic_and_options<'a, T, O>(
+    magic: [u8; MAGIC_LEN],
+    bytes: &'a [u8],
+    options: O,
+) -> Result<(T, &'a [u8])>
+where
+    T: serde::Deserialize<'a>,
+    O: Options,
+{
+    if bytes.len() < MAGIC_LEN {
+        return Err(ErrorKind::UnexpectedEof {
+            remaining: bytes.len(),
+            budget: None,
+            needed: Some(MAGIC_LEN - bytes.len()),
+        });
+    }
+    let (found, frame) = bytes.split_at(MAGIC_LEN);
+    if found != magic {
+        return Err(ErrorKind::MagicMismatch {
+            expected: magic,
+            found: [found[0], found[1], found[2], found[3]],
+        });
+    }
+    decode_with_options(frame, options)
+}
+
+/// Returns the offset of the first occurrence of `magic` in `bytes`, where the
+/// next frame may start.
+///
+/// The magic number can also show up inside a frame, so a candidate that
+/// fails to decode should be skipped by searching again from the byte after
+/// it. `None` means no whole magic number was found, though the last
+/// `MAGIC_LEN - 1` bytes may still be the start of one.
+pub fn scan_for_magic(magic: [u8; MAGIC_LEN], bytes: &[u8]) -> Option<usize> {
+    bytes.windows(MAGIC_LEN).position(|window| window == magic)
+}
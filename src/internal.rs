@@ -1,9 +1,14 @@
 use arrayvec::{Array, ArrayVec};
+#[cfg(feature = "unchecked-slices")]
+use arrayvec::CapacityError;
+use byteorder::ByteOrder;
 use serde;
 
-use config::Options;
-// use de::read::BincodeRead;
-use {ErrorKind, Result};
+use config::{Options, OptionsExt};
+use flavors::{InputFlavor, OutputFlavor};
+use de::read::BincodeRead;
+use ser::spans::FieldSpan;
+use {Direction, ErrorKind, Result};
 
 #[derive(Clone)]
 struct CountSize<L: SizeLimit> {
@@ -11,43 +16,451 @@ struct CountSize<L: SizeLimit> {
     other_limit: L,
 }
 
-pub(crate) fn serialize_into<A, T: ?Sized, O>(
+pub(crate) fn serialize_into<W, T: ?Sized, O>(writer: W, value: &T, mut options: O) -> Result<W>
+where
+    W: OutputFlavor,
+    T: serde::Serialize,
+    O: Options,
+{
+    if options.limit().limit().is_some() {
+        // "compute" the size for the side-effect
+        // of returning Err if the bound was reached.
+        serialized_size(value, &mut options)?;
+    }
+
+    let mut serializer = ::ser::Serializer::<_, O>::new(writer, options);
+    serde::Serialize::serialize(value, &mut serializer)?;
+    Ok(serializer.into_inner())
+}
+
+pub(crate) fn serialize_with_flavor<F, T: ?Sized, O>(flavor: F, value: &T, options: O) -> Result<usize>
+where
+    F: OutputFlavor,
+    T: serde::Serialize,
+    O: Options,
+{
+    serialize_into(flavor, value, options)?.finalize()
+}
+
+/// Appends `value` to `writer`, truncating it back to its old length on failure, and
+/// returns the number of bytes appended.
+pub(crate) fn serialize_into_array<A, T: ?Sized, O>(writer: &mut ArrayVec<A>, value: &T, mut options: O) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+    O: Options,
+{
+    let start = writer.len();
+    let available = writer.capacity() - start;
+    let error = match serialize_into(Erased(&mut *writer), value, &mut options) {
+        Ok(_) => return Ok(writer.len() - start),
+        Err(e) => e,
+    };
+    let written = writer.len() - start;
+    writer.truncate(start);
+    match error {
+        // Only measure the whole value once it's known not to fit.
+        ErrorKind::CapacityError(_) => Err(ErrorKind::BufferTooSmall {
+            needed: serialized_size(value, options.with_no_limit())?,
+            available,
+            written,
+        }),
+        e => Err(e),
+    }
+}
+
+/// Appends `value` to `writer` like `serialize_into_array`, recording the fields of the
+/// outermost struct into `spans`. Returns the number of bytes appended and of fields.
+pub(crate) fn serialize_into_with_spans<A, T: ?Sized, O>(
     writer: &mut ArrayVec<A>,
     value: &T,
+    spans: &mut [FieldSpan],
     mut options: O,
-) -> Result<()>
+) -> Result<(usize, usize)>
 where
     A: Array<Item = u8>,
     T: serde::Serialize,
     O: Options,
 {
     if options.limit().limit().is_some() {
-        // "compute" the size for the side-effect
-        // of returning Err if the bound was reached.
         serialized_size(value, &mut options)?;
     }
 
-    let mut serializer = ::ser::Serializer::<_, O>::new(writer, options);
-    serde::Serialize::serialize(value, &mut serializer)
+    let start = writer.len();
+    let available = writer.capacity() - start;
+    let result = {
+        let mut serializer = ::ser::Serializer::new(Erased(&mut *writer), &mut options);
+        ::ser::spans::serialize(&mut serializer, value, spans, start)
+    };
+    let error = match result {
+        Ok(fields) => return Ok((writer.len() - start, fields)),
+        Err(e) => e,
+    };
+    let written = writer.len() - start;
+    writer.truncate(start);
+    match error {
+        ErrorKind::CapacityError(_) => Err(ErrorKind::BufferTooSmall {
+            needed: serialized_size(value, options.with_no_limit())?,
+            available,
+            written,
+        }),
+        e => Err(e),
+    }
+}
+
+/// The part of an `ArrayVec` the serializer needs, as a trait object. Writing
+/// through `Erased` instead of `&mut ArrayVec<A>` keeps the capacity out of the
+/// serializer's type, so its code is generated once per value type instead of
+/// once per value type and buffer size.
+trait ByteBuf {
+    fn try_push(&mut self, byte: u8) -> Result<()>;
+    fn write(&mut self, bytes: &[u8]) -> Result<()>;
+    fn rewrite(&mut self, back: usize, bytes: &[u8]) -> Result<()>;
+}
+
+impl<A: Array<Item = u8>> ByteBuf for ArrayVec<A> {
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        let mut vec = self;
+        OutputFlavor::try_push(&mut vec, byte)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        let mut vec = self;
+        OutputFlavor::write(&mut vec, bytes)
+    }
+
+    fn rewrite(&mut self, back: usize, bytes: &[u8]) -> Result<()> {
+        let mut vec = self;
+        OutputFlavor::rewrite(&mut vec, back, bytes)
+    }
+}
+
+struct Erased<'a>(&'a mut dyn ByteBuf);
+
+impl<'a> OutputFlavor for Erased<'a> {
+    #[inline(always)]
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        self.0.try_push(byte)
+    }
+
+    #[inline(always)]
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.0.write(bytes)
+    }
+
+    fn finalize(self) -> Result<usize> {
+        Ok(0)
+    }
+
+    fn can_rewrite(&self) -> bool {
+        true
+    }
+
+    fn rewrite(&mut self, back: usize, bytes: &[u8]) -> Result<()> {
+        self.0.rewrite(back, bytes)
+    }
+}
+
+pub(crate) fn serialize_into_checked<A, T: ?Sized, O>(
+    writer: &mut ArrayVec<A>,
+    value: &T,
+    mut options: O,
+) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+    O: Options,
+{
+    let needed = serialized_size(value, &mut options)?;
+    let available = writer.capacity() - writer.len();
+    if needed > available as u64 {
+        return Err(ErrorKind::BufferTooSmall { needed, available, written: 0 });
+    }
+
+    let start = writer.len();
+    #[cfg(feature = "unchecked-slices")]
+    let result = serialize_into(
+        Trusted {
+            vec: &mut *writer,
+            room: available,
+        },
+        value,
+        options.with_no_limit(),
+    );
+    #[cfg(not(feature = "unchecked-slices"))]
+    let result = serialize_into(&mut *writer, value, options.with_no_limit());
+    // The value fits, but can still fail halfway, e.g. in a `Serialize`
+    // impl that doesn't produce the same output twice.
+    if let Err(e) = result {
+        writer.truncate(start);
+        return Err(e);
+    }
+    Ok(writer.len() - start)
+}
+
+/// Returns whether the value was cut short because `writer` filled up.
+pub(crate) fn serialize_into_lossy<A, T: ?Sized, O>(writer: &mut ArrayVec<A>, value: &T, options: O) -> Result<bool>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+    O: Options,
+{
+    let result = serialize_into(Lossy(&mut *writer), value, options).map(|_| ());
+    match result {
+        Ok(()) => Ok(false),
+        // `Lossy` only runs out of room once it has filled the buffer.
+        Err(ErrorKind::CapacityError(_)) if writer.is_full() => Ok(true),
+        Err(e) => Err(e),
+    }
 }
 
-// pub(crate) fn serialize<T: ?Sized, O>(value: &T, mut options: O) -> Result<Vec<u8>>
-// where
-//     T: serde::Serialize,
-//     O: Options,
-// {
-//     let mut writer = {
-//         let actual_size = serialized_size(value, &mut options)?;
-//         Vec::with_capacity(actual_size as usize)
-//     };
+/// Writes into an `ArrayVec` like the plain flavor, but fills it up to the
+/// last byte before running out of room, so a cut-off value leaves as much
+/// of its encoding behind as fits.
+///
+/// It doesn't rewrite its output: a sequence of unknown length cut off before
+/// its end would leave its zero length prefix behind, followed by elements.
+struct Lossy<'a, A: 'a + Array<Item = u8>>(&'a mut ArrayVec<A>);
 
-//     serialize_into(&mut writer, value, options.with_no_limit())?;
-//     Ok(writer)
-// }
+impl<'a, A: Array<Item = u8>> OutputFlavor for Lossy<'a, A> {
+    #[inline(always)]
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        self.0.try_push(byte).map_err(Into::into)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        let room = self.0.capacity() - self.0.len();
+        if bytes.len() > room {
+            self.0.extend(bytes[..room].iter().cloned());
+            return Err(::arrayvec::CapacityError::new(bytes[room]).into());
+        }
+        self.0.extend(bytes.iter().cloned());
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<usize> {
+        Ok(self.0.len())
+    }
+}
+
+pub(crate) fn serialize_into_with_crc<A, T: ?Sized, O>(writer: &mut ArrayVec<A>, value: &T, mut options: O) -> Result<()>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+    O: Options,
+{
+    let start = writer.len();
+    let available = writer.capacity() - start;
+    let result = serialize_into(::flavors::Crc32::new(Erased(&mut *writer)), value, &mut options).and_then(|flavor| {
+        let (mut writer, crc) = flavor.into_parts();
+        let mut buf = [0; 4];
+        O::Endian::write_u32(&mut buf, crc);
+        writer.write(&buf)
+    });
+    match result {
+        Err(ErrorKind::CapacityError(_)) => Err(ErrorKind::BufferTooSmall {
+            needed: serialized_size(value, options.with_no_limit())? + 4,
+            available,
+            written: writer.len() - start,
+        }),
+        result => result,
+    }
+}
+
+/// Writes into an `ArrayVec` that was checked to have room for the whole
+/// value. The room is counted down once per write instead of once per byte,
+/// so a value that serializes differently the second time around still runs
+/// out with the same `CapacityError` as the plain `ArrayVec` flavor.
+#[cfg(feature = "unchecked-slices")]
+struct Trusted<'a, A: 'a + Array<Item = u8>> {
+    vec: &'a mut ArrayVec<A>,
+    room: usize,
+}
+
+#[cfg(feature = "unchecked-slices")]
+impl<'a, A: Array<Item = u8>> OutputFlavor for Trusted<'a, A> {
+    #[inline(always)]
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        if self.room == 0 {
+            return Err(CapacityError::new(byte).into());
+        }
+        self.room -= 1;
+        ::unsafe_ops::push_unchecked(self.vec, byte);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        if bytes.len() > self.room {
+            return Err(CapacityError::new(bytes[self.room]).into());
+        }
+        self.room -= bytes.len();
+        for &byte in bytes {
+            ::unsafe_ops::push_unchecked(self.vec, byte);
+        }
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<usize> {
+        Ok(self.vec.len())
+    }
+
+    fn can_rewrite(&self) -> bool {
+        true
+    }
+
+    fn rewrite(&mut self, back: usize, bytes: &[u8]) -> Result<()> {
+        let mut vec = &mut *self.vec;
+        OutputFlavor::rewrite(&mut vec, back, bytes)
+    }
+}
+
+pub(crate) fn serialize_into_slice<T: ?Sized, O>(out: &mut [u8], value: &T, mut options: O) -> Result<usize>
+where
+    T: serde::Serialize,
+    O: Options,
+{
+    let available = out.len();
+    let mut slice = ::flavors::Slice::new(out);
+    let tally = Tally {
+        inner: &mut slice,
+        written: 0,
+    };
+    match serialize_with_flavor(tally, value, &mut options) {
+        Ok(_) => Ok(slice.written()),
+        Err(ErrorKind::CapacityError(_)) => Err(ErrorKind::BufferTooSmall {
+            needed: serialized_size(value, options.with_no_limit())?,
+            available,
+            written: slice.written(),
+        }),
+        Err(e) => Err(e),
+    }
+}
+
+
+/// Overwrites the `T` encoded at `offset` in `buf` with `value`, which has to encode to
+/// the same number of bytes.
+pub(crate) fn patch<T, O>(buf: &mut [u8], offset: usize, value: &T, mut options: O) -> Result<()>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+    O: Options,
+{
+    if offset > buf.len() {
+        return Err(ErrorKind::UnexpectedEof {
+            remaining: 0,
+            budget: None,
+            needed: Some(offset - buf.len()),
+        });
+    }
+    let (_, region): (T, usize) = deserialize_prefix(&buf[offset..], &mut options)?;
+    let encoded = serialized_size(value, &mut options)?;
+    if encoded != region as u64 {
+        return Err(ErrorKind::PatchLengthMismatch { region, encoded });
+    }
+    serialize_into_slice(&mut buf[offset..offset + region], value, options)?;
+    Ok(())
+}
+
+/// Passes bytes on to a borrowed flavor, counting them.
+struct Tally<'a, W: 'a> {
+    inner: &'a mut W,
+    written: usize,
+}
+
+impl<'a, W: OutputFlavor> OutputFlavor for Tally<'a, W> {
+    fn try_push(&mut self, byte: u8) -> Result<()> {
+        self.inner.try_push(byte)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.inner.write(bytes)?;
+        self.written += bytes.len();
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<usize> {
+        Ok(self.written)
+    }
+
+    fn can_rewrite(&self) -> bool {
+        self.inner.can_rewrite()
+    }
+
+    fn rewrite(&mut self, back: usize, bytes: &[u8]) -> Result<()> {
+        self.inner.rewrite(back, bytes)
+    }
+}
+
+pub(crate) fn serialize_prefix<W, T: ?Sized, O>(writer: &mut W, value: &T, options: O) -> Result<usize>
+where
+    W: OutputFlavor,
+    T: serde::Serialize,
+    O: Options,
+{
+    let tally = Tally {
+        inner: writer,
+        written: 0,
+    };
+    serialize_with_flavor(tally, value, options)
+}
+
+pub(crate) fn serialize_len<W, O>(writer: &mut W, len: usize, options: O) -> Result<usize>
+where
+    W: OutputFlavor,
+    O: Options,
+{
+    let tally = Tally {
+        inner: writer,
+        written: 0,
+    };
+    let mut serializer = ::ser::Serializer::<_, O>::new(tally, options);
+    serializer.serialize_len(len)?;
+    serializer.into_inner().finalize()
+}
+
+pub(crate) fn serialize_padded<W, T: ?Sized, O>(
+    mut writer: W,
+    value: &T,
+    slot_size: usize,
+    pad: u8,
+    mut options: O,
+) -> Result<usize>
+where
+    W: OutputFlavor,
+    T: serde::Serialize,
+    O: Options,
+{
+    if serialized_size(value, &mut options)? > slot_size as u64 {
+        return Err(ErrorKind::SizeLimit(Direction::Serialize));
+    }
+
+    let written = serialize_prefix(&mut writer, value, options)?;
+    for _ in written..slot_size {
+        writer.try_push(pad)?;
+    }
+    writer.finalize()
+}
+
+#[cfg(feature = "alloc")]
+pub(crate) fn serialize_to_vec<T: ?Sized, O>(value: &T, mut options: O) -> Result<::alloc::vec::Vec<u8>>
+where
+    T: serde::Serialize,
+    O: Options,
+{
+    let mut writer = {
+        let actual_size = serialized_size(value, &mut options)?;
+        ::alloc::vec::Vec::with_capacity(actual_size as usize)
+    };
+
+    serialize_into(&mut writer, value, options.with_no_limit())?;
+    Ok(writer)
+}
 
 impl<L: SizeLimit> SizeLimit for CountSize<L> {
-    fn add(&mut self, c: u64) -> Result<()> {
-        self.other_limit.add(c)?;
+    fn add(&mut self, c: u64, direction: Direction) -> Result<()> {
+        self.other_limit.add(c, direction)?;
         self.total += c;
         Ok(())
     }
@@ -55,6 +468,10 @@ impl<L: SizeLimit> SizeLimit for CountSize<L> {
     fn limit(&self) -> Option<u64> {
         unreachable!();
     }
+
+    fn record(&mut self, container: &'static str, name: &'static str, bytes: u64) {
+        self.other_limit.record(container, name, bytes)
+    }
 }
 
 pub(crate) fn serialized_size<T: ?Sized, O: Options>(value: &T, mut options: O) -> Result<u64>
@@ -62,50 +479,62 @@ where
     T: serde::Serialize,
 {
     let old_limiter = options.limit().clone();
-    let mut size_counter = ::ser::SizeChecker {
-        options: ::config::WithOtherLimit::new(
-            options,
-            CountSize {
-                total: 0,
-                other_limit: old_limiter,
-            },
-        ),
-    };
+    let mut size_counter = ::ser::SizeChecker::new(::config::WithOtherLimit::new(
+        options,
+        CountSize {
+            total: 0,
+            other_limit: old_limiter,
+        },
+    ));
 
     let result = value.serialize(&mut size_counter);
     result.map(|_| size_counter.options.new_limit.total)
 }
 
-// pub(crate) fn deserialize_from<R, T, O>(reader: R, options: O) -> Result<T>
-// where
-//     R: Read,
-//     T: serde::de::DeserializeOwned,
-//     O: Options,
-// {
-//     let reader = ::de::read::IoReader::new(reader);
-//     let mut deserializer = ::de::Deserializer::<_, O>::new(reader, options);
-//     serde::Deserialize::deserialize(&mut deserializer)
-// }
-
-// pub(crate) fn deserialize_from_custom<'a, R, T, O>(reader: R, options: O) -> Result<T>
-// where
-//     R: BincodeRead<'a>,
-//     T: serde::de::DeserializeOwned,
-//     O: Options,
-// {
-//     let mut deserializer = ::de::Deserializer::<_, O>::new(reader, options);
-//     serde::Deserialize::deserialize(&mut deserializer)
-// }
-
-// pub(crate) fn deserialize_in_place<'a, R, T, O>(reader: R, options: O, place: &mut T) -> Result<()>
-// where
-//     R: BincodeRead<'a>,
-//     T: serde::de::Deserialize<'a>,
-//     O: Options,
-// {
-//     let mut deserializer = ::de::Deserializer::<_, _>::new(reader, options);
-//     serde::Deserialize::deserialize_in_place(&mut deserializer, place)
-// }
+pub(crate) fn serialized_size_bounded<T: ?Sized, O: Options>(value: &T, max: u64, mut options: O) -> Result<Option<u64>>
+where
+    T: serde::Serialize,
+{
+    // A configured limit that is tighter still applies.
+    let max = options.limit().limit().map_or(max, |limit| limit.min(max));
+    match serialized_size(value, ::config::WithOtherLimit::new(options, Bounded(max))) {
+        Ok(size) => Ok(Some(size)),
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn deserialize_from<R, T, O>(reader: R, options: O) -> Result<T>
+where
+    R: ::std::io::Read,
+    T: serde::de::DeserializeOwned,
+    O: Options,
+{
+    let reader = ::de::read::IoReader::new(reader);
+    let mut deserializer = ::de::Deserializer::<_, O>::new(reader, options);
+    serde::Deserialize::deserialize(&mut deserializer)
+}
+
+pub(crate) fn deserialize_from_custom<'a, R, T, O>(reader: R, options: O) -> Result<T>
+where
+    R: BincodeRead<'a>,
+    T: serde::de::Deserialize<'a>,
+    O: Options,
+{
+    let mut deserializer = ::de::Deserializer::<_, O>::new(reader, options);
+    serde::Deserialize::deserialize(&mut deserializer)
+}
+
+pub(crate) fn deserialize_in_place<'a, T, O>(bytes: &'a [u8], place: &mut T, options: O) -> Result<()>
+where
+    T: serde::de::Deserialize<'a>,
+    O: Options,
+{
+    let reader = ::de::read::SliceReader::new(bytes);
+    let mut deserializer = ::de::Deserializer::new(reader, options);
+    serde::Deserialize::deserialize_in_place(&mut deserializer, place)
+}
 
 pub(crate) fn deserialize<'a, T, O>(bytes: &'a [u8], options: O) -> Result<T>
 where
@@ -113,17 +542,252 @@ where
     O: Options,
 {
     let reader = ::de::read::SliceReader::new(bytes);
-    let options = ::config::WithOtherLimit::new(options, Infinite);
     let mut deserializer = ::de::Deserializer::new(reader, options);
     serde::Deserialize::deserialize(&mut deserializer)
 }
 
-pub(crate) trait SizeLimit: Clone {
-    /// Tells the SizeLimit that a certain number of bytes has been
-    /// read or written.  Returns Err if the limit has been exceeded.
-    fn add(&mut self, n: u64) -> Result<()>;
-    /// Returns the hard limit (if one exists)
+pub(crate) fn deserialize_seed<'a, S, O>(seed: S, bytes: &'a [u8], options: O) -> Result<S::Value>
+where
+    S: serde::de::DeserializeSeed<'a>,
+    O: Options,
+{
+    let reader = ::de::read::SliceReader::new(bytes);
+    let mut deserializer = ::de::Deserializer::new(reader, options);
+    seed.deserialize(&mut deserializer)
+}
+
+pub(crate) fn peek_tag<O: Options>(bytes: &[u8], _options: O) -> Result<u32> {
+    ::de::read::SliceReader::new(bytes).peek_u32::<O::Endian>()
+}
+
+/// The number of bytes the version of a versioned envelope takes up.
+const VERSION_LEN: usize = 4;
+
+/// Appends `version` as a `u32` and then `value` to `writer`, like
+/// `serialize_into_array`. The value is serialized on its own, as if it started
+/// the buffer, so it can be decoded from the bytes after the version.
+pub(crate) fn encode_versioned<A, T: ?Sized, O>(version: u32, value: &T, writer: &mut ArrayVec<A>, options: O) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+    O: Options,
+{
+    let start = writer.len();
+    let available = writer.capacity() - start;
+    if available < VERSION_LEN {
+        return Err(ErrorKind::BufferTooSmall {
+            needed: VERSION_LEN as u64 + serialized_size(value, options.with_no_limit())?,
+            available,
+            written: 0,
+        });
+    }
+
+    let mut prefix = [0; VERSION_LEN];
+    O::Endian::write_u32(&mut prefix, version);
+    writer.extend(prefix.iter().cloned());
+    match serialize_into_array(&mut *writer, value, options) {
+        Ok(len) => Ok(VERSION_LEN + len),
+        Err(e) => {
+            writer.truncate(start);
+            Err(match e {
+                ErrorKind::BufferTooSmall { needed, written, .. } => ErrorKind::BufferTooSmall {
+                    needed: VERSION_LEN as u64 + needed,
+                    available,
+                    written: VERSION_LEN + written,
+                },
+                e => e,
+            })
+        }
+    }
+}
+
+/// Reads the version at the start of `bytes` and hands it to `f` along with
+/// the bytes after it.
+pub(crate) fn decode_versioned_with<'a, F, R, O>(bytes: &'a [u8], f: F, options: O) -> Result<R>
+where
+    F: FnOnce(u32, &'a [u8]) -> Result<R>,
+    O: Options,
+{
+    let version = peek_tag(bytes, options)?;
+    f(version, &bytes[VERSION_LEN..])
+}
+
+pub(crate) fn decode_versioned<'a, T, O>(bytes: &'a [u8], mut options: O) -> Result<(u32, T)>
+where
+    T: serde::de::Deserialize<'a>,
+    O: Options,
+{
+    let version = peek_tag(bytes, &mut options)?;
+    let value = deserialize(&bytes[VERSION_LEN..], options)?;
+    Ok((version, value))
+}
+
+pub(crate) fn deserialize_with_crc<'a, T, O>(bytes: &'a [u8], options: O) -> Result<T>
+where
+    T: serde::de::Deserialize<'a>,
+    O: Options,
+{
+    let mut reader = ::de::read::CrcReader::new(::de::read::SliceReader::new(bytes));
+    let value = {
+        let mut deserializer = ::de::Deserializer::new(&mut reader, options);
+        serde::Deserialize::deserialize(&mut deserializer)?
+    };
+    let actual = reader.crc();
+    let expected = reader.into_inner().read_u32::<O::Endian>()?;
+    if expected != actual {
+        return Err(ErrorKind::ChecksumMismatch { expected, actual });
+    }
+    Ok(value)
+}
+
+pub(crate) fn deserialize_with_flavor<'a, F, T, O>(flavor: F, options: O) -> Result<T>
+where
+    F: InputFlavor<'a>,
+    T: serde::de::Deserialize<'a>,
+    O: Options,
+{
+    let payload = flavor.into_payload()?;
+    deserialize(payload, options)
+}
+
+pub(crate) fn deserialize_exact<'a, T, O>(bytes: &'a [u8], options: O) -> Result<T>
+where
+    T: serde::de::Deserialize<'a>,
+    O: Options,
+{
+    let reader = ::de::read::SliceReader::new(bytes);
+    let mut deserializer = ::de::Deserializer::new(reader, options);
+    let value = serde::Deserialize::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+pub(crate) fn deserialize_prefix<'a, T, O>(bytes: &'a [u8], options: O) -> Result<(T, usize)>
+where
+    T: serde::de::Deserialize<'a>,
+    O: Options,
+{
+    let reader = ::de::read::SliceReader::new(bytes);
+    let mut deserializer = ::de::Deserializer::new(reader, options);
+    let value = serde::Deserialize::deserialize(&mut deserializer)?;
+    Ok((value, deserializer.bytes_read()))
+}
+
+pub(crate) fn deserialize_partial<'a, T, O>(bytes: &'a [u8], options: O) -> Result<(T, &'a [u8])>
+where
+    T: serde::de::Deserialize<'a>,
+    O: Options,
+{
+    let (value, read) = deserialize_prefix(bytes, options)?;
+    Ok((value, &bytes[read..]))
+}
+
+pub(crate) fn deserialize_padded<'a, T, O>(slot: &'a [u8], slot_size: usize, pad: u8, options: O) -> Result<T>
+where
+    T: serde::de::Deserialize<'a>,
+    O: Options,
+{
+    if slot.len() < slot_size {
+        return Err(ErrorKind::UnexpectedEof {
+            remaining: slot.len(),
+            budget: None,
+            needed: Some(slot_size - slot.len()),
+        });
+    }
+    let slot = &slot[..slot_size];
+    let (value, read) = deserialize_prefix(slot, options)?;
+    match slot[read..].iter().position(|&b| b != pad) {
+        Some(i) => Err(ErrorKind::DirtyPadding {
+            offset: read + i,
+            found: slot[read + i],
+        }),
+        None => Ok(value),
+    }
+}
+
+pub(crate) fn deserialize_len<O: Options>(bytes: &[u8], options: O) -> Result<(usize, usize)> {
+    let reader = ::de::read::SliceReader::new(bytes);
+    let options = ::config::WithOtherLimit::new(options, Infinite);
+    let mut deserializer = ::de::Deserializer::new(reader, options);
+    let len = deserializer.read_len()?;
+    Ok((len, bytes.len() - deserializer.remaining()))
+}
+
+/// The byte limit of a set of `Options`, e.g. `Bounded` or `Infinite`.
+///
+/// A limit is charged for one value at a time: every (de)serialization starts
+/// from the limit in the options it was given, and `add` is called once for
+/// each piece of the value as it is read or measured, so the calls add up to
+/// the value's encoded size. A piece is a number, tag or length prefix, the
+/// padding before a number when aligning, or the whole contents of a string
+/// or byte slice. That lets a limit tell a single large string apart from a
+/// large value made of many small pieces.
+///
+/// Deserializing charges the limit as it reads. Serializing charges a clone
+/// of it while measuring the value up front, and only if `limit` returns
+/// `Some`; the value is then written without consulting it.
+///
+/// A limiter that fails once memory runs low:
+///
+/// ```rust
+/// extern crate bincode;
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use bincode::{Direction, DefaultOptions, ErrorKind, OptionsExt, Result, SizeLimit};
+///
+/// static LOW_MEMORY: AtomicBool = AtomicBool::new(false);
+///
+/// /// At most `total` bytes, and no strings over 64 bytes while memory is low.
+/// #[derive(Clone)]
+/// struct Pressure {
+///     total: u64,
+/// }
+///
+/// impl SizeLimit for Pressure {
+///     fn add(&mut self, n: u64, direction: Direction) -> Result<()> {
+///         if n > self.total || (n > 64 && LOW_MEMORY.load(Ordering::Relaxed)) {
+///             return Err(ErrorKind::SizeLimit(direction));
+///         }
+///         self.total -= n;
+///         Ok(())
+///     }
+///
+///     fn limit(&self) -> Option<u64> {
+///         Some(self.total)
+///     }
+/// }
+///
+/// fn main() {
+///     let mut input = vec![100, 0, 0, 0, 0, 0, 0, 0];
+///     input.extend(std::iter::repeat(b'x').take(100));
+///
+///     let options = DefaultOptions::new().with_size_limit(Pressure { total: 1024 });
+///     let s: &str = bincode::deserialize_with_options(&input, options).unwrap();
+///     assert_eq!(s.len(), 100);
+///
+///     LOW_MEMORY.store(true, Ordering::Relaxed);
+///     let options = DefaultOptions::new().with_size_limit(Pressure { total: 1024 });
+///     match bincode::deserialize_with_options::<&str, _>(&input, options) {
+///         Err(ErrorKind::SizeLimit(Direction::Deserialize)) => {}
+///         other => panic!("{:?}", other),
+///     }
+/// }
+/// ```
+pub trait SizeLimit: Clone {
+    /// Charges `n` more bytes of the current value to the limit. Returns
+    /// `ErrorKind::SizeLimit(direction)` if they don't fit, which aborts the
+    /// (de)serialization.
+    fn add(&mut self, n: u64, direction: Direction) -> Result<()>;
+    /// The number of bytes left, or `None` if there is no fixed number. This
+    /// is what `UnexpectedEof` reports as the `budget`.
     fn limit(&self) -> Option<u64>;
+
+    /// Called while measuring a value, e.g. by `serialized_size`, with the
+    /// bytes taken by each struct field, under the name of the struct or
+    /// struct variant, and by each enum variant, index included, under the
+    /// name of the enum. Nested values count towards every field and variant
+    /// around them. Does nothing by default; see `profile::Profile`.
+    #[inline(always)]
+    fn record(&mut self, _container: &'static str, _name: &'static str, _bytes: u64) {}
 }
 
 /// A SizeLimit that restricts serialized or deserialized messages from
@@ -138,12 +802,12 @@ pub struct Infinite;
 
 impl SizeLimit for Bounded {
     #[inline(always)]
-    fn add(&mut self, n: u64) -> Result<()> {
+    fn add(&mut self, n: u64, direction: Direction) -> Result<()> {
         if self.0 >= n {
             self.0 -= n;
             Ok(())
         } else {
-            Err(ErrorKind::SizeLimit)
+            Err(ErrorKind::SizeLimit(direction))
         }
     }
 
@@ -155,7 +819,7 @@ impl SizeLimit for Bounded {
 
 impl SizeLimit for Infinite {
     #[inline(always)]
-    fn add(&mut self, _: u64) -> Result<()> {
+    fn add(&mut self, _: u64, _: Direction) -> Result<()> {
         Ok(())
     }
 
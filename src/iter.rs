@@ -0,0 +1,88 @@
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+
+use serde;
+
+use {config, Config, ErrorKind, Result};
+
+/// An iterator over values of one type encoded back to back in a slice, see
+/// `deserialize_iter`.
+///
+/// It ends once the input is used up exactly. A value that fails to decode,
+/// including one cut short by the end of the input, is yielded as an error
+/// and ends the iteration; `offset` then tells where that value started.
+/// Values may borrow from the slice.
+///
+/// ```rust
+/// extern crate bincode;
+/// use bincode::arrayvec::ArrayVec;
+/// use bincode::deserialize_iter;
+///
+/// fn main() {
+///     let mut log: ArrayVec<[u8; 32]> = ArrayVec::new();
+///     for record in &[(1u8, 'a'), (2, 'b'), (3, 'c')] {
+///         bincode::serialize_into(&mut log, record).unwrap();
+///     }
+///
+///     let records: Result<Vec<(u8, char)>, _> = deserialize_iter(&log).collect();
+///     assert_eq!(records.unwrap(), [(1, 'a'), (2, 'b'), (3, 'c')]);
+/// }
+/// ```
+pub struct DeserializeIter<'a, T> {
+    bytes: &'a [u8],
+    offset: usize,
+    config: Config,
+    failed: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> DeserializeIter<'a, T> {
+    /// Creates an iterator over `bytes` using the default configuration.
+    pub fn new(bytes: &'a [u8]) -> DeserializeIter<'a, T> {
+        DeserializeIter::with_config(bytes, config())
+    }
+
+    /// Creates an iterator over `bytes` using `config`.
+    pub fn with_config(bytes: &'a [u8], config: Config) -> DeserializeIter<'a, T> {
+        DeserializeIter {
+            bytes,
+            offset: 0,
+            config,
+            failed: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The offset of the next value, or of the one that failed to decode.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a, T: serde::Deserialize<'a>> Iterator for DeserializeIter<'a, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        let rest = &self.bytes[self.offset..];
+        if self.failed || rest.is_empty() {
+            return None;
+        }
+        match self.config.deserialize_prefix(rest) {
+            Ok((value, read)) if read > 0 => {
+                self.offset += read;
+                Some(Ok(value))
+            }
+            // A value that takes up no bytes would never use up the input.
+            Ok(_) => {
+                self.failed = true;
+                Some(Err(ErrorKind::TrailingBytes(rest.len())))
+            }
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<'a, T: serde::Deserialize<'a>> FusedIterator for DeserializeIter<'a, T> {}
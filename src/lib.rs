@@ -9,13 +9,15 @@
 //!
 //! ```rust
 //! extern crate bincode;
-//! use bincode::{serialize, deserialize};
+//! use bincode::arrayvec::ArrayVec;
+//! use bincode::{serialize_into, deserialize};
 //! fn main() {
 //!     // The object that we will serialize.
-//!     let target: Option<String>  = Some("hello world".to_string());
+//!     let target: Option<&str>  = Some("hello world");
 //!
-//!     let encoded: Vec<u8>        = serialize(&target).unwrap();
-//!     let decoded: Option<String> = deserialize(&encoded[..]).unwrap();
+//!     let mut encoded: ArrayVec<[u8; 32]> = ArrayVec::new();
+//!     serialize_into(&mut encoded, &target).unwrap();
+//!     let decoded: Option<&str> = deserialize(&encoded[..]).unwrap();
 //!     assert_eq!(target, decoded);
 //! }
 //! ```
@@ -30,28 +32,88 @@
 //! [dependencies.bincode]
 //! features = ["i128"]
 //! ```
+//!
+//! Without it, (de)serializing either type fails with
+//! `ErrorKind::U128Unsupported`.
 
 #![no_std]
+#![deny(unsafe_code)]
+#![cfg_attr(not(feature = "unchecked-slices"), forbid(unsafe_code))]
 #![crate_name = "bincode"]
 #![crate_type = "rlib"]
 #![crate_type = "dylib"]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 extern crate byteorder;
 #[macro_use]
 extern crate serde;
 pub extern crate arrayvec;
+#[cfg(feature = "bytes")]
+pub extern crate bytes;
+#[cfg(feature = "embedded-io")]
+pub extern crate embedded_io;
+#[cfg(feature = "heapless")]
+pub extern crate heapless;
 
+#[cfg(feature = "arrays")]
+pub mod arrays;
+mod buffer;
+pub mod byte_array;
 mod config;
 mod de;
 mod error;
+mod ext;
+pub mod fingerprint;
+pub mod flavors;
+pub mod frame;
 mod internal;
+mod iter;
+pub mod max_size;
+pub mod migration;
+pub mod nul_str;
+pub mod packed;
+pub mod presets;
+pub mod primitives;
+#[cfg(feature = "profile")]
+pub mod profile;
 mod ser;
+#[cfg(feature = "stats")]
+pub mod stats;
+pub mod str_filters;
+pub mod stream;
+pub mod stride;
+#[cfg(feature = "unchecked-slices")]
+mod unsafe_ops;
 
 use arrayvec::{Array, ArrayVec};
 
-pub use config::Config;
-pub use de::read::{BincodeRead, SliceReader};
-pub use error::{Error, ErrorKind, Result};
+pub use buffer::BufferSerializer;
+pub use config::{
+    Config, DefaultOptions, LengthEncoding, Options, OptionsExt, StrFilter, WithOtherEndian, WithOtherLimit,
+};
+pub use de::read::{
+    BincodeRead, BlockCachedReader, ChainedSliceReader, Checkpoint, Chunk, CrcReader, DynReader, ObjectRead,
+    ScratchReader, SliceReader,
+};
+#[cfg(feature = "bytes")]
+pub use de::read::BufReader;
+#[cfg(feature = "embedded-io")]
+pub use de::read::EmbeddedIoReader;
+#[cfg(feature = "std")]
+pub use de::read::IoReader;
+pub use de::{Deserializer, Marker};
+pub use error::{Direction, Error, ErrorKind, Result};
+pub use ext::{BincodeDeExt, BincodeExt};
+pub use flavors::{InputFlavor, OutputFlavor};
+pub use internal::{Bounded, Infinite, SizeLimit};
+pub use iter::DeserializeIter;
+pub use max_size::MaxSize;
+pub use ser::spans::FieldSpan;
+pub use ser::{Serializer, SizeChecker};
 
 /// An object that implements this trait can be passed a
 /// serde::Deserializer without knowing its concrete type.
@@ -81,75 +143,543 @@ pub trait SerializerAcceptor {
 ///
 /// ### Default Configuration:
 ///
-/// | Byte limit | Endianness |
-/// |------------|------------|
-/// | Unlimited  | Little     |
-pub fn config() -> Config {
+/// | Byte limit | Endianness | Length prefixes | Option tags | Alignment |
+/// |------------|------------|-----------------|-------------|-----------|
+/// | Unlimited  | Little     | `u64`           | 0 and 1     | Off       |
+///
+/// Strings are checked for UTF-8 and passed on as they are, and padded records are filled with
+/// `0x00`. This is the encoding the functions without a configuration use, so
+/// `config().serialize_into(w, v)` is `serialize_into(w, v)`, and `DefaultOptions::new()` used
+/// with the `_with_options` functions gives the same bytes again.
+pub const fn config() -> Config {
     Config::new()
 }
 
-/// Serializes an object directly into a `Writer` using the default configuration.
+/// Serializes an object into a new `ArrayVec` using the default configuration. The
+/// capacity is picked through the array type, usually with a turbofish or an annotation.
 ///
-/// If the serialization would take more bytes than allowed by the size limit, an error
-/// is returned and *no bytes* will be written into the `Writer`.
-pub fn serialize_into<A, T: ?Sized>(writer: &mut ArrayVec<A>, value: &T) -> Result<()>
+/// Running out of room is reported as `BufferTooSmall`, with the number of bytes the value
+/// needs.
+///
+/// ```rust
+/// extern crate bincode;
+/// use bincode::arrayvec::ArrayVec;
+///
+/// fn main() {
+///     let bytes = bincode::serialize::<[u8; 16], _>(&(1u8, 2u16)).unwrap();
+///     assert_eq!(&bytes[..], &[1, 2, 0]);
+///
+///     let bytes: ArrayVec<[u8; 16]> = bincode::config().big_endian().serialize(&2u16).unwrap();
+///     assert_eq!(&bytes[..], &[0, 2]);
+///
+///     assert!(bincode::serialize::<[u8; 2], _>(&7u32).is_err());
+/// }
+/// ```
+pub fn serialize<A, T: ?Sized>(value: &T) -> Result<ArrayVec<A>>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+{
+    config().serialize(value)
+}
+
+/// Serializes an object directly into a `Writer` using the default configuration,
+/// returning the number of bytes it appended.
+///
+/// If the serialization fails for any reason, the `Writer` is truncated back to its
+/// length before the call, so it only ever gains complete values. Running out of room
+/// is reported as `BufferTooSmall`, with the number of bytes the value needs.
+pub fn serialize_into<A, T: ?Sized>(writer: &mut ArrayVec<A>, value: &T) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+{
+    serialize_into_with_options(writer, value, DefaultOptions::new())
+}
+
+/// Serializes an object directly into a `Writer` using `options`, returning the number of
+/// bytes it appended. See `serialize_into`, which this is with the options chosen by type.
+///
+/// ```rust
+/// extern crate bincode;
+/// use bincode::arrayvec::ArrayVec;
+/// use bincode::{DefaultOptions, OptionsExt};
+///
+/// fn main() {
+///     let mut buf: ArrayVec<[u8; 8]> = ArrayVec::new();
+///     let options = DefaultOptions::new().with_big_endian().with_limit(4);
+///     bincode::serialize_into_with_options(&mut buf, &1u32, options).unwrap();
+///     assert_eq!(&buf[..], &[0, 0, 0, 1]);
+///
+///     let options = DefaultOptions::new().with_big_endian();
+///     assert_eq!(bincode::deserialize_with_options::<u32, _>(&buf, options).unwrap(), 1);
+/// }
+/// ```
+pub fn serialize_into_with_options<A, T: ?Sized, O>(writer: &mut ArrayVec<A>, value: &T, options: O) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+    O: Options,
+{
+    internal::serialize_into_array(writer, value, options)
+}
+
+/// Serializes each of `items` into `writer` back to back using the default configuration,
+/// stopping at the first one that doesn't fit. Returns the number of items written.
+///
+/// The item that didn't fit leaves no bytes behind, so `writer` always ends with a complete
+/// item. See `Config::encode_many`.
+///
+/// ```rust
+/// extern crate bincode;
+/// use bincode::arrayvec::ArrayVec;
+///
+/// fn main() {
+///     let mut buf: ArrayVec<[u8; 10]> = ArrayVec::new();
+///     assert_eq!(bincode::encode_many(&[1u32, 2, 3], &mut buf).unwrap(), 2);
+///     assert_eq!(buf.len(), 8);
+///
+///     let mut values = Vec::new();
+///     assert_eq!(bincode::decode_many::<u32, _>(&buf, &mut values).unwrap(), 2);
+///     assert_eq!(values, [1, 2]);
+/// }
+/// ```
+pub fn encode_many<'t, A, T, I>(items: I, writer: &mut ArrayVec<A>) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: 't + ?Sized + serde::Serialize,
+    I: IntoIterator<Item = &'t T>,
+{
+    config().encode_many(items, writer)
+}
+
+/// Serializes an object directly into a `Writer` using the default configuration, after
+/// checking that it fits in the `Writer`'s remaining capacity. Returns the number of
+/// bytes it appended.
+///
+/// If it doesn't fit, `BufferTooSmall` is returned and *no bytes* will be written.
+pub fn serialize_into_checked<A, T: ?Sized>(writer: &mut ArrayVec<A>, value: &T) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+{
+    config().serialize_into_checked(writer, value)
+}
+
+/// Serializes as much of an object as fits into a `Writer` using the default configuration,
+/// returning the number of bytes appended and whether the object was cut short. See
+/// `Config::serialize_into_lossy`.
+pub fn serialize_into_lossy<A, T: ?Sized>(writer: &mut ArrayVec<A>, value: &T) -> Result<(usize, bool)>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+{
+    config().serialize_into_lossy(writer, value)
+}
+
+/// Serializes an object directly into a `Writer` using the default configuration, recording
+/// where each field of the outermost struct was written into `spans`. Returns the number
+/// of bytes appended and the number of fields. See `Config::serialize_into_with_spans`.
+pub fn serialize_into_with_spans<A, T: ?Sized>(
+    writer: &mut ArrayVec<A>,
+    value: &T,
+    spans: &mut [FieldSpan],
+) -> Result<(usize, usize)>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+{
+    config().serialize_into_with_spans(writer, value, spans)
+}
+
+/// Serializes an object directly into a `Writer` using the default configuration, followed
+/// by the little-endian CRC-32 (IEEE) of its bytes. Returns the number of bytes it appended,
+/// checksum included.
+///
+/// If the serialization fails for any reason, the `Writer` is truncated back to its
+/// length before the call.
+pub fn serialize_into_with_crc<A, T: ?Sized>(writer: &mut ArrayVec<A>, value: &T) -> Result<usize>
 where
     A: Array<Item = u8>,
     T: serde::Serialize,
 {
-    config().serialize_into(writer, value)
+    config().serialize_into_with_crc(writer, value)
+}
+
+/// Serializes an object through an `OutputFlavor` using the default configuration,
+/// returning the number of bytes the flavor produced.
+pub fn serialize_with_flavor<F, T: ?Sized>(flavor: F, value: &T) -> Result<usize>
+where
+    F: OutputFlavor,
+    T: serde::Serialize,
+{
+    config().serialize_with_flavor(flavor, value)
+}
+
+/// Serializes an object into the start of a byte slice using the default configuration,
+/// returning the number of bytes written.
+///
+/// If `out` is too short, `BufferTooSmall` is returned and the contents of `out` are
+/// unspecified.
+pub fn serialize_into_slice<T: ?Sized>(value: &T, out: &mut [u8]) -> Result<usize>
+where
+    T: serde::Serialize,
+{
+    config().serialize_into_slice(value, out)
+}
+
+/// Overwrites the `T` encoded at `offset` in `buf` with `value` using the default
+/// configuration. See `Config::patch`.
+pub fn patch<T>(buf: &mut [u8], offset: usize, value: &T) -> Result<()>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    config().patch(buf, offset, value)
+}
+
+/// Overwrites the `T` encoded at `offset` in `buf` with `value` using `options`. See
+/// `Config::patch`.
+///
+/// ```rust
+/// extern crate bincode;
+/// #[macro_use]
+/// extern crate serde_derive;
+/// use bincode::arrayvec::ArrayVec;
+/// use bincode::{DefaultOptions, ErrorKind, FieldSpan, OptionsExt};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Packet<'a> {
+///     seq: u32,
+///     payload: &'a str,
+/// }
+///
+/// fn main() {
+///     let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+///     let mut spans = [FieldSpan::default(); 2];
+///     let packet = Packet { seq: 1, payload: "hello" };
+///     bincode::config().big_endian().serialize_into_with_spans(&mut buf, &packet, &mut spans).unwrap();
+///
+///     let options = || DefaultOptions::new().with_big_endian();
+///     bincode::patch_with_options(&mut buf, spans[0].offset, &2u32, options()).unwrap();
+///     assert_eq!(&buf[..4], &[0, 0, 0, 2]);
+///
+///     // A longer payload would move everything after it.
+///     match bincode::patch_with_options(&mut buf, spans[1].offset, &"goodbye".to_string(), options()) {
+///         Err(ErrorKind::PatchLengthMismatch { region: 13, encoded: 15 }) => {}
+///         other => panic!("{:?}", other),
+///     }
+/// }
+/// ```
+pub fn patch_with_options<T, O>(buf: &mut [u8], offset: usize, value: &T, options: O) -> Result<()>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+    O: Options,
+{
+    internal::patch(buf, offset, value, options)
+}
+
+/// Serializes an object into a callback using the default configuration, returning the number
+/// of bytes passed to it. The callback gets each contiguous run of bytes in one call, e.g. a
+/// whole string, so the encoding can be hashed or forwarded without buffering it.
+///
+/// ```rust
+/// extern crate bincode;
+///
+/// fn main() {
+///     let mut chunks = Vec::new();
+///     bincode::serialize_with_sink(&(7u8, "abc"), |chunk: &[u8]| {
+///         chunks.push(chunk.to_vec());
+///         Ok(())
+///     })
+///     .unwrap();
+///     assert_eq!(chunks, [vec![7], vec![3, 0, 0, 0, 0, 0, 0, 0], b"abc".to_vec()]);
+/// }
+/// ```
+pub fn serialize_with_sink<T: ?Sized, F>(value: &T, sink: F) -> Result<usize>
+where
+    T: serde::Serialize,
+    F: FnMut(&[u8]) -> Result<()>,
+{
+    config().serialize_with_sink(value, sink)
+}
+
+/// Serializes an object into an `io::Write`r using the default configuration, returning the
+/// number of bytes written.
+///
+/// If the serialization would take more bytes than allowed by the size limit, an error
+/// is returned and *no bytes* will be written into the writer.
+#[cfg(feature = "std")]
+pub fn serialize_into_writer<W, T: ?Sized>(writer: W, value: &T) -> Result<usize>
+where
+    W: std::io::Write,
+    T: serde::Serialize,
+{
+    config().serialize_into_writer(writer, value)
 }
 
 /// Serializes a serializable object into a `Vec` of bytes using the default configuration.
-// pub fn serialize<T: ?Sized>(value: &T) -> Result<Vec<u8>>
-// where
-//     T: serde::Serialize,
-// {
-//     config().serialize(value)
-// }
+#[cfg(feature = "alloc")]
+pub fn serialize_to_vec<T: ?Sized>(value: &T) -> Result<alloc::vec::Vec<u8>>
+where
+    T: serde::Serialize,
+{
+    config().serialize_to_vec(value)
+}
 
 /// Deserializes an object directly from a `Read`er using the default configuration.
 ///
 /// If this returns an `Error`, `reader` may be in an invalid state.
-// pub fn deserialize_from<R, T>(reader: R) -> Result<T>
-// where
-//     R: core_io::Read,
-//     T: serde::de::DeserializeOwned,
-// {
-//     config().deserialize_from(reader)
-// }
+#[cfg(feature = "std")]
+pub fn deserialize_from<R, T>(reader: R) -> Result<T>
+where
+    R: std::io::Read,
+    T: serde::de::DeserializeOwned,
+{
+    config().deserialize_from(reader)
+}
 
 /// Deserializes an object from a custom `BincodeRead`er using the default configuration.
 /// It is highly recommended to use `deserialize_from` unless you need to implement
 /// `BincodeRead` for performance reasons.
 ///
+/// Pass `&mut reader` to keep reading from the same position afterwards,
+/// for example to mix hand-written header fields with serde-decoded bodies.
+///
 /// If this returns an `Error`, `reader` may be in an invalid state.
-// pub fn deserialize_from_custom<'a, R, T>(reader: R) -> Result<T>
-// where
-//     R: de::read::BincodeRead<'a>,
-//     T: serde::de::DeserializeOwned,
-// {
-//     config().deserialize_from_custom(reader)
-// }
+pub fn deserialize_from_custom<'a, R, T>(reader: R) -> Result<T>
+where
+    R: de::read::BincodeRead<'a>,
+    T: serde::de::Deserialize<'a>,
+{
+    config().deserialize_from_custom(reader)
+}
 
-/// Only use this if you know what you're doing.
+/// Deserializes an object from one of the built-in readers using the default configuration,
+/// without making the caller generic over the reader type.
 ///
-/// This is part of the public API.
-// #[doc(hidden)]
-// pub fn deserialize_in_place<'a, R, T>(reader: R, place: &mut T) -> Result<()>
-// where
-//     T: serde::de::Deserialize<'a>,
-//     R: BincodeRead<'a>,
-// {
-//     config().deserialize_in_place(reader, place)
-// }
+/// If this returns an `Error`, `reader` may be in an invalid state.
+pub fn deserialize_dyn<'a, T>(reader: &mut DynReader<'a>) -> Result<T>
+where
+    T: serde::de::Deserialize<'a>,
+{
+    config().deserialize_dyn(reader)
+}
+
+/// Deserializes a slice of bytes into an existing `T` using the default configuration,
+/// through serde's `Deserialize::deserialize_in_place`.
+///
+/// Types that support it overwrite their contents instead of being rebuilt, so e.g. a
+/// `Vec` or `String` keeps its allocation and a large array isn't moved. Structs support
+/// it when derived with serde_derive's `deserialize_in_place` feature; every other type
+/// falls back to replacing `*place` with a freshly deserialized value.
+///
+/// If this returns an `Error`, `place` is still a valid `T` but may be partly overwritten:
+/// the fields before the one that failed hold their new values, and that field and the
+/// ones after it may hold their old values or, for sequences, some of the new elements.
+pub fn deserialize_in_place<'a, T>(bytes: &'a [u8], place: &mut T) -> Result<()>
+where
+    T: serde::de::Deserialize<'a>,
+{
+    config().deserialize_in_place(bytes, place)
+}
 
 /// Deserializes a slice of bytes into an instance of `T` using the default configuration.
 pub fn deserialize<'a, T>(bytes: &'a [u8]) -> Result<T>
 where
     T: serde::de::Deserialize<'a>,
 {
-    config().deserialize(bytes)
+    deserialize_with_options(bytes, DefaultOptions::new())
+}
+
+/// Deserializes a slice of bytes into an instance of `T` using `options`. See
+/// `serialize_into_with_options`.
+pub fn deserialize_with_options<'a, T, O>(bytes: &'a [u8], options: O) -> Result<T>
+where
+    T: serde::de::Deserialize<'a>,
+    O: Options,
+{
+    internal::deserialize_from_custom(SliceReader::new(bytes), options)
+}
+
+/// Deserializes a slice of bytes through `seed` using the default configuration.
+///
+/// This is `deserialize` for state the value needs while being built, such as an
+/// arena or a container to fill in place.
+///
+/// ```rust
+/// extern crate bincode;
+/// extern crate serde;
+/// use bincode::arrayvec::ArrayVec;
+/// use serde::de::{DeserializeSeed, Deserializer, SeqAccess, Visitor};
+/// use std::fmt;
+///
+/// // Appends a sequence of `u32`s to an existing buffer.
+/// struct AppendTo<'b>(&'b mut ArrayVec<[u32; 8]>);
+///
+/// impl<'de, 'b> DeserializeSeed<'de> for AppendTo<'b> {
+///     type Value = ();
+///
+///     fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+///         deserializer.deserialize_seq(self)
+///     }
+/// }
+///
+/// impl<'de, 'b> Visitor<'de> for AppendTo<'b> {
+///     type Value = ();
+///
+///     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+///         formatter.write_str("a sequence of u32")
+///     }
+///
+///     fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+///         while let Some(value) = seq.next_element()? {
+///             self.0.try_push(value).map_err(|_| serde::de::Error::custom("buffer full"))?;
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// fn main() {
+///     let mut buf = [0; 64];
+///     let len = bincode::serialize_into_slice(&[1u32, 2, 3][..], &mut buf).unwrap();
+///
+///     let mut values = ArrayVec::new();
+///     values.push(0);
+///     bincode::deserialize_seed(AppendTo(&mut values), &buf[..len]).unwrap();
+///     assert_eq!(&values[..], &[0, 1, 2, 3]);
+/// }
+/// ```
+pub fn deserialize_seed<'a, S>(seed: S, bytes: &'a [u8]) -> Result<S::Value>
+where
+    S: serde::de::DeserializeSeed<'a>,
+{
+    config().deserialize_seed(seed, bytes)
+}
+
+/// Deserializes a slice of bytes into an instance of `T` using the default configuration,
+/// failing with `TrailingBytes` if any bytes are left over. See `Config::deserialize_exact`.
+pub fn deserialize_exact<'a, T>(bytes: &'a [u8]) -> Result<T>
+where
+    T: serde::de::Deserialize<'a>,
+{
+    config().deserialize_exact(bytes)
+}
+
+/// Deserializes an instance of `T` from the start of `bytes` using the default configuration,
+/// returning it together with the number of bytes consumed. See `Config::deserialize_prefix`.
+pub fn deserialize_prefix<'a, T>(bytes: &'a [u8]) -> Result<(T, usize)>
+where
+    T: serde::de::Deserialize<'a>,
+{
+    config().deserialize_prefix(bytes)
+}
+
+/// Deserializes an instance of `T` from the start of `bytes` using the default configuration,
+/// returning it together with the bytes after it. See `Config::deserialize_partial`.
+pub fn deserialize_partial<'a, T>(bytes: &'a [u8]) -> Result<(T, &'a [u8])>
+where
+    T: serde::de::Deserialize<'a>,
+{
+    config().deserialize_partial(bytes)
+}
+
+/// Decodes the values of type `T` encoded back to back in `bytes` using the default
+/// configuration and adds them to `out`, returning how many were added. See
+/// `Config::decode_many`.
+pub fn decode_many<'a, T, E>(bytes: &'a [u8], out: &mut E) -> Result<usize>
+where
+    T: serde::de::Deserialize<'a>,
+    E: Extend<T>,
+{
+    config().decode_many(bytes, out)
+}
+
+/// Returns an iterator over values of type `T` encoded back to back in `bytes`, using the
+/// default configuration. See `DeserializeIter`.
+pub fn deserialize_iter<'a, T>(bytes: &'a [u8]) -> DeserializeIter<'a, T>
+where
+    T: serde::de::Deserialize<'a>,
+{
+    DeserializeIter::new(bytes)
+}
+
+/// Reads the `u32` at the start of `bytes` using the default configuration, without
+/// deserializing anything. See `Config::peek_tag`.
+pub fn peek_tag(bytes: &[u8]) -> Result<u32> {
+    config().peek_tag(bytes)
+}
+
+/// Appends `version` and then `value` to `buf` using the default configuration, returning
+/// the number of bytes appended. See `Config::encode_versioned`.
+pub fn encode_versioned<A, T: ?Sized>(version: u32, value: &T, buf: &mut ArrayVec<A>) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: serde::Serialize,
+{
+    config().encode_versioned(version, value, buf)
+}
+
+/// Appends `version` and then `value` to `buf` using `options`, returning the number of bytes
+/// appended. See `Config::encode_versioned`.
+pub fn encode_versioned_with_options<A, T, O>(
+    version: u32,
+    value: &T,
+    buf: &mut ArrayVec<A>,
+    options: O,
+) -> Result<usize>
+where
+    A: Array<Item = u8>,
+    T: ?Sized + serde::Serialize,
+    O: Options,
+{
+    internal::encode_versioned(version, value, buf, options)
+}
+
+/// Decodes a value written by `encode_versioned` using the default configuration, returning
+/// its version alongside it. See `Config::decode_versioned`.
+pub fn decode_versioned<'a, T>(bytes: &'a [u8]) -> Result<(u32, T)>
+where
+    T: serde::de::Deserialize<'a>,
+{
+    config().decode_versioned(bytes)
+}
+
+/// Decodes a value written by `encode_versioned_with_options` using `options`, returning its
+/// version alongside it. See `Config::decode_versioned`.
+pub fn decode_versioned_with_options<'a, T, O>(bytes: &'a [u8], options: O) -> Result<(u32, T)>
+where
+    T: serde::de::Deserialize<'a>,
+    O: Options,
+{
+    internal::decode_versioned(bytes, options)
+}
+
+/// Reads the version written by `encode_versioned` using the default configuration and
+/// hands it to `f` with the bytes after it. See `Config::decode_versioned_with`.
+pub fn decode_versioned_with<'a, F, R>(bytes: &'a [u8], f: F) -> Result<R>
+where
+    F: FnOnce(u32, &'a [u8]) -> Result<R>,
+{
+    config().decode_versioned_with(bytes, f)
+}
+
+/// Deserializes an instance of `T` written by `serialize_into_with_crc` using the default
+/// configuration, checking the CRC-32 that follows it. See `Config::deserialize_with_crc`.
+pub fn deserialize_with_crc<'a, T>(bytes: &'a [u8]) -> Result<T>
+where
+    T: serde::de::Deserialize<'a>,
+{
+    config().deserialize_with_crc(bytes)
+}
+
+/// Deserializes an instance of `T` from the payload of an `InputFlavor` using the default
+/// configuration.
+pub fn deserialize_with_flavor<'a, F, T>(flavor: F) -> Result<T>
+where
+    F: InputFlavor<'a>,
+    T: serde::de::Deserialize<'a>,
+{
+    config().deserialize_with_flavor(flavor)
 }
 
 /// Returns the size that an object would be if serialized using Bincode with the default configuration.
@@ -157,7 +687,30 @@ pub fn serialized_size<T: ?Sized>(value: &T) -> Result<u64>
 where
     T: serde::Serialize,
 {
-    config().serialized_size(value)
+    serialized_size_with_options(value, DefaultOptions::new())
+}
+
+/// Returns the size that an object would be if serialized using `options`. See
+/// `serialize_into_with_options`.
+///
+/// A byte limit on `options` applies as it does when serializing, so a value that is
+/// larger fails with `SizeLimit`.
+pub fn serialized_size_with_options<T: ?Sized, O>(value: &T, options: O) -> Result<u64>
+where
+    T: serde::Serialize,
+    O: Options,
+{
+    internal::serialized_size(value, options)
+}
+
+/// Returns the size that an object would be if serialized using Bincode with the default
+/// configuration, or `None` if that is more than `max` bytes. See
+/// `Config::serialized_size_bounded`.
+pub fn serialized_size_bounded<T: ?Sized>(value: &T, max: u64) -> Result<Option<u64>>
+where
+    T: serde::Serialize,
+{
+    config().serialized_size_bounded(value, max)
 }
 
 /// Executes the acceptor with a serde::Deserializer instance.
@@ -0,0 +1,148 @@
+//! Compile-time upper bounds on the encoded size of a type.
+//!
+//! `MaxSize::MAX_SIZE` is the most bytes any value of the type can encode to,
+//! so a buffer of that many bytes always fits it. Only types with a bounded
+//! encoding have one: numbers, `bool`, `char`, `()`, tuples, arrays and
+//! `Option`s of those, and structs and enums made of them. Strings, slices and
+//! other sequences carry a length and have no bound.
+//!
+//! The bound holds for every configuration except `Config::align`, which adds
+//! padding depending on where a number falls in the message.
+//!
+//! Structs and enums get an impl from `impl_max_size!`, listing the types of
+//! their fields; each enum variant is listed as a tuple of its field types.
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate bincode;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! use bincode::arrayvec::ArrayVec;
+//! use bincode::MaxSize;
+//!
+//! #[derive(Serialize)]
+//! struct Point {
+//!     x: i16,
+//!     y: i16,
+//! }
+//! impl_max_size!(struct Point { i16, i16 });
+//!
+//! #[derive(Serialize)]
+//! enum Command {
+//!     Stop,
+//!     Goto(Point, Option<u8>),
+//!     Turn { degrees: f32 },
+//! }
+//! impl_max_size!(enum Command { (), (Point, Option<u8>), (f32,) });
+//!
+//! fn main() {
+//!     // A `u32` variant index, then the largest variant.
+//!     assert_eq!(Command::MAX_SIZE, 4 + 4 + 2);
+//!
+//!     let mut buf: ArrayVec<[u8; Command::MAX_SIZE]> = ArrayVec::new();
+//!     bincode::serialize_into(&mut buf, &Command::Goto(Point { x: 1, y: 2 }, Some(3))).unwrap();
+//!     assert!(buf.is_full());
+//! }
+//! ```
+
+use core::marker::PhantomData;
+
+/// A type whose encoding never takes more than `MAX_SIZE` bytes, see the
+/// module documentation.
+pub trait MaxSize {
+    /// The most bytes a value of this type can encode to.
+    const MAX_SIZE: usize;
+}
+
+/// The largest of `sizes`, or 0 if there are none. Used by `impl_max_size!`
+/// for enums.
+pub const fn max_of(sizes: &[usize]) -> usize {
+    let mut max = 0;
+    let mut i = 0;
+    while i < sizes.len() {
+        if sizes[i] > max {
+            max = sizes[i];
+        }
+        i += 1;
+    }
+    max
+}
+
+/// Implements `MaxSize` for a struct or an enum from the types of its fields,
+/// see the `max_size` module.
+///
+/// `impl_max_size!(struct Name { A, B })` adds up the fields.
+/// `impl_max_size!(enum Name { (), (A,), (B, C) })` takes one tuple of field
+/// types per variant, in declaration order, and adds the variant index to the
+/// largest.
+#[macro_export]
+macro_rules! impl_max_size {
+    (struct $ty:ty { $($field:ty),* $(,)* }) => {
+        impl $crate::MaxSize for $ty {
+            const MAX_SIZE: usize = 0 $(+ <$field as $crate::MaxSize>::MAX_SIZE)*;
+        }
+    };
+    (enum $ty:ty { $($variant:ty),* $(,)* }) => {
+        impl $crate::MaxSize for $ty {
+            // Variant indices are encoded as a `u32`.
+            const MAX_SIZE: usize = 4 + $crate::max_size::max_of(&[$(<$variant as $crate::MaxSize>::MAX_SIZE),*]);
+        }
+    };
+}
+
+macro_rules! impl_fixed {
+    ($($ty:ty => $size:expr),*) => {
+        $(
+            impl MaxSize for $ty {
+                const MAX_SIZE: usize = $size;
+            }
+        )*
+    }
+}
+
+impl_fixed! {
+    u8 => 1, u16 => 2, u32 => 4, u64 => 8, u128 => 16,
+    i8 => 1, i16 => 2, i32 => 4, i64 => 8, i128 => 16,
+    f32 => 4, f64 => 8,
+    bool => 1,
+    // UTF-8, which takes up to four bytes.
+    char => 4,
+    () => 0
+}
+
+impl<T: ?Sized> MaxSize for PhantomData<T> {
+    const MAX_SIZE: usize = 0;
+}
+
+impl<T: MaxSize> MaxSize for Option<T> {
+    const MAX_SIZE: usize = 1 + T::MAX_SIZE;
+}
+
+impl<T: MaxSize, const N: usize> MaxSize for [T; N] {
+    const MAX_SIZE: usize = N * T::MAX_SIZE;
+}
+
+impl<'a, T: MaxSize + ?Sized> MaxSize for &'a T {
+    const MAX_SIZE: usize = T::MAX_SIZE;
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: MaxSize),+> MaxSize for ($($name,)+) {
+            const MAX_SIZE: usize = 0 $(+ $name::MAX_SIZE)+;
+        }
+    }
+}
+
+impl_tuple!(A);
+impl_tuple!(A B);
+impl_tuple!(A B C);
+impl_tuple!(A B C D);
+impl_tuple!(A B C D E);
+impl_tuple!(A B C D E F);
+impl_tuple!(A B C D E F G);
+impl_tuple!(A B C D E F G H);
+impl_tuple!(A B C D E F G H I);
+impl_tuple!(A B C D E F G H I J);
+impl_tuple!(A B C D E F G H I J K);
+impl_tuple!(A B C D E F G H I J K L);
@@ -0,0 +1,68 @@
+//! Helpers for reading data written by older or differently configured peers.
+//!
+//! During a format transition the same input may have been written under one
+//! of several configurations. `deserialize_first_match` tries each candidate
+//! and reports which one the input was written with.
+//!
+//! ```rust
+//! extern crate bincode;
+//! use bincode::arrayvec::ArrayVec;
+//! use bincode::migration::deserialize_first_match;
+//! use bincode::{config, LengthEncoding};
+//!
+//! fn main() {
+//!     let current = config();
+//!     let mut legacy = config();
+//!     legacy.length_encoding(LengthEncoding::U32);
+//!
+//!     let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+//!     legacy.serialize_into(&mut buf, &"device-7").unwrap();
+//!
+//!     let (index, name): (usize, &str) = deserialize_first_match(&buf, &[current, legacy]).unwrap();
+//!     assert_eq!((index, name), (1, "device-7"));
+//! }
+//! ```
+
+use serde;
+
+use {Config, ErrorKind, Result};
+
+/// Deserializes `bytes` using the first of `configs` under which the whole
+/// input decodes, returning the index of that configuration alongside the value.
+///
+/// A configuration only matches if the value spans the entire input; leftover
+/// bytes count as a failed attempt. To avoid silently picking the wrong layout,
+/// the remaining configurations are tried as well and the input is rejected with
+/// `ErrorKind::AmbiguousEncoding` if another one also matches.
+///
+/// If no configuration matches, the error from the first one is returned. An empty
+/// `configs` never matches and yields `ErrorKind::NoCandidates`.
+pub fn deserialize_first_match<'a, T>(bytes: &'a [u8], configs: &[Config]) -> Result<(usize, T)>
+where
+    T: serde::Deserialize<'a>,
+{
+    let mut first_error = None;
+    let mut found = None;
+
+    for (index, config) in configs.iter().enumerate() {
+        match config.deserialize_exact::<T>(bytes) {
+            Ok(value) => {
+                if found.is_some() {
+                    return Err(ErrorKind::AmbiguousEncoding);
+                }
+                found = Some((index, value));
+            }
+            Err(e) => {
+                if first_error.is_none() {
+                    first_error = Some(e);
+                }
+            }
+        }
+    }
+
+    match (found, first_error) {
+        (Some(found), _) => Ok(found),
+        (None, Some(e)) => Err(e),
+        (None, None) => Err(ErrorKind::NoCandidates),
+    }
+}
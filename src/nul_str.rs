@@ -0,0 +1,82 @@
+//! NUL-terminated strings, for peers that send C-style strings instead of
+//! length-prefixed ones.
+//!
+//! A `NulStr` is written as its bytes followed by a single `0x00`, with no
+//! length prefix. Reading one scans for the terminator, at most `MAX` bytes
+//! into the input, and borrows the string in between.
+//!
+//! ```rust
+//! extern crate bincode;
+//! use bincode::arrayvec::ArrayVec;
+//! use bincode::nul_str::NulStr;
+//!
+//! fn main() {
+//!     let mut buf: ArrayVec<[u8; 16]> = ArrayVec::new();
+//!     bincode::serialize_into(&mut buf, &(NulStr::<32>("eth0"), 7u8)).unwrap();
+//!     assert_eq!(&buf[..], b"eth0\0\x07");
+//!
+//!     let (name, n): (NulStr<32>, u8) = bincode::deserialize(&buf).unwrap();
+//!     assert_eq!((name.0, n), ("eth0", 7));
+//! }
+//! ```
+//!
+//! Only readers that can look ahead find the terminator. The slice-based
+//! readers search the rest of the input. `ScratchReader` and `BufReader` only
+//! search the bytes they have buffered, or the current chunk, and report
+//! `MissingNulTerminator` if it isn't there. `IoReader` and
+//! `EmbeddedIoReader` can't look ahead at all and fail with
+//! `LookAheadUnsupported`.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde;
+use serde::de::Error as DeError;
+use serde::ser::{Error as SerError, SerializeTupleStruct};
+
+/// The name the deserializer recognizes to scan for a terminator instead of
+/// decoding a tuple struct.
+pub(crate) const TOKEN: &str = "$bincode::private::NulStr";
+
+/// A string encoded as its bytes and a `0x00` terminator. `MAX` bounds the
+/// length of the string, not counting the terminator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NulStr<'a, const MAX: usize = 255>(pub &'a str);
+
+impl<'a, const MAX: usize> serde::Serialize for NulStr<'a, MAX> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.0.len() > MAX {
+            return Err(S::Error::custom("string is longer than the NulStr maximum"));
+        }
+        if self.0.bytes().any(|b| b == 0) {
+            return Err(S::Error::custom("string contains a NUL byte"));
+        }
+        let mut tuple = serializer.serialize_tuple_struct(TOKEN, self.0.len() + 1)?;
+        for byte in self.0.bytes() {
+            tuple.serialize_field(&byte)?;
+        }
+        tuple.serialize_field(&0u8)?;
+        tuple.end()
+    }
+}
+
+impl<'de: 'a, 'a, const MAX: usize> serde::Deserialize<'de> for NulStr<'a, MAX> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor<'a, const MAX: usize>(PhantomData<&'a str>);
+
+        impl<'de: 'a, 'a, const MAX: usize> serde::de::Visitor<'de> for Visitor<'a, MAX> {
+            type Value = NulStr<'a, MAX>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a NUL-terminated string of at most {} bytes", MAX)
+            }
+
+            fn visit_borrowed_str<E: DeError>(self, v: &'de str) -> Result<NulStr<'a, MAX>, E> {
+                Ok(NulStr(v))
+            }
+        }
+
+        // The length carries the maximum through to the deserializer.
+        deserializer.deserialize_tuple_struct(TOKEN, MAX, Visitor::<MAX>(PhantomData))
+    }
+}
@@ -0,0 +1,194 @@
+//! Bit-packed encoding for sequences of bools.
+//!
+//! `PackedBools` wraps a collection of bools and encodes it as the number of
+//! bools, as a `u64`, followed by `ceil(len / 8)` bytes. Bool `i` is bit
+//! `i % 8` of byte `i / 8`, least significant bit first, and the unused high
+//! bits of the last byte are zero.
+//!
+//! This is not the plain encoding of a bool sequence, which takes one byte
+//! per element: data written through `PackedBools` must be read through it
+//! too. It is a pair of the count and a tuple of the packed bytes as far as
+//! serde is concerned, so it works with any serializer, and no buffer is
+//! needed to pack the bits into first.
+//!
+//! Decoding needs a `BoolStorage` to push the bools into. Running out of
+//! room is an error rather than a panic, so fixed-capacity storage like an
+//! `ArrayVec` can't be overrun by a bogus count.
+//!
+//! ```rust
+//! extern crate bincode;
+//! use bincode::arrayvec::ArrayVec;
+//! use bincode::packed::PackedBools;
+//!
+//! fn main() {
+//!     let mask = PackedBools([true, false, true, true, false, false, false, false, true]);
+//!     let mut buf: ArrayVec<[u8; 16]> = ArrayVec::new();
+//!     bincode::serialize_into(&mut buf, &mask).unwrap();
+//!     assert_eq!(&buf[..], &[9, 0, 0, 0, 0, 0, 0, 0, 0b0000_1101, 0b0000_0001]);
+//!
+//!     let decoded: PackedBools<ArrayVec<[bool; 16]>> = bincode::deserialize(&buf).unwrap();
+//!     assert_eq!(&decoded.0[..], &mask.0[..]);
+//!     assert!(bincode::deserialize::<PackedBools<ArrayVec<[bool; 8]>>>(&buf).is_err());
+//! }
+//! ```
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use arrayvec::{Array, ArrayVec};
+use serde;
+use serde::de::{DeserializeSeed, Error};
+use serde::ser::SerializeTuple;
+
+/// A collection of bools encoded one bit each.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PackedBools<C>(pub C);
+
+/// Storage that `PackedBools` can decode into.
+pub trait BoolStorage: Default {
+    /// Appends `value`, returning `false` if there is no room for it.
+    fn try_push(&mut self, value: bool) -> bool;
+}
+
+#[cfg(feature = "alloc")]
+impl BoolStorage for ::alloc::vec::Vec<bool> {
+    fn try_push(&mut self, value: bool) -> bool {
+        self.push(value);
+        true
+    }
+}
+
+impl<A: Array<Item = bool>> BoolStorage for ArrayVec<A> {
+    fn try_push(&mut self, value: bool) -> bool {
+        ArrayVec::try_push(self, value).is_ok()
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> BoolStorage for ::heapless::Vec<bool, N> {
+    fn try_push(&mut self, value: bool) -> bool {
+        self.push(value).is_ok()
+    }
+}
+
+/// The bytes of a `PackedBools`, without their count.
+struct Packed<'a, C: 'a>(&'a C, usize);
+
+impl<'a, C> serde::Serialize for Packed<'a, C>
+where
+    for<'b> &'b C: IntoIterator<Item = &'b bool>,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(packed_len(self.1))?;
+        let mut byte = 0u8;
+        let mut bit = 0;
+        for &value in self.0 {
+            byte |= (value as u8) << bit;
+            bit += 1;
+            if bit == 8 {
+                tuple.serialize_element(&byte)?;
+                byte = 0;
+                bit = 0;
+            }
+        }
+        if bit != 0 {
+            tuple.serialize_element(&byte)?;
+        }
+        tuple.end()
+    }
+}
+
+fn packed_len(len: usize) -> usize {
+    len.div_ceil(8)
+}
+
+impl<C> serde::Serialize for PackedBools<C>
+where
+    for<'a> &'a C: IntoIterator<Item = &'a bool>,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let len = (&self.0).into_iter().count();
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&(len as u64))?;
+        tuple.serialize_element(&Packed(&self.0, len))?;
+        tuple.end()
+    }
+}
+
+impl<'de, C: BoolStorage> serde::Deserialize<'de> for PackedBools<C> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor<C>(PhantomData<C>);
+
+        impl<'de, C: BoolStorage> serde::de::Visitor<'de> for Visitor<C> {
+            type Value = PackedBools<C>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a bit-packed bool sequence")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<PackedBools<C>, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let len: u64 = match seq.next_element()? {
+                    Some(len) => len,
+                    None => return Err(A::Error::invalid_length(0, &self)),
+                };
+                if len > usize::MAX as u64 {
+                    return Err(A::Error::custom("packed bool count doesn't fit in a usize"));
+                }
+                match seq.next_element_seed(Unpack(len as usize, PhantomData))? {
+                    Some(values) => Ok(PackedBools(values)),
+                    None => Err(A::Error::invalid_length(1, &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_tuple(2, Visitor(PhantomData))
+    }
+}
+
+/// Decodes the bytes of `.0` packed bools.
+struct Unpack<C>(usize, PhantomData<C>);
+
+impl<'de, C: BoolStorage> DeserializeSeed<'de> for Unpack<C> {
+    type Value = C;
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<C, D::Error> {
+        deserializer.deserialize_tuple(packed_len(self.0), self)
+    }
+}
+
+impl<'de, C: BoolStorage> serde::de::Visitor<'de> for Unpack<C> {
+    type Value = C;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{} packed bools", self.0)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<C, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        let len = self.0;
+        let mut values = C::default();
+        let mut left = len;
+        while left > 0 {
+            let byte: u8 = match seq.next_element()? {
+                Some(byte) => byte,
+                None => return Err(A::Error::invalid_length(len - left, &self)),
+            };
+            let bits = ::core::cmp::min(left, 8);
+            if bits < 8 && byte >> bits != 0 {
+                return Err(A::Error::custom("packed bools have stray high bits"));
+            }
+            for bit in 0..bits {
+                if !values.try_push(byte & (1 << bit) != 0) {
+                    return Err(A::Error::invalid_length(len, &"no more bools than the storage holds"));
+                }
+            }
+            left -= bits;
+        }
+        Ok(values)
+    }
+}
@@ -0,0 +1,79 @@
+//! Named option sets for the encodings people keep configuring by hand, for
+//! use with a `Serializer`, a `Deserializer` or the `_with_options` functions.
+//!
+//! Each preset is pinned by the test suite, so its bytes never change. For
+//! this reference value:
+//!
+//! ```rust
+//! # #[macro_use] extern crate serde_derive;
+//! # extern crate bincode;
+//! #[derive(Serialize)]
+//! struct Reference<'a> {
+//!     id: u16,
+//!     flag: bool,
+//!     name: &'a str,
+//!     value: Option<u32>,
+//! }
+//!
+//! # fn main() {
+//! # use bincode::arrayvec::ArrayVec;
+//! # use bincode::presets;
+//! let reference = Reference { id: 0x0102, flag: true, name: "hi", value: Some(0x0304_0506) };
+//!
+//! let mut legacy: ArrayVec<[u8; 32]> = ArrayVec::new();
+//! bincode::serialize_into_with_options(&mut legacy, &reference, presets::legacy()).unwrap();
+//! assert_eq!(&legacy[..], &[
+//!     0x02, 0x01,                                     // id
+//!     0x01,                                           // flag
+//!     0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // name length
+//!     b'h', b'i',                                     // name
+//!     0x01, 0x06, 0x05, 0x04, 0x03,                   // value
+//! ][..]);
+//!
+//! let mut standard: ArrayVec<[u8; 32]> = ArrayVec::new();
+//! bincode::serialize_into_with_options(&mut standard, &reference, presets::standard()).unwrap();
+//! assert_eq!(standard, legacy);
+//!
+//! let mut network: ArrayVec<[u8; 32]> = ArrayVec::new();
+//! bincode::serialize_into_with_options(&mut network, &reference, presets::network()).unwrap();
+//! assert_eq!(&network[..], &[
+//!     0x01, 0x02,                                     // id
+//!     0x01,                                           // flag
+//!     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, // name length
+//!     b'h', b'i',                                     // name
+//!     0x01, 0x03, 0x04, 0x05, 0x06,                   // value
+//! ][..]);
+//! # }
+//! ```
+
+use byteorder::{BigEndian, LittleEndian};
+
+use config::{DefaultOptions, OptionsExt, WithOtherEndian};
+
+/// The options returned by `legacy`.
+pub type Legacy = WithOtherEndian<DefaultOptions, LittleEndian>;
+
+/// The options returned by `standard`.
+pub type Standard = WithOtherEndian<DefaultOptions, LittleEndian>;
+
+/// The options returned by `network`.
+pub type Network = WithOtherEndian<DefaultOptions, BigEndian>;
+
+/// What this crate does without a configuration: little-endian numbers,
+/// `u64` length prefixes, `0`/`1` option tags, no padding and no limit.
+pub fn legacy() -> Legacy {
+    DefaultOptions::new().with_little_endian()
+}
+
+/// Byte-compatible with the default configuration of upstream bincode 1.x,
+/// which fixed-size integers and `u64` lengths make the same encoding as
+/// `legacy`. It is kept separate so code can say which one it means.
+pub fn standard() -> Standard {
+    DefaultOptions::new().with_little_endian()
+}
+
+/// `legacy` with big-endian numbers and length prefixes, as in network
+/// byte order.
+pub fn network() -> Network {
+    DefaultOptions::new().with_big_endian()
+}
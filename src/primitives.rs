@@ -0,0 +1,67 @@
+//! Encoding and decoding of single primitives, for framing code that needs
+//! one field at a known offset rather than a whole value.
+//!
+//! Every function honors the endianness and length encoding of the `Config`
+//! it is given and goes through the same code as the full serializer and
+//! deserializer, so the bytes always agree with those produced and accepted
+//! by `serialize_into` and `deserialize`. Readers return the value together
+//! with the number of bytes consumed; writers return the number of bytes
+//! written.
+//!
+//! ```rust
+//! extern crate bincode;
+//! use bincode::config;
+//! use bincode::primitives::{read_u32, write_u32};
+//!
+//! fn main() {
+//!     let mut buf = [0u8; 8];
+//!     let written = write_u32(&mut bincode::flavors::Slice::new(&mut buf[2..]), 7, config().big_endian())
+//!         .unwrap();
+//!     assert_eq!(written, 4);
+//!     assert_eq!(read_u32(&buf[2..], config().big_endian()).unwrap(), (7, 4));
+//! }
+//! ```
+
+use config::Config;
+use flavors::OutputFlavor;
+use Result;
+
+macro_rules! impl_primitive {
+    ($ty:ty, $read:ident, $write:ident) => {
+        #[doc = concat!("Reads a `", stringify!($ty), "` from the start of `bytes`.")]
+        #[inline]
+        pub fn $read(bytes: &[u8], config: &Config) -> Result<($ty, usize)> {
+            config.deserialize_prefix(bytes)
+        }
+
+        #[doc = concat!("Writes a `", stringify!($ty), "` to `writer`.")]
+        #[inline]
+        pub fn $write<W: OutputFlavor>(writer: &mut W, value: $ty, config: &Config) -> Result<usize> {
+            config.serialize_prefix(writer, &value)
+        }
+    };
+}
+
+impl_primitive!(u8, read_u8, write_u8);
+impl_primitive!(u16, read_u16, write_u16);
+impl_primitive!(u32, read_u32, write_u32);
+impl_primitive!(u64, read_u64, write_u64);
+impl_primitive!(i8, read_i8, write_i8);
+impl_primitive!(i16, read_i16, write_i16);
+impl_primitive!(i32, read_i32, write_i32);
+impl_primitive!(i64, read_i64, write_i64);
+impl_primitive!(f32, read_f32, write_f32);
+impl_primitive!(f64, read_f64, write_f64);
+
+/// Reads a sequence, string or map length prefix from the start of `bytes`.
+#[inline]
+pub fn read_len(bytes: &[u8], config: &Config) -> Result<(usize, usize)> {
+    config.deserialize_len(bytes)
+}
+
+/// Writes a sequence, string or map length prefix to `writer`, failing with
+/// `ErrorKind::LengthOverflow` if the configured encoding can't represent it.
+#[inline]
+pub fn write_len<W: OutputFlavor>(writer: &mut W, len: usize, config: &Config) -> Result<usize> {
+    config.serialize_len(writer, len)
+}
@@ -0,0 +1,195 @@
+//! Where the bytes of a value go, per struct field and enum variant.
+//!
+//! `Profile` measures a value like `serialized_size` and keeps, for up to `N`
+//! names, how often each field or variant came up and how many bytes it took.
+//! A field is keyed by the name of its struct, or of its variant for a struct
+//! variant; a variant by the name of its enum. Bytes are inclusive, so a
+//! nested value counts towards every field and variant around it, and
+//! tuples, sequences and maps are only counted as part of the field holding
+//! them. A full table counts the records it had to drop.
+//!
+//! ```rust
+//! extern crate bincode;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! use bincode::profile::Profile;
+//!
+//! #[derive(Serialize)]
+//! struct Snapshot {
+//!     id: u32,
+//!     log: [u64; 4],
+//!     mode: Mode,
+//! }
+//!
+//! #[derive(Serialize)]
+//! enum Mode {
+//!     Idle,
+//!     Busy { job: u16 },
+//! }
+//!
+//! fn main() {
+//!     let mut profile = Profile::<8>::new();
+//!     let snapshot = Snapshot { id: 1, log: [0; 4], mode: Mode::Busy { job: 7 } };
+//!     assert_eq!(profile.measure(&snapshot).unwrap(), 42);
+//!
+//!     assert_eq!(profile.get("Snapshot", "id").unwrap().bytes, 4);
+//!     assert_eq!(profile.get("Snapshot", "log").unwrap().bytes, 32);
+//!     assert_eq!(profile.get("Snapshot", "mode").unwrap().bytes, 6);
+//!     assert_eq!(profile.get("Mode", "Busy").unwrap().bytes, 6);
+//!     assert_eq!(profile.get("Busy", "job").unwrap().bytes, 2);
+//!     assert!(profile.get("Mode", "Idle").is_none());
+//! }
+//! ```
+
+use core::mem;
+
+use serde;
+
+use config::{DefaultOptions, Options, WithOtherLimit};
+use internal::SizeLimit;
+use ser::SizeChecker;
+use {Direction, Result};
+
+/// The totals recorded for one field or variant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Entry {
+    /// The struct, struct variant or enum the name belongs to.
+    pub container: &'static str,
+    /// The name of the field or variant.
+    pub name: &'static str,
+    /// The number of times it was measured.
+    pub count: u32,
+    /// The total number of bytes it took.
+    pub bytes: u64,
+}
+
+/// Byte totals for up to `N` fields and variants, see the module
+/// documentation.
+///
+/// It is also a `SizeLimit` without a limit, so it can be put into any
+/// `Options` with `OptionsExt::with_size_limit`; `measure_with_options` does
+/// that for you.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profile<const N: usize> {
+    entries: [Entry; N],
+    len: usize,
+    total: u64,
+    dropped: u32,
+}
+
+impl<const N: usize> Profile<N> {
+    /// Creates an empty table.
+    pub fn new() -> Profile<N> {
+        Profile {
+            entries: [Entry::default(); N],
+            len: 0,
+            total: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Measures `value` with the default configuration, adding its fields and
+    /// variants to the table, and returns its size.
+    pub fn measure<T: ?Sized + serde::Serialize>(&mut self, value: &T) -> Result<u64> {
+        self.measure_with_options(value, DefaultOptions::new())
+    }
+
+    /// Measures `value` with `options`, see `measure`. The byte limit of
+    /// `options` is replaced by the profile and doesn't apply.
+    pub fn measure_with_options<T: ?Sized, O>(&mut self, value: &T, options: O) -> Result<u64>
+    where
+        T: serde::Serialize,
+        O: Options,
+    {
+        let start = self.total;
+        let profile = mem::take(self);
+        let mut checker = SizeChecker::new(WithOtherLimit::new(options, profile));
+        let result = value.serialize(&mut checker);
+        *self = checker.options.new_limit;
+        result.map(|()| self.total - start)
+    }
+
+    /// Adds `bytes` to the totals of `name` in `container`.
+    ///
+    /// Returns `false`, and counts the record as dropped, if the name is new
+    /// and the table is full.
+    pub fn record(&mut self, container: &'static str, name: &'static str, bytes: u64) -> bool {
+        let index = match self.position(container, name) {
+            Some(index) => index,
+            None if self.len < N => {
+                self.entries[self.len] = Entry {
+                    container,
+                    name,
+                    count: 0,
+                    bytes: 0,
+                };
+                self.len += 1;
+                self.len - 1
+            }
+            None => {
+                self.dropped = self.dropped.saturating_add(1);
+                return false;
+            }
+        };
+        let entry = &mut self.entries[index];
+        entry.count = entry.count.saturating_add(1);
+        entry.bytes = entry.bytes.saturating_add(bytes);
+        true
+    }
+
+    /// The totals for `name` in `container`, if any were recorded.
+    pub fn get(&self, container: &str, name: &str) -> Option<Entry> {
+        self.position(container, name).map(|index| self.entries[index])
+    }
+
+    /// The totals for every field and variant seen so far, in order of first
+    /// record. Fields come before the variant or field holding them.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries[..self.len]
+    }
+
+    /// The total size of every value measured.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The number of records dropped because the table was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Clears every entry, the total and the dropped count.
+    pub fn reset(&mut self) {
+        *self = Profile::new();
+    }
+
+    fn position(&self, container: &str, name: &str) -> Option<usize> {
+        self.entries()
+            .iter()
+            .position(|entry| entry.container == container && entry.name == name)
+    }
+}
+
+impl<const N: usize> Default for Profile<N> {
+    fn default() -> Profile<N> {
+        Profile::new()
+    }
+}
+
+impl<const N: usize> SizeLimit for Profile<N> {
+    #[inline(always)]
+    fn add(&mut self, n: u64, _direction: Direction) -> Result<()> {
+        self.total = self.total.saturating_add(n);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn limit(&self) -> Option<u64> {
+        None
+    }
+
+    #[inline(always)]
+    fn record(&mut self, container: &'static str, name: &'static str, bytes: u64) {
+        Profile::record(self, container, name, bytes);
+    }
+}
@@ -7,42 +7,175 @@ use serde;
 use byteorder::ByteOrder;
 
 use super::internal::SizeLimit;
-use super::{Error, ErrorKind, Result};
-use config::Options;
+use super::{Direction, Error, ErrorKind, Result};
+use config::{option_tags, padding, LengthEncoding, Options, MAX_LEN_WIDTH};
 use core::fmt::{Display, Write};
+use flavors::OutputFlavor;
 
-/// An Serializer that encodes values directly into a Writer.
+pub(crate) mod spans;
+
+/// An Serializer that encodes values directly into an `OutputFlavor`.
 ///
 /// The specified byte-order will impact the endianness that is
 /// used during the encoding.
 ///
 /// This struct should not be used often.
-/// For most cases, prefer the `encode_into` function.
-pub(crate) struct Serializer<'w, A: Array<Item = u8> + 'w, O: Options> {
-    writer: &'w mut ArrayVec<A>,
-    _options: O,
+/// For most cases, prefer the `serialize_into` function.
+///
+/// The constructors, `into_inner` and the `serde::Serializer` impl are part of
+/// the stable API. Unlike `serialize_into`, a failed value may leave a partial
+/// encoding in the output.
+///
+/// ```rust
+/// extern crate bincode;
+/// extern crate serde;
+/// use bincode::arrayvec::ArrayVec;
+/// use bincode::{DefaultOptions, Serializer};
+/// use serde::Serialize;
+///
+/// fn main() {
+///     let mut buf: ArrayVec<[u8; 16]> = ArrayVec::new();
+///     {
+///         let mut s = Serializer::from_array_vec(&mut buf, DefaultOptions::new());
+///         1u16.serialize(&mut s).unwrap();
+///         'a'.serialize(&mut s).unwrap();
+///     }
+///     assert_eq!(&buf[..], &[1, 0, b'a']);
+/// }
+/// ```
+pub struct Serializer<W, O: Options> {
+    writer: W,
+    options: O,
+    pos: usize,
 }
 
-impl<'w, A: Array<Item = u8>, O: Options> Serializer<'w, A, O> {
-    /// Creates a new Serializer with the given `Write`r.
-    pub fn new(w: &'w mut ArrayVec<A>, options: O) -> Serializer<'w, A, O> {
+impl<'a, A: Array<Item = u8>, O: Options> Serializer<&'a mut ArrayVec<A>, O> {
+    /// Creates a new Serializer appending to `writer`.
+    pub fn from_array_vec(writer: &'a mut ArrayVec<A>, options: O) -> Serializer<&'a mut ArrayVec<A>, O> {
+        Serializer::new(writer, options)
+    }
+}
+
+impl<W: OutputFlavor, O: Options> Serializer<W, O> {
+    /// Creates a new Serializer with the given `OutputFlavor`.
+    pub fn new(w: W, options: O) -> Serializer<W, O> {
         Serializer {
             writer: w,
-            _options: options,
+            options,
+            pos: 0,
+        }
+    }
+
+    /// Borrows the `OutputFlavor` the serializer is writing into.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Borrows the `OutputFlavor` the serializer is writing into mutably.
+    ///
+    /// Bytes written through it don't move the serializer's position, so the
+    /// padding in front of later numbers won't account for them. Use
+    /// `write_raw` for bytes that have to be aligned around.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Unwraps the `OutputFlavor` the serializer was writing into.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes `bytes` as they are, outside of any value. They still move the
+    /// position that alignment padding is computed from.
+    pub fn write_raw(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write(bytes)
+    }
+
+    /// Writes `n` in the configured endianness, like `write_raw`.
+    pub fn write_u16_raw(&mut self, n: u16) -> Result<()> {
+        let mut buf = [0; 2];
+        O::Endian::write_u16(&mut buf, n);
+        self.write_raw(&buf)
+    }
+
+    /// Writes `n` in the configured endianness, like `write_raw`.
+    pub fn write_u32_raw(&mut self, n: u32) -> Result<()> {
+        let mut buf = [0; 4];
+        O::Endian::write_u32(&mut buf, n);
+        self.write_raw(&buf)
+    }
+
+    /// Writes `n` in the configured endianness, like `write_raw`.
+    pub fn write_u64_raw(&mut self, n: u64) -> Result<()> {
+        let mut buf = [0; 8];
+        O::Endian::write_u64(&mut buf, n);
+        self.write_raw(&buf)
+    }
+
+    pub(crate) fn serialize_len(&mut self, len: usize) -> Result<()> {
+        let encoding = self.options.length_encoding();
+        let mut buf = [0; MAX_LEN_WIDTH];
+        let prefix = encoding.encode_len::<O::Endian>(len, &mut buf)?;
+        self.align(encoding.alignment() as usize)?;
+        self.write(prefix)
+    }
+
+    fn push(&mut self, byte: u8) -> Result<()> {
+        self.pos += 1;
+        self.writer.try_push(byte)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.pos += bytes.len();
+        self.writer.write(bytes)
+    }
+
+    /// Writes an encoded number after the padding in front of it. Numbers of
+    /// every width share this, and encode themselves with nothing but the
+    /// endianness.
+    fn write_number(&mut self, bytes: &[u8]) -> Result<()> {
+        self.align(bytes.len())?;
+        self.write(bytes)
+    }
+
+    /// Pads the output up to a multiple of `width` if alignment is enabled.
+    fn align(&mut self, width: usize) -> Result<()> {
+        if self.options.align() && !self.pos.is_multiple_of(width) {
+            let padding = padding(self.pos as u64, width as u64) as usize;
+            self.write(&[0; 16][..padding])?;
+        }
+        Ok(())
+    }
+
+    /// Starts a sequence or map whose length isn't known yet by writing a
+    /// zero length prefix, which `Compound` fills in at the end. Only works
+    /// with flavors that can rewrite their output, and fixed-width lengths.
+    fn begin_unknown_len(&mut self) -> Result<Compound<'_, W, O>> {
+        let encoding = self.options.length_encoding();
+        if !self.writer.can_rewrite() || encoding == LengthEncoding::Varint {
+            return Err(ErrorKind::SequenceMustHaveLength);
         }
+        let width = encoding.width() as usize;
+        self.align(width)?;
+        let start = self.pos;
+        self.write(&[0; 8][..width])?;
+        Ok(Compound {
+            ser: self,
+            unknown_len: Some(UnknownLen { start, count: 0 }),
+        })
     }
 }
 
-impl<'a, 'w, A: Array<Item = u8>, O: Options> serde::Serializer for &'a mut Serializer<'w, A, O> {
+impl<'a, W: OutputFlavor, O: Options> serde::Serializer for &'a mut Serializer<W, O> {
     type Ok = ();
     type Error = Error;
-    type SerializeSeq = Compound<'a, 'w, A, O>;
-    type SerializeTuple = Compound<'a, 'w, A, O>;
-    type SerializeTupleStruct = Compound<'a, 'w, A, O>;
-    type SerializeTupleVariant = Compound<'a, 'w, A, O>;
-    type SerializeMap = Compound<'a, 'w, A, O>;
-    type SerializeStruct = Compound<'a, 'w, A, O>;
-    type SerializeStructVariant = Compound<'a, 'w, A, O>;
+    type SerializeSeq = Compound<'a, W, O>;
+    type SerializeTuple = Compound<'a, W, O>;
+    type SerializeTupleStruct = Compound<'a, W, O>;
+    type SerializeTupleVariant = Compound<'a, W, O>;
+    type SerializeMap = Compound<'a, W, O>;
+    type SerializeStruct = Compound<'a, W, O>;
+    type SerializeStructVariant = Compound<'a, W, O>;
 
     fn serialize_unit(self) -> Result<()> {
         Ok(())
@@ -53,153 +186,120 @@ impl<'a, 'w, A: Array<Item = u8>, O: Options> serde::Serializer for &'a mut Seri
     }
 
     fn serialize_bool(self, v: bool) -> Result<()> {
-        self.writer
-            .try_push(if v { 1 } else { 0 })
-            .map_err(Into::into)
+        self.push(if v { 1 } else { 0 })
     }
 
     fn serialize_u8(self, v: u8) -> Result<()> {
-        self.writer.try_push(v).map_err(Into::into)
+        self.push(v)
     }
 
     fn serialize_u16(self, v: u16) -> Result<()> {
         let mut buf = [0; 2];
         O::Endian::write_u16(&mut buf, v);
-        for &val in &buf {
-            self.writer.try_push(val)?;
-        }
-        Ok(())
+        self.write_number(&buf)
     }
 
     fn serialize_u32(self, v: u32) -> Result<()> {
         let mut buf = [0; 4];
         O::Endian::write_u32(&mut buf, v);
-        for &val in &buf {
-            self.writer.try_push(val)?;
-        }
-        Ok(())
+        self.write_number(&buf)
     }
 
     fn serialize_u64(self, v: u64) -> Result<()> {
         let mut buf = [0; 8];
         O::Endian::write_u64(&mut buf, v);
-        for &val in &buf {
-            self.writer.try_push(val)?;
-        }
-        Ok(())
+        self.write_number(&buf)
     }
 
     fn serialize_i8(self, v: i8) -> Result<()> {
-        self.writer.try_push(v as u8).map_err(Into::into)
+        self.push(v as u8)
     }
 
     fn serialize_i16(self, v: i16) -> Result<()> {
-        let mut buf = [0; 2];
-        O::Endian::write_i16(&mut buf, v);
-        for &val in &buf {
-            self.writer.try_push(val)?;
-        }
-        Ok(())
+        self.serialize_u16(v as u16)
     }
 
     fn serialize_i32(self, v: i32) -> Result<()> {
-        let mut buf = [0; 4];
-        O::Endian::write_i32(&mut buf, v);
-        for &val in &buf {
-            self.writer.try_push(val)?;
-        }
-        Ok(())
+        self.serialize_u32(v as u32)
     }
 
     fn serialize_i64(self, v: i64) -> Result<()> {
-        let mut buf = [0; 8];
-        O::Endian::write_i64(&mut buf, v);
-        for &val in &buf {
-            self.writer.try_push(val)?;
-        }
-        Ok(())
+        self.serialize_u64(v as u64)
     }
 
     #[cfg(feature = "i128")]
     fn serialize_u128(self, v: u128) -> Result<()> {
-        self.writer.write_u128::<O::Endian>(v).map_err(Into::into)
+        let mut buf = [0; 16];
+        O::Endian::write_u128(&mut buf, v);
+        self.write_number(&buf)
     }
 
     #[cfg(feature = "i128")]
     fn serialize_i128(self, v: i128) -> Result<()> {
-        self.writer.write_i128::<O::Endian>(v).map_err(Into::into)
+        self.serialize_u128(v as u128)
     }
 
     serde_if_integer128! {
         #[cfg(not(feature = "i128"))]
         fn serialize_u128(self, v: u128) -> Result<()> {
             let _ = v;
-            panic!("u128 is not supported. Enable the `i128` feature of `bincode`")
+            Err(ErrorKind::U128Unsupported)
         }
 
         #[cfg(not(feature = "i128"))]
         fn serialize_i128(self, v: i128) -> Result<()> {
             let _ = v;
-            panic!("i128 is not supported. Enable the `i128` feature of `bincode`")
+            Err(ErrorKind::U128Unsupported)
         }
     }
 
     fn serialize_f32(self, v: f32) -> Result<()> {
-        let mut buf = [0; 4];
-        O::Endian::write_f32(&mut buf, v);
-        for &val in &buf {
-            self.writer.try_push(val)?;
-        }
-        Ok(())
+        self.serialize_u32(v.to_bits())
     }
 
     fn serialize_f64(self, v: f64) -> Result<()> {
-        let mut buf = [0; 8];
-        O::Endian::write_f64(&mut buf, v);
-        for &val in &buf {
-            self.writer.try_push(val)?;
-        }
-        Ok(())
+        self.serialize_u64(v.to_bits())
     }
 
     fn collect_str<T: ?Sized>(self, value: &T) -> Result<()>
     where
         T: Display,
     {
-        let pos = self.writer.len();
-        try!(self.serialize_u64(0));
-        write!(ArrayVecWrite(self.writer), "{}", value)?;
-        let new_pos = self.writer.len();
-        let len = new_pos - pos - 8;
-        O::Endian::write_u64(&mut self.writer[pos..], len as u64);
+        // Not every flavor can be rewound, and a varint prefix can't be patched
+        // in place, so measure the string before writing it.
+        let mut count_write = CountWrite(0);
+        write!(&mut count_write, "{}", value)?;
+        self.serialize_len(count_write.0)?;
+
+        let mut flavor_write = FlavorWrite {
+            writer: &mut self.writer,
+            remaining: count_write.0,
+            error: None,
+        };
+        if write!(&mut flavor_write, "{}", value).is_err() || flavor_write.remaining != 0 {
+            return Err(flavor_write.error.unwrap_or(ErrorKind::Fmt(fmt::Error)));
+        }
+        self.pos += count_write.0;
         Ok(())
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        try!(self.serialize_u64(v.len() as u64));
-        for &val in v.as_bytes() {
-            self.writer.try_push(val)?;
-        }
-        Ok(())
+        try!(self.serialize_len(v.len()));
+        self.write(v.as_bytes())
     }
 
     fn serialize_char(self, c: char) -> Result<()> {
-        for &val in encode_utf8(c).as_slice() {
-            self.writer.try_push(val)?;
-        }
-        Ok(())
+        self.write(encode_utf8(c).as_slice())
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
-        try!(self.serialize_u64(v.len() as u64));
-        for &val in v {
-            self.writer.try_push(val)?;
-        }
-        Ok(())
+        try!(self.serialize_len(v.len()));
+        self.write(v)
     }
 
     fn serialize_none(self) -> Result<()> {
-        self.writer.try_push(0)?;
+        let (none, _) = option_tags(&self.options)?;
+        self.push(none)?;
         Ok(())
     }
 
@@ -207,18 +307,23 @@ impl<'a, 'w, A: Array<Item = u8>, O: Options> serde::Serializer for &'a mut Seri
     where
         T: serde::Serialize,
     {
-        self.writer.try_push(1)?;
+        let (_, some) = option_tags(&self.options)?;
+        self.push(some)?;
         v.serialize(self)
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        let len = try!(len.ok_or(ErrorKind::SequenceMustHaveLength));
-        try!(self.serialize_u64(len as u64));
-        Ok(Compound { ser: self })
+        match len {
+            Some(len) => {
+                try!(self.serialize_len(len));
+                Ok(Compound::new(self))
+            }
+            None => self.begin_unknown_len(),
+        }
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        Ok(Compound { ser: self })
+        Ok(Compound::new(self))
     }
 
     fn serialize_tuple_struct(
@@ -226,7 +331,7 @@ impl<'a, 'w, A: Array<Item = u8>, O: Options> serde::Serializer for &'a mut Seri
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        Ok(Compound { ser: self })
+        Ok(Compound::new(self))
     }
 
     fn serialize_tuple_variant(
@@ -237,17 +342,21 @@ impl<'a, 'w, A: Array<Item = u8>, O: Options> serde::Serializer for &'a mut Seri
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
         try!(self.serialize_u32(variant_index));
-        Ok(Compound { ser: self })
+        Ok(Compound::new(self))
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        let len = try!(len.ok_or(ErrorKind::SequenceMustHaveLength));
-        try!(self.serialize_u64(len as u64));
-        Ok(Compound { ser: self })
+        match len {
+            Some(len) => {
+                try!(self.serialize_len(len));
+                Ok(Compound::new(self))
+            }
+            None => self.begin_unknown_len(),
+        }
     }
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        Ok(Compound { ser: self })
+        Ok(Compound::new(self))
     }
 
     fn serialize_struct_variant(
@@ -258,9 +367,10 @@ impl<'a, 'w, A: Array<Item = u8>, O: Options> serde::Serializer for &'a mut Seri
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
         try!(self.serialize_u32(variant_index));
-        Ok(Compound { ser: self })
+        Ok(Compound::new(self))
     }
 
+    #[inline(always)]
     fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<()>
     where
         T: serde::ser::Serialize,
@@ -296,35 +406,93 @@ impl<'a, 'w, A: Array<Item = u8>, O: Options> serde::Serializer for &'a mut Seri
     }
 }
 
-pub(crate) struct SizeChecker<O: Options> {
-    pub options: O,
+/// A `serde::Serializer` that counts the bytes a `Serializer` with the same
+/// options would write, padding included, without writing them.
+pub struct SizeChecker<O: Options> {
+    pub(crate) options: O,
+    pos: u64,
 }
 
 impl<O: Options> SizeChecker<O> {
+    /// Creates a SizeChecker that charges what it counts to the limit in `options`.
     pub fn new(options: O) -> SizeChecker<O> {
-        SizeChecker { options: options }
+        SizeChecker {
+            options,
+            pos: 0,
+        }
     }
 
-    fn add_raw(&mut self, size: u64) -> Result<()> {
-        self.options.limit().add(size)
+    /// The number of bytes counted so far.
+    pub fn size(&self) -> u64 {
+        self.pos
+    }
+
+    /// Counts `size` bytes written with `Serializer::write_raw`, charging them
+    /// to the limit.
+    pub fn add_raw(&mut self, size: u64) -> Result<()> {
+        self.options.limit().add(size, Direction::Serialize)?;
+        self.pos += size;
+        Ok(())
     }
 
     fn add_value<T>(&mut self, t: T) -> Result<()> {
         use core::mem::size_of_val;
-        self.add_raw(size_of_val(&t) as u64)
+        let size = size_of_val(&t) as u64;
+        self.add_padding(size)?;
+        self.add_raw(size)
+    }
+
+    fn add_len(&mut self, len: usize) -> Result<()> {
+        let encoding = self.options.length_encoding();
+        let len = encoding.check_len(len)?;
+        self.add_padding(encoding.alignment())?;
+        self.add_raw(encoding.encoded_width(len))
+    }
+
+    /// Counts the padding the serializer writes before a `width` byte number.
+    fn add_padding(&mut self, width: u64) -> Result<()> {
+        if self.options.align() && !self.pos.is_multiple_of(width) {
+            self.add_raw(padding(self.pos, width))?;
+        }
+        Ok(())
+    }
+
+    /// Counts the length prefix of a sequence or map of unknown length, which
+    /// is checked against the length encoding at the end.
+    fn begin_unknown_len(&mut self) -> Result<SizeCompound<'_, O>> {
+        let encoding = self.options.length_encoding();
+        if encoding == LengthEncoding::Varint {
+            return Err(ErrorKind::SequenceMustHaveLength);
+        }
+        let width = encoding.width();
+        self.add_padding(width)?;
+        self.add_raw(width)?;
+        let mut compound = SizeCompound::new(self, "");
+        compound.unknown_len = Some(0);
+        Ok(compound)
     }
 }
 
 use core::fmt;
 
-struct ArrayVecWrite<'a, A: Array<Item = u8> + 'a>(&'a mut ArrayVec<A>);
+struct FlavorWrite<'a, W: 'a> {
+    writer: &'a mut W,
+    remaining: usize,
+    error: Option<Error>,
+}
 
-impl<'a, A: Array<Item = u8>> fmt::Write for ArrayVecWrite<'a, A> {
+impl<'a, W: OutputFlavor> fmt::Write for FlavorWrite<'a, W> {
     fn write_str(&mut self, s: &str) -> ::core::result::Result<(), fmt::Error> {
-        for &b in s.as_bytes() {
-            self.0.try_push(b).map_err(|_| fmt::Error)?;
+        // The length prefix is already written, so the `Display` impl must
+        // produce exactly as many bytes as it did while being measured.
+        if s.len() > self.remaining {
+            return Err(fmt::Error);
         }
-        Ok(())
+        self.remaining -= s.len();
+        self.writer.write(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
     }
 }
 
@@ -392,13 +560,28 @@ impl<'a, O: Options> serde::Serializer for &'a mut SizeChecker<O> {
         self.add_value(v)
     }
 
+    #[cfg(feature = "i128")]
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.add_value(v)
+    }
+
+    #[cfg(feature = "i128")]
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.add_value(v)
+    }
+
+    // Measuring has to fail like serializing does.
     serde_if_integer128! {
+        #[cfg(not(feature = "i128"))]
         fn serialize_u128(self, v: u128) -> Result<()> {
-            self.add_value(v)
+            let _ = v;
+            Err(ErrorKind::U128Unsupported)
         }
 
+        #[cfg(not(feature = "i128"))]
         fn serialize_i128(self, v: i128) -> Result<()> {
-            self.add_value(v)
+            let _ = v;
+            Err(ErrorKind::U128Unsupported)
         }
     }
 
@@ -414,15 +597,15 @@ impl<'a, O: Options> serde::Serializer for &'a mut SizeChecker<O> {
     where
         T: Display,
     {
-        self.add_value(0 as u64)?;
         let mut count_write = CountWrite(0);
         write!(&mut count_write, "{}", value)?;
+        self.add_len(count_write.0)?;
         self.add_raw(count_write.0 as u64)?;
         Ok(())
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        try!(self.add_value(0 as u64));
+        try!(self.add_len(v.len()));
         self.add_raw(v.len() as u64)
     }
 
@@ -431,11 +614,12 @@ impl<'a, O: Options> serde::Serializer for &'a mut SizeChecker<O> {
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
-        try!(self.add_value(0 as u64));
+        try!(self.add_len(v.len()));
         self.add_raw(v.len() as u64)
     }
 
     fn serialize_none(self) -> Result<()> {
+        option_tags(&self.options)?;
         self.add_value(0 as u8)
     }
 
@@ -443,62 +627,72 @@ impl<'a, O: Options> serde::Serializer for &'a mut SizeChecker<O> {
     where
         T: serde::Serialize,
     {
+        option_tags(&self.options)?;
         try!(self.add_value(1 as u8));
         v.serialize(self)
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        let len = try!(len.ok_or(ErrorKind::SequenceMustHaveLength));
-
-        try!(self.serialize_u64(len as u64));
-        Ok(SizeCompound { ser: self })
+        match len {
+            Some(len) => {
+                try!(self.add_len(len));
+                Ok(SizeCompound::new(self, ""))
+            }
+            None => self.begin_unknown_len(),
+        }
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        Ok(SizeCompound { ser: self })
+        Ok(SizeCompound::new(self, ""))
     }
 
     fn serialize_tuple_struct(
         self,
-        _name: &'static str,
+        name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        Ok(SizeCompound { ser: self })
+        Ok(SizeCompound::new(self, name))
     }
 
     fn serialize_tuple_variant(
         self,
-        _name: &'static str,
+        name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
+        let start = self.pos;
         try!(self.add_value(variant_index));
-        Ok(SizeCompound { ser: self })
+        Ok(SizeCompound::variant(self, name, variant, start))
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        let len = try!(len.ok_or(ErrorKind::SequenceMustHaveLength));
-
-        try!(self.serialize_u64(len as u64));
-        Ok(SizeCompound { ser: self })
+        match len {
+            Some(len) => {
+                try!(self.add_len(len));
+                Ok(SizeCompound::new(self, ""))
+            }
+            None => self.begin_unknown_len(),
+        }
     }
 
-    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        Ok(SizeCompound { ser: self })
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(SizeCompound::new(self, name))
     }
 
     fn serialize_struct_variant(
         self,
-        _name: &'static str,
+        name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
+        let start = self.pos;
         try!(self.add_value(variant_index));
-        Ok(SizeCompound { ser: self })
+        Ok(SizeCompound::variant(self, name, variant, start))
     }
 
+    #[inline(always)]
     fn serialize_newtype_struct<V: serde::Serialize + ?Sized>(
         self,
         _name: &'static str,
@@ -509,22 +703,26 @@ impl<'a, O: Options> serde::Serializer for &'a mut SizeChecker<O> {
 
     fn serialize_unit_variant(
         self,
-        _name: &'static str,
+        name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<()> {
-        self.add_value(variant_index)
+        let start = self.pos;
+        self.add_value(variant_index)?;
+        SizeCompound::variant(self, name, variant, start).finish()
     }
 
     fn serialize_newtype_variant<V: serde::Serialize + ?Sized>(
         self,
-        _name: &'static str,
+        name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         value: &V,
     ) -> Result<()> {
+        let start = self.pos;
         try!(self.add_value(variant_index));
-        value.serialize(self)
+        value.serialize(&mut *self)?;
+        SizeCompound::variant(self, name, variant, start).finish()
     }
 
     fn is_human_readable(&self) -> bool {
@@ -532,13 +730,52 @@ impl<'a, O: Options> serde::Serializer for &'a mut SizeChecker<O> {
     }
 }
 
-pub(crate) struct Compound<'a, 'w: 'a, A: Array<Item = u8> + 'w + 'a, O: Options + 'a> {
-    ser: &'a mut Serializer<'w, A, O>,
+/// The `serde::ser::SerializeSeq` and friends of a `Serializer`.
+pub struct Compound<'a, W: 'a, O: Options + 'a> {
+    ser: &'a mut Serializer<W, O>,
+    unknown_len: Option<UnknownLen>,
+}
+
+/// A sequence or map serialized without a length: where its length prefix
+/// was written, and the number of elements or entries so far.
+struct UnknownLen {
+    start: usize,
+    count: usize,
 }
 
-impl<'a, 'w, A, O> serde::ser::SerializeSeq for Compound<'a, 'w, A, O>
+impl<'a, W: OutputFlavor, O: Options> Compound<'a, W, O> {
+    fn new(ser: &'a mut Serializer<W, O>) -> Compound<'a, W, O> {
+        Compound {
+            ser,
+            unknown_len: None,
+        }
+    }
+
+    fn count(&mut self) {
+        if let Some(ref mut unknown_len) = self.unknown_len {
+            unknown_len.count += 1;
+        }
+    }
+
+    /// Fills in the length prefix of a sequence or map of unknown length.
+    fn finish(self) -> Result<()> {
+        if let Some(unknown_len) = self.unknown_len {
+            let mut buf = [0; MAX_LEN_WIDTH];
+            let prefix = self
+                .ser
+                .options
+                .length_encoding()
+                .encode_len::<O::Endian>(unknown_len.count, &mut buf)?;
+            let back = self.ser.pos - unknown_len.start;
+            self.ser.writer.rewrite(back, prefix)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, W, O> serde::ser::SerializeSeq for Compound<'a, W, O>
 where
-    A: Array<Item = u8>,
+    W: OutputFlavor,
     O: Options,
 {
     type Ok = ();
@@ -549,18 +786,19 @@ where
     where
         T: serde::ser::Serialize,
     {
+        self.count();
         value.serialize(&mut *self.ser)
     }
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.finish()
     }
 }
 
-impl<'a, 'w, A, O> serde::ser::SerializeTuple for Compound<'a, 'w, A, O>
+impl<'a, W, O> serde::ser::SerializeTuple for Compound<'a, W, O>
 where
-    A: Array<Item = u8>,
+    W: OutputFlavor,
     O: Options,
 {
     type Ok = ();
@@ -580,9 +818,9 @@ where
     }
 }
 
-impl<'a, 'w, A, O> serde::ser::SerializeTupleStruct for Compound<'a, 'w, A, O>
+impl<'a, W, O> serde::ser::SerializeTupleStruct for Compound<'a, W, O>
 where
-    A: Array<Item = u8>,
+    W: OutputFlavor,
     O: Options,
 {
     type Ok = ();
@@ -602,9 +840,9 @@ where
     }
 }
 
-impl<'a, 'w, A, O> serde::ser::SerializeTupleVariant for Compound<'a, 'w, A, O>
+impl<'a, W, O> serde::ser::SerializeTupleVariant for Compound<'a, W, O>
 where
-    A: Array<Item = u8>,
+    W: OutputFlavor,
     O: Options,
 {
     type Ok = ();
@@ -624,9 +862,9 @@ where
     }
 }
 
-impl<'a, 'w, A, O> serde::ser::SerializeMap for Compound<'a, 'w, A, O>
+impl<'a, W, O> serde::ser::SerializeMap for Compound<'a, W, O>
 where
-    A: Array<Item = u8>,
+    W: OutputFlavor,
     O: Options,
 {
     type Ok = ();
@@ -637,6 +875,7 @@ where
     where
         K: serde::ser::Serialize,
     {
+        self.count();
         value.serialize(&mut *self.ser)
     }
 
@@ -650,13 +889,13 @@ where
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.finish()
     }
 }
 
-impl<'a, 'w, A, O> serde::ser::SerializeStruct for Compound<'a, 'w, A, O>
+impl<'a, W, O> serde::ser::SerializeStruct for Compound<'a, W, O>
 where
-    A: Array<Item = u8>,
+    W: OutputFlavor,
     O: Options,
 {
     type Ok = ();
@@ -676,9 +915,9 @@ where
     }
 }
 
-impl<'a, 'w, A, O> serde::ser::SerializeStructVariant for Compound<'a, 'w, A, O>
+impl<'a, W, O> serde::ser::SerializeStructVariant for Compound<'a, W, O>
 where
-    A: Array<Item = u8>,
+    W: OutputFlavor,
     O: Options,
 {
     type Ok = ();
@@ -698,8 +937,62 @@ where
     }
 }
 
-pub(crate) struct SizeCompound<'a, S: Options + 'a> {
+/// The `serde::ser::SerializeSeq` and friends of a `SizeChecker`.
+pub struct SizeCompound<'a, S: Options + 'a> {
     ser: &'a mut SizeChecker<S>,
+    /// The name fields are recorded under: the struct, or the variant of a
+    /// struct variant.
+    container: &'static str,
+    /// The enum, the variant and where it started, recorded at the end.
+    variant: Option<(&'static str, &'static str, u64)>,
+    /// The number of elements or entries so far of a sequence or map of
+    /// unknown length.
+    unknown_len: Option<usize>,
+}
+
+impl<'a, O: Options> SizeCompound<'a, O> {
+    fn new(ser: &'a mut SizeChecker<O>, container: &'static str) -> SizeCompound<'a, O> {
+        SizeCompound {
+            ser,
+            container,
+            variant: None,
+            unknown_len: None,
+        }
+    }
+
+    fn variant(ser: &'a mut SizeChecker<O>, name: &'static str, variant: &'static str, start: u64) -> SizeCompound<'a, O> {
+        SizeCompound {
+            ser,
+            container: variant,
+            variant: Some((name, variant, start)),
+            unknown_len: None,
+        }
+    }
+
+    fn field<T: ?Sized + serde::Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        let start = self.ser.pos;
+        value.serialize(&mut *self.ser)?;
+        let bytes = self.ser.pos - start;
+        self.ser.options.limit().record(self.container, key, bytes);
+        Ok(())
+    }
+
+    fn count(&mut self) {
+        if let Some(ref mut count) = self.unknown_len {
+            *count += 1;
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        if let Some((name, variant, start)) = self.variant {
+            let bytes = self.ser.pos - start;
+            self.ser.options.limit().record(name, variant, bytes);
+        }
+        if let Some(count) = self.unknown_len {
+            self.ser.options.length_encoding().check_len(count)?;
+        }
+        Ok(())
+    }
 }
 
 impl<'a, O: Options> serde::ser::SerializeSeq for SizeCompound<'a, O> {
@@ -711,12 +1004,13 @@ impl<'a, O: Options> serde::ser::SerializeSeq for SizeCompound<'a, O> {
     where
         T: serde::ser::Serialize,
     {
+        self.count();
         value.serialize(&mut *self.ser)
     }
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.finish()
     }
 }
 
@@ -770,7 +1064,7 @@ impl<'a, O: Options> serde::ser::SerializeTupleVariant for SizeCompound<'a, O> {
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.finish()
     }
 }
 
@@ -783,6 +1077,7 @@ impl<'a, O: Options + 'a> serde::ser::SerializeMap for SizeCompound<'a, O> {
     where
         K: serde::ser::Serialize,
     {
+        self.count();
         value.serialize(&mut *self.ser)
     }
 
@@ -796,7 +1091,7 @@ impl<'a, O: Options + 'a> serde::ser::SerializeMap for SizeCompound<'a, O> {
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.finish()
     }
 }
 
@@ -805,11 +1100,11 @@ impl<'a, O: Options> serde::ser::SerializeStruct for SizeCompound<'a, O> {
     type Error = Error;
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: serde::ser::Serialize,
     {
-        value.serialize(&mut *self.ser)
+        self.field(key, value)
     }
 
     #[inline]
@@ -823,16 +1118,16 @@ impl<'a, O: Options> serde::ser::SerializeStructVariant for SizeCompound<'a, O>
     type Error = Error;
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: serde::ser::Serialize,
     {
-        value.serialize(&mut *self.ser)
+        self.field(key, value)
     }
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        self.finish()
     }
 }
 const TAG_CONT: u8 = 0b1000_0000;
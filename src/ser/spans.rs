@@ -0,0 +1,172 @@
+//! Recording where the fields of the outermost struct were written, see
+//! `Config::serialize_into_with_spans`.
+
+use core::fmt::Display;
+
+use serde;
+use serde::ser::SerializeStruct;
+
+use super::{Compound, Serializer};
+use config::Options;
+use flavors::OutputFlavor;
+use {Error, Result};
+
+/// Where a field of the outermost struct was written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FieldSpan {
+    /// The name of the field.
+    pub name: &'static str,
+    /// The offset of its first byte into the output.
+    pub offset: usize,
+    /// The number of bytes it took, nested values and padding included.
+    pub len: usize,
+}
+
+/// Serializes `value`, filling `spans` with the fields of the outermost
+/// struct, their offsets counted from `base`. Returns the number of fields,
+/// which can be more than fit into `spans`.
+pub(crate) fn serialize<W, O, T: ?Sized>(ser: &mut Serializer<W, O>, value: &T, spans: &mut [FieldSpan], base: usize) -> Result<usize>
+where
+    W: OutputFlavor,
+    O: Options,
+    T: serde::Serialize,
+{
+    let mut recorder = SpanSerializer {
+        ser,
+        spans,
+        base,
+        fields: 0,
+    };
+    value.serialize(&mut recorder)?;
+    Ok(recorder.fields)
+}
+
+/// Hands everything to the wrapped `Serializer`, except for the outermost
+/// struct, whose fields it measures as they are written. Nested values go
+/// straight to the `Serializer` and are never seen here.
+struct SpanSerializer<'a, W: 'a, O: Options + 'a> {
+    ser: &'a mut Serializer<W, O>,
+    spans: &'a mut [FieldSpan],
+    base: usize,
+    fields: usize,
+}
+
+macro_rules! forward {
+    ($($method:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*) => {
+        $(
+            #[inline]
+            fn $method(self, $($arg: $ty),*) -> Result<$ret> {
+                serde::Serializer::$method(&mut *self.ser, $($arg),*)
+            }
+        )*
+    }
+}
+
+impl<'a, 'b, W: OutputFlavor, O: Options> serde::Serializer for &'b mut SpanSerializer<'a, W, O> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'b, W, O>;
+    type SerializeTuple = Compound<'b, W, O>;
+    type SerializeTupleStruct = Compound<'b, W, O>;
+    type SerializeTupleVariant = Compound<'b, W, O>;
+    type SerializeMap = Compound<'b, W, O>;
+    type SerializeStruct = SpanCompound<'b, W, O>;
+    type SerializeStructVariant = Compound<'b, W, O>;
+
+    forward! {
+        serialize_bool(v: bool) -> ();
+        serialize_i8(v: i8) -> ();
+        serialize_i16(v: i16) -> ();
+        serialize_i32(v: i32) -> ();
+        serialize_i64(v: i64) -> ();
+        serialize_i128(v: i128) -> ();
+        serialize_u8(v: u8) -> ();
+        serialize_u16(v: u16) -> ();
+        serialize_u32(v: u32) -> ();
+        serialize_u64(v: u64) -> ();
+        serialize_u128(v: u128) -> ();
+        serialize_f32(v: f32) -> ();
+        serialize_f64(v: f64) -> ();
+        serialize_char(v: char) -> ();
+        serialize_str(v: &str) -> ();
+        serialize_bytes(v: &[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(name: &'static str) -> ();
+        serialize_unit_variant(name: &'static str, variant_index: u32, variant: &'static str) -> ();
+        serialize_seq(len: Option<usize>) -> Compound<'b, W, O>;
+        serialize_tuple(len: usize) -> Compound<'b, W, O>;
+        serialize_tuple_struct(name: &'static str, len: usize) -> Compound<'b, W, O>;
+        serialize_tuple_variant(name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> Compound<'b, W, O>;
+        serialize_map(len: Option<usize>) -> Compound<'b, W, O>;
+        serialize_struct_variant(name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> Compound<'b, W, O>;
+    }
+
+    fn collect_str<T: ?Sized + Display>(self, value: &T) -> Result<()> {
+        serde::Serializer::collect_str(&mut *self.ser, value)
+    }
+
+    fn serialize_some<T: ?Sized + serde::Serialize>(self, value: &T) -> Result<()> {
+        serde::Serializer::serialize_some(&mut *self.ser, value)
+    }
+
+    // A newtype adds nothing to the encoding, so the struct it wraps still
+    // counts as the outermost one.
+    fn serialize_newtype_struct<T: ?Sized + serde::Serialize>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + serde::Serialize>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        serde::Serializer::serialize_newtype_variant(&mut *self.ser, name, variant_index, variant, value)
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<SpanCompound<'b, W, O>> {
+        Ok(SpanCompound {
+            compound: serde::Serializer::serialize_struct(&mut *self.ser, name, len)?,
+            spans: &mut *self.spans,
+            base: self.base,
+            fields: &mut self.fields,
+        })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// The fields of the outermost struct.
+struct SpanCompound<'a, W: 'a, O: Options + 'a> {
+    compound: Compound<'a, W, O>,
+    spans: &'a mut [FieldSpan],
+    base: usize,
+    fields: &'a mut usize,
+}
+
+impl<'a, W: OutputFlavor, O: Options> SerializeStruct for SpanCompound<'a, W, O> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + serde::Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        let start = self.compound.ser.pos;
+        self.compound.serialize_field(key, value)?;
+        if let Some(span) = self.spans.get_mut(*self.fields) {
+            *span = FieldSpan {
+                name: key,
+                offset: self.base + start,
+                len: self.compound.ser.pos - start,
+            };
+        }
+        *self.fields += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        self.compound.end()
+    }
+}
@@ -0,0 +1,217 @@
+//! Per-message-type counts of serialized bytes, for planning flash wear.
+//!
+//! `Stats` keeps a count and a byte total for up to `N` caller-chosen type
+//! ids. Counters saturate rather than wrap, and a full table counts the
+//! records it had to drop. The table serializes with this crate into a
+//! compact snapshot that holds only the ids seen so far.
+//!
+//! ```rust
+//! extern crate bincode;
+//! use bincode::arrayvec::ArrayVec;
+//! use bincode::stats::Stats;
+//!
+//! fn main() {
+//!     let mut stats = Stats::<4>::new();
+//!     let mut journal: ArrayVec<[u8; 64]> = ArrayVec::new();
+//!     stats.serialize_recorded(1, &mut &mut journal, &(3u16, "boot")).unwrap();
+//!     stats.serialize_recorded(2, &mut &mut journal, &42u32).unwrap();
+//!
+//!     assert_eq!(stats.get(1).unwrap().bytes, 14);
+//!
+//!     let mut snapshot: ArrayVec<[u8; 64]> = ArrayVec::new();
+//!     bincode::serialize_into(&mut snapshot, &stats).unwrap();
+//!     let uploaded: Stats<4> = bincode::deserialize(&snapshot).unwrap();
+//!     assert_eq!(uploaded, stats);
+//! }
+//! ```
+
+use core::fmt;
+
+use serde;
+use serde::de::Error as DeError;
+use serde::ser::SerializeSeq;
+
+use flavors::OutputFlavor;
+use Result;
+
+/// The totals recorded for one type id.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Entry {
+    /// The caller-supplied id.
+    pub type_id: u32,
+    /// The number of messages recorded.
+    pub count: u32,
+    /// The total number of bytes recorded.
+    pub bytes: u64,
+}
+
+/// Counts and byte totals for up to `N` type ids.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stats<const N: usize> {
+    entries: [Entry; N],
+    len: usize,
+    dropped: u32,
+}
+
+impl<const N: usize> Stats<N> {
+    /// Creates an empty table.
+    pub fn new() -> Stats<N> {
+        Stats {
+            entries: [Entry::default(); N],
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Records a message of `len` bytes under `type_id`.
+    ///
+    /// Returns `false`, and counts the record as dropped, if `type_id` is new
+    /// and the table is full.
+    pub fn record(&mut self, type_id: u32, len: usize) -> bool {
+        let index = match self.position(type_id) {
+            Some(index) => index,
+            None if self.len < N => {
+                self.entries[self.len] = Entry {
+                    type_id,
+                    count: 0,
+                    bytes: 0,
+                };
+                self.len += 1;
+                self.len - 1
+            }
+            None => {
+                self.dropped = self.dropped.saturating_add(1);
+                return false;
+            }
+        };
+        let entry = &mut self.entries[index];
+        entry.count = entry.count.saturating_add(1);
+        entry.bytes = entry.bytes.saturating_add(len as u64);
+        true
+    }
+
+    /// Serializes `value` into `writer` with the default configuration and
+    /// records the bytes written under `type_id`.
+    pub fn serialize_recorded<W, T: ?Sized>(&mut self, type_id: u32, writer: &mut W, value: &T) -> Result<usize>
+    where
+        W: OutputFlavor,
+        T: serde::Serialize,
+    {
+        let len = ::config().serialize_prefix(writer, value)?;
+        self.record(type_id, len);
+        Ok(len)
+    }
+
+    /// The totals for `type_id`, if any were recorded.
+    pub fn get(&self, type_id: u32) -> Option<Entry> {
+        self.position(type_id).map(|index| self.entries[index])
+    }
+
+    /// The totals for every type id seen so far, in order of first record.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries[..self.len]
+    }
+
+    /// The number of records dropped because the table was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Clears every entry and the dropped count.
+    pub fn reset(&mut self) {
+        *self = Stats::new();
+    }
+
+    fn position(&self, type_id: u32) -> Option<usize> {
+        self.entries().iter().position(|entry| entry.type_id == type_id)
+    }
+}
+
+impl<const N: usize> Default for Stats<N> {
+    fn default() -> Stats<N> {
+        Stats::new()
+    }
+}
+
+impl serde::Serialize for Entry {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error> {
+        (self.type_id, self.count, self.bytes).serialize(serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Entry {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> ::core::result::Result<Entry, D::Error> {
+        let (type_id, count, bytes) = serde::Deserialize::deserialize(deserializer)?;
+        Ok(Entry {
+            type_id,
+            count,
+            bytes,
+        })
+    }
+}
+
+/// Serialized as the dropped count followed by the recorded entries.
+impl<const N: usize> serde::Serialize for Stats<N> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error> {
+        struct Entries<'a>(&'a [Entry]);
+
+        impl<'a> serde::Serialize for Entries<'a> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error> {
+                let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+                for entry in self.0 {
+                    seq.serialize_element(entry)?;
+                }
+                seq.end()
+            }
+        }
+
+        (self.dropped, Entries(self.entries())).serialize(serializer)
+    }
+}
+
+impl<'de, const N: usize> serde::Deserialize<'de> for Stats<N> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> ::core::result::Result<Stats<N>, D::Error> {
+        struct Entries<const N: usize>([Entry; N], usize);
+
+        impl<'de, const N: usize> serde::Deserialize<'de> for Entries<N> {
+            fn deserialize<D: serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> ::core::result::Result<Entries<N>, D::Error> {
+                struct Visitor<const N: usize>;
+
+                impl<'de, const N: usize> serde::de::Visitor<'de> for Visitor<N> {
+                    type Value = Entries<N>;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        write!(formatter, "at most {} entries", N)
+                    }
+
+                    fn visit_seq<A>(self, mut seq: A) -> ::core::result::Result<Entries<N>, A::Error>
+                    where
+                        A: serde::de::SeqAccess<'de>,
+                    {
+                        let mut entries = [Entry::default(); N];
+                        let mut len = 0;
+                        while let Some(entry) = seq.next_element()? {
+                            if len == N {
+                                return Err(A::Error::invalid_length(len + 1, &self));
+                            }
+                            entries[len] = entry;
+                            len += 1;
+                        }
+                        Ok(Entries(entries, len))
+                    }
+                }
+
+                deserializer.deserialize_seq(Visitor::<N>)
+            }
+        }
+
+        let (dropped, Entries(entries, len)) = serde::Deserialize::deserialize(deserializer)?;
+        Ok(Stats {
+            entries,
+            len,
+            dropped,
+        })
+    }
+}
@@ -0,0 +1,16 @@
+//! Ready-made string filters for `Config::str_filter`.
+
+/// Returns the string unchanged. This is the default filter.
+pub fn identity(s: &str) -> &str {
+    s
+}
+
+/// Strips trailing NUL characters, as left behind by fixed-size C buffers.
+pub fn trim_nul(s: &str) -> &str {
+    s.trim_end_matches('\0')
+}
+
+/// Strips leading and trailing ASCII whitespace.
+pub fn trim_ascii_whitespace(s: &str) -> &str {
+    s.trim_matches(|c: char| c.is_ascii_whitespace())
+}
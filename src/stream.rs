@@ -0,0 +1,170 @@
+//! Decoding a stream of messages that arrives in arbitrarily sized pieces.
+//!
+//! Each message is framed as its encoded length, written like any other
+//! length prefix in the configured `LengthEncoding`, followed by the encoded
+//! value. `write_frame` produces such frames. A `StreamDeserializer` holds on
+//! to the bytes pushed into it until a whole frame is there, so a chunk may
+//! end in the middle of a message or carry several at once.
+//!
+//! ```rust
+//! extern crate bincode;
+//! use bincode::arrayvec::ArrayVec;
+//! use bincode::stream::{write_frame, StreamDeserializer};
+//!
+//! fn main() {
+//!     let mut wire: ArrayVec<[u8; 64]> = ArrayVec::new();
+//!     {
+//!         let mut writer = &mut wire;
+//!         write_frame(&mut writer, &(1u16, 'a'), &bincode::config()).unwrap();
+//!         write_frame(&mut writer, &(2u16, 'b'), &bincode::config()).unwrap();
+//!     }
+//!
+//!     let mut stream: StreamDeserializer<(u16, char), [u8; 32]> = StreamDeserializer::new();
+//!     let mut decoded = Vec::new();
+//!     for chunk in wire.chunks(5) {
+//!         assert_eq!(stream.push(chunk), chunk.len());
+//!         while let Some(message) = stream.try_next().unwrap() {
+//!             decoded.push(message);
+//!         }
+//!     }
+//!     assert_eq!(decoded, [(1, 'a'), (2, 'b')]);
+//! }
+//! ```
+
+use core::marker::PhantomData;
+
+use arrayvec::{Array, ArrayVec};
+use serde;
+
+use flavors::OutputFlavor;
+use {config, Config, ErrorKind, Options, Result};
+
+/// Writes `value` to `writer` as a frame: its length in bytes as a length
+/// prefix, then the value itself. Returns the number of bytes written.
+pub fn write_frame<W, T: ?Sized>(writer: &mut W, value: &T, config: &Config) -> Result<usize>
+where
+    W: OutputFlavor,
+    T: serde::Serialize,
+{
+    config.write_stream_frame(writer, value)
+}
+
+/// Writes `value` to `writer` as a frame using `options`, see `write_frame`.
+pub fn write_frame_with_options<W, T, O>(writer: &mut W, value: &T, mut options: O) -> Result<usize>
+where
+    W: OutputFlavor,
+    T: ?Sized + serde::Serialize,
+    O: Options,
+{
+    let len = ::internal::serialized_size(value, &mut options)?;
+    let prefix = ::internal::serialize_len(writer, len as usize, &mut options)?;
+    Ok(prefix + ::internal::serialize_prefix(writer, value, options)?)
+}
+
+/// Collects pushed bytes into a fixed-size buffer and decodes a `T` from each
+/// complete frame in it, see the module documentation.
+///
+/// A frame has to fit into the buffer as a whole; a longer one fails with
+/// `ScratchTooSmall` as soon as its length prefix is in.
+pub struct StreamDeserializer<T, A: Array<Item = u8>> {
+    buffer: ArrayVec<A>,
+    config: Config,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, A: Array<Item = u8>> StreamDeserializer<T, A> {
+    /// Creates an empty stream using the default configuration.
+    pub fn new() -> StreamDeserializer<T, A> {
+        StreamDeserializer::with_config(config())
+    }
+
+    /// Creates an empty stream using `config`, which has to match the one the
+    /// frames were written with.
+    pub fn with_config(config: Config) -> StreamDeserializer<T, A> {
+        StreamDeserializer {
+            buffer: ArrayVec::new(),
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Appends as much of `bytes` as there is room for, returning how many
+    /// bytes were taken. The rest has to be pushed again once `try_next` has
+    /// made room.
+    pub fn push(&mut self, bytes: &[u8]) -> usize {
+        let taken = ::core::cmp::min(bytes.len(), self.buffer.capacity() - self.buffer.len());
+        self.buffer.extend(bytes[..taken].iter().cloned());
+        taken
+    }
+
+    /// The bytes pushed but not decoded yet.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Drops the first `n` buffered bytes, or all of them if there are
+    /// fewer, returning how many were dropped. For getting past a length
+    /// prefix `try_next` keeps failing on, e.g. up to a known frame boundary.
+    pub fn skip(&mut self, n: usize) -> usize {
+        let n = ::core::cmp::min(n, self.buffer.len());
+        self.buffer.drain(..n);
+        n
+    }
+
+    /// Drops every buffered byte, e.g. when the connection is reset.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+impl<T, A: Array<Item = u8>> Default for StreamDeserializer<T, A> {
+    fn default() -> StreamDeserializer<T, A> {
+        StreamDeserializer::new()
+    }
+}
+
+impl<T: serde::de::DeserializeOwned, A: Array<Item = u8>> StreamDeserializer<T, A> {
+    /// Decodes the next message, or returns `Ok(None)` if its frame isn't
+    /// complete yet.
+    ///
+    /// A frame whose value fails to decode, or doesn't take up the whole
+    /// frame, is dropped with the error, so the next call carries on with the
+    /// frame after it. A length prefix that can't be decoded or is too long
+    /// for the buffer is returned again on every call, as there is no telling
+    /// where the next frame starts, until it is dropped with `skip` or
+    /// `clear`.
+    pub fn try_next(&mut self) -> Result<Option<T>> {
+        let config = self.config;
+        config.next_stream_frame(self)
+    }
+
+    /// Decodes the next message using `options` instead of the configuration
+    /// the stream was created with, see `try_next`.
+    pub fn try_next_with_options<O: Options>(&mut self, mut options: O) -> Result<Option<T>> {
+        let (len, prefix) = match ::internal::deserialize_len(&self.buffer, &mut options) {
+            Ok(len) => len,
+            Err(ErrorKind::UnexpectedEof { .. }) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let frame = match prefix.checked_add(len) {
+            Some(frame) if frame <= self.buffer.capacity() => frame,
+            _ => {
+                return Err(ErrorKind::ScratchTooSmall {
+                    len,
+                    max: self.buffer.capacity() - prefix,
+                })
+            }
+        };
+        if self.buffer.len() < frame {
+            return Ok(None);
+        }
+
+        let decoded = match ::internal::deserialize_prefix(&self.buffer[prefix..frame], options) {
+            Ok((value, used)) if used == len => Ok(Some(value)),
+            Ok((_, used)) => Err(ErrorKind::TrailingBytes(len - used)),
+            Err(e) => Err(e),
+        };
+        self.buffer.drain(..frame);
+        decoded
+    }
+}
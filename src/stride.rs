@@ -0,0 +1,284 @@
+//! The layout of encoded slices, for hosts that decode them with a stride.
+//!
+//! A slice (or any other sequence) of `T` is encoded as its length prefix, in
+//! the configured `LengthEncoding`, followed by the encoding of each element
+//! back to back. Elements are encoded field by field, so `#[repr(C)]` padding
+//! and alignment never show up on the wire, and nothing is inserted between
+//! elements.
+//!
+//! When every field of `T` has a fixed width, every element takes the same
+//! number of bytes and element `i` starts at `prefix + i * stride`.
+//! `encoded_stride` computes that width from `T`'s `Deserialize` impl, and
+//! `encoded_stride_with_options` (or `Config::encoded_stride`) does the same
+//! for other options. The length encoding only changes the width of the
+//! prefix, which for `LengthEncoding::Varint` depends on the length itself.
+//!
+//! With `Config::align`, numbers are padded depending on where they fall in
+//! the message, so an element's padding depends on where it starts. The
+//! stride then includes that padding and assumes the first element is
+//! aligned, see `encoded_stride_with_options`.
+//!
+//! ```rust
+//! extern crate bincode;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! use bincode::arrayvec::ArrayVec;
+//! use bincode::stride::encoded_stride;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! #[repr(C)]
+//! struct Sample {
+//!     channel: u8,
+//!     value: u32,
+//! }
+//!
+//! fn main() {
+//!     // 5 bytes, not the 8 of the in-memory layout.
+//!     assert_eq!(encoded_stride::<Sample>(), Some(5));
+//!
+//!     let samples = [Sample { channel: 1, value: 10 }, Sample { channel: 2, value: 20 }];
+//!     let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+//!     bincode::serialize_into(&mut buf, &samples[..]).unwrap();
+//!     assert_eq!(buf.len(), 8 + 2 * 5);
+//!     let second: Sample = bincode::deserialize(&buf[8 + 5..]).unwrap();
+//!     assert_eq!(second.value, 20);
+//! }
+//! ```
+
+use serde;
+use serde::de::Error as DeError;
+
+use config::{DefaultOptions, Options};
+use {Error, ErrorKind, Result};
+
+/// Returns the number of bytes every encoded `T` takes up with the default
+/// options, or `None` if that depends on the value.
+///
+/// Only types made of numbers, bools, units, fixed-size arrays, tuples and
+/// structs of those have a stride. Strings, chars, options, sequences, maps
+/// and enums don't.
+///
+/// The type is walked by handing its visitor made-up numbers, first zeroes,
+/// then ones, then maximums. A `Deserialize` impl that rejects all three,
+/// like one that validates its fields against each other, has no stride.
+pub fn encoded_stride<T: serde::Deserialize<'static>>() -> Option<usize> {
+    encoded_stride_with_options::<T, _>(DefaultOptions::new())
+}
+
+/// Like `encoded_stride`, but with the given options. Only `Options::align`
+/// changes the result.
+///
+/// With alignment, elements are laid out as if the first one started at a
+/// multiple of the widest number in `T`, and a stride is only returned if
+/// every element after it starts at such a multiple too, e.g. `(u8, u32)`
+/// (with three bytes of padding) but not `(u32, u8)`. Whether the first
+/// element is aligned depends on what comes before the slice, e.g. it is
+/// after a `LengthEncoding::U64` prefix at the start of the message.
+pub fn encoded_stride_with_options<T, O>(options: O) -> Option<usize>
+where
+    T: serde::Deserialize<'static>,
+    O: Options,
+{
+    let align = options.align();
+    for &fill in &[Fill::Zero, Fill::One, Fill::Max] {
+        let mut probe = Probe { pos: 0, widest: 1, align, fill, variable: false };
+        if T::deserialize(&mut probe).is_ok() {
+            return if probe.pos.is_multiple_of(probe.widest) { Some(probe.pos) } else { None };
+        }
+        if probe.variable {
+            return None;
+        }
+    }
+    // The visitor rejected every made-up value.
+    None
+}
+
+/// The values handed to visitors while probing.
+#[derive(Clone, Copy, PartialEq)]
+enum Fill {
+    Zero,
+    One,
+    Max,
+}
+
+/// Walks a type's shape without any input, adding up the width of every
+/// fixed-size field and failing on anything else.
+struct Probe {
+    pos: usize,
+    widest: usize,
+    align: bool,
+    fill: Fill,
+    variable: bool,
+}
+
+impl Probe {
+    fn variable(&mut self) -> Error {
+        self.variable = true;
+        Error::custom("the encoded size depends on the value")
+    }
+
+    /// Adds a number of `width` bytes, padded like the serializer does.
+    fn advance(&mut self, width: usize) {
+        if self.align {
+            self.pos = self.pos.next_multiple_of(width);
+            self.widest = self.widest.max(width);
+        }
+        self.pos += width;
+    }
+}
+
+macro_rules! probe_nums {
+    ($ty:ty, $dser_method:ident, $visitor_method:ident) => {
+        fn $dser_method<V>(self, visitor: V) -> Result<V::Value>
+            where V: serde::de::Visitor<'de>,
+        {
+            self.advance(::core::mem::size_of::<$ty>());
+            visitor.$visitor_method(match self.fill {
+                Fill::Zero => 0 as $ty,
+                Fill::One => 1 as $ty,
+                Fill::Max => <$ty>::MAX,
+            })
+        }
+    }
+}
+
+impl<'de, 'a> serde::Deserializer<'de> for &'a mut Probe {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.variable = true;
+        Err(ErrorKind::DeserializeAnyNotSupported)
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.pos += 1;
+        visitor.visit_bool(self.fill != Fill::Zero)
+    }
+
+    probe_nums!(u8, deserialize_u8, visit_u8);
+    probe_nums!(u16, deserialize_u16, visit_u16);
+    probe_nums!(u32, deserialize_u32, visit_u32);
+    probe_nums!(u64, deserialize_u64, visit_u64);
+    probe_nums!(i8, deserialize_i8, visit_i8);
+    probe_nums!(i16, deserialize_i16, visit_i16);
+    probe_nums!(i32, deserialize_i32, visit_i32);
+    probe_nums!(i64, deserialize_i64, visit_i64);
+    probe_nums!(f32, deserialize_f32, visit_f32);
+    probe_nums!(f64, deserialize_f64, visit_f64);
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        struct Access<'a> {
+            probe: &'a mut Probe,
+            len: usize,
+        }
+
+        impl<'de, 'a> serde::de::SeqAccess<'de> for Access<'a> {
+            type Error = Error;
+
+            fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+            where
+                T: serde::de::DeserializeSeed<'de>,
+            {
+                if self.len == 0 {
+                    return Ok(None);
+                }
+                self.len -= 1;
+                seed.deserialize(&mut *self.probe).map(Some)
+            }
+
+            fn size_hint(&self) -> Option<usize> {
+                Some(self.len)
+            }
+        }
+
+        visitor.visit_seq(Access { probe: self, len })
+    }
+
+    fn deserialize_tuple_struct<V>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // A `NulStr` is as long as its contents.
+        if name == ::nul_str::TOKEN {
+            return Err(self.variable());
+        }
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // Encoded as UTF-8, so anywhere from one to four bytes.
+        Err(self.variable())
+    }
+
+    fn deserialize_option<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(self.variable())
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        Err(self.variable())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf seq map identifier ignored_any
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
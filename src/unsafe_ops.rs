@@ -0,0 +1,31 @@
+//! Every use of `unsafe` in the crate lives in this module.
+//!
+//! The rest of the crate is built with `deny(unsafe_code)`, and with
+//! `forbid(unsafe_code)` unless the `unchecked-slices` feature, which pulls in
+//! this module, is enabled. Each operation here states the invariant its
+//! caller must uphold and checks it with `debug_assert!`, so debug builds and
+//! the test suite catch a violated invariant as a panic.
+#![allow(unsafe_code)]
+
+use arrayvec::{Array, ArrayVec};
+
+/// Splits `slice` into its first `len` bytes and the rest, without a bounds
+/// check.
+///
+/// Invariant: `len <= slice.len()`. `SliceReader` checks this before every
+/// call and reports running out of input as an error instead.
+#[inline(always)]
+pub(crate) fn split_prefix(slice: &[u8], len: usize) -> (&[u8], &[u8]) {
+    debug_assert!(len <= slice.len());
+    unsafe { (slice.get_unchecked(..len), slice.get_unchecked(len..)) }
+}
+
+/// Appends `byte` to `vec` without a capacity check.
+///
+/// Invariant: `vec` is not full. `Trusted` counts down the room it found
+/// before serializing and reports running out as an error instead.
+#[inline(always)]
+pub(crate) fn push_unchecked<A: Array<Item = u8>>(vec: &mut ArrayVec<A>, byte: u8) {
+    debug_assert!(!vec.is_full());
+    unsafe { vec.push_unchecked(byte) }
+}
@@ -1,17 +1,26 @@
 #[macro_use]
 extern crate serde_derive;
 
+#[macro_use]
 extern crate bincode;
 extern crate byteorder;
 extern crate serde;
 extern crate serde_bytes;
 
 use std::fmt::Debug;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::borrow::Cow;
 
-use bincode::{config, deserialize, deserialize_from, deserialize_in_place, serialize,
-              serialized_size, ErrorKind, Result};
+use bincode::arrayvec::ArrayVec;
+use bincode::{config, deserialize, serialize_into, serialized_size, Direction, ErrorKind, Result};
+
+type Buffer = ArrayVec<[u8; 1024]>;
+
+fn serialize<T: ?Sized + serde::Serialize>(value: &T) -> Result<Buffer> {
+    let mut buf = Buffer::new();
+    serialize_into(&mut buf, value)?;
+    Ok(buf)
+}
 
 fn the_same<V>(element: V)
 where
@@ -28,15 +37,14 @@ where
     }
 
     {
-        let encoded = config().big_endian().serialize(&element).unwrap();
-        let decoded = config().big_endian().deserialize(&encoded[..]).unwrap();
-        let decoded_reader = config()
+        let mut encoded = Buffer::new();
+        config()
             .big_endian()
-            .deserialize_from(&mut &encoded[..])
+            .serialize_into(&mut encoded, &element)
             .unwrap();
+        let decoded = config().big_endian().deserialize(&encoded[..]).unwrap();
 
         assert_eq!(element, decoded);
-        assert_eq!(element, decoded_reader);
         assert_eq!(size, encoded.len() as u64);
     }
 }
@@ -82,6 +90,93 @@ fn test_numbers_128bit() {
     the_same(i128::min_value());
 }
 
+#[cfg(feature = "i128")]
+#[test]
+fn test_numbers_128bit_layout() {
+    let value = 0x0102030405060708090a0b0c0d0e0f10u128;
+    let little = serialize(&value).unwrap();
+    assert_eq!(&little[..], &[16, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1]);
+
+    let mut big = Buffer::new();
+    config().big_endian().serialize_into(&mut big, &value).unwrap();
+    assert_eq!(&big[..], &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]);
+
+    let mut big = Buffer::new();
+    config().big_endian().serialize_into(&mut big, &-2i128).unwrap();
+    assert_eq!(&big[..15], &[0xFF; 15]);
+    assert_eq!(big[15], 0xFE);
+    assert_eq!(config().big_endian().deserialize::<i128>(&big).unwrap(), -2);
+}
+
+#[cfg(not(feature = "i128"))]
+#[test]
+fn test_numbers_128bit_unsupported() {
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Reading {
+        id: u8,
+        total: i128,
+    }
+
+    let mut buf = Buffer::new();
+    buf.push(0xAA);
+    match serialize_into(&mut buf, &Reading { id: 1, total: -1 }) {
+        Err(ErrorKind::U128Unsupported) => {}
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(&buf[..], &[0xAA]);
+    match serialized_size(&5u128) {
+        Err(ErrorKind::U128Unsupported) => {}
+        other => panic!("{:?}", other),
+    }
+    match deserialize::<Reading>(&[0; 17]) {
+        Err(ErrorKind::U128Unsupported) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[cfg(feature = "i128")]
+#[test]
+fn test_numbers_128bit_endianness() {
+    use bincode::{BincodeRead, SliceReader};
+    use byteorder::{BigEndian, LittleEndian};
+
+    for &big in &[false, true] {
+        let mut config = config();
+        if big {
+            config.big_endian();
+        }
+        for &value in &[0u128, 1, u128::max_value()] {
+            let mut encoded = Buffer::new();
+            config.serialize_into(&mut encoded, &value).unwrap();
+            assert_eq!(config.deserialize::<u128>(&encoded).unwrap(), value);
+            let mut reader = SliceReader::new(&encoded);
+            let read = if big {
+                reader.read_u128::<BigEndian>()
+            } else {
+                reader.read_u128::<LittleEndian>()
+            };
+            assert_eq!(read.unwrap(), value);
+        }
+        for &value in &[0i128, -1, i128::min_value(), i128::max_value()] {
+            let mut encoded = Buffer::new();
+            config.serialize_into(&mut encoded, &value).unwrap();
+            assert_eq!(config.deserialize::<i128>(&encoded).unwrap(), value);
+            let mut reader = SliceReader::new(&encoded);
+            let read = if big {
+                reader.read_i128::<BigEndian>()
+            } else {
+                reader.read_i128::<LittleEndian>()
+            };
+            assert_eq!(read.unwrap(), value);
+        }
+    }
+
+    match deserialize::<u128>(&[0; 15]) {
+        Err(ErrorKind::UnexpectedEof { remaining: 15, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
 #[test]
 fn test_string() {
     the_same("".to_string());
@@ -196,7 +291,7 @@ fn test_vec() {
 
 #[test]
 fn test_map() {
-    let mut m = HashMap::new();
+    let mut m = BTreeMap::new();
     m.insert(4u64, "foo".to_string());
     m.insert(0u64, "bar".to_string());
     the_same(m);
@@ -223,11 +318,11 @@ fn test_fixed_size_array() {
 
 #[test]
 fn deserializing_errors() {
-    match *deserialize::<bool>(&vec![0xA][..]).unwrap_err() {
+    match deserialize::<bool>(&vec![0xA][..]).unwrap_err() {
         ErrorKind::InvalidBoolEncoding(0xA) => {}
         _ => panic!(),
     }
-    match *deserialize::<String>(&vec![1, 0, 0, 0, 0, 0, 0, 0, 0xFF][..]).unwrap_err() {
+    match deserialize::<String>(&vec![1, 0, 0, 0, 0, 0, 0, 0, 0xFF][..]).unwrap_err() {
         ErrorKind::InvalidUtf8Encoding(_) => {}
         _ => panic!(),
     }
@@ -239,18 +334,19 @@ fn deserializing_errors() {
         Two,
     };
 
-    match *deserialize::<Test>(&vec![0, 0, 0, 5][..]).unwrap_err() {
+    match deserialize::<Test>(&vec![0, 0, 0, 5][..]).unwrap_err() {
         // Error message comes from serde
-        ErrorKind::Custom(_) => {}
+        ErrorKind::Serde => {}
         _ => panic!(),
     }
-    match *deserialize::<Option<u8>>(&vec![5, 0][..]).unwrap_err() {
+    match deserialize::<Option<u8>>(&vec![5, 0][..]).unwrap_err() {
         ErrorKind::InvalidTagEncoding(_) => {}
         _ => panic!(),
     }
 }
 
 #[test]
+#[cfg(feature = "std")]
 fn too_big_deserialize() {
     let serialized = vec![0, 0, 0, 3];
     let deserialized: Result<u32> = config().limit(3).deserialize_from(&mut &serialized[..]);
@@ -265,9 +361,10 @@ fn too_big_deserialize() {
 fn char_serialization() {
     let chars = "Aa\0☺♪";
     for c in chars.chars() {
-        let encoded = config()
+        let mut encoded = Buffer::new();
+        config()
             .limit(4)
-            .serialize(&c)
+            .serialize_into(&mut encoded, &c)
             .expect("serializing char failed");
         let decoded: char = deserialize(&encoded).expect("deserializing failed");
         assert_eq!(decoded, c);
@@ -277,18 +374,19 @@ fn char_serialization() {
 #[test]
 fn too_big_char_deserialize() {
     let serialized = vec![0x41];
-    let deserialized: Result<char> = config().limit(1).deserialize_from(&mut &serialized[..]);
+    let deserialized: Result<char> = config().limit(1).deserialize(&serialized[..]);
     assert!(deserialized.is_ok());
     assert_eq!(deserialized.unwrap(), 'A');
 }
 
 #[test]
 fn too_big_serialize() {
-    assert!(config().limit(3).serialize(&0u32).is_err());
-    assert!(config().limit(4).serialize(&0u32).is_ok());
+    let mut buf = Buffer::new();
+    assert!(config().limit(3).serialize_into(&mut buf, &0u32).is_err());
+    assert!(config().limit(4).serialize_into(&mut buf, &0u32).is_ok());
 
-    assert!(config().limit(8 + 4).serialize(&"abcde").is_err());
-    assert!(config().limit(8 + 5).serialize(&"abcde").is_ok());
+    assert!(config().limit(8 + 4).serialize_into(&mut buf, &"abcde").is_err());
+    assert!(config().limit(8 + 5).serialize_into(&mut buf, &"abcde").is_ok());
 }
 
 #[test]
@@ -343,20 +441,20 @@ fn encode_box() {
 #[test]
 fn test_cow_serialize() {
     let large_object = vec![1u32, 2, 3, 4, 5, 6];
-    let mut large_map = HashMap::new();
+    let mut large_map = BTreeMap::new();
     large_map.insert(1, 2);
 
 
     #[derive(Serialize, Deserialize, Debug)]
     enum Message<'a> {
         M1(Cow<'a, Vec<u32>>),
-        M2(Cow<'a, HashMap<u32, u32>>),
+        M2(Cow<'a, BTreeMap<u32, u32>>),
     }
 
     // Test 1
     {
         let serialized = serialize(&Message::M1(Cow::Borrowed(&large_object))).unwrap();
-        let deserialized: Message<'static> = deserialize_from(&mut &serialized[..]).unwrap();
+        let deserialized: Message<'static> = deserialize(&serialized[..]).unwrap();
 
         match deserialized {
             Message::M1(b) => assert!(&b.into_owned() == &large_object),
@@ -367,7 +465,7 @@ fn test_cow_serialize() {
     // Test 2
     {
         let serialized = serialize(&Message::M2(Cow::Borrowed(&large_map))).unwrap();
-        let deserialized: Message<'static> = deserialize_from(&mut &serialized[..]).unwrap();
+        let deserialized: Message<'static> = deserialize(&serialized[..]).unwrap();
 
         match deserialized {
             Message::M2(b) => assert!(&b.into_owned() == &large_map),
@@ -380,7 +478,7 @@ fn test_cow_serialize() {
 fn test_strbox_serialize() {
     let strx: &'static str = "hello world";
     let serialized = serialize(&Cow::Borrowed(strx)).unwrap();
-    let deserialized: Cow<'static, String> = deserialize_from(&mut &serialized[..]).unwrap();
+    let deserialized: Cow<'static, String> = deserialize(&serialized[..]).unwrap();
     let stringx: String = deserialized.into_owned();
     assert!(strx == &stringx[..]);
 }
@@ -390,7 +488,7 @@ fn test_slicebox_serialize() {
     let slice = [1u32, 2, 3, 4, 5];
     let serialized = serialize(&Cow::Borrowed(&slice[..])).unwrap();
     println!("{:?}", serialized);
-    let deserialized: Cow<'static, Vec<u32>> = deserialize_from(&mut &serialized[..]).unwrap();
+    let deserialized: Cow<'static, Vec<u32>> = deserialize(&serialized[..]).unwrap();
     {
         let sb: &[u32] = &deserialized;
         assert!(slice == sb);
@@ -405,14 +503,17 @@ fn test_multi_strings_serialize() {
 }
 
 #[test]
+#[cfg(feature = "std")]
 fn test_oom_protection() {
+    use bincode::deserialize_from;
+    use serde_bytes::ByteBuf;
     use std::io::Cursor;
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
     struct FakeVec {
         len: u64,
         byte: u8,
     }
-    let x = config()
+    let x: Buffer = config()
         .limit(10)
         .serialize(&FakeVec {
             len: 0xffffffffffffffffu64,
@@ -423,9 +524,25 @@ fn test_oom_protection() {
         .limit(10)
         .deserialize_from(&mut Cursor::new(&x[..]));
     assert!(y.is_err());
+
+    // Without a limit, the buffer only grows as bytes arrive.
+    let x = serialize(&FakeVec { len: 1 << 60, byte: 1 }).unwrap();
+    match deserialize_from::<_, ByteBuf>(&mut Cursor::new(&x[..])) {
+        Err(ErrorKind::UnexpectedEof { remaining: 1, needed: Some(n), .. }) => assert_eq!(n, (1 << 60) - 1),
+        other => panic!("unexpected {:?}", other),
+    }
+    match deserialize_from::<_, String>(&mut Cursor::new(&x[..])) {
+        Err(ErrorKind::UnexpectedEof { .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    match deserialize_from::<_, u32>(&mut &[1u8, 2][..]) {
+        Err(ErrorKind::UnexpectedEof { remaining: 2, needed: Some(2), .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
 }
 
 #[test]
+#[cfg(feature = "std")]
 fn path_buf() {
     use std::path::{Path, PathBuf};
     let path = Path::new("foo").to_path_buf();
@@ -455,7 +572,8 @@ fn serde_bytes() {
 fn endian_difference() {
     let x = 10u64;
     let little = serialize(&x).unwrap();
-    let big = config().big_endian().serialize(&x).unwrap();
+    let mut big = Buffer::new();
+    config().big_endian().serialize_into(&mut big, &x).unwrap();
     assert_ne!(little, big);
 }
 
@@ -479,9 +597,9 @@ fn test_zero_copy_parse() {
 }
 
 #[test]
+#[cfg(feature = "std")]
 fn test_zero_copy_parse_deserialize_into() {
-    use bincode::BincodeRead;
-    use std::io;
+    use bincode::{BincodeRead, DefaultOptions, Deserializer};
 
     /// A BincodeRead implementation for byte slices
     pub struct SliceReader<'storage> {
@@ -490,21 +608,17 @@ fn test_zero_copy_parse_deserialize_into() {
 
     impl<'storage> SliceReader<'storage> {
         #[inline(always)]
-        fn unexpected_eof() -> Box<::ErrorKind> {
-            return Box::new(::ErrorKind::Io(
-                io::Error::new(io::ErrorKind::UnexpectedEof, ""),
-            ));
-        }
-    }
-
-    impl<'storage> io::Read for SliceReader<'storage> {
-        #[inline(always)]
-        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
-            (&mut self.slice).read(out)
-        }
-        #[inline(always)]
-        fn read_exact(&mut self, out: &mut [u8]) -> io::Result<()> {
-            (&mut self.slice).read_exact(out)
+        fn take(&mut self, length: usize) -> Result<&'storage [u8]> {
+            if length > self.slice.len() {
+                return Err(ErrorKind::UnexpectedEof {
+                    remaining: self.slice.len(),
+                    budget: None,
+                    needed: Some(length - self.slice.len()),
+                });
+            }
+            let (taken, rest) = self.slice.split_at(length);
+            self.slice = rest;
+            Ok(taken)
         }
     }
 
@@ -514,29 +628,16 @@ fn test_zero_copy_parse_deserialize_into() {
         where
             V: serde::de::Visitor<'storage>,
         {
-            use ErrorKind;
-            if length > self.slice.len() {
-                return Err(SliceReader::unexpected_eof());
-            }
-
-            let string = match ::std::str::from_utf8(&self.slice[..length]) {
+            let string = match ::std::str::from_utf8(self.take(length)?) {
                 Ok(s) => s,
-                Err(e) => return Err(ErrorKind::InvalidUtf8Encoding(e).into()),
+                Err(e) => return Err(ErrorKind::InvalidUtf8Encoding(e)),
             };
-            let r = visitor.visit_borrowed_str(string);
-            self.slice = &self.slice[length..];
-            r
+            visitor.visit_borrowed_str(string)
         }
 
         #[inline(always)]
         fn get_byte_buffer(&mut self, length: usize) -> Result<Vec<u8>> {
-            if length > self.slice.len() {
-                return Err(SliceReader::unexpected_eof());
-            }
-
-            let r = &self.slice[..length];
-            self.slice = &self.slice[length..];
-            Ok(r.to_vec())
+            Ok(self.take(length)?.to_vec())
         }
 
         #[inline(always)]
@@ -544,13 +645,7 @@ fn test_zero_copy_parse_deserialize_into() {
         where
             V: serde::de::Visitor<'storage>,
         {
-            if length > self.slice.len() {
-                return Err(SliceReader::unexpected_eof());
-            }
-
-            let r = visitor.visit_borrowed_bytes(&self.slice[..length]);
-            self.slice = &self.slice[length..];
-            r
+            visitor.visit_borrowed_bytes(self.take(length)?)
         }
     }
 
@@ -571,12 +666,9 @@ fn test_zero_copy_parse_deserialize_into() {
             borrowed_str: "hello",
             borrowed_bytes: &[10, 11, 12, 13],
         };
-        deserialize_in_place(
-            SliceReader {
-                slice: &encoded[..],
-            },
-            &mut target,
-        ).unwrap();
+        let reader = SliceReader { slice: &encoded[..] };
+        let mut deserializer = Deserializer::new(reader, DefaultOptions::new());
+        serde::Deserialize::deserialize_in_place(&mut deserializer, &mut target).unwrap();
         assert_eq!(target, f);
     }
 }
@@ -592,3 +684,5604 @@ fn not_human_readable() {
         serialized_size(&ip).unwrap()
     );
 }
+
+#[test]
+fn flavor_slice() {
+    use bincode::flavors::Slice;
+    use bincode::serialize_with_flavor;
+
+    let mut buf = [0u8; 16];
+    let len = serialize_with_flavor(Slice::new(&mut buf), &(1u16, "ab")).unwrap();
+    assert_eq!(&buf[..len], &serialize(&(1u16, "ab")).unwrap()[..]);
+
+    let mut buf = [0u8; 3];
+    match serialize_with_flavor(Slice::new(&mut buf), &0u32).unwrap_err() {
+        ErrorKind::CapacityError(_) => {}
+        e => panic!("{:?}", e),
+    }
+}
+
+#[test]
+fn flavor_crc32() {
+    use bincode::flavors::{Crc32, Slice};
+    use bincode::{deserialize_with_flavor, serialize_with_flavor};
+
+    // The standard CRC-32 check value.
+    let mut buf = [0u8; 16];
+    let len = serialize_with_flavor(Crc32::new(Slice::new(&mut buf)), b"123456789").unwrap();
+    assert_eq!(&buf[..len], b"123456789\x26\x39\xF4\xCB");
+
+    let decoded: [u8; 9] = deserialize_with_flavor(Crc32::new(Slice::new(&mut buf[..len]))).unwrap();
+    assert_eq!(&decoded, b"123456789");
+
+    buf[3] ^= 0x10;
+    match deserialize_with_flavor::<_, [u8; 9]>(Crc32::new(Slice::new(&mut buf[..len])))
+        .unwrap_err()
+    {
+        ErrorKind::ChecksumMismatch { expected, .. } => assert_eq!(expected, 0xCBF4_3926),
+        e => panic!("{:?}", e),
+    }
+}
+
+#[test]
+fn flavor_cobs() {
+    use bincode::flavors::{Cobs, Slice};
+    use bincode::{deserialize_with_flavor, serialize_with_flavor};
+    use serde_bytes::Bytes;
+
+    let mut buf = [0u8; 16];
+    let len = serialize_with_flavor(Cobs::new(Slice::new(&mut buf)), &(1u32, 0u8)).unwrap();
+    assert_eq!(&buf[..len], &[2, 1, 1, 1, 1, 1, 0]);
+
+    let decoded: (u32, u8) = deserialize_with_flavor(Cobs::new(Slice::new(&mut buf[..len]))).unwrap();
+    assert_eq!(decoded, (1, 0));
+
+    // Runs longer than 254 bytes are split without an implied zero.
+    let data: Vec<u8> = (0..600u32).map(|i| (i % 255) as u8 + 1).collect();
+    let mut buf = [0u8; 700];
+    let len = serialize_with_flavor(Cobs::new(Slice::new(&mut buf)), Bytes::new(&data)).unwrap();
+    assert_eq!(buf[..len].iter().position(|&b| b == 0), Some(len - 1));
+
+    let decoded: &[u8] = deserialize_with_flavor(Cobs::new(Slice::new(&mut buf[..len]))).unwrap();
+    assert_eq!(decoded, &data[..]);
+
+    // A code byte that runs past the end of the frame.
+    let mut buf = [5u8, 1, 0];
+    match deserialize_with_flavor::<_, u8>(Cobs::new(Slice::new(&mut buf))).unwrap_err() {
+        ErrorKind::InvalidCobsEncoding => {}
+        e => panic!("{:?}", e),
+    }
+}
+
+#[test]
+fn flavor_composition() {
+    use bincode::flavors::{Cobs, Crc32, Slice};
+    use bincode::{deserialize_with_flavor, serialize_with_flavor};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Frame<'a> {
+        id: u32,
+        name: &'a str,
+        flags: [u8; 4],
+    }
+
+    let frame = Frame {
+        id: 0x100,
+        name: "sensor",
+        flags: [0, 1, 0, 2],
+    };
+
+    let mut buf = [0u8; 64];
+    let len = serialize_with_flavor(Crc32::new(Cobs::new(Slice::new(&mut buf))), &frame).unwrap();
+    assert_eq!(buf[..len].iter().position(|&b| b == 0), Some(len - 1));
+
+    let mut copy = buf;
+    let decoded: Frame = deserialize_with_flavor(Crc32::new(Cobs::new(Slice::new(&mut copy[..len]))))
+        .unwrap();
+    assert_eq!(decoded, frame);
+
+    let mut copy = buf;
+    copy[len - 3] ^= 0x01;
+    match deserialize_with_flavor::<_, Frame>(Crc32::new(Cobs::new(Slice::new(&mut copy[..len]))))
+        .unwrap_err()
+    {
+        ErrorKind::ChecksumMismatch { .. } | ErrorKind::InvalidCobsEncoding => {}
+        e => panic!("{:?}", e),
+    }
+}
+
+#[test]
+fn length_encoding() {
+    use bincode::LengthEncoding;
+
+    let mut buf = Buffer::new();
+    config()
+        .length_encoding(LengthEncoding::U16)
+        .serialize_into(&mut buf, &("ab", vec![1u8]))
+        .unwrap();
+    assert_eq!(&buf[..], &[2, 0, b'a', b'b', 1, 0, 1]);
+
+    let mut buf = Buffer::new();
+    config()
+        .length_encoding(LengthEncoding::U32)
+        .big_endian()
+        .serialize_into(&mut buf, &("ab", vec![1u8]))
+        .unwrap();
+    assert_eq!(&buf[..], &[0, 0, 0, 2, b'a', b'b', 0, 0, 0, 1, 1]);
+
+    for &encoding in &[LengthEncoding::U16, LengthEncoding::U32, LengthEncoding::U64, LengthEncoding::Varint] {
+        let mut buf = Buffer::new();
+        let mut map = BTreeMap::new();
+        map.insert("key".to_string(), vec![1u32, 2]);
+        let value = (map, "text", serde_bytes::Bytes::new(b"bytes"));
+        config()
+            .length_encoding(encoding)
+            .serialize_into(&mut buf, &value)
+            .unwrap();
+        assert_eq!(
+            config()
+                .length_encoding(encoding)
+                .serialized_size(&value)
+                .unwrap(),
+            buf.len() as u64
+        );
+        let decoded: (BTreeMap<String, Vec<u32>>, &str, &serde_bytes::Bytes) = config()
+            .length_encoding(encoding)
+            .deserialize(&buf)
+            .unwrap();
+        assert_eq!(decoded, value);
+    }
+}
+
+#[test]
+fn migration_first_match() {
+    use bincode::migration::deserialize_first_match;
+    use bincode::LengthEncoding;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Device<'a> {
+        id: u32,
+        name: &'a str,
+    }
+
+    let current = config();
+    let mut legacy = config();
+    legacy.length_encoding(LengthEncoding::U32);
+    let configs = [current, legacy];
+
+    let current_fixture = [7, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, b'f', b'o', b'o'];
+    let legacy_fixture = [7, 0, 0, 0, 3, 0, 0, 0, b'f', b'o', b'o'];
+    let expected = Device { id: 7, name: "foo" };
+
+    let (index, device) = deserialize_first_match::<Device>(&current_fixture, &configs).unwrap();
+    assert_eq!((index, device), (0, Device { id: 7, name: "foo" }));
+
+    let (index, device) = deserialize_first_match::<Device>(&legacy_fixture, &configs).unwrap();
+    assert_eq!((index, device), (1, expected));
+
+    // Without any length prefix both layouts are identical.
+    match deserialize_first_match::<(u32, u32)>(&[1, 0, 0, 0, 2, 0, 0, 0], &configs).unwrap_err() {
+        ErrorKind::AmbiguousEncoding => {}
+        e => panic!("{:?}", e),
+    }
+
+    // Trailing bytes don't count as a match.
+    let mut padded = legacy_fixture.to_vec();
+    padded.push(0);
+    assert!(deserialize_first_match::<Device>(&padded, &configs).is_err());
+
+    // Nothing to try isn't mistaken for an ambiguous input.
+    match deserialize_first_match::<Device>(&current_fixture, &[]).unwrap_err() {
+        ErrorKind::NoCandidates => {}
+        e => panic!("{:?}", e),
+    }
+}
+
+#[test]
+fn str_filter() {
+    use bincode::str_filters::{trim_ascii_whitespace, trim_nul};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Device<'a> {
+        name: &'a str,
+        owner: String,
+    }
+
+    let encoded = serialize(&("sensor\0\0\0", " bob \0")).unwrap();
+
+    let device: Device = config().str_filter(trim_nul).deserialize(&encoded).unwrap();
+    assert_eq!(device.name, "sensor");
+    assert_eq!(device.owner, " bob ");
+
+    // Still zero-copy: the filtered string points into the input.
+    let range = encoded.as_ptr() as usize..encoded.as_ptr() as usize + encoded.len();
+    assert!(range.contains(&(device.name.as_ptr() as usize)));
+    assert!(range.contains(&(device.name.as_ptr() as usize + device.name.len() - 1)));
+
+    let encoded = serialize(&"\t padded \n").unwrap();
+    let name: &str = config()
+        .str_filter(trim_ascii_whitespace)
+        .deserialize(&encoded)
+        .unwrap();
+    assert_eq!(name, "padded");
+
+    // The default leaves strings untouched.
+    let name: &str = deserialize(&encoded).unwrap();
+    assert_eq!(name, "\t padded \n");
+}
+
+#[test]
+fn block_cached_reader() {
+    use bincode::{deserialize_from_custom, BlockCachedReader, SliceReader};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Record<'a> {
+        tag: u8,
+        id: u32,
+        name: &'a str,
+        samples: Vec<i16>,
+        scale: f64,
+        flags: (bool, char, Option<u64>),
+    }
+
+    let record = Record {
+        tag: 7,
+        id: 0xDEAD_BEEF,
+        name: "pressure",
+        samples: vec![-3, 0, 1000, i16::MIN],
+        scale: 0.25,
+        flags: (true, 'ÿ', Some(u64::MAX)),
+    };
+    let encoded = serialize(&record).unwrap();
+
+    let expected: Record = deserialize_from_custom(SliceReader::new(&encoded)).unwrap();
+    assert_eq!(expected, record);
+
+    fn check<'a, const LINE: usize>(encoded: &'a [u8], expected: &Record) {
+        let mut reader = BlockCachedReader::<LINE>::new(encoded);
+        let decoded: Record = deserialize_from_custom(&mut reader).unwrap();
+        assert_eq!(&decoded, expected);
+        assert_eq!(reader.position(), encoded.len());
+
+        // Strings still borrow from the mapping, not from the cache.
+        let range = encoded.as_ptr() as usize..encoded.as_ptr() as usize + encoded.len();
+        assert!(range.contains(&(decoded.name.as_ptr() as usize)));
+    }
+    check::<1>(&encoded, &expected);
+    check::<3>(&encoded, &expected);
+    check::<16>(&encoded, &expected);
+    check::<1024>(&encoded, &expected);
+
+    // A run of primitives costs one fetch per line touched.
+    let encoded = serialize(&[1u32; 8]).unwrap();
+    let mut reader = BlockCachedReader::<16>::new(&encoded);
+    let decoded: [u32; 8] = deserialize_from_custom(&mut reader).unwrap();
+    assert_eq!(decoded, [1; 8]);
+    assert_eq!(reader.line_fetches(), 2);
+
+    // Running off the end of the mapping reports the same error as a slice.
+    let mut reader = BlockCachedReader::<16>::new(&encoded[..30]);
+    let result: Result<[u32; 8]> = deserialize_from_custom(&mut reader);
+    match result {
+        Err(ErrorKind::UnexpectedEof {
+            remaining: 2,
+            budget: None,
+            needed: Some(2),
+        }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn length_overflow() {
+    use bincode::LengthEncoding;
+    use serde::ser::{SerializeMap, SerializeSeq};
+
+    // Claims a length without producing any elements.
+    struct Seq(usize);
+    struct Map(usize);
+
+    impl serde::Serialize for Seq {
+        fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+            s.serialize_seq(Some(self.0))?.end()
+        }
+    }
+
+    impl serde::Serialize for Map {
+        fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+            s.serialize_map(Some(self.0))?.end()
+        }
+    }
+
+    fn check<T: serde::Serialize>(encoding: LengthEncoding, value: &T, overflow: Option<usize>) {
+        let mut buf = Buffer::new();
+        buf.push(0xAA);
+        let result = config()
+            .length_encoding(encoding)
+            .serialize_into(&mut buf, value);
+        let size = config().length_encoding(encoding).serialized_size(value);
+        match overflow {
+            None => {
+                result.unwrap();
+                assert_eq!(size.unwrap(), buf.len() as u64 - 1);
+            }
+            Some(len) => {
+                for result in vec![result.map(|_| 0), size] {
+                    match result {
+                        Err(ErrorKind::LengthOverflow { len: l, max }) => {
+                            assert_eq!(l, len as u64);
+                            assert_eq!(max, encoding.max_len());
+                        }
+                        other => panic!("unexpected {:?}", other),
+                    }
+                }
+                assert_eq!(&buf[..], &[0xAA]);
+            }
+        }
+    }
+
+    let mut widths = vec![LengthEncoding::U16];
+    if cfg!(target_pointer_width = "64") {
+        widths.push(LengthEncoding::U32);
+    }
+    for encoding in widths {
+        let max = encoding.max_len() as usize;
+        check(encoding, &Seq(max), None);
+        check(encoding, &Map(max), None);
+        check(encoding, &Seq(max + 1), Some(max + 1));
+        check(encoding, &Map(max + 1), Some(max + 1));
+    }
+
+    // Strings and bytes go through the same check.
+    let long = "x".repeat(0x1_0000);
+    check(LengthEncoding::U16, &long, Some(0x1_0000));
+    check(LengthEncoding::U16, &serde_bytes::Bytes::new(long.as_bytes()), Some(0x1_0000));
+    check(LengthEncoding::U16, &format_args!("{}", long), Some(0x1_0000));
+}
+
+#[test]
+fn primitives() {
+    use bincode::primitives::*;
+    use bincode::LengthEncoding;
+
+    macro_rules! check {
+        ($value:expr, $config:expr, $read:ident, $write:ident) => {{
+            let mut full = Buffer::new();
+            $config.serialize_into(&mut full, &$value).unwrap();
+
+            let mut framed = Buffer::new();
+            framed.push(0xFF);
+            framed.extend(full.iter().cloned());
+            framed.push(0xFF);
+            assert_eq!($read(&framed[1..], $config).unwrap(), ($value, full.len()));
+
+            let mut written = Buffer::new();
+            assert_eq!($write(&mut &mut written, $value, $config).unwrap(), full.len());
+            assert_eq!(written, full);
+        }};
+    }
+
+    let mut configs = vec![];
+    for &length in &[LengthEncoding::U16, LengthEncoding::U32, LengthEncoding::U64] {
+        let mut little = config();
+        little.little_endian().length_encoding(length);
+        let mut big = config();
+        big.big_endian().length_encoding(length);
+        configs.push(little);
+        configs.push(big);
+    }
+
+    for config in &configs {
+        check!(0xA5u8, config, read_u8, write_u8);
+        check!(0xA55Au16, config, read_u16, write_u16);
+        check!(0xA55A_0FF0u32, config, read_u32, write_u32);
+        check!(0xA55A_0FF0_1234_5678u64, config, read_u64, write_u64);
+        check!(-5i8, config, read_i8, write_i8);
+        check!(-0x1234i16, config, read_i16, write_i16);
+        check!(-0x1234_5678i32, config, read_i32, write_i32);
+        check!(-0x1234_5678_9ABC_DEF0i64, config, read_i64, write_i64);
+        check!(1.5f32, config, read_f32, write_f32);
+        check!(-1.0e300f64, config, read_f64, write_f64);
+
+        // A length prefix agrees with the one in front of a full string.
+        let mut full = Buffer::new();
+        config.serialize_into(&mut full, &"four").unwrap();
+        let (len, consumed) = read_len(&full, config).unwrap();
+        assert_eq!(len, 4);
+        assert_eq!(&full[consumed..], b"four");
+
+        let mut written = Buffer::new();
+        assert_eq!(write_len(&mut &mut written, 4, config).unwrap(), consumed);
+        assert_eq!(&written[..], &full[..consumed]);
+    }
+
+    match read_u32(&[1, 2, 3], &config()) {
+        Err(ErrorKind::UnexpectedEof { remaining: 3, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn option_borrowed() {
+    use bincode::{LengthEncoding, SliceReader};
+
+    fn encode<T: serde::Serialize>(value: &T, encoding: LengthEncoding) -> Buffer {
+        let mut config = config();
+        config.length_encoding(encoding);
+
+        let mut encoded = Buffer::new();
+        config.serialize_into(&mut encoded, value).unwrap();
+        let size = config.serialized_size(value).unwrap();
+        assert_eq!(size, encoded.len() as u64);
+
+        // The budget covers the tag and the payload, exactly.
+        let mut budget = Buffer::new();
+        config.limit(size).serialize_into(&mut budget, value).unwrap();
+        let mut budget = Buffer::new();
+        match config.limit(size - 1).serialize_into(&mut budget, value) {
+            Err(ErrorKind::SizeLimit(Direction::Serialize)) => assert!(budget.is_empty()),
+            other => panic!("unexpected {:?}", other),
+        }
+        encoded
+    }
+
+    fn decode<'a, T>(encoded: &'a [u8], encoding: LengthEncoding, expected: T) -> T
+    where
+        T: serde::Deserialize<'a> + PartialEq + Debug,
+    {
+        let mut config = config();
+        config.length_encoding(encoding);
+        let size = encoded.len() as u64;
+
+        match config.limit(size - 1).deserialize_from_custom::<_, T>(SliceReader::new(encoded)) {
+            Err(ErrorKind::SizeLimit(Direction::Deserialize)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Every truncation fails instead of producing a shorter value.
+        for cut in 0..encoded.len() {
+            assert!(config.no_limit().deserialize::<T>(&encoded[..cut]).is_err());
+        }
+
+        let decoded: T = config.limit(size).deserialize_from_custom(SliceReader::new(encoded)).unwrap();
+        assert_eq!(decoded, expected);
+        decoded
+    }
+
+    for &encoding in &[LengthEncoding::U16, LengthEncoding::U32, LengthEncoding::U64] {
+        let width = encoding.width();
+        let inner = "hello";
+        let encoded = encode(&Some(inner), encoding);
+        assert_eq!(encoded.len() as u64, 1 + encode(&inner, encoding).len() as u64);
+        assert_eq!(encoded.len() as u64, 1 + width + 5);
+        let decoded = decode(&encoded, encoding, Some(inner)).unwrap();
+        let range = encoded.as_ptr() as usize..encoded.as_ptr() as usize + encoded.len();
+        assert!(range.contains(&(decoded.as_ptr() as usize)));
+
+        let inner = &b"\x00\x01\x02"[..];
+        let encoded = encode(&Some(inner), encoding);
+        assert_eq!(encoded.len() as u64, 1 + encode(&inner, encoding).len() as u64);
+        assert_eq!(encoded.len() as u64, 1 + width + 3);
+        let decoded = decode(&encoded, encoding, Some(inner)).unwrap();
+        let range = encoded.as_ptr() as usize..encoded.as_ptr() as usize + encoded.len();
+        assert!(range.contains(&(decoded.as_ptr() as usize)));
+
+        let encoded = encode(&Some(""), encoding);
+        assert_eq!(encoded.len() as u64, 1 + width);
+        decode(&encoded, encoding, Some(""));
+
+        let encoded = encode(&None::<&str>, encoding);
+        assert_eq!(&encoded[..], &[0]);
+        decode(&encoded, encoding, None::<&str>);
+        decode(&encoded, encoding, None::<&[u8]>);
+    }
+}
+
+#[test]
+fn extension_traits() {
+    use bincode::{BincodeDeExt, BincodeExt};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Msg<'a> {
+        id: u32,
+        name: &'a str,
+        readings: Vec<i16>,
+    }
+
+    let msg = Msg {
+        id: 42,
+        name: "probe",
+        readings: vec![-1, 0, 1],
+    };
+
+    let mut buf = Buffer::new();
+    let len = msg.to_bincode_into(&mut buf).unwrap();
+    assert_eq!(len, buf.len());
+    assert_eq!(msg.bincode_size().unwrap(), len as u64);
+    assert_eq!(Msg::from_bincode(&buf).unwrap(), msg);
+
+    // Unsized values work too.
+    let mut buf = Buffer::new();
+    "unsized".to_bincode_into(&mut buf).unwrap();
+    assert_eq!(<&str>::from_bincode(&buf).unwrap(), "unsized");
+}
+
+#[test]
+fn borrowed_map_keys() {
+    use bincode::{BlockCachedReader, SliceReader};
+    use bincode::deserialize_from_custom;
+    use serde::de::{Deserializer, Error, MapAccess, Visitor};
+    use std::fmt;
+
+    // A bounded sorted map of keys borrowed from the input.
+    #[derive(Debug, PartialEq)]
+    struct SortedMap<'a>(ArrayVec<[(&'a str, u32); 4]>);
+
+    impl<'de> serde::Deserialize<'de> for SortedMap<'de> {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+            struct MapVisitor;
+
+            impl<'de> Visitor<'de> for MapVisitor {
+                type Value = SortedMap<'de>;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("a sorted map")
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+                    let mut entries = ArrayVec::<[(&'de str, u32); 4]>::new();
+                    while let Some(key) = map.next_key::<&'de str>()? {
+                        if entries.last().map_or(false, |&(last, _)| last >= key) {
+                            return Err(A::Error::custom("keys out of order"));
+                        }
+                        let value = map.next_value()?;
+                        entries
+                            .try_push((key, value))
+                            .map_err(|_| A::Error::custom("too many entries"))?;
+                    }
+                    Ok(SortedMap(entries))
+                }
+            }
+
+            d.deserialize_map(MapVisitor)
+        }
+    }
+
+    let mut map = BTreeMap::new();
+    map.insert("alpha", 1u32);
+    map.insert("beta", 2);
+    map.insert("gamma", 3);
+    let encoded = serialize(&map).unwrap();
+    let range = encoded.as_ptr() as usize..encoded.as_ptr() as usize + encoded.len();
+
+    let decoded: BTreeMap<&str, u32> = deserialize(&encoded).unwrap();
+    assert_eq!(decoded, map);
+    for key in decoded.keys() {
+        assert!(range.contains(&(key.as_ptr() as usize)));
+    }
+
+    let decoded: SortedMap = deserialize(&encoded).unwrap();
+    assert_eq!(&decoded.0[..], &[("alpha", 1), ("beta", 2), ("gamma", 3)]);
+    for &(key, _) in decoded.0.iter() {
+        assert!(range.contains(&(key.as_ptr() as usize)));
+    }
+
+    // Truncated inside the last value, after its key was read: the reader is
+    // left at the start of that value.
+    let value_start = encoded.len() - 4;
+    let truncated = &encoded[..value_start + 2];
+
+    let mut reader = SliceReader::new(truncated);
+    match deserialize_from_custom::<_, SortedMap>(&mut reader) {
+        Err(ErrorKind::UnexpectedEof { remaining: 2, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(reader.remaining(), truncated.len() - value_start);
+
+    let mut reader = BlockCachedReader::<8>::new(truncated);
+    match deserialize_from_custom::<_, BTreeMap<&str, u32>>(&mut reader) {
+        Err(ErrorKind::UnexpectedEof { remaining: 2, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(reader.position(), value_start);
+
+    // A failing value after a good key reports the value's error.
+    let encoded = serialize(&[("key", 2u8)].iter().cloned().collect::<BTreeMap<_, _>>()).unwrap();
+    match deserialize::<BTreeMap<&str, bool>>(&encoded) {
+        Err(ErrorKind::InvalidBoolEncoding(2)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // A length that overstates the entries fails rather than truncating the map.
+    let mut overstated = serialize(&map).unwrap();
+    overstated[0] = 4;
+    let result = deserialize::<SortedMap>(&overstated);
+    match result {
+        Err(ErrorKind::UnexpectedEof { remaining: 0, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn dyn_reader() {
+    use bincode::{deserialize_dyn, BlockCachedReader, DynReader, SliceReader};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Message<'a> {
+        seq: u32,
+        topic: &'a str,
+        payload: (i64, f32, Option<char>),
+    }
+
+    let message = Message {
+        seq: 9,
+        topic: "telemetry/imu",
+        payload: (-77, 0.5, Some('z')),
+    };
+    let mut encoded = serialize(&message).unwrap();
+    let len = encoded.len();
+    encoded.extend(b"tail".iter().cloned());
+
+    // Application code that must not be generic over the reader.
+    fn decode<'a>(reader: &mut DynReader<'a>) -> (Message<'a>, usize) {
+        let message = deserialize_dyn(reader).unwrap();
+        (message, reader.remaining())
+    }
+
+    let mut slice = DynReader::Slice(SliceReader::new(&encoded));
+    let mut cached = DynReader::BlockCached(BlockCachedReader::new(&encoded));
+    let (from_slice, slice_remaining) = decode(&mut slice);
+    let (from_cached, cached_remaining) = decode(&mut cached);
+
+    assert_eq!(from_slice, message);
+    assert_eq!(from_cached, message);
+    assert_eq!(encoded.len() - slice_remaining, len);
+    assert_eq!(encoded.len() - cached_remaining, len);
+}
+
+#[test]
+fn zero_sized_matrix() {
+    use std::marker::PhantomData;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+    struct Unit;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+    struct EmptyTuple();
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+    struct EmptyStruct {}
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+    enum Empty {
+        Unit,
+        Tuple(),
+        Struct {},
+        Newtype(Unit),
+        Nested(EmptyTuple, EmptyStruct),
+    }
+
+    fn pinned<V>(value: V, size: usize)
+    where
+        V: serde::Serialize + serde::de::DeserializeOwned + PartialEq + Debug + 'static,
+    {
+        let encoded = serialize(&value).unwrap();
+        assert_eq!(encoded.len(), size, "{:?}", value);
+        assert_eq!(serialized_size(&value).unwrap(), size as u64, "{:?}", value);
+        the_same(value);
+    }
+
+    // Alone.
+    pinned((), 0);
+    pinned(Unit, 0);
+    pinned(EmptyTuple(), 0);
+    pinned(EmptyStruct {}, 0);
+    pinned(PhantomData::<u64>, 0);
+    pinned([Unit; 4], 0);
+    pinned((Unit, EmptyTuple(), EmptyStruct {}, PhantomData::<u8>), 0);
+
+    // Enum variants are their u32 index and nothing else.
+    pinned(Empty::Unit, 4);
+    pinned(Empty::Tuple(), 4);
+    pinned(Empty::Struct {}, 4);
+    pinned(Empty::Newtype(Unit), 4);
+    pinned(Empty::Nested(EmptyTuple(), EmptyStruct {}), 4);
+
+    // Nested in options, sequences and enums.
+    pinned(Some(Unit), 1);
+    pinned(None::<Unit>, 1);
+    pinned(Some(EmptyStruct {}), 1);
+    pinned(Some(Empty::Struct {}), 5);
+    pinned(vec![Unit; 3], 8);
+    pinned(vec![EmptyTuple(); 0], 8);
+    pinned(vec![Empty::Tuple(), Empty::Struct {}], 8 + 4 + 4);
+    pinned(vec![Some(Unit), None], 8 + 1 + 1);
+    pinned(Some(vec![PhantomData::<u32>; 2]), 1 + 8);
+    pinned((1u8, Unit, Empty::Unit, EmptyStruct {}, 2u8), 1 + 4 + 1);
+
+    // Zero-sized values decode from nothing.
+    assert_eq!(deserialize::<EmptyStruct>(&[]).unwrap(), EmptyStruct {});
+    assert_eq!(deserialize::<[Unit; 4]>(&[]).unwrap(), [Unit; 4]);
+}
+
+#[cfg(feature = "stats")]
+#[test]
+fn stats() {
+    use bincode::stats::{Entry, Stats};
+
+    let mut stats = Stats::<2>::new();
+    let mut journal = Buffer::new();
+    for i in 0..3u32 {
+        stats.serialize_recorded(7, &mut &mut journal, &(i, "reading")).unwrap();
+    }
+    stats.serialize_recorded(9, &mut &mut journal, &Some(1.5f64)).unwrap();
+    assert_eq!(
+        stats.entries(),
+        &[
+            Entry {
+                type_id: 7,
+                count: 3,
+                bytes: 3 * (4 + 8 + 7),
+            },
+            Entry {
+                type_id: 9,
+                count: 1,
+                bytes: 9,
+            },
+        ]
+    );
+    assert_eq!(
+        stats.entries().iter().map(|e| e.bytes).sum::<u64>(),
+        journal.len() as u64
+    );
+
+    // A full table drops new ids but keeps counting known ones.
+    assert!(!stats.record(11, 100));
+    assert!(stats.record(9, 1));
+    assert_eq!(stats.dropped(), 1);
+    assert_eq!(stats.get(11), None);
+    assert_eq!(stats.get(9).unwrap().count, 2);
+
+    // Counters saturate.
+    stats.record(9, usize::MAX);
+    stats.record(9, usize::MAX);
+    assert_eq!(stats.get(9).unwrap().bytes, u64::MAX);
+
+    // The snapshot round-trips and holds only the entries in use.
+    let snapshot = serialize(&stats).unwrap();
+    assert_eq!(snapshot.len(), 4 + 8 + 2 * (4 + 4 + 8));
+    let uploaded: Stats<2> = deserialize(&snapshot).unwrap();
+    assert_eq!(uploaded, stats);
+    match deserialize::<Stats<1>>(&snapshot) {
+        Err(ErrorKind::Serde) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    stats.reset();
+    assert_eq!(stats, Stats::new());
+    assert_eq!(serialize(&stats).unwrap().len(), 4 + 8);
+}
+
+#[cfg(feature = "profile")]
+#[test]
+fn profile() {
+    use bincode::profile::{Entry, Profile};
+    use bincode::{DefaultOptions, LengthEncoding, OptionsExt};
+
+    #[derive(Serialize)]
+    struct Point(i16, i16);
+
+    #[derive(Serialize)]
+    enum Shape {
+        Dot,
+        Circle(u8),
+        Line(Point, Point),
+        Poly { points: Vec<Point>, closed: bool },
+    }
+
+    #[derive(Serialize)]
+    struct Layer<'a> {
+        name: &'a str,
+        shapes: Vec<Shape>,
+    }
+
+    let layer = Layer {
+        name: "base",
+        shapes: vec![
+            Shape::Dot,
+            Shape::Circle(3),
+            Shape::Line(Point(0, 0), Point(1, 1)),
+            Shape::Poly { points: vec![Point(0, 0)], closed: true },
+        ],
+    };
+
+    let mut profile = Profile::<8>::new();
+    let size = profile.measure(&layer).unwrap();
+    assert_eq!(size, serialized_size(&layer).unwrap());
+    assert_eq!(profile.total(), size);
+    assert_eq!(
+        profile.entries(),
+        &[
+            Entry { container: "Layer", name: "name", count: 1, bytes: 8 + 4 },
+            Entry { container: "Shape", name: "Dot", count: 1, bytes: 4 },
+            Entry { container: "Shape", name: "Circle", count: 1, bytes: 4 + 1 },
+            Entry { container: "Shape", name: "Line", count: 1, bytes: 4 + 8 },
+            Entry { container: "Poly", name: "points", count: 1, bytes: 8 + 4 },
+            Entry { container: "Poly", name: "closed", count: 1, bytes: 1 },
+            Entry { container: "Shape", name: "Poly", count: 1, bytes: 4 + 12 + 1 },
+            Entry { container: "Layer", name: "shapes", count: 1, bytes: 8 + 4 + 5 + 12 + 17 },
+        ][..]
+    );
+
+    // Measuring again adds up, and a full table drops what doesn't fit.
+    let mut profile = Profile::<1>::new();
+    profile.measure(&Shape::Circle(1)).unwrap();
+    profile.measure(&Shape::Circle(2)).unwrap();
+    profile.measure(&Shape::Dot).unwrap();
+    assert_eq!(profile.entries(), &[Entry { container: "Shape", name: "Circle", count: 2, bytes: 10 }][..]);
+    assert_eq!(profile.total(), 14);
+    assert_eq!(profile.dropped(), 1);
+    profile.reset();
+    assert_eq!(profile, Profile::new());
+
+    // Options other than the limit apply; the limit doesn't.
+    let mut profile = Profile::<8>::new();
+    let options = DefaultOptions::from_config(config().length_encoding(LengthEncoding::U16)).with_limit(1);
+    assert_eq!(profile.measure_with_options(&layer, options).unwrap(), size - 3 * 6);
+    assert_eq!(profile.get("Layer", "name").unwrap().bytes, 2 + 4);
+
+    // Any options can carry a profile as their limit.
+    let mut options = DefaultOptions::new().with_size_limit(Profile::<8>::new());
+    assert_eq!(bincode::serialized_size_with_options(&layer, &mut options).unwrap(), size);
+}
+
+#[test]
+fn str_length_boundaries() {
+    use bincode::{deserialize_from_custom, BincodeRead, BlockCachedReader, DynReader, SliceReader};
+
+    // (empty, rest) where `rest` ends exactly at the end of the input.
+    let value = ("", "to the end");
+    let encoded = serialize(&value).unwrap();
+    let empty_only = serialize(&"").unwrap();
+
+    let mut slice = SliceReader::new(&encoded);
+    assert_eq!(deserialize_from_custom::<_, (&str, &str)>(&mut slice).unwrap(), value);
+    assert!(slice.remaining_slice().is_empty());
+    let mut slice = SliceReader::new(&empty_only);
+    assert_eq!(deserialize_from_custom::<_, &str>(&mut slice).unwrap(), "");
+    assert!(slice.remaining_slice().is_empty());
+
+    let mut cached = BlockCachedReader::<4>::new(&encoded);
+    assert_eq!(deserialize_from_custom::<_, (&str, &str)>(&mut cached).unwrap(), value);
+    assert_eq!(cached.remaining(), 0);
+    let mut cached = BlockCachedReader::<4>::new(&empty_only);
+    assert_eq!(deserialize_from_custom::<_, &str>(&mut cached).unwrap(), "");
+    assert_eq!(cached.remaining(), 0);
+
+    for mut reader in vec![
+        DynReader::Slice(SliceReader::new(&encoded)),
+        DynReader::BlockCached(BlockCachedReader::new(&encoded)),
+    ] {
+        assert_eq!(deserialize_from_custom::<_, (&str, &str)>(&mut reader).unwrap(), value);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    // Zero-length reads at the very end succeed without touching the source.
+    let mut cached = BlockCachedReader::<4>::new(&empty_only);
+    cached.read_exact(&mut [0; 8]).unwrap();
+    let fetches = cached.line_fetches();
+    cached.read_exact(&mut []).unwrap();
+    assert!(deserialize_from_custom::<_, &[u8]>(&mut cached).is_err());
+    assert_eq!(cached.line_fetches(), fetches);
+
+    let mut cached = BlockCachedReader::<4>::new(&[]);
+    cached.read_exact(&mut []).unwrap();
+    assert_eq!(cached.line_fetches(), 0);
+
+    // One byte short of the declared length is still an error.
+    let mut slice = SliceReader::new(&encoded[..encoded.len() - 1]);
+    assert!(deserialize_from_custom::<_, (&str, &str)>(&mut slice).is_err());
+    let mut cached = BlockCachedReader::<4>::new(&encoded[..encoded.len() - 1]);
+    assert!(deserialize_from_custom::<_, (&str, &str)>(&mut cached).is_err());
+}
+
+#[cfg(feature = "arrays")]
+#[test]
+fn array_adapters() {
+    use bincode::arrayvec::ArrayString;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Frame {
+        #[serde(with = "bincode::arrays::bytes")]
+        key: ArrayVec<[u8; 8]>,
+        #[serde(with = "bincode::arrays::string")]
+        name: ArrayString<[u8; 4]>,
+    }
+
+    let frame = Frame {
+        key: [1u8, 2, 3].iter().cloned().collect(),
+        name: ArrayString::from("abc").unwrap(),
+    };
+    let encoded = serialize(&frame).unwrap();
+
+    // Identical to the plain slice and str encodings.
+    let golden = serialize(&(&b"\x01\x02\x03"[..], "abc")).unwrap();
+    assert_eq!(encoded, golden);
+    assert_eq!(serialized_size(&frame).unwrap(), golden.len() as u64);
+    assert_eq!(deserialize::<Frame>(&encoded).unwrap(), frame);
+
+    // Empty and exactly full.
+    the_same_frame(Frame {
+        key: ArrayVec::new(),
+        name: ArrayString::new(),
+    });
+    the_same_frame(Frame {
+        key: [9u8; 8].iter().cloned().collect(),
+        name: ArrayString::from("full").unwrap(),
+    });
+
+    fn the_same_frame(frame: Frame) {
+        let encoded = serialize(&frame).unwrap();
+        assert_eq!(deserialize::<Frame>(&encoded).unwrap(), frame);
+    }
+
+    // Over capacity fails rather than truncating.
+    let too_many_bytes = serialize(&(&[0u8; 9][..], "abc")).unwrap();
+    match deserialize::<Frame>(&too_many_bytes) {
+        Err(ErrorKind::Serde) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    let too_long = serialize(&(&[0u8; 1][..], "abcde")).unwrap();
+    match deserialize::<Frame>(&too_long) {
+        Err(ErrorKind::Serde) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn eof_and_budget() {
+    use bincode::SliceReader;
+
+    let encoded = serialize(&(1u32, "0123456789", 2u64)).unwrap();
+    assert_eq!(encoded.len(), 4 + 8 + 10 + 8);
+
+    // Budget smaller than the buffer: the budget trips first.
+    let result = config()
+        .limit(10)
+        .deserialize_from_custom::<_, (u32, &str, u64)>(SliceReader::new(&encoded));
+    match result {
+        Err(ErrorKind::SizeLimit(Direction::Deserialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Buffer smaller than the budget: truncation is reported, with both
+    // counts, and the budget isn't charged for the missing bytes.
+    let truncated = &encoded[..16];
+    let result = config()
+        .limit(1024)
+        .deserialize_from_custom::<_, (u32, &str, u64)>(SliceReader::new(truncated));
+    match result {
+        Err(ErrorKind::UnexpectedEof {
+            remaining: 4,
+            budget: Some(1012),
+            needed: Some(6),
+        }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Both too short for the string: the truncation wins.
+    let result = config()
+        .limit(14)
+        .deserialize_from_custom::<_, (u32, &str, u64)>(SliceReader::new(truncated));
+    match result {
+        Err(ErrorKind::UnexpectedEof {
+            remaining: 4,
+            budget: Some(2),
+            needed: Some(6),
+        }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Without a limit there is no budget to report.
+    match deserialize::<(u32, &str, u64)>(truncated) {
+        Err(ErrorKind::UnexpectedEof {
+            remaining: 4,
+            budget: None,
+            needed: Some(6),
+        }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn packed_bools() {
+    use bincode::packed::PackedBools;
+
+    type Bits = ArrayVec<[bool; 64]>;
+
+    for &len in &[0usize, 1, 7, 8, 9, 64] {
+        let values: Vec<bool> = (0..len).map(|i| i % 3 == 0 || i == len - 1).collect();
+        let packed = PackedBools(values.clone());
+        let encoded = serialize(&packed).unwrap();
+        assert_eq!(encoded.len(), 8 + len.div_ceil(8));
+        assert_eq!(serialized_size(&packed).unwrap(), encoded.len() as u64);
+
+        let decoded: PackedBools<Bits> = deserialize(&encoded).unwrap();
+        assert_eq!(&decoded.0[..], &values[..]);
+        #[cfg(feature = "alloc")]
+        {
+            let decoded: PackedBools<Vec<bool>> = deserialize(&encoded).unwrap();
+            assert_eq!(decoded.0, values);
+        }
+
+        // The length prefix is an explicit count, whatever the configured
+        // length encoding.
+        let mut narrow = Buffer::new();
+        config()
+            .length_encoding(bincode::LengthEncoding::U16)
+            .serialize_into(&mut narrow, &packed)
+            .unwrap();
+        assert_eq!(&narrow[..], &encoded[..]);
+    }
+
+    let encoded = serialize(&PackedBools(vec![
+        true, true, false, false, false, false, false, true, // 0x83
+        false, true, // 0x02
+    ]))
+    .unwrap();
+    assert_eq!(&encoded[..], &[10, 0, 0, 0, 0, 0, 0, 0, 0x83, 0x02]);
+
+    // Bits past the length must be clear.
+    let bad = [3, 0, 0, 0, 0, 0, 0, 0, 0b1000_0101];
+    assert!(deserialize::<PackedBools<Bits>>(&bad).is_err());
+
+    // Truncated packed bytes are an error, not a shorter sequence.
+    assert!(deserialize::<PackedBools<Bits>>(&encoded[..9]).is_err());
+
+    // More bools than fixed-capacity storage holds is an error, not a panic.
+    assert!(deserialize::<PackedBools<ArrayVec<[bool; 9]>>>(&encoded).is_err());
+    let mut huge = [0xFF; 16];
+    huge[7] = 0;
+    assert!(deserialize::<PackedBools<Bits>>(&huge).is_err());
+}
+
+#[test]
+fn nul_str() {
+    use bincode::nul_str::NulStr;
+    use bincode::{deserialize_from_custom, BlockCachedReader, SliceReader};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Legacy<'a> {
+        id: u16,
+        #[serde(borrow)]
+        name: NulStr<'a, 8>,
+        #[serde(borrow)]
+        unit: NulStr<'a>,
+        value: i32,
+    }
+
+    let legacy = Legacy {
+        id: 3,
+        name: NulStr("temp"),
+        unit: NulStr(""),
+        value: -40,
+    };
+    let encoded = serialize(&legacy).unwrap();
+    assert_eq!(&encoded[..], b"\x03\x00temp\0\0\xd8\xff\xff\xff");
+    assert_eq!(serialized_size(&legacy).unwrap(), encoded.len() as u64);
+
+    let decoded: Legacy = deserialize(&encoded).unwrap();
+    assert_eq!(decoded, legacy);
+    let range = encoded.as_ptr() as usize..encoded.as_ptr() as usize + encoded.len();
+    assert!(range.contains(&(decoded.name.0.as_ptr() as usize)));
+    let cached: Legacy = deserialize_from_custom(BlockCachedReader::<4>::new(&encoded)).unwrap();
+    assert_eq!(cached, legacy);
+
+    // The terminator and the string are charged to the size limit.
+    let exact = encoded.len() as u64;
+    config()
+        .limit(exact)
+        .deserialize_from_custom::<_, Legacy>(SliceReader::new(&encoded))
+        .unwrap();
+    match config()
+        .limit(6)
+        .deserialize_from_custom::<_, Legacy>(SliceReader::new(&encoded))
+    {
+        Err(ErrorKind::SizeLimit(Direction::Deserialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Exactly the maximum fits, one more doesn't.
+    let full = serialize(&NulStr::<8>("12345678")).unwrap();
+    assert_eq!(deserialize::<NulStr<8>>(&full).unwrap(), NulStr("12345678"));
+    match deserialize::<NulStr<7>>(&full) {
+        Err(ErrorKind::MissingNulTerminator { max: 7 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(serialize(&NulStr::<7>("12345678")).is_err());
+
+    // No terminator before the end of the input.
+    match deserialize::<NulStr>(b"abc") {
+        Err(ErrorKind::MissingNulTerminator { max: 255 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // A reader that can't look ahead says so, rather than that the
+    // terminator is missing.
+    #[cfg(feature = "std")]
+    match deserialize_from_custom::<_, NulStr<'static>>(bincode::IoReader::new(&b"abc\0"[..])) {
+        Err(ErrorKind::LookAheadUnsupported) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Invalid UTF-8 before the terminator.
+    match deserialize::<NulStr>(b"a\xffb\0") {
+        Err(ErrorKind::InvalidUtf8Encoding(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // An embedded NUL can't be written.
+    assert!(serialize(&NulStr::<8>("a\0b")).is_err());
+}
+
+#[test]
+fn padded_slots() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Record {
+        seq: u32,
+        reading: i16,
+    }
+
+    let record = Record { seq: 7, reading: -2 };
+    let mut cfg = config();
+    cfg.pad_byte(0xFF);
+
+    // A clean slot round-trips, and the padding is the pad byte.
+    let mut slot = Buffer::new();
+    assert_eq!(cfg.serialize_padded(&mut slot, &record, 16).unwrap(), 16);
+    assert_eq!(&slot[..], b"\x07\x00\x00\x00\xfe\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff");
+    assert_eq!(cfg.deserialize_padded::<Record>(&slot, 16).unwrap(), record);
+
+    // Stale data after the record is a torn write.
+    let mut dirty = slot.clone();
+    dirty[9] = 0x00;
+    match cfg.deserialize_padded::<Record>(&dirty, 16) {
+        Err(ErrorKind::DirtyPadding {
+            offset: 9,
+            found: 0x00,
+        }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    // Bytes past the slot aren't checked.
+    assert_eq!(cfg.deserialize_padded::<Record>(&dirty, 8).unwrap(), record);
+
+    // A record that exactly fills the slot has no padding to check.
+    let mut exact = Buffer::new();
+    assert_eq!(cfg.serialize_padded(&mut exact, &record, 6).unwrap(), 6);
+    assert_eq!(cfg.deserialize_padded::<Record>(&exact, 6).unwrap(), record);
+
+    // A record that doesn't fit writes nothing.
+    let mut small = Buffer::new();
+    match cfg.serialize_padded(&mut small, &record, 5) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(small.is_empty());
+
+    // A slot shorter than the slot size is truncated input.
+    match cfg.deserialize_padded::<Record>(&slot[..10], 16) {
+        Err(ErrorKind::UnexpectedEof { remaining: 10, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // The default pad byte is zero.
+    let mut zeroed = Buffer::new();
+    config().serialize_padded(&mut zeroed, &record, 8).unwrap();
+    assert_eq!(&zeroed[6..], &[0, 0]);
+    assert!(cfg.deserialize_padded::<Record>(&zeroed, 8).is_err());
+}
+
+#[test]
+fn option_tags() {
+    let mut vendor = config();
+    vendor.none_tag(0xFF).some_tag(0x01);
+
+    let mut buf = Buffer::new();
+    vendor.serialize_into(&mut buf, &(None::<u16>, Some(7u16))).unwrap();
+    assert_eq!(&buf[..], &[0xFF, 0x01, 7, 0]);
+    assert_eq!(
+        vendor.deserialize::<(Option<u16>, Option<u16>)>(&buf).unwrap(),
+        (None, Some(7))
+    );
+    assert_eq!(
+        vendor.serialized_size(&(None::<u16>, Some(7u16))).unwrap(),
+        buf.len() as u64
+    );
+
+    // The default config doesn't know the vendor's None.
+    match deserialize::<(Option<u16>, Option<u16>)>(&buf) {
+        Err(ErrorKind::InvalidTagEncoding(0xFF)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    // And the vendor config doesn't accept bincode's.
+    let standard = serialize(&None::<u16>).unwrap();
+    match vendor.deserialize::<Option<u16>>(&standard) {
+        Err(ErrorKind::InvalidTagEncoding(0)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // The tags apply to nested options as well.
+    let mut nested = Buffer::new();
+    vendor.serialize_into(&mut nested, &Some(None::<u8>)).unwrap();
+    assert_eq!(&nested[..], &[0x01, 0xFF]);
+    assert_eq!(vendor.deserialize::<Option<Option<u8>>>(&nested).unwrap(), Some(None));
+
+    // Tags that can't be told apart are refused, but can be swapped one at a
+    // time.
+    let mut swapped = config();
+    swapped.none_tag(1);
+    match swapped.serialize_into(&mut Buffer::new(), &Some(3u8)) {
+        Err(ErrorKind::EqualOptionTags) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    swapped.some_tag(0);
+    let mut buf = Buffer::new();
+    swapped.serialize_into(&mut buf, &(None::<u8>, Some(3u8))).unwrap();
+    assert_eq!(&buf[..], &[1, 0, 3]);
+}
+
+#[test]
+fn slice_stride() {
+    use bincode::stride::encoded_stride;
+    use bincode::LengthEncoding;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+    #[repr(C)]
+    struct Sample {
+        channel: u8,
+        raw: i16,
+        volts: f32,
+        timestamp: u64,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+    struct Triple(u16, [u8; 3], bool);
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+    struct Marker;
+
+    fn check<T>(values: &[T])
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + Debug,
+    {
+        let stride = encoded_stride::<T>().unwrap();
+        let encoded = serialize(values).unwrap();
+        assert_eq!(encoded.len(), 8 + values.len() * stride);
+
+        // The host only knows the prefix width and the stride.
+        let len = deserialize::<u64>(&encoded).unwrap() as usize;
+        assert_eq!(len, values.len());
+        for (i, value) in values.iter().enumerate() {
+            let element = &encoded[8 + i * stride..][..stride];
+            assert_eq!(&deserialize::<T>(element).unwrap(), value);
+        }
+
+        let full: Vec<T> = deserialize(&encoded).unwrap();
+        assert_eq!(&full[..], values);
+    }
+
+    assert_eq!(encoded_stride::<Sample>(), Some(1 + 2 + 4 + 8));
+    check(&[
+        Sample { channel: 0, raw: -1, volts: 0.5, timestamp: 1 },
+        Sample { channel: 3, raw: 2047, volts: -3.3, timestamp: u64::max_value() },
+        Sample { channel: 7, raw: 0, volts: 0.0, timestamp: 0 },
+    ]);
+
+    assert_eq!(encoded_stride::<Triple>(), Some(2 + 3 + 1));
+    check(&[Triple(1, [2, 3, 4], true), Triple(0xFFFF, [0; 3], false)]);
+
+    assert_eq!(encoded_stride::<(u32, Marker, ())>(), Some(4));
+    check(&[(5u32, Marker, ()), (6, Marker, ())]);
+    check::<Sample>(&[]);
+
+    // Visitors that reject a zero are handed other values.
+    assert_eq!(encoded_stride::<std::num::NonZeroU32>(), Some(4));
+    assert_eq!(encoded_stride::<(u8, std::num::NonZeroU16)>(), Some(3));
+
+    // The length encoding only changes the prefix.
+    let pairs = [(1u32, 2u32), (3, 4), (5, 6)];
+    for &length in &[LengthEncoding::U16, LengthEncoding::Varint] {
+        let mut cfg = config();
+        cfg.length_encoding(length);
+        assert_eq!(cfg.encoded_stride::<(u32, u32)>(), Some(8));
+        let mut buf = Buffer::new();
+        cfg.serialize_into(&mut buf, &pairs[..]).unwrap();
+        let prefix = buf.len() - 3 * 8;
+        assert_eq!(deserialize::<(u32, u32)>(&buf[prefix + 8..]).unwrap(), (3, 4));
+    }
+
+    // With alignment, the stride includes the padding of an aligned element.
+    let mut aligned = config();
+    aligned.align(true);
+    assert_eq!(aligned.encoded_stride::<(u32, u32)>(), Some(8));
+    assert_eq!(aligned.encoded_stride::<[u16; 3]>(), Some(6));
+    assert_eq!(aligned.encoded_stride::<(u8, bool)>(), Some(2));
+    assert_eq!(aligned.encoded_stride::<(u8, u32)>(), Some(8));
+    assert_eq!(aligned.encoded_stride::<(u32, u8)>(), None);
+    assert_eq!(aligned.encoded_stride::<Sample>(), Some(16));
+    let samples = [
+        Sample { channel: 1, raw: -2, volts: 3.0, timestamp: 4 },
+        Sample { channel: 5, raw: 6, volts: -7.5, timestamp: 8 },
+    ];
+    let mut buf = Buffer::new();
+    aligned.serialize_into(&mut buf, &samples[..]).unwrap();
+    assert_eq!(buf.len(), 8 + 2 * 16);
+    let mut second = Buffer::new();
+    aligned.serialize_into(&mut second, &samples[1]).unwrap();
+    assert_eq!(&buf[8 + 16..], &second[..]);
+
+    // Anything whose encoding depends on the value has no stride.
+    #[derive(Deserialize)]
+    struct Named {
+        _id: u32,
+        _name: String,
+    }
+    assert_eq!(encoded_stride::<Named>(), None);
+    assert_eq!(encoded_stride::<&str>(), None);
+    assert_eq!(encoded_stride::<char>(), None);
+    assert_eq!(encoded_stride::<Option<u32>>(), None);
+    assert_eq!(encoded_stride::<Vec<u8>>(), None);
+    assert_eq!(encoded_stride::<std::result::Result<u8, u8>>(), None);
+    assert_eq!(encoded_stride::<bincode::nul_str::NulStr>(), None);
+}
+
+#[test]
+fn limit_direction() {
+    let value = (1u32, "four", [0u8; 4]);
+    let size = serialized_size(&value).unwrap();
+    let encoded = serialize(&value).unwrap();
+
+    // Encode-side trips, both while measuring and while writing.
+    match config().limit(size - 1).serialized_size(&value) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    let mut buf = Buffer::new();
+    match config().limit(size - 1).serialize_into(&mut buf, &value) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Decode-side trip on the same bytes.
+    match config()
+        .limit(size - 1)
+        .deserialize_from_custom::<_, (u32, &str, [u8; 4])>(bincode::SliceReader::new(&encoded))
+    {
+        Err(ErrorKind::SizeLimit(Direction::Deserialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    assert_eq!(
+        ErrorKind::SizeLimit(Direction::Serialize).to_string(),
+        "the size limit was reached while serializing"
+    );
+    assert_eq!(
+        ErrorKind::SizeLimit(Direction::Deserialize).to_string(),
+        "the size limit was reached while deserializing"
+    );
+}
+
+#[test]
+fn error_messages() {
+    let utf8 = std::str::from_utf8(&[0xFF]).unwrap_err();
+    assert_eq!(
+        ErrorKind::InvalidUtf8Encoding(utf8).to_string(),
+        format!("string is not valid utf8: {}", utf8)
+    );
+    assert_eq!(
+        ErrorKind::InvalidBoolEncoding(2).to_string(),
+        "invalid u8 while decoding bool, expected 0 or 1, found 2"
+    );
+    assert_eq!(ErrorKind::InvalidCharEncoding.to_string(), "char is not valid");
+    assert_eq!(ErrorKind::InvalidTagEncoding(9).to_string(), "tag for enum is not valid, found 9");
+    assert_eq!(
+        ErrorKind::SequenceMustHaveLength.to_string(),
+        "bincode can only encode sequences and maps that have a knowable size ahead of time"
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn std_error() {
+    use std::error::Error as StdError;
+
+    let utf8 = std::str::from_utf8(&[0xFF]).unwrap_err();
+    let boxed: Box<dyn StdError> = Box::new(ErrorKind::InvalidUtf8Encoding(utf8));
+    assert_eq!(boxed.to_string(), format!("string is not valid utf8: {}", utf8));
+    assert_eq!(boxed.source().unwrap().to_string(), utf8.to_string());
+    assert!(ErrorKind::InvalidCharEncoding.source().is_none());
+}
+
+// Measures the stack a chain of newtypes takes like a painted-stack check on
+// the device would: paint the stack below the current frame, run the
+// (de)serialization, then count how much of the paint was written over.
+#[test]
+fn newtype_chain_stack() {
+    use std::hint::black_box;
+    use std::ptr;
+
+    const PAINT: usize = 32 * 1024;
+    const COLOR: u8 = 0x5A;
+    // Left unpainted below the measuring frame, for the calls it makes itself.
+    const GAP: usize = 256;
+
+    // Paints the unused stack below its frame, runs `f`, and returns how far
+    // down `f` wrote over the paint. The paint goes on byte by byte, not
+    // through a large local, whose stack probes would write over it, or a
+    // memset, which would run on the stack it paints. Not generic and given a
+    // pointer, so `f` never shares a frame with the measurement.
+    #[inline(never)]
+    fn stack_used(f: fn()) -> usize {
+        let marker = 0u8;
+        let top = black_box(&marker) as *const u8 as usize;
+        let low = (top - GAP - PAINT) as *mut u8;
+        let mut i = 0;
+        while i < PAINT {
+            unsafe { ptr::write_volatile(low.wrapping_add(i), COLOR) };
+            i += 1;
+        }
+        f();
+        let mut untouched = 0;
+        while untouched < PAINT && unsafe { ptr::read_volatile(low.wrapping_add(untouched)) } == COLOR {
+            untouched += 1;
+        }
+        GAP + PAINT - untouched
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Leaf(u32);
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Millivolts(Leaf);
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Volts(Millivolts);
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Calibrated(Volts);
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Filtered(Calibrated);
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Channel(Filtered);
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Reading(Channel);
+
+    fn chain() -> Reading {
+        Reading(Channel(Filtered(Calibrated(Volts(Millivolts(Leaf(7)))))))
+    }
+
+    fn serialize_bare() {
+        black_box(serialize(black_box(&Leaf(7))).unwrap());
+    }
+    fn serialize_chain() {
+        black_box(serialize(black_box(&chain())).unwrap());
+    }
+    fn deserialize_bare() {
+        assert_eq!(deserialize::<Leaf>(black_box(&[7, 0, 0, 0])).unwrap(), Leaf(7));
+    }
+    fn deserialize_chain() {
+        assert_eq!(deserialize::<Reading>(black_box(&[7, 0, 0, 0])).unwrap(), chain());
+    }
+
+    // A spawned thread's stack is mapped up front, unlike the main thread's,
+    // which only grows as it is used.
+    let (serialize, deserialize) = std::thread::Builder::new()
+        .stack_size(256 * 1024)
+        .spawn(|| {
+            (
+                (stack_used(serialize_bare), stack_used(serialize_chain)),
+                (stack_used(deserialize_bare), stack_used(deserialize_chain)),
+            )
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+
+    // Without optimizations every derived impl keeps a frame of its own, so
+    // there only the growth per layer is bounded.
+    let slack = if cfg!(debug_assertions) { 6 * 320 } else { 64 };
+    assert!(serialize.1 <= serialize.0 + slack, "{:?}", serialize);
+    assert!(deserialize.1 <= deserialize.0 + slack, "{:?}", deserialize);
+}
+
+#[test]
+fn config_matrix() {
+    check_config_matrix(&[(0, 1), (1, 0), (0xFF, 0x01), (0x80, 0x7F), (7, 7)]);
+}
+
+// Takes a while, especially in debug builds, so it only runs when asked for
+// with `cargo test --release -- --ignored`.
+#[test]
+#[ignore]
+fn config_matrix_all_tags() {
+    let tags: Vec<(u8, u8)> = (0..=255u8).flat_map(|none| (0..=255u8).map(move |some| (none, some))).collect();
+    check_config_matrix(&tags);
+}
+
+/// Round-trips a value touching every part of the encoding under every
+/// combination of options, with each of the pairs of `Option` tags in `tags`.
+fn check_config_matrix(tags: &[(u8, u8)]) {
+    use bincode::nul_str::NulStr;
+    use bincode::packed::PackedBools;
+    use bincode::{LengthEncoding, SliceReader};
+    use serde_bytes::Bytes;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Unit;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Newtype(i16);
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Kind {
+        Empty,
+        Wrapped(u32),
+        Pair(u8, char),
+        Named { x: f32, y: Option<u16> },
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Corpus<'a> {
+        flags: (bool, bool),
+        unsigned: (u8, u16, u32, u64),
+        signed: (i8, i16, i32, i64),
+        floats: (f32, f64),
+        chars: [char; 3],
+        text: &'a str,
+        #[serde(borrow)]
+        bytes: &'a Bytes,
+        seq: Vec<u16>,
+        map: BTreeMap<u8, &'a str>,
+        options: (Option<u8>, Option<Option<u8>>, Option<Option<u8>>, Option<Unit>),
+        newtype: Newtype,
+        kinds: Vec<Kind>,
+        #[serde(borrow)]
+        legacy: NulStr<'a, 16>,
+        bits: PackedBools<ArrayVec<[bool; 16]>>,
+    }
+
+    let mut map = BTreeMap::new();
+    map.insert(1, "one");
+    map.insert(200, "");
+    let corpus = Corpus {
+        flags: (true, false),
+        unsigned: (0xFE, 0xFEDC, 0xFEDC_BA98, u64::max_value() - 1),
+        signed: (-2, i16::min_value(), -70000, i64::min_value() + 1),
+        floats: (-1.5, 3.25e300),
+        chars: ['a', 'é', '🦀'],
+        text: "hello",
+        bytes: Bytes::new(&[0, 1, 0xFF]),
+        seq: vec![1, 0x100, 0xFFFF],
+        map,
+        options: (Some(0), Some(None), None, Some(Unit)),
+        newtype: Newtype(-300),
+        kinds: vec![
+            Kind::Empty,
+            Kind::Wrapped(9),
+            Kind::Pair(1, 'z'),
+            Kind::Named { x: 0.5, y: Some(3) },
+            Kind::Named { x: -0.0, y: None },
+        ],
+        legacy: NulStr("eth0"),
+        bits: PackedBools([true, false, true, true, false, false, false, false, true].iter().cloned().collect()),
+    };
+
+    #[derive(Clone, Copy, Debug)]
+    enum Endian {
+        Little,
+        Big,
+        Native,
+    }
+
+    let endians = [Endian::Little, Endian::Big, Endian::Native];
+    let lengths = [LengthEncoding::U16, LengthEncoding::U32, LengthEncoding::U64, LengthEncoding::Varint];
+    for &endian in &endians {
+        for &length in &lengths {
+            for &(none, some) in tags {
+                for &align in &[false, true] {
+                    let knobs = (endian, length, none, some, align);
+                    let mut config = config();
+                    match endian {
+                        Endian::Little => config.little_endian(),
+                        Endian::Big => config.big_endian(),
+                        Endian::Native => config.native_endian(),
+                    };
+                    config.length_encoding(length).none_tag(none).some_tag(some).align(align);
+
+                    // Equal tags can't be told apart, so any Option is refused.
+                    if none == some {
+                        let mut encoded = Buffer::new();
+                        for result in &[
+                            config.serialized_size(&corpus).map(|_| ()),
+                            config.serialize_into(&mut encoded, &corpus).map(|_| ()),
+                            config.deserialize::<Option<u8>>(&[none]).map(|_| ()),
+                        ] {
+                            match result {
+                                Err(ErrorKind::EqualOptionTags) => {}
+                                other => panic!("{:?}: unexpected {:?}", knobs, other),
+                            }
+                        }
+                        continue;
+                    }
+
+                    let size = config.serialized_size(&corpus).unwrap();
+                    config.limit(size);
+                    let mut encoded = Buffer::new();
+                    if let Err(e) = config.serialize_into(&mut encoded, &corpus) {
+                        panic!("{:?}: serializing failed with {:?}", knobs, e);
+                    }
+                    assert_eq!(encoded.len() as u64, size, "{:?}: size mismatch", knobs);
+
+                    match config.deserialize_from_custom::<_, Corpus>(SliceReader::new(&encoded)) {
+                        Ok(ref decoded) if *decoded == corpus => {}
+                        other => panic!("{:?}: round trip gave {:?}", knobs, other),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn serialize_into_slice() {
+    let value = (0x0102u16, "dma", Some(7u8));
+    let expected = serialize(&value).unwrap();
+
+    let mut out = [0xAAu8; 32];
+    let n = bincode::serialize_into_slice(&value, &mut out).unwrap();
+    assert_eq!(&out[..n], &expected[..]);
+    assert!(out[n..].iter().all(|&b| b == 0xAA));
+    assert_eq!(deserialize::<(u16, &str, Option<u8>)>(&out[..n]).unwrap(), value);
+
+    // Exactly enough room.
+    let mut exact = vec![0; expected.len()];
+    assert_eq!(bincode::serialize_into_slice(&value, &mut exact).unwrap(), exact.len());
+
+    // One byte short names both lengths.
+    let mut short = vec![0; expected.len() - 1];
+    match bincode::serialize_into_slice(&value, &mut short) {
+        Err(ErrorKind::BufferTooSmall { needed, available, written }) => {
+            assert_eq!(needed, expected.len() as u64);
+            assert_eq!(available, expected.len() - 1);
+            assert_eq!(written, 14);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    match bincode::serialize_into_slice(&value, &mut []) {
+        Err(ErrorKind::BufferTooSmall {
+            needed: 15,
+            available: 0,
+            written: 0,
+        }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // The configured encoding and limit still apply.
+    let mut config = config();
+    config.big_endian().length_encoding(bincode::LengthEncoding::U16);
+    let n = config.serialize_into_slice(&value, &mut out).unwrap();
+    assert_eq!(&out[..n], b"\x01\x02\x00\x03dma\x01\x07");
+    match config.limit(4).serialize_into_slice(&value, &mut out) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn io_writer() {
+    use std::io;
+
+    let value = (1u8, 0x0203u16, "host side", vec![Some(4i64), None], 'ü');
+    let expected = serialize(&value).unwrap();
+
+    let mut out = Vec::new();
+    let n = bincode::serialize_into_writer(&mut out, &value).unwrap();
+    assert_eq!(n, expected.len());
+    assert_eq!(&out[..], &expected[..]);
+    let decoded: (u8, u16, &str, Vec<Option<i64>>, char) = deserialize(&out).unwrap();
+    assert_eq!(decoded, value);
+
+    // Same bytes under a non-default configuration.
+    let mut config = config();
+    config.big_endian().length_encoding(bincode::LengthEncoding::U32);
+    let mut buffered = io::BufWriter::new(Vec::new());
+    config.serialize_into_writer(&mut buffered, &value).unwrap();
+    let mut expected = Buffer::new();
+    config.serialize_into(&mut expected, &value).unwrap();
+    assert_eq!(&buffered.into_inner().unwrap()[..], &expected[..]);
+
+    // Nothing is written when the limit is too small.
+    let mut out = Vec::new();
+    match config.limit(4).serialize_into_writer(&mut out, &value) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => assert!(out.is_empty()),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Writer errors come back as `Io`.
+    let mut full = [0u8; 4];
+    match bincode::serialize_into_writer(&mut full[..], &value) {
+        Err(ErrorKind::Io(ref e)) if e.kind() == io::ErrorKind::WriteZero => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn io_reader() {
+    use bincode::deserialize_from;
+    use serde_bytes::ByteBuf;
+    use std::io;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Owned {
+        name: String,
+        tag: Cow<'static, str>,
+        payload: ByteBuf,
+        samples: Vec<Option<i64>>,
+        initial: char,
+    }
+
+    let value = Owned {
+        name: "host side".to_string(),
+        tag: Cow::Borrowed("tcp"),
+        payload: ByteBuf::from(vec![1, 2, 3]),
+        samples: vec![Some(4), None],
+        initial: 'ü',
+    };
+
+    // Values come off the stream one after another.
+    let mut stream = Vec::new();
+    stream.extend_from_slice(&serialize(&value).unwrap());
+    stream.extend_from_slice(&serialize(&0x0203u16).unwrap());
+    let mut reader = io::Cursor::new(stream);
+    let decoded: Owned = deserialize_from(&mut reader).unwrap();
+    assert_eq!(decoded, value);
+    let next: u16 = deserialize_from(&mut reader).unwrap();
+    assert_eq!(next, 0x0203);
+
+    // Same as deserializing from a slice under a non-default configuration.
+    let mut config = config();
+    config.big_endian().length_encoding(bincode::LengthEncoding::U16);
+    let mut encoded = Buffer::new();
+    config.serialize_into(&mut encoded, &value).unwrap();
+    let decoded: Owned = config.deserialize_from(&encoded[..]).unwrap();
+    assert_eq!(decoded, value);
+
+    // A huge length prefix hits the limit before anything is allocated for it.
+    let huge = [0xFF; 8];
+    match config.limit(16).deserialize_from::<_, String>(&huge[..]) {
+        Err(ErrorKind::SizeLimit(Direction::Deserialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Running out of input is `UnexpectedEof`, as for the other readers, here
+    // in the middle of `name`.
+    let encoded = serialize(&value).unwrap();
+    match deserialize_from::<_, Owned>(&encoded[..12]) {
+        Err(ErrorKind::UnexpectedEof { remaining: 4, needed: Some(5), .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn custom_output_flavor() {
+    use bincode::{serialize_with_flavor, OutputFlavor};
+
+    // A sink that only keeps a running sum, as a stand-in for a FIFO or
+    // a peripheral register.
+    struct Summing {
+        sum: u32,
+        count: usize,
+        slices: usize,
+    }
+
+    impl<'a> OutputFlavor for &'a mut Summing {
+        fn try_push(&mut self, byte: u8) -> Result<()> {
+            self.sum += byte as u32;
+            self.count += 1;
+            Ok(())
+        }
+
+        fn write(&mut self, bytes: &[u8]) -> Result<()> {
+            self.slices += 1;
+            for &byte in bytes {
+                self.try_push(byte)?;
+            }
+            Ok(())
+        }
+
+        fn finalize(self) -> Result<usize> {
+            Ok(self.count)
+        }
+    }
+
+    let value = (1u8, 0x0102u16, "ab", Some(3u64));
+    let expected = serialize(&value).unwrap();
+
+    let mut sink = Summing { sum: 0, count: 0, slices: 0 };
+    assert_eq!(serialize_with_flavor(&mut sink, &value).unwrap(), expected.len());
+    assert_eq!(sink.sum, expected.iter().map(|&b| b as u32).sum::<u32>());
+    // Multi-byte values arrive through `write`.
+    assert!(sink.slices > 0);
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn heapless_vec() {
+    use bincode::heapless::Vec as HVec;
+    use bincode::serialize_with_flavor;
+
+    let value = (0x0102u16, "abc", Some(5u8));
+    let expected = serialize(&value).unwrap();
+    assert_eq!(expected.len(), 15);
+
+    // Exact fit.
+    let mut exact: HVec<u8, 15> = HVec::new();
+    assert_eq!(serialize_with_flavor(&mut exact, &value).unwrap(), 15);
+    assert_eq!(&exact[..], &expected[..]);
+    assert_eq!(deserialize::<(u16, &str, Option<u8>)>(&exact).unwrap(), value);
+
+    // One byte short, the same error as a full `ArrayVec`.
+    let mut short: HVec<u8, 14> = HVec::new();
+    match serialize_with_flavor(&mut short, &value) {
+        Err(ErrorKind::CapacityError(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    let mut array: ArrayVec<[u8; 14]> = ArrayVec::new();
+    match serialize_with_flavor(&mut array, &value) {
+        Err(ErrorKind::CapacityError(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // No room at all.
+    let mut empty: HVec<u8, 0> = HVec::new();
+    match serialize_with_flavor(&mut empty, &value) {
+        Err(ErrorKind::CapacityError(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Bytes already in the vector are kept.
+    let mut framed: HVec<u8, 32> = HVec::new();
+    framed.push(0x7E).unwrap();
+    assert_eq!(serialize_with_flavor(&mut framed, &value).unwrap(), 16);
+    assert_eq!(&framed[1..], &expected[..]);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn serialize_to_vec() {
+    use bincode::serialize_with_flavor;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Owned {
+        name: String,
+        payload: Vec<u8>,
+        #[serde(with = "serde_bytes")]
+        blob: Vec<u8>,
+        samples: Vec<u32>,
+    }
+
+    let value = Owned {
+        name: "host".to_string(),
+        payload: vec![1, 2, 3],
+        blob: vec![0xFF; 5],
+        samples: (0..40).collect(),
+    };
+
+    let bytes = bincode::serialize_to_vec(&value).unwrap();
+    assert_eq!(bytes.len() as u64, serialized_size(&value).unwrap());
+    assert_eq!(&bytes[..], &serialize(&value).unwrap()[..]);
+    assert_eq!(deserialize::<Owned>(&bytes).unwrap(), value);
+
+    let mut config = config();
+    config.big_endian();
+    let big = config.serialize_to_vec(&value).unwrap();
+    assert_eq!(config.deserialize::<Owned>(&big).unwrap(), value);
+    match config.limit(10).serialize_to_vec(&value) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // A `Vec` flavor appends.
+    let mut framed = vec![0x7E];
+    serialize_with_flavor(&mut framed, &value).unwrap();
+    assert_eq!(&framed[1..], &bytes[..]);
+}
+
+#[test]
+fn array_vec_bulk_write() {
+    // Numbers go in as one write: a number that doesn't fit leaves none of
+    // its bytes behind.
+    let mut buf: ArrayVec<[u8; 6]> = ArrayVec::new();
+    match bincode::serialize_with_flavor(&mut buf, &(7u8, 0x01020304u32, 0x0506u16)) {
+        Err(ErrorKind::CapacityError(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(&buf[..], &[7, 4, 3, 2, 1]);
+
+    let mut buf: ArrayVec<[u8; 64]> = ArrayVec::new();
+    let frame: [u32; 16] = [0xDEADBEEF; 16];
+    serialize_into(&mut buf, &frame).unwrap();
+    assert!(buf.chunks(4).all(|c| c == [0xEF, 0xBE, 0xAD, 0xDE]));
+    assert_eq!(deserialize::<[u32; 16]>(&buf).unwrap(), frame);
+}
+
+#[test]
+fn bulk_str_and_bytes() {
+    use serde_bytes::Bytes;
+
+    let blob: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+    let text: String = std::iter::repeat("abcé").take(500).collect();
+
+    let mut buf: ArrayVec<[u8; 8192]> = ArrayVec::new();
+    serialize_into(&mut buf, &(Bytes::new(&blob), &text[..], 'é', 'ü')).unwrap();
+    let (b, t, c, d): (&[u8], &str, char, char) = deserialize(&buf).unwrap();
+    assert_eq!((b, t, c, d), (&blob[..], &text[..], 'é', 'ü'));
+
+    // Running out mid-payload is a capacity error, and none of the
+    // payload is written.
+    let mut small: ArrayVec<[u8; 1024]> = ArrayVec::new();
+    match bincode::serialize_with_flavor(&mut small, Bytes::new(&blob)) {
+        Err(ErrorKind::CapacityError(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(small.len(), 8);
+    let mut small: ArrayVec<[u8; 1024]> = ArrayVec::new();
+    match bincode::serialize_with_flavor(&mut small, &text[..]) {
+        Err(ErrorKind::CapacityError(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(small.len(), 8);
+    let mut tiny: ArrayVec<[u8; 1]> = ArrayVec::new();
+    match bincode::serialize_with_flavor(&mut tiny, &'é') {
+        Err(ErrorKind::CapacityError(_)) => assert!(tiny.is_empty()),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn serialize_into_rolls_back() {
+    #[derive(Serialize)]
+    struct Fails;
+
+    impl Fails {
+        fn fail<S: serde::Serializer>(&self, _: S) -> std::result::Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("nope"))
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Message<'a> {
+        id: u16,
+        body: &'a str,
+        #[serde(serialize_with = "Fails::fail")]
+        trailer: Fails,
+    }
+
+    let mut buf: ArrayVec<[u8; 24]> = ArrayVec::new();
+    serialize_into(&mut buf, &(1u16, "first")).unwrap();
+    let first = buf.clone();
+    assert_eq!(first.len(), 15);
+
+    // Too little room left: the second message is dropped whole.
+    match serialize_into(&mut buf, &(2u16, "second")) {
+        Err(ErrorKind::BufferTooSmall {
+            needed: 16,
+            available: 9,
+            written: 2,
+        }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(&buf[..], &first[..]);
+    assert_eq!(deserialize::<(u16, &str)>(&buf).unwrap(), (1, "first"));
+
+    // A failing `Serialize` impl after some fields were written.
+    let mut buf: ArrayVec<[u8; 64]> = ArrayVec::new();
+    serialize_into(&mut buf, &(1u16, "first")).unwrap();
+    let message = Message { id: 2, body: "partial", trailer: Fails };
+    match serialize_into(&mut buf, &message) {
+        Err(ErrorKind::Serde) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(&buf[..], &first[..]);
+
+    // And it still appends normally afterwards.
+    serialize_into(&mut buf, &(3u16, "third")).unwrap();
+    assert_eq!(buf.len(), 15 + 15);
+}
+
+#[test]
+fn serialize_into_checked() {
+    use bincode::serialize_into_checked;
+
+    let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+    serialize_into_checked(&mut buf, &(1u16, "first")).unwrap();
+    assert_eq!(buf.len(), 15);
+
+    // 17 bytes left: a 17-byte value exactly fills them.
+    let exact = (2u16, "second!");
+    assert_eq!(serialized_size(&exact).unwrap(), 17);
+    let mut full = buf.clone();
+    serialize_into_checked(&mut full, &exact).unwrap();
+    assert!(full.is_full());
+    assert_eq!(&full[15..], &serialize(&exact).unwrap()[..]);
+
+    // One more byte is refused up front with both numbers.
+    match serialize_into_checked(&mut buf, &(2u16, "second!!")) {
+        Err(ErrorKind::BufferTooSmall {
+            needed: 18,
+            available: 17,
+            written: 0,
+        }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(buf.len(), 15);
+    match serialize_into_checked(&mut full, &()) {
+        Ok(0) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    match serialize_into_checked(&mut full, &0u8) {
+        Err(ErrorKind::BufferTooSmall {
+            needed: 1,
+            available: 0,
+            written: 0,
+        }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // The configured encoding and limit are honored.
+    let mut config = config();
+    config.length_encoding(bincode::LengthEncoding::U16);
+    let mut small: ArrayVec<[u8; 11]> = ArrayVec::new();
+    config.serialize_into_checked(&mut small, &(2u16, "second!")).unwrap();
+    assert!(small.is_full());
+    let mut small: ArrayVec<[u8; 11]> = ArrayVec::new();
+    match config.limit(10).serialize_into_checked(&mut small, &(2u16, "second!")) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => assert!(small.is_empty()),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // A value that fails after it was measured is rolled back as well.
+    struct FailsSecondTime(std::cell::Cell<u8>);
+    impl serde::Serialize for FailsSecondTime {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            use serde::ser::{Error, SerializeTuple};
+            let calls = self.0.get() + 1;
+            self.0.set(calls);
+            let mut tuple = serializer.serialize_tuple(2)?;
+            tuple.serialize_element(&7u32)?;
+            if calls > 1 {
+                return Err(S::Error::custom("changed its mind"));
+            }
+            tuple.serialize_element(&8u32)?;
+            tuple.end()
+        }
+    }
+    let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+    buf.push(0xAA);
+    match serialize_into_checked(&mut buf, &FailsSecondTime(std::cell::Cell::new(0))) {
+        Err(ErrorKind::Serde) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(&buf[..], &[0xAA]);
+}
+
+#[test]
+fn serialize_into_checked_fast_path() {
+    use bincode::serialize_into_checked;
+    use std::cell::Cell;
+
+    #[derive(Serialize)]
+    struct Many {
+        a: [u8; 16],
+        b: [u16; 8],
+        c: [u32; 4],
+        d: bool,
+        e: Option<i8>,
+        f: &'static str,
+    }
+
+    let value = Many {
+        a: [1; 16],
+        b: [2; 8],
+        c: [3; 4],
+        d: true,
+        e: Some(-1),
+        f: "small fields",
+    };
+    let mut plain: ArrayVec<[u8; 128]> = ArrayVec::new();
+    serialize_into(&mut plain, &value).unwrap();
+    let mut checked: ArrayVec<[u8; 128]> = ArrayVec::new();
+    serialize_into_checked(&mut checked, &value).unwrap();
+    assert_eq!(&checked[..], &plain[..]);
+
+    // A value that grows between measuring and writing still runs out of
+    // room with an error, not by writing past the end.
+    struct Growing(Cell<usize>);
+
+    impl serde::Serialize for Growing {
+        fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+            let n = self.0.get();
+            self.0.set(n + 8);
+            s.serialize_bytes(&[7; 16][..n])
+        }
+    }
+
+    let mut buf: ArrayVec<[u8; 12]> = ArrayVec::new();
+    match serialize_into_checked(&mut buf, &Growing(Cell::new(4))) {
+        Err(ErrorKind::CapacityError(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn serialize_into_reports_written() {
+    use bincode::serialize_into_checked;
+
+    #[derive(Serialize)]
+    struct Inner {
+        id: u16,
+        name: String,
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        tag: u8,
+        inner: Inner,
+        rest: Vec<Inner>,
+    }
+
+    struct Shouted(&'static str, usize);
+
+    impl serde::Serialize for Shouted {
+        fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+            s.collect_str(&format_args!("{}!", self.0.repeat(self.1)))
+        }
+    }
+
+    let value = Outer {
+        tag: 3,
+        inner: Inner {
+            id: 1,
+            name: "x".repeat(300),
+        },
+        rest: vec![
+            Inner {
+                id: 2,
+                name: "second".into(),
+            },
+            Inner {
+                id: 3,
+                name: String::new(),
+            },
+        ],
+    };
+    let size = serialized_size(&value).unwrap() as usize;
+
+    // The count covers only this value, not what was in the buffer before.
+    let mut buf: ArrayVec<[u8; 1024]> = ArrayVec::new();
+    buf.extend(b"header".iter().cloned());
+    assert_eq!(serialize_into(&mut buf, &value).unwrap(), size);
+    assert_eq!(buf.len(), 6 + size);
+
+    let shouted = Shouted("ab", 100);
+    assert_eq!(serialized_size(&shouted).unwrap(), 8 + 201);
+    assert_eq!(serialize_into(&mut buf, &shouted).unwrap(), 8 + 201);
+    assert_eq!(&buf[6 + size + 8..], &*format!("{}!", "ab".repeat(100)).as_bytes());
+
+    let mut checked: ArrayVec<[u8; 1024]> = ArrayVec::new();
+    checked.push(0xff);
+    assert_eq!(serialize_into_checked(&mut checked, &value).unwrap(), size);
+    assert_eq!(serialize_into_checked(&mut checked, &shouted).unwrap(), 8 + 201);
+    assert_eq!(&checked[1..], &buf[6..]);
+
+    let mut config = config();
+    config.length_encoding(bincode::LengthEncoding::U16);
+    let mut short: ArrayVec<[u8; 1024]> = ArrayVec::new();
+    assert_eq!(config.serialize_into(&mut short, &shouted).unwrap(), 2 + 201);
+}
+
+#[test]
+fn buffer_serializer_reuse() {
+    use bincode::BufferSerializer;
+
+    let mut buf: ArrayVec<[u8; 8]> = ArrayVec::new();
+    let mut config = config();
+    config.big_endian().limit(4);
+    let mut serializer = BufferSerializer::with_config(&mut buf, config);
+
+    // Values accumulate until the buffer is reset, each under its own limit.
+    assert_eq!(serializer.serialize(&0x0102u16).unwrap(), 2);
+    assert_eq!(serializer.serialize(&0x03040506u32).unwrap(), 4);
+    assert_eq!(serializer.as_slice(), &[1, 2, 3, 4, 5, 6]);
+    match serializer.serialize(&0u64) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    match serializer.serialize(&(7u8, 8u8, 9u8)) {
+        Err(ErrorKind::BufferTooSmall {
+            needed: 3,
+            available: 2,
+            written: 2,
+        }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(serializer.as_slice(), &[1, 2, 3, 4, 5, 6]);
+
+    serializer.reset();
+    assert!(serializer.as_slice().is_empty());
+    assert_eq!(serializer.serialize(&(7u8, 8u8, 9u8)).unwrap(), 3);
+    assert_eq!(serializer.into_inner().as_slice(), &[7, 8, 9]);
+}
+
+#[test]
+fn scatter_segments() {
+    use bincode::flavors::Scatter;
+    use bincode::serialize_with_flavor;
+
+    let (mut first, mut empty, mut second) = ([0u8; 6], [0u8; 0], [0u8; 11]);
+    {
+        let mut segments = [&mut first[..], &mut empty[..], &mut second[..]];
+        let mut scatter = Scatter::new(&mut segments);
+
+        // The u32 straddles the boundary two bytes in, skipping the empty segment.
+        let value = (0x1111u16, 0x2222u16, 0x44332211u32, 9u8);
+        assert_eq!(serialize_with_flavor(&mut scatter, &value).unwrap(), 9);
+        assert_eq!((scatter.fill(0), scatter.fill(1), scatter.fill(2)), (6, 0, 3));
+
+        // More output continues where the last value ended; the count is the
+        // total across all segments so far.
+        assert_eq!(serialize_with_flavor(&mut scatter, &0x0807060504030201u64).unwrap(), 17);
+        assert_eq!(scatter.fill(2), 11);
+        match serialize_with_flavor(&mut scatter, &0u8) {
+            Err(ErrorKind::CapacityError(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+    assert_eq!(first, [0x11, 0x11, 0x22, 0x22, 0x11, 0x22]);
+    assert_eq!(second, [0x33, 0x44, 9, 1, 2, 3, 4, 5, 6, 7, 8]);
+
+    // A write that doesn't fit in what's left of all segments writes nothing.
+    let (mut a, mut b) = ([0u8; 2], [0u8; 2]);
+    let mut segments = [&mut a[..], &mut b[..]];
+    let mut scatter = Scatter::new(&mut segments);
+    serialize_with_flavor(&mut scatter, &7u8).unwrap();
+    match serialize_with_flavor(&mut scatter, &0xffff_ffffu32) {
+        Err(ErrorKind::CapacityError(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!((scatter.fill(0), scatter.fill(1)), (1, 0));
+}
+
+#[cfg(feature = "embedded-io")]
+#[test]
+fn embedded_io_writer() {
+    use bincode::embedded_io::{ErrorKind as IoKind, ErrorType, Write};
+    use bincode::flavors::EmbeddedIoWriter;
+    use bincode::serialize_with_flavor;
+
+    // Takes at most three bytes per call, like a small FIFO, and times out
+    // once `room` bytes have gone out.
+    struct Uart {
+        sent: Vec<u8>,
+        room: usize,
+        flushed: bool,
+    }
+
+    impl ErrorType for Uart {
+        type Error = IoKind;
+    }
+
+    impl Write for Uart {
+        fn write(&mut self, buf: &[u8]) -> std::result::Result<usize, IoKind> {
+            let n = buf.len().min(3).min(self.room - self.sent.len());
+            if n == 0 {
+                return Err(IoKind::TimedOut);
+            }
+            self.sent.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> std::result::Result<(), IoKind> {
+            self.flushed = true;
+            Ok(())
+        }
+    }
+
+    let value = (0x01020304u32, "telemetry", Some(7u8));
+    let mut uart = Uart {
+        sent: Vec::new(),
+        room: 64,
+        flushed: false,
+    };
+    assert_eq!(serialize_with_flavor(EmbeddedIoWriter::new(&mut uart), &value).unwrap(), 23);
+    assert_eq!(&uart.sent[..], &serialize(&value).unwrap()[..]);
+    assert!(uart.flushed);
+
+    let mut uart = Uart {
+        sent: Vec::new(),
+        room: 10,
+        flushed: false,
+    };
+    match serialize_with_flavor(EmbeddedIoWriter::new(&mut uart), &value) {
+        Err(ErrorKind::EmbeddedIo(IoKind::TimedOut)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(!uart.flushed);
+}
+
+#[test]
+fn serialize_into_with_crc() {
+    use bincode::serialize_into_with_crc;
+
+    // The standard CRC-32 check value, so the polynomial and seed can't drift.
+    let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+    assert_eq!(serialize_into_with_crc(&mut buf, b"123456789").unwrap(), 13);
+    assert_eq!(&buf[9..], &[0x26, 0x39, 0xf4, 0xcb]);
+    buf.clear();
+    config().big_endian().serialize_into_with_crc(&mut buf, b"123456789").unwrap();
+    assert_eq!(&buf[9..], &[0xcb, 0xf4, 0x39, 0x26]);
+
+    #[derive(Serialize)]
+    struct Reading<'a> {
+        id: u16,
+        value: i32,
+        label: &'a str,
+    }
+
+    let reading = Reading {
+        id: 7,
+        value: -2,
+        label: "temp",
+    };
+    let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+    buf.push(0xaa);
+    assert_eq!(serialize_into_with_crc(&mut buf, &reading).unwrap(), 22);
+    assert_eq!(
+        &buf[1..],
+        &[
+            7, 0, 0xfe, 0xff, 0xff, 0xff, 4, 0, 0, 0, 0, 0, 0, 0, b't', b'e', b'm', b'p', 0xbb, 0x81, 0x08, 0x12
+        ]
+    );
+
+    // Running out of room leaves the buffer as it was.
+    let mut short: ArrayVec<[u8; 20]> = ArrayVec::new();
+    match serialize_into_with_crc(&mut short, &reading) {
+        Err(ErrorKind::BufferTooSmall {
+            needed: 22,
+            available: 20,
+            written: 18,
+        }) => assert!(short.is_empty()),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn aligned_numbers() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Mixed<'a> {
+        a: u8,
+        b: u64,
+        c: u8,
+        d: u16,
+        e: u8,
+        f: f32,
+        g: Option<u64>,
+        h: &'a str,
+        i: u8,
+    }
+
+    let value = Mixed {
+        a: 1,
+        b: 0x0202020202020202,
+        c: 3,
+        d: 0x0404,
+        e: 5,
+        f: 1.0,
+        g: Some(7),
+        h: "hi",
+        i: 9,
+    };
+
+    // Off by default: no padding at all.
+    assert_eq!(serialized_size(&value).unwrap(), 1 + 8 + 1 + 2 + 1 + 4 + 9 + 10 + 1);
+
+    let mut config = config();
+    config.align(true);
+    let mut buf: ArrayVec<[u8; 64]> = ArrayVec::new();
+    config.serialize_into(&mut buf, &value).unwrap();
+    let expected: &[u8] = &[
+        1, 0, 0, 0, 0, 0, 0, 0,          // a, padded to 8
+        2, 2, 2, 2, 2, 2, 2, 2,          // b
+        3, 0, 4, 4, 5, 0, 0, 0,          // c, d at 18, e, f padded to 24
+        0, 0, 0x80, 0x3f, 1, 0, 0, 0,    // f, Some, padded to 32
+        7, 0, 0, 0, 0, 0, 0, 0,          // g
+        2, 0, 0, 0, 0, 0, 0, 0,          // length of h
+        b'h', b'i', 9,
+    ];
+    assert_eq!(&buf[..], expected);
+    assert_eq!(config.serialized_size(&value).unwrap(), expected.len() as u64);
+    let decoded: Mixed = config.deserialize(&buf).unwrap();
+    assert_eq!(decoded, value);
+
+    // Alignment is relative to the start of the message, not of the buffer.
+    let mut offset: ArrayVec<[u8; 64]> = ArrayVec::new();
+    offset.push(0xAA);
+    config.serialize_into(&mut offset, &value).unwrap();
+    assert_eq!(&offset[1..], expected);
+}
+
+#[test]
+fn chunked_packets() {
+    use bincode::flavors::Chunked;
+    use bincode::serialize_with_flavor;
+
+    #[derive(Serialize)]
+    struct State {
+        id: u32,
+        samples: Vec<u16>,
+        name: &'static str,
+    }
+
+    let state = State {
+        id: 7,
+        samples: (0..140).collect(),
+        name: "several hundred bytes",
+    };
+    let expected = serialize(&state).unwrap();
+    assert_eq!(expected.len(), 4 + 8 + 280 + 8 + 21);
+
+    let mut packets: Vec<Vec<u8>> = Vec::new();
+    {
+        let mut chunked = Chunked::<_, (), 64>::new(|packet: &[u8]| {
+            packets.push(packet.to_vec());
+            Ok(())
+        });
+        assert_eq!(serialize_with_flavor(&mut chunked, &state).unwrap(), 321);
+        // A second value continues in the same packet.
+        assert_eq!(serialize_with_flavor(&mut chunked, &0xABu8).unwrap(), 322);
+        assert_eq!(chunked.finish().unwrap(), 322);
+        assert_eq!(chunked.finish().unwrap(), 322);
+    }
+    let lens: Vec<usize> = packets.iter().map(|p| p.len()).collect();
+    assert_eq!(lens, [64, 64, 64, 64, 64, 2]);
+    let joined: Vec<u8> = packets.concat();
+    assert_eq!(&joined[..321], &expected[..]);
+    assert_eq!(joined[321], 0xAB);
+
+    // The callback's error stops serializing and can be taken back.
+    let mut sent = 0;
+    let mut chunked = Chunked::<_, &str, 64>::new(|_: &[u8]| {
+        sent += 1;
+        if sent == 3 {
+            Err("link down")
+        } else {
+            Ok(())
+        }
+    });
+    match serialize_with_flavor(&mut chunked, &state) {
+        Err(ErrorKind::FlushFailed) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(chunked.take_error(), Some("link down"));
+    assert_eq!(chunked.take_error(), None);
+}
+
+#[test]
+fn collect_str_multi_segment() {
+    use bincode::flavors::Chunked;
+    use bincode::{serialize_with_flavor, LengthEncoding};
+    use std::cell::Cell;
+    use std::fmt;
+
+    // Formats as several `write_str` calls of different lengths.
+    struct Segments(&'static [&'static str]);
+
+    impl fmt::Display for Segments {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            for segment in self.0 {
+                f.write_str(segment)?;
+            }
+            write!(f, "-{}", self.0.len())
+        }
+    }
+
+    impl serde::Serialize for Segments {
+        fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+            s.collect_str(self)
+        }
+    }
+
+    let value = Segments(&["ab", "", "cde", "f"]);
+    for &length in &[LengthEncoding::U16, LengthEncoding::U32, LengthEncoding::U64] {
+        let mut config = config();
+        config.length_encoding(length);
+        let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+        config.serialize_into(&mut buf, &value).unwrap();
+        let width = length.width() as usize;
+        assert_eq!(buf.len(), width + 8);
+        assert_eq!(config.serialized_size(&value).unwrap(), buf.len() as u64);
+        let decoded: &str = config.deserialize(&buf).unwrap();
+        assert_eq!(decoded, "abcdef-4");
+    }
+
+    // Nothing is rewound, so a writer that can't seek gets the same bytes.
+    let mut packets: Vec<u8> = Vec::new();
+    {
+        let mut chunked = Chunked::<_, (), 3>::new(|packet: &[u8]| {
+            packets.extend_from_slice(packet);
+            Ok(())
+        });
+        serialize_with_flavor(&mut chunked, &value).unwrap();
+        chunked.finish().unwrap();
+    }
+    assert_eq!(&packets[..], &serialize(&value).unwrap()[..]);
+
+    // A `Display` impl that formats differently the second time can't
+    // produce a prefix that disagrees with the string.
+    struct Unstable(Cell<bool>);
+
+    impl fmt::Display for Unstable {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let longer = self.0.replace(true);
+            f.write_str("abc")?;
+            if longer {
+                f.write_str("d")?;
+            }
+            Ok(())
+        }
+    }
+
+    impl serde::Serialize for Unstable {
+        fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+            s.collect_str(self)
+        }
+    }
+
+    let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+    match serialize_into(&mut buf, &Unstable(Cell::new(false))) {
+        Err(ErrorKind::Fmt(_)) => assert!(buf.is_empty()),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn serialize_with_sink() {
+    use bincode::serialize_with_sink;
+    use serde_bytes::Bytes;
+
+    #[derive(Serialize)]
+    struct Record<'a> {
+        id: u16,
+        tag: Option<u8>,
+        name: &'a str,
+        #[serde(borrow)]
+        payload: &'a Bytes,
+    }
+
+    let payload = [0x5Au8; 1000];
+    let record = Record {
+        id: 3,
+        tag: Some(1),
+        name: "log line",
+        payload: Bytes::new(&payload),
+    };
+
+    let mut chunks: Vec<Vec<u8>> = Vec::new();
+    let n = serialize_with_sink(&record, |chunk: &[u8]| {
+        chunks.push(chunk.to_vec());
+        Ok(())
+    })
+    .unwrap();
+
+    let mut encoded: ArrayVec<[u8; 2048]> = ArrayVec::new();
+    assert_eq!(serialize_into(&mut encoded, &record).unwrap(), n);
+    assert_eq!(&chunks.concat()[..], &encoded[..]);
+
+    // One call per field piece; the kilobyte payload arrives in one piece.
+    let lens: Vec<usize> = chunks.iter().map(|c| c.len()).collect();
+    assert_eq!(lens, [2, 1, 1, 8, 8, 8, 1000]);
+
+    // A failing sink stops serializing with its error.
+    let mut calls = 0;
+    match config().big_endian().serialize_with_sink(&record, |_: &[u8]| {
+        calls += 1;
+        if calls == 4 {
+            Err(ErrorKind::Serde)
+        } else {
+            Ok(())
+        }
+    }) {
+        Err(ErrorKind::Serde) => assert_eq!(calls, 4),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn buffer_serializer_manual_bytes() {
+    use bincode::{serialize_into_with_crc, BufferSerializer};
+
+    let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+    {
+        let mut serializer = BufferSerializer::new(&mut buf);
+        serializer.get_mut().extend([0xB1, 0x7E].iter().cloned());
+        assert_eq!(serializer.serialize_with_crc(&(2u8, -1i16)).unwrap(), 7);
+        assert_eq!(serializer.get_ref().len(), 9);
+        serializer.get_mut().push(0xFF);
+        assert_eq!(serializer.serialize(&1u8).unwrap(), 1);
+    }
+
+    let mut expected: ArrayVec<[u8; 32]> = ArrayVec::new();
+    expected.extend([0xB1, 0x7E].iter().cloned());
+    serialize_into_with_crc(&mut expected, &(2u8, -1i16)).unwrap();
+    expected.push(0xFF);
+    expected.push(1);
+    assert_eq!(&buf[..], &expected[..]);
+}
+
+#[test]
+fn serialize_into_lossy() {
+    use bincode::serialize_into_lossy;
+    use std::fmt;
+
+    struct Shown(&'static str);
+
+    impl fmt::Display for Shown {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            for part in self.0.split(' ') {
+                f.write_str(part)?;
+                f.write_str("_")?;
+            }
+            Ok(())
+        }
+    }
+
+    impl serde::Serialize for Shown {
+        fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+            s.collect_str(self)
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Record {
+        code: u32,
+        message: &'static str,
+        detail: Shown,
+    }
+
+    let record = Record {
+        code: 0xDEAD,
+        message: "stack overflow",
+        detail: Shown("in task main"),
+    };
+    let full = serialize(&record).unwrap();
+    assert_eq!(full.len(), 4 + 8 + 14 + 8 + 13);
+
+    // Room to spare: nothing is lost.
+    let mut roomy: ArrayVec<[u8; 64]> = ArrayVec::new();
+    assert_eq!(serialize_into_lossy(&mut roomy, &record).unwrap(), (47, false));
+    assert_eq!(&roomy[..], &full[..]);
+
+    // Every cut, including the middle of both strings, leaves a prefix of
+    // the full encoding that fills the buffer.
+    for &(cut, place) in &[(20usize, "message"), (40, "detail"), (4, "code end"), (2, "code")] {
+        let mut buf: ArrayVec<[u8; 48]> = ArrayVec::new();
+        buf.extend(std::iter::repeat(0xAA).take(48 - cut));
+        match serialize_into_lossy(&mut buf, &record) {
+            Ok((n, true)) => assert_eq!(n, cut, "{}", place),
+            other => panic!("{}: unexpected {:?}", place, other),
+        }
+        assert!(buf.is_full());
+        assert_eq!(&buf[48 - cut..], &full[..cut], "{}", place);
+    }
+
+    // A sequence of unknown length can't have its prefix filled in once cut
+    // off, so it isn't written at all.
+    struct Unsized(usize);
+
+    impl serde::Serialize for Unsized {
+        fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+            use serde::ser::SerializeSeq;
+
+            let mut seq = s.serialize_seq(None)?;
+            for _ in 0..self.0 {
+                seq.serialize_element(&7u8)?;
+            }
+            seq.end()
+        }
+    }
+
+    let mut buf: ArrayVec<[u8; 12]> = ArrayVec::new();
+    match serialize_into_lossy(&mut buf, &Unsized(10)) {
+        Err(ErrorKind::SequenceMustHaveLength) => assert!(buf.is_empty()),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Other errors still roll back.
+    let mut buf: ArrayVec<[u8; 4]> = ArrayVec::new();
+    buf.push(1);
+    match config().limit(2).serialize_into_lossy(&mut buf, &record) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => assert_eq!(&buf[..], &[1]),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn serialize_into_reports_needed() {
+    #[derive(Serialize)]
+    struct Log<'a> {
+        level: u8,
+        lines: Vec<&'a str>,
+    }
+
+    let log = Log {
+        level: 2,
+        lines: vec!["boot", "link up", "a considerably longer line than the others"],
+    };
+    let needed = serialized_size(&log).unwrap();
+    assert_eq!(needed, 1 + 8 + (8 + 4) + (8 + 7) + (8 + 42));
+
+    // Runs out early in the value but still reports the full size.
+    let mut buf: ArrayVec<[u8; 40]> = ArrayVec::new();
+    buf.extend(b"hdr".iter().cloned());
+    match serialize_into(&mut buf, &log) {
+        Err(ErrorKind::BufferTooSmall { needed: n, available: 37, written: 36 }) => assert_eq!(n, needed),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(&buf[..], b"hdr");
+
+    // The size is measured in the configured encoding.
+    let mut config = config();
+    config.length_encoding(bincode::LengthEncoding::U16).limit(200);
+    let mut small: ArrayVec<[u8; 8]> = ArrayVec::new();
+    match config.serialize_into(&mut small, &log) {
+        Err(ErrorKind::BufferTooSmall {
+            needed: 62,
+            available: 8,
+            written: 5,
+        }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn buffer_serializer_raw_bytes() {
+    use bincode::BufferSerializer;
+
+    let mut buf: ArrayVec<[u8; 16]> = ArrayVec::new();
+    let mut config = config();
+    config.big_endian().limit(2);
+    {
+        let mut serializer = BufferSerializer::with_config(&mut buf, config);
+        serializer.write_raw(&[0xB1, 0x7E, 0, 0, 0, 1]).unwrap();
+        assert_eq!(serializer.serialize(&0x0203u16).unwrap(), 2);
+        // Raw bytes are outside of the size limit.
+        serializer.write_u32_raw(0x04050607).unwrap();
+        match serializer.write_u64_raw(0) {
+            Err(ErrorKind::BufferTooSmall {
+                needed: 8,
+                available: 4,
+                written: 0,
+            }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        serializer.write_u16_raw(0x0809).unwrap();
+    }
+    assert_eq!(&buf[..], &[0xB1, 0x7E, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+}
+
+#[test]
+fn serializer_raw_bytes_align() {
+    use bincode::{DefaultOptions, OptionsExt, Serializer, SizeChecker};
+    use serde::Serialize;
+
+    let mut aligned = config();
+    aligned.align(true).big_endian();
+
+    // The raw bytes count towards the padding in front of the next number.
+    let mut buf = Buffer::new();
+    {
+        let mut s = Serializer::from_array_vec(&mut buf, DefaultOptions::from_config(&aligned).with_big_endian());
+        s.write_raw(&[0xB1]).unwrap();
+        s.write_u16_raw(0x0203).unwrap();
+        7u32.serialize(&mut s).unwrap();
+    }
+    assert_eq!(&buf[..], &[0xB1, 2, 3, 0, 0, 0, 0, 7]);
+
+    let mut checker = SizeChecker::new(DefaultOptions::from_config(&aligned).with_limit(7));
+    checker.add_raw(3).unwrap();
+    7u32.serialize(&mut checker).unwrap_err();
+    let mut checker = SizeChecker::new(DefaultOptions::from_config(&aligned).with_limit(8));
+    checker.add_raw(3).unwrap();
+    7u32.serialize(&mut checker).unwrap();
+    assert_eq!(checker.size(), buf.len() as u64);
+
+    // Bytes written through get_mut bypass the position.
+    let mut buf = Buffer::new();
+    {
+        let mut s = Serializer::from_array_vec(&mut buf, DefaultOptions::from_config(&aligned).with_big_endian());
+        s.get_mut().push(0xB1);
+        assert_eq!(s.get_ref().len(), 1);
+        7u32.serialize(&mut s).unwrap();
+    }
+    assert_eq!(&buf[..], &[0xB1, 0, 0, 0, 7]);
+}
+
+#[test]
+fn chained_slice_reader() {
+    use bincode::{deserialize_dyn, deserialize_from_custom, ChainedSliceReader, DynReader};
+
+    let frame = (0x0102_0304_0506_0708u64, "ring bü".to_string(), 0xBEEFu16);
+    let encoded = serialize(&frame).unwrap();
+    let text = 8 + 8..8 + 8 + frame.1.len();
+
+    // Every split point, so both the u64 and every byte of the string,
+    // including the middle of 'ü', land on the seam.
+    for split in 0..encoded.len() + 1 {
+        let (head, tail) = encoded.split_at(split);
+        let mut reader = ChainedSliceReader::new(head, tail);
+        let decoded: (u64, String, u16) = deserialize_from_custom(&mut reader).unwrap();
+        assert_eq!(decoded, frame);
+        assert_eq!(reader.remaining(), 0);
+
+        let mut reader = DynReader::Chained(ChainedSliceReader::new(head, tail));
+        let decoded: (u64, String, u16) = deserialize_dyn(&mut reader).unwrap();
+        assert_eq!(decoded, frame);
+
+        // Strings that don't cross the seam are still borrowed.
+        if split <= text.start || split >= text.end {
+            let decoded: (u64, &str, u16) = deserialize_from_custom(ChainedSliceReader::new(head, tail)).unwrap();
+            assert_eq!(decoded.1, frame.1);
+            let range = encoded.as_ptr() as usize..encoded.as_ptr() as usize + encoded.len();
+            assert!(range.contains(&(decoded.1.as_ptr() as usize)));
+        }
+    }
+
+    // Strings that cross the seam are copied into a bounded scratch buffer.
+    let long = "x".repeat(65);
+    let encoded = serialize(&long).unwrap();
+    let (head, tail) = encoded.split_at(12);
+    match deserialize_from_custom::<_, String>(ChainedSliceReader::new(head, tail)) {
+        Err(ErrorKind::SeamTooLong { len: 65, max: 64 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    let encoded = serialize(&long[..64]).unwrap();
+    let (head, tail) = encoded.split_at(12);
+    let decoded: String = deserialize_from_custom(ChainedSliceReader::new(head, tail)).unwrap();
+    assert_eq!(decoded, &long[..64]);
+
+    match deserialize_from_custom::<_, u64>(ChainedSliceReader::new(&[1, 2, 3], &[4, 5])) {
+        Err(ErrorKind::UnexpectedEof { remaining: 5, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn buf_reader() {
+    use bincode::bytes::{Buf, Bytes};
+    use bincode::{deserialize_from_custom, BufReader};
+    use serde_bytes::ByteBuf;
+    use std::collections::VecDeque;
+
+    // A `Buf` made of several small `Bytes`, like frames out of a network stack.
+    struct Segments(VecDeque<Bytes>);
+
+    impl Buf for Segments {
+        fn remaining(&self) -> usize {
+            self.0.iter().map(|b| b.len()).sum()
+        }
+
+        fn chunk(&self) -> &[u8] {
+            self.0.front().map_or(&[], |b| &b[..])
+        }
+
+        fn advance(&mut self, mut cnt: usize) {
+            while cnt > 0 {
+                let front = self.0.front_mut().unwrap();
+                let n = std::cmp::min(cnt, front.len());
+                front.advance(n);
+                cnt -= n;
+                if front.is_empty() {
+                    self.0.pop_front();
+                }
+            }
+        }
+    }
+
+    let value = (
+        0x0102_0304_0506_0708u64,
+        "chunked bü".to_string(),
+        ByteBuf::from(vec![9u8; 11]),
+        'ü',
+    );
+    let mut encoded = serialize(&value).unwrap().to_vec();
+    encoded.extend_from_slice(b"next");
+
+    for size in 1..encoded.len() + 1 {
+        let segments = encoded.chunks(size).map(Bytes::copy_from_slice).collect();
+        let mut reader = BufReader::new(Segments(segments));
+        let decoded: (u64, String, ByteBuf, char) = deserialize_from_custom(&mut reader).unwrap();
+        assert_eq!(decoded, value, "chunk size {}", size);
+        assert_eq!(reader.into_inner().copy_to_bytes(4), &b"next"[..]);
+    }
+
+    let segments = encoded[..20].chunks(3).map(Bytes::copy_from_slice).collect();
+    match deserialize_from_custom::<_, (u64, String)>(BufReader::new(Segments(segments))) {
+        Err(ErrorKind::UnexpectedEof { remaining: 4, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+#[test]
+fn embedded_io_reader() {
+    use bincode::embedded_io::{ErrorKind as IoKind, ErrorType, Read};
+    use bincode::{deserialize_from_custom, EmbeddedIoReader};
+    use serde_bytes::ByteBuf;
+
+    // Hands out at most four bytes per call, and fails once `fail_at` bytes
+    // have been read.
+    struct Flash<'a> {
+        data: &'a [u8],
+        pos: usize,
+        fail_at: usize,
+    }
+
+    impl<'a> ErrorType for Flash<'a> {
+        type Error = IoKind;
+    }
+
+    impl<'a> Read for Flash<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::result::Result<usize, IoKind> {
+            if self.pos >= self.fail_at {
+                return Err(IoKind::Other);
+            }
+            let n = buf.len().min(4).min(self.data.len() - self.pos);
+            buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Settings {
+        id: u32,
+        name: String,
+        key: ByteBuf,
+        mode: char,
+    }
+
+    let settings = Settings {
+        id: 0xC0FFEE,
+        name: "sensör".to_string(),
+        key: ByteBuf::from(vec![0xAA; 6]),
+        mode: 'ü',
+    };
+    let mut encoded = serialize(&settings).unwrap();
+    let len = encoded.len();
+    encoded.extend(b"rest".iter().cloned());
+
+    let flash = Flash {
+        data: &encoded,
+        pos: 0,
+        fail_at: usize::MAX,
+    };
+    let mut reader = EmbeddedIoReader::<_, [u8; 16]>::new(flash);
+    let decoded: Settings = deserialize_from_custom(&mut reader).unwrap();
+    assert_eq!(decoded, settings);
+    // Nothing past the value was read.
+    assert_eq!(reader.into_inner().pos, len);
+
+    let flash = Flash {
+        data: &encoded,
+        pos: 0,
+        fail_at: usize::MAX,
+    };
+    match deserialize_from_custom::<_, Settings>(EmbeddedIoReader::<_, [u8; 4]>::new(flash)) {
+        Err(ErrorKind::ScratchTooSmall { len: 7, max: 4 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let flash = Flash {
+        data: &encoded,
+        pos: 0,
+        fail_at: 12,
+    };
+    match deserialize_from_custom::<_, Settings>(EmbeddedIoReader::<_, [u8; 16]>::new(flash)) {
+        Err(ErrorKind::EmbeddedIo(IoKind::Other)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let flash = Flash {
+        data: &encoded[..14],
+        pos: 0,
+        fail_at: usize::MAX,
+    };
+    match deserialize_from_custom::<_, Settings>(EmbeddedIoReader::<_, [u8; 16]>::new(flash)) {
+        Err(ErrorKind::UnexpectedEof { .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn deserialize_with_crc() {
+    use bincode::{deserialize_from_custom, deserialize_with_crc, serialize_into_with_crc, CrcReader, SliceReader};
+    use serde_bytes::{ByteBuf, Bytes};
+
+    let value = (0xDEAD_BEEFu32, "log", Bytes::new(&[1, 2, 3]), 7u16);
+    let mut encoded = Buffer::new();
+    serialize_into_with_crc(&mut encoded, &value).unwrap();
+    let decoded: (u32, &str, &Bytes, u16) = deserialize_with_crc(&encoded).unwrap();
+    assert_eq!(decoded, value);
+
+    // The reader sees strings and byte slices too, whether borrowed or copied.
+    let plain = serialize(&value).unwrap();
+    let mut reader = CrcReader::new(SliceReader::new(&plain));
+    let _: (u32, String, ByteBuf, u16) = deserialize_from_custom(&mut reader).unwrap();
+    assert_eq!(&reader.crc().to_le_bytes()[..], &encoded[plain.len()..]);
+
+    let text = 4 + 8..4 + 8 + 3;
+    let bytes = text.end + 8..text.end + 8 + 3;
+    for offset in 0..encoded.len() {
+        for bit in 0..8 {
+            let mut corrupt = encoded.clone();
+            corrupt[offset] ^= 1 << bit;
+            let result = deserialize_with_crc::<(u32, &str, &Bytes, u16)>(&corrupt);
+            // Flips that leave the value decodable must be caught by the checksum.
+            let decodable = offset < 4 || (text.contains(&offset) && bit < 7) || offset >= bytes.start;
+            match result {
+                Err(ErrorKind::ChecksumMismatch { expected, actual }) => assert_ne!(expected, actual),
+                Err(_) if !decodable => {}
+                other => panic!("offset {} bit {}: unexpected {:?}", offset, bit, other),
+            }
+        }
+    }
+
+    // Under another configuration, the checksum is stored in its endianness.
+    let mut config = config();
+    config.big_endian();
+    let mut encoded = Buffer::new();
+    config.serialize_into_with_crc(&mut encoded, &value).unwrap();
+    let decoded: (u32, &str, &Bytes, u16) = config.deserialize_with_crc(&encoded).unwrap();
+    assert_eq!(decoded, value);
+
+    match config.deserialize_with_crc::<(u32, &str, &Bytes, u16)>(&encoded[..encoded.len() - 1]) {
+        Err(ErrorKind::UnexpectedEof { remaining: 3, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn bytes_read() {
+    use bincode::{deserialize_from_custom, deserialize_prefix, SliceReader};
+
+    let value = (0x0102u16, "ring", Some(9u8));
+    let mut encoded = serialize(&value).unwrap();
+    let len = encoded.len();
+    encoded.extend(b"next".iter().cloned());
+
+    let (decoded, read): ((u16, &str, Option<u8>), usize) = deserialize_prefix(&encoded).unwrap();
+    assert_eq!(decoded, value);
+    assert_eq!(read, len);
+
+    let mut config = config();
+    config.big_endian().length_encoding(bincode::LengthEncoding::U16);
+    let mut encoded = Buffer::new();
+    config.serialize_into(&mut encoded, &value).unwrap();
+    let (_, read) = config.deserialize_prefix::<(u16, &str, Option<u8>)>(&encoded).unwrap();
+    assert_eq!(read, 2 + 2 + 4 + 2);
+
+    // After a borrowed string.
+    let encoded = serialize(&value).unwrap();
+    let mut reader = SliceReader::new(&encoded);
+    let _: (u16, &str) = deserialize_from_custom(&mut reader).unwrap();
+    assert_eq!(reader.bytes_read(), 2 + 8 + 4);
+
+    // Running out of input doesn't consume the partial read.
+    let mut reader = SliceReader::new(&encoded[..12]);
+    match deserialize_from_custom::<_, (u16, &str)>(&mut reader) {
+        Err(ErrorKind::UnexpectedEof { remaining: 2, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(reader.bytes_read(), 10);
+
+    // A string that fails to decode has been read all the same.
+    let mut invalid = encoded.clone();
+    invalid[10] = 0xFF;
+    let mut reader = SliceReader::new(&invalid);
+    match deserialize_from_custom::<_, (u16, &str)>(&mut reader) {
+        Err(ErrorKind::InvalidUtf8Encoding(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(reader.bytes_read(), 14);
+}
+
+#[test]
+fn single_byte_reads() {
+    use bincode::{BincodeRead, SliceReader};
+
+    assert_eq!(deserialize::<(u8, i8, bool)>(&[0xFE, 0xFE, 1]).unwrap(), (0xFE, -2, true));
+
+    // Through the deserializer.
+    match deserialize::<u8>(&[]) {
+        Err(ErrorKind::UnexpectedEof { remaining: 0, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    match deserialize::<i8>(&[]) {
+        Err(ErrorKind::UnexpectedEof { remaining: 0, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Straight from the reader.
+    let mut reader = SliceReader::new(&[0x80]);
+    assert_eq!(reader.read_i8().unwrap(), -128);
+    match reader.read_u8() {
+        Err(ErrorKind::UnexpectedEof { remaining: 0, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    match reader.read_i8() {
+        Err(ErrorKind::UnexpectedEof { remaining: 0, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(reader.remaining(), 0);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn byte_buf_round_trip() {
+    use bincode::{deserialize_from_custom, BincodeRead, SliceReader};
+    use serde_bytes::ByteBuf;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Blob {
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+        extra: ByteBuf,
+    }
+
+    let blob = Blob {
+        data: (0..200).map(|i| i as u8).collect(),
+        extra: ByteBuf::from(vec![]),
+    };
+    let encoded = serialize(&blob).unwrap();
+    assert_eq!(deserialize::<Blob>(&encoded).unwrap(), blob);
+
+    let mut config = config();
+    config.big_endian().length_encoding(bincode::LengthEncoding::U16);
+    let mut encoded = Buffer::new();
+    config.serialize_into(&mut encoded, &blob).unwrap();
+    assert_eq!(config.deserialize::<Blob>(&encoded).unwrap(), blob);
+
+    // The reader hands over the bytes, consuming them.
+    let mut reader = SliceReader::new(&[1, 2, 3, 4]);
+    assert_eq!(reader.get_byte_buffer(3).unwrap(), [1, 2, 3]);
+    match reader.get_byte_buffer(2) {
+        Err(ErrorKind::UnexpectedEof { remaining: 1, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    let rest: u8 = deserialize_from_custom(&mut reader).unwrap();
+    assert_eq!(rest, 4);
+
+    let encoded = serialize(&ByteBuf::from(vec![7; 3])).unwrap();
+    match deserialize::<ByteBuf>(&encoded[..10]) {
+        Err(ErrorKind::UnexpectedEof { remaining: 2, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn byte_buf_from_io_reader() {
+    use bincode::deserialize_from;
+    use serde_bytes::ByteBuf;
+
+    let values = vec![ByteBuf::from(vec![1, 2, 3]), ByteBuf::from(vec![]), ByteBuf::from(vec![9; 40])];
+    let encoded = serialize(&values).unwrap();
+    let decoded: Vec<ByteBuf> = deserialize_from(&encoded[..]).unwrap();
+    assert_eq!(decoded, values);
+}
+
+#[test]
+fn peek_tag() {
+    use bincode::{deserialize_from_custom, peek_tag, SliceReader};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Message<'a> {
+        Ping(u16),
+        Log(&'a str),
+        Reading { id: u8, value: i32 },
+    }
+
+    for message in &[
+        Message::Ping(7),
+        Message::Log("boot"),
+        Message::Reading { id: 3, value: -40 },
+    ] {
+        for &big in &[false, true] {
+            let mut config = config();
+            if big {
+                config.big_endian();
+            }
+            let mut encoded = Buffer::new();
+            config.serialize_into(&mut encoded, message).unwrap();
+
+            let tag = config.peek_tag(&encoded).unwrap();
+            let expected = match *message {
+                Message::Ping(_) => 0,
+                Message::Log(_) => 1,
+                Message::Reading { .. } => 2,
+            };
+            assert_eq!(tag, expected);
+
+            // Peeking leaves the input as it was.
+            let decoded: Message = config.deserialize(&encoded).unwrap();
+            assert_eq!(&decoded, message);
+        }
+    }
+
+    let encoded = serialize(&Message::Log("boot")).unwrap();
+    let mut reader = SliceReader::new(&encoded);
+    assert_eq!(reader.peek(4).unwrap(), &[1, 0, 0, 0]);
+    assert_eq!(reader.peek_u32::<byteorder::BigEndian>().unwrap(), 1 << 24);
+    let decoded: Message = deserialize_from_custom(&mut reader).unwrap();
+    assert_eq!(decoded, Message::Log("boot"));
+
+    // Peeking past the end fails without consuming anything.
+    let reader = SliceReader::new(&encoded[..3]);
+    match reader.peek_u32::<byteorder::LittleEndian>() {
+        Err(ErrorKind::UnexpectedEof { remaining: 3, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(reader.bytes_read(), 0);
+    assert_eq!(reader.peek(3).unwrap(), &encoded[..3]);
+    match peek_tag(&encoded[..3]) {
+        Err(ErrorKind::UnexpectedEof { remaining: 3, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn scratch_reader() {
+    use bincode::{deserialize_from_custom, ScratchReader};
+    use serde_bytes::ByteBuf;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Packet {
+        seq: u64,
+        name: String,
+        body: ByteBuf,
+        flag: (bool, char, i16),
+    }
+
+    let packet = Packet {
+        seq: 0x0102_0304_0506_0708,
+        name: "fifo bü".to_string(),
+        body: ByteBuf::from(vec![5; 9]),
+        flag: (true, 'ü', -300),
+    };
+    let mut encoded = serialize(&packet).unwrap();
+    encoded.extend(b"more".iter().cloned());
+
+    // One byte per call, so every read crosses a refill.
+    for &size in &[9, 10, 16, 64] {
+        let mut source = encoded.iter();
+        let mut scratch = vec![0; size];
+        let mut calls = 0;
+        let decoded: Packet = {
+            let fill = |buf: &mut [u8]| {
+                calls += 1;
+                match source.next() {
+                    Some(&byte) => {
+                        buf[0] = byte;
+                        Ok(1)
+                    }
+                    None => Ok(0),
+                }
+            };
+            deserialize_from_custom(ScratchReader::new(&mut scratch, fill)).unwrap()
+        };
+        assert_eq!(decoded, packet);
+        assert_eq!(calls, encoded.len() - 4);
+        assert_eq!(source.as_slice(), b"more");
+    }
+
+    // Larger fills leave the rest buffered.
+    let mut chunks = encoded.chunks(5);
+    let mut scratch = [0; 16];
+    let mut reader = ScratchReader::new(&mut scratch, |buf: &mut [u8]| {
+        Ok(match chunks.next() {
+            Some(chunk) => {
+                let n = chunk.len().min(buf.len());
+                buf[..n].copy_from_slice(&chunk[..n]);
+                assert_eq!(n, chunk.len());
+                n
+            }
+            None => 0,
+        })
+    });
+    let decoded: Packet = deserialize_from_custom(&mut reader).unwrap();
+    assert_eq!(decoded, packet);
+    assert_eq!(reader.buffered(), b"more");
+
+    // A string longer than the scratch buffer fails without consuming it.
+    let mut source = encoded[8..].iter();
+    let mut scratch = [0; 7];
+    let mut reader = ScratchReader::new(&mut scratch, |buf: &mut [u8]| {
+        Ok(match source.next() {
+            Some(&byte) => {
+                buf[0] = byte;
+                1
+            }
+            None => 0,
+        })
+    });
+    match deserialize_from_custom::<_, String>(&mut reader) {
+        Err(ErrorKind::ScratchTooSmall { len: 8, max: 7 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(reader.buffered().is_empty());
+    let mut prefix = [0; 4];
+    bincode::BincodeRead::read_exact(&mut reader, &mut prefix).unwrap();
+    assert_eq!(&prefix, b"fifo");
+
+    let mut source = encoded[..12].iter();
+    let mut scratch = [0; 16];
+    let reader = ScratchReader::new(&mut scratch, |buf: &mut [u8]| {
+        Ok(match source.next() {
+            Some(&byte) => {
+                buf[0] = byte;
+                1
+            }
+            None => 0,
+        })
+    });
+    match deserialize_from_custom::<_, Packet>(reader) {
+        Err(ErrorKind::UnexpectedEof { .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // A callback claiming more than it was given room for is caught, instead
+    // of buffering bytes past the end of the scratch buffer.
+    let mut scratch = [0; 16];
+    let mut calls = 0;
+    let reader = ScratchReader::new(&mut scratch, |buf: &mut [u8]| {
+        calls += 1;
+        buf[0] = 1;
+        Ok(if calls == 1 { 4 } else { buf.len() + 1 })
+    });
+    match deserialize_from_custom::<_, u64>(reader) {
+        Err(ErrorKind::FillOverrun { written: 17, room: 16 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn lossy_utf8() {
+    // "ab", a stray continuation byte, "c", then a trailing u16.
+    let encoded = [4, 0, 0, 0, 0, 0, 0, 0, b'a', b'b', 0x80, b'c', 7, 0];
+
+    // Strict decoding stays the default.
+    match config().deserialize::<(String, u16)>(&encoded) {
+        Err(ErrorKind::InvalidUtf8Encoding(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // The whole string is consumed either way, so the next field lines up.
+    let (s, n): (String, u16) = config().lossy_utf8(true).deserialize(&encoded).unwrap();
+    if cfg!(feature = "alloc") {
+        assert_eq!(s, "ab\u{FFFD}c");
+    } else {
+        assert_eq!(s, "ab");
+    }
+    assert_eq!(n, 7);
+
+    // Valid strings still borrow from the input.
+    let encoded = [2, 0, 0, 0, 0, 0, 0, 0, b'o', b'k'];
+    let s: &str = config().lossy_utf8(true).deserialize(&encoded).unwrap();
+    assert_eq!(s, "ok");
+}
+
+#[test]
+fn forward_read_exact() {
+    use bincode::{BincodeRead, ChainedSliceReader, SliceReader};
+
+    let mut encoded = [0u8; 34];
+    for (i, byte) in encoded.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+
+    let mut key = [0u8; 32];
+    let mut reader = SliceReader::new(&encoded);
+    reader.forward_read_exact(&mut key).unwrap();
+    assert_eq!(&key[..], &encoded[..32]);
+    assert_eq!(reader.read_u16::<byteorder::LittleEndian>().unwrap(), 0x2120);
+
+    // Through a reference, and across the seam of a chained reader.
+    let mut key = [0u8; 32];
+    let (head, tail) = encoded.split_at(10);
+    let mut reader = ChainedSliceReader::new(head, tail);
+    (&mut reader).forward_read_exact(&mut key).unwrap();
+    assert_eq!(&key[..], &encoded[..32]);
+    assert_eq!(reader.remaining(), 2);
+
+    // A short input reports EOF.
+    let mut reader = SliceReader::new(&encoded[..31]);
+    match reader.forward_read_exact(&mut key) {
+        Err(ErrorKind::UnexpectedEof { .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn deserialize_through_slice_reader_ref() {
+    use bincode::{deserialize_from_custom, BincodeRead, SliceReader};
+
+    let encoded = [0xAB, 2, 0, 0, 0, 0, 0, 0, 0, b'h', b'i', 0xCD];
+    let mut reader = SliceReader::new(&encoded);
+
+    // Manual header, serde in the middle, manual trailer, one cursor.
+    assert_eq!(reader.read_u8().unwrap(), 0xAB);
+    let name: &str = deserialize_from_custom(&mut reader).unwrap();
+    assert_eq!(name, "hi");
+    assert_eq!(reader.bytes_read(), 11);
+    assert_eq!(reader.read_u8().unwrap(), 0xCD);
+    assert_eq!(reader.remaining(), 0);
+}
+
+#[test]
+fn object_read() {
+    use bincode::{deserialize_from_custom, ChainedSliceReader, ObjectRead, ScratchReader, SliceReader};
+
+    let frame = (7u16, "radio".to_string(), -1i32);
+    let encoded = serialize(&frame).unwrap();
+
+    // Sources picked at runtime behind one trait object type.
+    let (head, tail) = encoded.split_at(12);
+    let mut slice = SliceReader::new(&encoded);
+    let mut chained = ChainedSliceReader::new(head, tail);
+    let mut sources: Vec<&mut dyn ObjectRead> = vec![&mut slice, &mut chained];
+    for source in sources.iter_mut() {
+        let decoded: (u16, String, i32) = deserialize_from_custom(&mut **source).unwrap();
+        assert_eq!(decoded, frame);
+    }
+
+    // Borrowed strings still borrow through the trait object.
+    let mut slice = SliceReader::new(&encoded);
+    let decoded: (u16, &str, i32) = {
+        let source: &mut dyn ObjectRead = &mut slice;
+        deserialize_from_custom(source).unwrap()
+    };
+    let range = encoded.as_ptr() as usize..encoded.as_ptr() as usize + encoded.len();
+    assert!(range.contains(&(decoded.1.as_ptr() as usize)));
+
+    // Readers that copy work too, as long as nothing borrows.
+    let mut source = encoded.iter();
+    let mut scratch = [0; 16];
+    let mut reader = ScratchReader::new(&mut scratch, |buf: &mut [u8]| {
+        Ok(match source.next() {
+            Some(&byte) => {
+                buf[0] = byte;
+                1
+            }
+            None => 0,
+        })
+    });
+    let source: &mut dyn ObjectRead<'static> = &mut reader;
+    let decoded: (u16, String, i32) = deserialize_from_custom(source).unwrap();
+    assert_eq!(decoded, frame);
+
+    // Invalid UTF-8 is still caught.
+    let mut bad = SliceReader::new(&[1, 0, 0, 0, 0, 0, 0, 0, 0xFF]);
+    let source: &mut dyn ObjectRead = &mut bad;
+    match deserialize_from_custom::<_, String>(source) {
+        Err(ErrorKind::InvalidUtf8Encoding(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn slice_reader_rollback() {
+    use bincode::{deserialize_from_custom, BincodeRead, SliceReader};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Wide {
+        id: u32,
+        name: String,
+        extra: u64,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Narrow {
+        id: u32,
+        name: String,
+    }
+
+    let narrow = Narrow {
+        id: 3,
+        name: "short".to_string(),
+    };
+    let mut encoded = serialize(&narrow).unwrap();
+    encoded.push(0xEE);
+    let limit = (encoded.len() - 1) as u64;
+
+    let mut reader = SliceReader::new(&encoded);
+    let checkpoint = reader.checkpoint();
+
+    // Speculatively try the wider type; it runs out of input partway through
+    // `extra` after consuming the whole `Narrow` and charging it to the limit.
+    match config().limit(limit).deserialize_from_custom::<_, Wide>(&mut reader) {
+        Err(ErrorKind::UnexpectedEof { .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(reader.bytes_read(), limit as usize);
+
+    // After rolling back, the fallback decodes from the start under the full
+    // limit again.
+    reader.rollback(checkpoint).unwrap();
+    assert_eq!(reader.bytes_read(), 0);
+    let decoded: Narrow = config().limit(limit).deserialize_from_custom(&mut reader).unwrap();
+    assert_eq!(decoded, narrow);
+    assert_eq!(reader.read_u8().unwrap(), 0xEE);
+
+    // Checkpoints taken partway through can be returned to more than once.
+    reader.rollback(checkpoint).unwrap();
+    reader.read_exact(&mut [0; 4]).unwrap();
+    let middle = reader.checkpoint();
+    for _ in 0..2 {
+        let name: String = deserialize_from_custom(&mut reader).unwrap();
+        assert_eq!(name, "short");
+        reader.rollback(middle).unwrap();
+    }
+
+    // Checkpoints of other inputs are refused, even of a part of this one.
+    let other = SliceReader::new(&encoded[..4]).checkpoint();
+    let copy = encoded.clone();
+    let elsewhere = SliceReader::new(&copy).checkpoint();
+    let mut tail = SliceReader::new(&encoded[4..]);
+    for &foreign in &[other, elsewhere, checkpoint] {
+        match tail.rollback(foreign) {
+            Err(ErrorKind::ForeignCheckpoint) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(tail.bytes_read(), 0);
+    }
+    assert!(reader.rollback(elsewhere).is_err());
+    assert_eq!(reader.bytes_read(), 4);
+}
+
+#[test]
+fn deserializer_checkpoint() {
+    use bincode::{DefaultOptions, Deserializer, OptionsExt};
+    use serde::Deserialize;
+
+    let mut aligned = config();
+    aligned.align(true);
+    let encoded = aligned.serialize::<[u8; 16], _>(&(9u8, 7u32)).unwrap();
+    assert_eq!(&encoded[..], &[9, 0, 0, 0, 7, 0, 0, 0]);
+
+    let options = DefaultOptions::from_config(&aligned).with_limit(8);
+    let mut d = Deserializer::from_slice(&encoded, options);
+    assert_eq!(u8::deserialize(&mut d).unwrap(), 9);
+    let marker = d.checkpoint();
+
+    // The wider guess charges its padding to the limit and moves the padding
+    // position on before it runs out of input.
+    match u64::deserialize(&mut d) {
+        Err(ErrorKind::UnexpectedEof { .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(d.bytes_read(), 8);
+
+    // Rolled back, the padding and the limit are what they were at the marker.
+    d.rollback(marker.clone()).unwrap();
+    assert_eq!(d.bytes_read(), 1);
+    assert_eq!(u32::deserialize(&mut d).unwrap(), 7);
+    assert_eq!(d.end().unwrap(), 8);
+
+    // Markers of other inputs are refused.
+    let copy = encoded.clone();
+    let mut other = Deserializer::from_slice(&copy, DefaultOptions::from_config(&aligned).with_limit(8));
+    match other.rollback(marker) {
+        Err(ErrorKind::ForeignCheckpoint) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(other.bytes_read(), 0);
+}
+
+#[test]
+fn stream_deserializer() {
+    use bincode::stream::{write_frame, write_frame_with_options, StreamDeserializer};
+    use bincode::{DefaultOptions, LengthEncoding, OptionsExt};
+
+    let messages = [(1u8, "one".to_string()), (2, String::new()), (3, "three".to_string())];
+    let mut config = config();
+    config.length_encoding(LengthEncoding::U16);
+    let mut wire: ArrayVec<[u8; 64]> = ArrayVec::new();
+    for message in &messages {
+        let mut writer = &mut wire;
+        write_frame(&mut writer, message, &config).unwrap();
+    }
+    // 2 bytes of frame length, 1 byte of id, 2 bytes of string length.
+    assert_eq!(wire.len(), 3 * 5 + 3 + 5);
+
+    // Any chunking, including one and a half messages at a time.
+    for size in 1..wire.len() + 1 {
+        let mut config = bincode::config();
+        config.length_encoding(LengthEncoding::U16);
+        let mut stream: StreamDeserializer<(u8, String), [u8; 16]> = StreamDeserializer::with_config(config);
+        let mut decoded = Vec::new();
+        for chunk in wire.chunks(size) {
+            let mut chunk = chunk;
+            while !chunk.is_empty() {
+                let taken = stream.push(chunk);
+                chunk = &chunk[taken..];
+                while let Some(message) = stream.try_next().unwrap() {
+                    decoded.push(message);
+                }
+            }
+        }
+        assert_eq!(&decoded[..], &messages[..]);
+        assert!(stream.buffered().is_empty());
+    }
+
+    // A frame that doesn't decode is dropped and the stream carries on.
+    let mut stream: StreamDeserializer<bool, [u8; 32]> = StreamDeserializer::new();
+    stream.push(&[1, 0, 0, 0, 0, 0, 0, 0, 7]);
+    stream.push(&[1, 0, 0, 0, 0, 0, 0, 0, 1]);
+    match stream.try_next() {
+        Err(ErrorKind::InvalidBoolEncoding(7)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(stream.try_next().unwrap(), Some(true));
+    assert_eq!(stream.try_next().unwrap(), None);
+
+    // So is one with bytes left over after the value.
+    stream.push(&[2, 0, 0, 0, 0, 0, 0, 0, 1, 1]);
+    match stream.try_next() {
+        Err(ErrorKind::TrailingBytes(1)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(stream.buffered().is_empty());
+
+    // A frame that can never fit is reported once its length is known.
+    let mut stream: StreamDeserializer<u64, [u8; 12]> = StreamDeserializer::new();
+    stream.push(&[5, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(stream.try_next().unwrap(), None);
+    stream.push(&[0]);
+    match stream.try_next() {
+        Err(ErrorKind::ScratchTooSmall { len: 5, max: 4 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(stream.push(&[0; 8]), 4);
+
+    // Skipping the frame by hand gets the stream going again.
+    assert_eq!(stream.skip(1 + 8 + 5), 12);
+    assert!(stream.buffered().is_empty());
+    stream.push(&[8, 0, 0, 0, 0, 0, 0, 0, 9, 0]);
+    stream.clear();
+    assert!(stream.buffered().is_empty());
+
+    let mut stream: StreamDeserializer<u8, [u8; 16]> = Default::default();
+    stream.push(&[1, 0, 0, 0, 0, 0, 0, 0, 9]);
+    assert_eq!(stream.try_next().unwrap(), Some(9));
+
+    // Options chosen by type frame and decode like the configuration.
+    let mut big = config;
+    big.big_endian();
+    let options = || DefaultOptions::from_config(&big).with_big_endian();
+    let mut wire: ArrayVec<[u8; 16]> = ArrayVec::new();
+    {
+        let mut writer = &mut wire;
+        assert_eq!(write_frame_with_options(&mut writer, &(7u8, "hi"), options()).unwrap(), 7);
+        write_frame(&mut writer, &(8u8, "ho"), &big).unwrap();
+    }
+    assert_eq!(&wire[..], &[0, 5, 7, 0, 2, b'h', b'i', 0, 5, 8, 0, 2, b'h', b'o']);
+    let mut stream: StreamDeserializer<(u8, String), [u8; 16]> = StreamDeserializer::new();
+    stream.push(&wire);
+    assert_eq!(stream.try_next_with_options(options()).unwrap(), Some((7, "hi".to_string())));
+    assert_eq!(stream.try_next_with_options(options()).unwrap(), Some((8, "ho".to_string())));
+}
+
+#[test]
+fn eof_reports_needed_bytes() {
+    use bincode::{deserialize_from_custom, ChainedSliceReader, ScratchReader};
+
+    let encoded = serialize(&(0x0102u16, "hello", 7u64, 'x')).unwrap();
+    assert_eq!(encoded.len(), 2 + 8 + 5 + 8 + 1);
+
+    // Cutting the input inside a field reports the rest of that field.
+    let check = |end: usize, expected: usize| {
+        let truncated = &encoded[..end];
+        match deserialize::<(u16, &str, u64, char)>(truncated) {
+            Err(ErrorKind::UnexpectedEof { needed: Some(needed), .. }) => assert_eq!(needed, expected, "at {}", end),
+            other => panic!("unexpected {:?} at {}", other, end),
+        }
+        // So does a chained reader split in the middle.
+        let (head, tail) = truncated.split_at(end / 2);
+        match deserialize_from_custom::<_, (u16, String, u64, char)>(ChainedSliceReader::new(head, tail)) {
+            Err(ErrorKind::UnexpectedEof { needed: Some(needed), .. }) => assert_eq!(needed, expected, "at {}", end),
+            other => panic!("unexpected {:?} at {}", other, end),
+        }
+    };
+    check(0, 2);
+    check(1, 1);
+    check(2, 8);
+    check(6, 4);
+    check(10, 5);
+    check(13, 2);
+    check(15, 8);
+    check(22, 1);
+    check(23, 1);
+
+    // A pull-based reader knows how much of its request went unanswered.
+    let mut source = encoded[..13].iter();
+    let mut scratch = [0; 16];
+    let reader = ScratchReader::new(&mut scratch, |buf: &mut [u8]| {
+        Ok(match source.next() {
+            Some(&byte) => {
+                buf[0] = byte;
+                1
+            }
+            None => 0,
+        })
+    });
+    match deserialize_from_custom::<_, (u16, String)>(reader) {
+        Err(ErrorKind::UnexpectedEof { needed: Some(2), .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn deserialize_partial() {
+    use bincode::deserialize_partial;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Header {
+        kind: u8,
+        len: u16,
+    }
+
+    let mut encoded = serialize(&Header { kind: 1, len: 9 }).unwrap();
+    encoded.extend(serialize(&("name", &b"raw"[..])).unwrap());
+    encoded.extend(serialize(&-3i64).unwrap());
+
+    let (header, rest): (Header, _) = deserialize_partial(&encoded).unwrap();
+    assert_eq!(header, Header { kind: 1, len: 9 });
+    let (pair, rest): ((&str, &[u8]), _) = deserialize_partial(rest).unwrap();
+    assert_eq!(pair, ("name", &b"raw"[..]));
+    // The tail starts right after the borrowed bytes, which end the value.
+    assert_eq!(rest.as_ptr() as usize, pair.1.as_ptr() as usize + 3);
+    let (n, rest): (i64, _) = deserialize_partial(rest).unwrap();
+    assert_eq!(n, -3);
+    assert!(rest.is_empty());
+
+    // A value cut short is an error, not a value and a bogus tail.
+    match deserialize_partial::<(&str, &[u8])>(&encoded[3..3 + 8 + 4 + 8 + 2]) {
+        Err(ErrorKind::UnexpectedEof { needed: Some(1), .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn deserialize_iter() {
+    use bincode::deserialize_iter;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Record<'a> {
+        seq: u32,
+        tag: &'a str,
+    }
+
+    let mut log = Vec::new();
+    for (seq, tag) in [(1, "boot"), (2, "tick"), (3, "halt")].iter() {
+        log.extend(serialize(&Record { seq: *seq, tag: *tag }).unwrap());
+    }
+
+    let records: Vec<Record> = deserialize_iter(&log).map(|r| r.unwrap()).collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[2], Record { seq: 3, tag: "halt" });
+    assert!(deserialize_iter::<Record>(&[]).next().is_none());
+
+    // A malformed record is reported once, with its offset, and ends the iteration.
+    let record_len = log.len() / 3;
+    let mut corrupt = log.clone();
+    corrupt[record_len + 4 + 8] = 0xFF;
+    let mut iter = deserialize_iter::<Record>(&corrupt);
+    assert!(iter.next().unwrap().is_ok());
+    match iter.next() {
+        Some(Err(ErrorKind::InvalidUtf8Encoding(_))) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(iter.offset(), record_len);
+    assert!(iter.next().is_none());
+    assert!(iter.next().is_none());
+
+    // So is a record cut short by the end of the input.
+    let mut iter = deserialize_iter::<Record>(&log[..log.len() - 1]);
+    assert_eq!(iter.by_ref().take(2).filter(|r| r.is_ok()).count(), 2);
+    match iter.next() {
+        Some(Err(ErrorKind::UnexpectedEof { .. })) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(iter.offset(), 2 * record_len);
+    assert!(iter.next().is_none());
+
+    // Other configurations go through `Config`.
+    let mut big: ArrayVec<[u8; 4]> = ArrayVec::new();
+    config().big_endian().serialize_into(&mut big, &[7u16, 8]).unwrap();
+    let values: Vec<u16> = config().big_endian().deserialize_iter(&big).map(|r| r.unwrap()).collect();
+    assert_eq!(values, [7, 8]);
+
+    // Values that take up no bytes can't use up the input.
+    let mut iter = deserialize_iter::<()>(&[0]);
+    match iter.next() {
+        Some(Err(ErrorKind::TrailingBytes(1))) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn deserialize_exact() {
+    use bincode::deserialize_exact;
+
+    let mut encoded = serialize(&(5u32, "payload")).unwrap();
+    assert_eq!(deserialize_exact::<(u32, &str)>(&encoded).unwrap(), (5, "payload"));
+
+    // One byte too many is caught, while `deserialize` still ignores it.
+    encoded.push(0);
+    match deserialize_exact::<(u32, &str)>(&encoded) {
+        Err(ErrorKind::TrailingBytes(1)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(deserialize::<(u32, &str)>(&encoded).unwrap(), (5, "payload"));
+    match config().big_endian().deserialize_exact::<u16>(&[0, 1, 2]) {
+        Err(ErrorKind::TrailingBytes(1)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn reader_end() {
+    use bincode::{deserialize_from_custom, BincodeRead, ChainedSliceReader, ScratchReader, SliceReader};
+
+    let mut encoded = serialize(&(9u8, "end")).unwrap();
+
+    let mut reader = SliceReader::new(&encoded);
+    let _: (u8, &str) = deserialize_from_custom(&mut reader).unwrap();
+    reader.end().unwrap();
+    assert_eq!(reader.bytes_read(), encoded.len());
+
+    encoded.extend([1, 2].iter().cloned());
+    let mut reader = SliceReader::new(&encoded);
+    let _: (u8, &str) = deserialize_from_custom(&mut reader).unwrap();
+    match reader.end() {
+        Err(ErrorKind::TrailingBytes(2)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    let (head, tail) = encoded.split_at(4);
+    let mut reader = ChainedSliceReader::new(head, tail);
+    let _: (u8, String) = deserialize_from_custom(&mut reader).unwrap();
+    match reader.end() {
+        Err(ErrorKind::TrailingBytes(2)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Readers that can't tell how much is left probe for one more byte.
+    let probe = |input: &[u8]| {
+        let mut source = input.iter();
+        let mut scratch = [0; 8];
+        let mut reader = ScratchReader::new(&mut scratch, |buf: &mut [u8]| {
+            Ok(match source.next() {
+                Some(&byte) => {
+                    buf[0] = byte;
+                    1
+                }
+                None => 0,
+            })
+        });
+        let _: (u8, String) = deserialize_from_custom(&mut reader).unwrap();
+        reader.end()
+    };
+    probe(&encoded[..encoded.len() - 2]).unwrap();
+    match probe(&encoded) {
+        Err(ErrorKind::TrailingBytes(1)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn io_reader_end() {
+    use bincode::{BincodeRead, IoReader};
+
+    let encoded = serialize(&7u32).unwrap();
+    let mut reader = IoReader::new(&encoded[..]);
+    let _: u32 = bincode::deserialize_from_custom(&mut reader).unwrap();
+    reader.end().unwrap();
+
+    let mut reader = IoReader::new(&[7, 0, 0, 0, 0][..]);
+    let _: u32 = bincode::deserialize_from_custom(&mut reader).unwrap();
+    match reader.end() {
+        Err(ErrorKind::TrailingBytes(1)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn byte_array() {
+    use bincode::stride::encoded_stride;
+    use bincode::LengthEncoding;
+    use bincode::{deserialize_from_custom, ChainedSliceReader};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Keyed {
+        id: u16,
+        #[serde(with = "bincode::byte_array")]
+        key: [u8; 32],
+        #[serde(with = "bincode::byte_array")]
+        mac: [u8; 6],
+    }
+
+    let mut key = [0; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    let value = Keyed {
+        id: 1,
+        key,
+        mac: [0xDE, 0xAD, 0xBE, 0xEF, 0, 1],
+    };
+
+    // The same bytes as plain arrays, so either side can use either form.
+    let encoded = serialize(&value).unwrap();
+    let plain = serialize(&(1u16, key, [0xDEu8, 0xAD, 0xBE, 0xEF, 0, 1])).unwrap();
+    assert_eq!(&encoded[..], &plain[..]);
+    assert_eq!(deserialize::<Keyed>(&plain).unwrap(), value);
+    assert_eq!(encoded_stride::<Keyed>(), Some(2 + 32 + 6));
+
+    // Readers that copy, including across a seam inside the key.
+    let (head, tail) = encoded.split_at(10);
+    let decoded: Keyed = deserialize_from_custom(ChainedSliceReader::new(head, tail)).unwrap();
+    assert_eq!(decoded, value);
+
+    match deserialize::<Keyed>(&encoded[..encoded.len() - 1]) {
+        Err(ErrorKind::UnexpectedEof { needed: Some(1), .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    match config().limit(20).deserialize_from_custom::<_, Keyed>(bincode::SliceReader::new(&encoded)) {
+        Err(ErrorKind::SizeLimit(Direction::Deserialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn slice_reader_after_error() {
+    use bincode::{deserialize_from_custom, SliceReader};
+
+    // Two frames of (u16, bool, u8); the first has a corrupt bool.
+    let input = [1, 0, 2, 9, 2, 0, 1, 8];
+    let mut reader = SliceReader::new(&input);
+    match deserialize_from_custom::<_, (u16, bool, u8)>(&mut reader) {
+        Err(ErrorKind::InvalidBoolEncoding(2)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    // The cursor stopped right after the offending byte.
+    assert_eq!(reader.bytes_read(), 3);
+    assert_eq!(reader.remaining_slice(), &input[3..]);
+
+    // Skip the rest of the broken frame and carry on.
+    let frame_len = 4;
+    let mut reader = SliceReader::new(&reader.remaining_slice()[frame_len - reader.bytes_read()..]);
+    let decoded: (u16, bool, u8) = deserialize_from_custom(&mut reader).unwrap();
+    assert_eq!(decoded, (2, true, 8));
+    assert!(reader.remaining_slice().is_empty());
+}
+
+#[test]
+fn skip_bytes() {
+    use bincode::{deserialize_from_custom, BincodeRead, ChainedSliceReader, SliceReader};
+
+    // A u8 block length, a vendor block to hop over, then a u16.
+    let input = [3, 0xAA, 0xBB, 0xCC, 0x34, 0x12];
+    let mut reader = SliceReader::new(&input);
+    let len: u8 = deserialize_from_custom(&mut reader).unwrap();
+    reader.skip_bytes(0).unwrap();
+    assert_eq!(reader.bytes_read(), 1);
+    reader.skip_bytes(len as usize).unwrap();
+    assert_eq!(reader.bytes_read(), 4);
+    let n: u16 = deserialize_from_custom(&mut reader).unwrap();
+    assert_eq!(n, 0x1234);
+
+    // Skipping all that is left, then past the end.
+    let mut reader = SliceReader::new(&input);
+    reader.skip_bytes(input.len()).unwrap();
+    assert_eq!(reader.remaining(), 0);
+    let mut reader = SliceReader::new(&input);
+    match reader.skip_bytes(input.len() + 1) {
+        Err(ErrorKind::UnexpectedEof { remaining: 6, needed: Some(1), .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(reader.bytes_read(), 0);
+
+    // Other readers skip in pieces, here across a seam and more than one piece.
+    let long = [7u8; 200];
+    let (head, tail) = long.split_at(50);
+    let mut reader = ChainedSliceReader::new(head, tail);
+    reader.skip_bytes(199).unwrap();
+    assert_eq!(reader.remaining(), 1);
+    match reader.skip_bytes(2) {
+        Err(ErrorKind::UnexpectedEof { .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn serialized_size_matches_output() {
+    use bincode::{serialized_size_bounded, LengthEncoding};
+
+    #[derive(Serialize)]
+    enum Shape {
+        Dot,
+        Circle(f32),
+        Poly { points: Vec<(i16, i16)>, closed: bool },
+    }
+
+    #[derive(Serialize)]
+    struct Record {
+        id: u64,
+        flag: u8,
+        name: Option<String>,
+        samples: Vec<u16>,
+        shapes: Vec<Shape>,
+        tail: (u8, i32, char),
+    }
+
+    // xorshift, so every run checks the same values.
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    let mut next = move |bound: u64| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state % bound
+    };
+
+    let mut configs = Vec::new();
+    for &length in &[LengthEncoding::U16, LengthEncoding::U32, LengthEncoding::U64] {
+        for &align in &[false, true] {
+            let mut config = config();
+            config.length_encoding(length).align(align);
+            configs.push(config);
+        }
+    }
+
+    for _ in 0..200 {
+        let record = Record {
+            id: next(u64::max_value()),
+            flag: next(256) as u8,
+            name: match next(3) {
+                0 => None,
+                _ => Some("ü".repeat(next(6) as usize)),
+            },
+            samples: (0..next(5)).map(|i| i as u16).collect(),
+            shapes: (0..next(4))
+                .map(|_| match next(3) {
+                    0 => Shape::Dot,
+                    1 => Shape::Circle(next(100) as f32),
+                    _ => Shape::Poly {
+                        points: (0..next(4)).map(|i| (i as i16, -(i as i16))).collect(),
+                        closed: next(2) == 0,
+                    },
+                })
+                .collect(),
+            tail: (1, -1, char::from(b'a' + next(26) as u8)),
+        };
+
+        for config in &configs {
+            let mut encoded = Buffer::new();
+            config.serialize_into(&mut encoded, &record).unwrap();
+            let size = encoded.len() as u64;
+            assert_eq!(config.serialized_size(&record).unwrap(), size);
+            assert_eq!(config.serialized_size_bounded(&record, size).unwrap(), Some(size));
+            assert_eq!(config.serialized_size_bounded(&record, size - 1).unwrap(), None);
+        }
+    }
+
+    // A configured limit still applies when it is the tighter one.
+    assert_eq!(serialized_size_bounded(&[0u8; 16], 100).unwrap(), Some(16));
+    assert_eq!(config().limit(8).serialized_size_bounded(&[0u8; 16], 100).unwrap(), None);
+}
+
+#[test]
+fn serialize_to_array_vec() {
+    let bytes = bincode::serialize::<[u8; 32], _>(&("hi", 3u8)).unwrap();
+    assert_eq!(&bytes[..], &serialize(&("hi", 3u8)).unwrap()[..]);
+
+    let bytes: ArrayVec<[u8; 4]> = config().big_endian().serialize(&0x0102_0304u32).unwrap();
+    assert_eq!(&bytes[..], &[1, 2, 3, 4]);
+
+    match bincode::serialize::<[u8; 4], _>(&"toolong") {
+        Err(ErrorKind::BufferTooSmall { needed: 15, available: 4, written: 0 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn deserialize_seed() {
+    use std::marker::PhantomData;
+
+    // Scales whatever `u16` it reads.
+    struct Scaled(u32);
+
+    impl<'de> serde::de::DeserializeSeed<'de> for Scaled {
+        type Value = u32;
+
+        fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> std::result::Result<u32, D::Error> {
+            let raw: u16 = serde::Deserialize::deserialize(deserializer)?;
+            Ok(u32::from(raw) * self.0)
+        }
+    }
+
+    let bytes = serialize(&7u16).unwrap();
+    assert_eq!(bincode::deserialize_seed(Scaled(3), &bytes).unwrap(), 21);
+    assert_eq!(config().big_endian().deserialize_seed(Scaled(1), &[1, 0]).unwrap(), 256);
+
+    let bytes = serialize("seeded").unwrap();
+    let s: &str = bincode::deserialize_seed(PhantomData, &bytes).unwrap();
+    assert_eq!(s, "seeded");
+
+    match bincode::deserialize_seed(Scaled(1), &[1]) {
+        Err(ErrorKind::UnexpectedEof { .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn encode_many() {
+    // Each item takes 8 + 3 = 11 bytes, so 27 bytes fit two and a half of them.
+    let items = ["abc", "def", "ghi"];
+    let mut buf: ArrayVec<[u8; 27]> = ArrayVec::new();
+    assert_eq!(bincode::encode_many(&items, &mut buf).unwrap(), 2);
+    assert_eq!(buf.len(), 22);
+    let mut decoded: Vec<&str> = Vec::new();
+    assert_eq!(bincode::decode_many::<&str, _>(&buf, &mut decoded).unwrap(), 2);
+    assert_eq!(decoded, ["abc", "def"]);
+
+    // Carrying on where it stopped appends after the complete items.
+    buf.clear();
+    buf.extend([0xAA; 17].iter().cloned());
+    assert_eq!(bincode::encode_many(&items[2..], &mut buf).unwrap(), 0);
+    assert_eq!(&buf[..], &[0xAA; 17][..]);
+
+    // Items of different sizes stop at the first that doesn't fit, even if a later one would.
+    let items: [&[u8]; 3] = [&[1; 4], &[2; 12], &[3; 1]];
+    let mut buf: ArrayVec<[u8; 27]> = ArrayVec::new();
+    assert_eq!(config().encode_many(items.iter().cloned(), &mut buf).unwrap(), 1);
+    assert_eq!(buf.len(), 12);
+
+    let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+    assert_eq!(config().big_endian().encode_many(&[1u16, 2, 3], &mut buf).unwrap(), 3);
+    assert_eq!(&buf[..], &[0, 1, 0, 2, 0, 3]);
+    let mut decoded = ArrayVec::<[u16; 4]>::new();
+    assert_eq!(config().big_endian().decode_many(&buf, &mut decoded).unwrap(), 3);
+    assert_eq!(&decoded[..], &[1, 2, 3]);
+}
+
+#[test]
+fn encode_many_limit() {
+    let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+    match config().limit(10).encode_many(&["a", "abc"], &mut buf) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(&buf[..], &serialize("a").unwrap()[..]);
+}
+
+#[test]
+fn decode_many_truncated() {
+    let mut buf: ArrayVec<[u8; 32]> = ArrayVec::new();
+    bincode::encode_many(&[7u32, 8, 9], &mut buf).unwrap();
+    let mut decoded = Vec::new();
+    match bincode::decode_many::<u32, _>(&buf[..10], &mut decoded) {
+        Err(ErrorKind::UnexpectedEof { .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(decoded, [7, 8]);
+}
+
+#[test]
+fn public_serializer_and_deserializer() {
+    use bincode::{Bounded, DefaultOptions, Deserializer, OptionsExt, Serializer, SliceReader, WithOtherLimit};
+    use serde::{Deserialize, Serialize};
+
+    let mut buf = Buffer::new();
+    {
+        let mut s = Serializer::from_array_vec(&mut buf, DefaultOptions::new().with_big_endian());
+        for value in &[1u32, 2, 3] {
+            value.serialize(&mut s).unwrap();
+        }
+        "end".serialize(&mut s).unwrap();
+    }
+    assert_eq!(&buf[..4], &[0, 0, 0, 1]);
+
+    let mut d = Deserializer::from_slice(&buf, DefaultOptions::new().with_big_endian());
+    let mut sum = 0;
+    for _ in 0..3 {
+        sum += u32::deserialize(&mut d).unwrap();
+    }
+    assert_eq!(sum, 6);
+    assert_eq!(d.bytes_read(), 12);
+    assert_eq!(<&str>::deserialize(&mut d).unwrap(), "end");
+    assert_eq!(d.end().unwrap(), buf.len() as u64);
+
+    // The options can be named, e.g. to keep a deserializer in a struct.
+    let options: WithOtherLimit<DefaultOptions, Bounded> = DefaultOptions::new().with_limit(6);
+    let mut d = Deserializer::new(SliceReader::new(&buf), options);
+    u32::deserialize(&mut d).unwrap();
+    match u32::deserialize(&mut d) {
+        Err(ErrorKind::SizeLimit(Direction::Deserialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(d.into_reader().remaining_slice().len(), buf.len() - 4);
+
+    // Options taken from a Config keep its other settings.
+    let mut cfg = config();
+    cfg.length_encoding(bincode::LengthEncoding::U16);
+    let mut buf = Buffer::new();
+    "hi".serialize(&mut Serializer::from_array_vec(&mut buf, DefaultOptions::from_config(&cfg))).unwrap();
+    assert_eq!(&buf[..], &[2, 0, b'h', b'i']);
+    assert_eq!(cfg.deserialize::<&str>(&buf).unwrap(), "hi");
+}
+
+#[test]
+fn const_config() {
+    const WIRE: bincode::Config = {
+        let mut config = bincode::config();
+        config.big_endian().length_encoding(bincode::LengthEncoding::U16);
+        config
+    };
+
+    let copy = WIRE;
+    let mut buf = Buffer::new();
+    copy.serialize_into(&mut buf, "ab").unwrap();
+    assert_eq!(&buf[..], &[0, 2, b'a', b'b']);
+    assert_eq!(WIRE.deserialize::<&str>(&buf).unwrap(), "ab");
+}
+
+#[test]
+fn with_options() {
+    use bincode::{DefaultOptions, OptionsExt};
+
+    let mut buf = Buffer::new();
+    let options = DefaultOptions::from_config(&config()).with_native_endian();
+    bincode::serialize_into_with_options(&mut buf, &(1u16, "x"), options).unwrap();
+    assert_eq!(&buf[..], &serialize(&(1u16, "x")).unwrap()[..]);
+    let value: (u16, &str) = bincode::deserialize_with_options(&buf, DefaultOptions::new()).unwrap();
+    assert_eq!(value, (1, "x"));
+
+    match bincode::deserialize_with_options::<(u16, &str), _>(&buf, DefaultOptions::new().with_limit(4)) {
+        Err(ErrorKind::SizeLimit(Direction::Deserialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    // `Config` honors its limit the same way.
+    let mut limited = config();
+    limited.limit(4);
+    match limited.deserialize::<(u16, &str)>(&buf) {
+        Err(ErrorKind::SizeLimit(Direction::Deserialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    limited.limit(buf.len() as u64);
+    assert_eq!(limited.deserialize::<(u16, &str)>(&buf).unwrap(), (1, "x"));
+
+    // A failed value leaves the buffer as it was.
+    let mut small: ArrayVec<[u8; 6]> = ArrayVec::new();
+    small.push(9);
+    match bincode::serialize_into_with_options(&mut small, "toolong", DefaultOptions::new()) {
+        Err(ErrorKind::BufferTooSmall { needed: 15, available: 5, written: 0 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(&small[..], &[9]);
+    match bincode::serialize_into_with_options(&mut small, &0u64, DefaultOptions::new().with_limit(4)) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(&small[..], &[9]);
+}
+
+#[test]
+fn options_combinators_commute() {
+    use bincode::{Bounded, DefaultOptions, Infinite, Options, OptionsExt};
+
+    #[derive(Serialize)]
+    struct Msg<'a> {
+        id: u32,
+        name: &'a str,
+        tags: [u16; 2],
+    }
+    let msg = Msg { id: 0x0102_0304, name: "m", tags: [5, 6] };
+
+    fn encode<O: Options>(msg: &Msg, options: O) -> Result<Buffer> {
+        let mut buf = Buffer::new();
+        bincode::serialize_into_with_options(&mut buf, msg, options)?;
+        Ok(buf)
+    }
+
+    let expected = encode(&msg, DefaultOptions::new().with_big_endian()).unwrap();
+    assert_eq!(&expected[..4], &[1, 2, 3, 4]);
+    let stacks = [
+        encode(&msg, DefaultOptions::new().with_limit(64).with_big_endian()).unwrap(),
+        encode(&msg, DefaultOptions::new().with_big_endian().with_limit(64)).unwrap(),
+        encode(&msg, DefaultOptions::new().with_little_endian().with_size_limit(Bounded(64)).with_big_endian()).unwrap(),
+        encode(&msg, DefaultOptions::new().with_limit(1).with_big_endian().with_size_limit(Infinite)).unwrap(),
+        encode(&msg, DefaultOptions::new().with_big_endian().with_limit(1).with_no_limit()).unwrap(),
+    ];
+    for buf in &stacks {
+        assert_eq!(&buf[..], &expected[..]);
+    }
+
+    // Whichever order, the outermost limit is the one that applies.
+    match encode(&msg, DefaultOptions::new().with_no_limit().with_big_endian().with_limit(4)) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => {}
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+    match encode(&msg, DefaultOptions::new().with_limit(4).with_big_endian().with_no_limit()) {
+        Ok(buf) => assert_eq!(&buf[..], &expected[..]),
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn custom_options() {
+    use bincode::{Direction, LengthEncoding, Options, OptionsExt, SizeLimit};
+
+    // Counts the bytes that went through instead of limiting them.
+    #[derive(Clone)]
+    struct Metered(u64);
+
+    impl SizeLimit for Metered {
+        fn add(&mut self, n: u64, _direction: Direction) -> Result<()> {
+            self.0 += n;
+            Ok(())
+        }
+
+        fn limit(&self) -> Option<u64> {
+            None
+        }
+    }
+
+    struct Peer {
+        metered: Metered,
+    }
+
+    impl Options for Peer {
+        type Limit = Metered;
+        type Endian = byteorder::BigEndian;
+
+        fn limit(&mut self) -> &mut Metered {
+            &mut self.metered
+        }
+
+        fn length_encoding(&self) -> LengthEncoding {
+            LengthEncoding::U32
+        }
+
+        fn none_tag(&self) -> u8 {
+            0xFF
+        }
+
+        fn some_tag(&self) -> u8 {
+            0x00
+        }
+    }
+
+    let value = (0x0102u16, Some("ab"), None::<u8>);
+    let mut expected = Buffer::new();
+    config()
+        .big_endian()
+        .length_encoding(LengthEncoding::U32)
+        .none_tag(0xFF)
+        .some_tag(0x00)
+        .serialize_into(&mut expected, &value)
+        .unwrap();
+
+    let mut buf = Buffer::new();
+    let mut peer = Peer { metered: Metered(0) };
+    bincode::serialize_into_with_options(&mut buf, &value, &mut peer).unwrap();
+    assert_eq!(&buf[..], &expected[..]);
+
+    let mut peer = Peer { metered: Metered(0) };
+    let decoded: (u16, Option<&str>, Option<u8>) = bincode::deserialize_with_options(&buf, &mut peer).unwrap();
+    assert_eq!(decoded, value);
+    assert_eq!(peer.metered.0, buf.len() as u64);
+
+    // The adapters apply to custom options like to the built-in ones.
+    let mut little = Buffer::new();
+    let options = Peer { metered: Metered(0) }.with_little_endian().with_limit(3);
+    match bincode::serialize_into_with_options(&mut little, &value, options) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    let options = Peer { metered: Metered(0) }.with_little_endian();
+    bincode::serialize_into_with_options(&mut little, &value, options).unwrap();
+    assert_eq!(&little[..3], &[0x02, 0x01, 0x00]);
+}
+
+#[test]
+fn custom_size_limit() {
+    use bincode::{DefaultOptions, Options, OptionsExt, SizeLimit};
+
+    // Records every charge, and refuses any single one over `max_piece`.
+    #[derive(Clone)]
+    struct Recording {
+        charges: Vec<u64>,
+        max_piece: u64,
+    }
+
+    impl SizeLimit for Recording {
+        fn add(&mut self, n: u64, direction: Direction) -> Result<()> {
+            if n > self.max_piece {
+                return Err(ErrorKind::SizeLimit(direction));
+            }
+            self.charges.push(n);
+            Ok(())
+        }
+
+        fn limit(&self) -> Option<u64> {
+            None
+        }
+    }
+
+    let value = (7u16, "abc", Some(1u32));
+    let encoded = serialize(&value).unwrap();
+
+    // One charge per number, tag and length prefix, and one for the string.
+    let mut options = DefaultOptions::new().with_size_limit(Recording { charges: Vec::new(), max_piece: 8 });
+    let decoded: (u16, &str, Option<u32>) = bincode::deserialize_with_options(&encoded, &mut options).unwrap();
+    assert_eq!(decoded, value);
+    assert_eq!(options.limit().charges, vec![2, 8, 3, 1, 4]);
+
+    // A string can be held to less than the message as a whole.
+    let long = serialize(&(7u16, "abcdefghi")).unwrap();
+    let options = DefaultOptions::new().with_size_limit(Recording { charges: Vec::new(), max_piece: 8 });
+    match bincode::deserialize_with_options::<(u16, &str), _>(&long, options) {
+        Err(ErrorKind::SizeLimit(Direction::Deserialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn preset_fixtures() {
+    use bincode::{presets, Options};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Reference<'a> {
+        id: u16,
+        flag: bool,
+        name: &'a str,
+        value: Option<u32>,
+        list: [i8; 2],
+    }
+    let reference = Reference { id: 0x0102, flag: true, name: "hi", value: Some(0x0304_0506), list: [-1, 2] };
+
+    fn check<O: Options>(reference: &Reference, preset: fn() -> O, fixture: &[u8]) {
+        let mut buf = Buffer::new();
+        bincode::serialize_into_with_options(&mut buf, reference, preset()).unwrap();
+        assert_eq!(&buf[..], fixture);
+        let back: Reference = bincode::deserialize_with_options(fixture, preset()).unwrap();
+        assert_eq!(&back, reference);
+    }
+
+    let little = [
+        0x02, 0x01, 0x01, 0x02, 0, 0, 0, 0, 0, 0, 0, b'h', b'i', 0x01, 0x06, 0x05, 0x04, 0x03, 0xFF, 0x02,
+    ];
+    check(&reference, presets::legacy, &little);
+    check(&reference, presets::standard, &little);
+    check(&reference, presets::network, &[
+        0x01, 0x02, 0x01, 0, 0, 0, 0, 0, 0, 0, 0x02, b'h', b'i', 0x01, 0x03, 0x04, 0x05, 0x06, 0xFF, 0x02,
+    ]);
+
+    // The legacy preset is what the functions without options produce.
+    assert_eq!(&serialize(&reference).unwrap()[..], &little[..]);
+}
+
+#[test]
+fn deserialize_in_place() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct GameState {
+        tick: u32,
+        positions: Vec<(i16, i16)>,
+        name: String,
+        grid: [u8; 16],
+    }
+
+    let mut state = GameState {
+        tick: 0,
+        positions: Vec::with_capacity(64),
+        name: String::with_capacity(32),
+        grid: [0; 16],
+    };
+    let positions = state.positions.as_ptr();
+    let name = state.name.as_ptr();
+
+    for tick in 1..4 {
+        let frame = GameState {
+            tick,
+            positions: (0..tick as i16 * 10).map(|i| (i, -i)).collect(),
+            name: "frame".repeat(tick as usize),
+            grid: [tick as u8; 16],
+        };
+        let bytes = serialize(&frame).unwrap();
+        bincode::deserialize_in_place(&bytes, &mut state).unwrap();
+        assert_eq!(state, frame);
+        // The buffers were reused rather than reallocated.
+        assert_eq!(state.positions.as_ptr(), positions);
+        assert_eq!(state.positions.capacity(), 64);
+        assert_eq!(state.name.as_ptr(), name);
+        assert_eq!(state.name.capacity(), 32);
+    }
+
+    // A shorter sequence drops the extra elements.
+    let bytes = serialize(&GameState { tick: 9, positions: vec![(1, 1)], name: String::new(), grid: [9; 16] }).unwrap();
+    config().deserialize_in_place(&bytes, &mut state).unwrap();
+    assert_eq!(state.positions, [(1, 1)]);
+    assert_eq!(state.positions.as_ptr(), positions);
+
+    // A failed decode keeps the fields before the failure and leaves the rest alone.
+    let frame = GameState { tick: 10, positions: vec![(2, 2), (3, 3)], name: "cut".into(), grid: [10; 16] };
+    let bytes = serialize(&frame).unwrap();
+    let cut = 4 + 8 + 2 * 4 + 8 + 1;
+    match bincode::deserialize_in_place(&bytes[..cut], &mut state) {
+        Err(ErrorKind::UnexpectedEof { .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(state.tick, 10);
+    assert_eq!(state.positions, [(2, 2), (3, 3)]);
+    assert_eq!(state.name, "");
+    assert_eq!(state.grid, [9; 16]);
+}
+
+#[test]
+fn endianness_with_options() {
+    use bincode::{DefaultOptions, OptionsExt};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Sample {
+        a: u16,
+        b: u32,
+        c: i64,
+        d: f32,
+    }
+    let sample = Sample { a: 0x0102, b: 0x0304_0506, c: -2, d: 1.5 };
+    let widths = [2, 4, 8, 4];
+
+    let mut little = Buffer::new();
+    let mut big = Buffer::new();
+    bincode::serialize_into_with_options(&mut little, &sample, DefaultOptions::new().with_little_endian()).unwrap();
+    bincode::serialize_into_with_options(&mut big, &sample, DefaultOptions::new().with_big_endian()).unwrap();
+    assert_eq!(&little[..], &serialize(&sample).unwrap()[..]);
+
+    // Every field is the same bytes in the opposite order.
+    let mut offset = 0;
+    for &width in &widths {
+        let mut swapped = big[offset..offset + width].to_vec();
+        swapped.reverse();
+        assert_eq!(&little[offset..offset + width], &swapped[..]);
+        offset += width;
+    }
+    assert_eq!(offset, little.len());
+
+    let size = bincode::serialized_size_with_options(&sample, DefaultOptions::new().with_big_endian()).unwrap();
+    assert_eq!(size, big.len() as u64);
+    assert_eq!(bincode::serialized_size(&sample).unwrap(), size);
+    let back: Sample = bincode::deserialize_with_options(&big, DefaultOptions::new().with_big_endian()).unwrap();
+    assert_eq!(back, sample);
+
+    match bincode::serialized_size_with_options(&sample, DefaultOptions::new().with_limit(17)) {
+        Err(ErrorKind::SizeLimit(Direction::Serialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(bincode::serialized_size_with_options(&sample, DefaultOptions::new().with_limit(18)).unwrap(), 18);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn cow_borrows_from_input() {
+    use bincode::{deserialize_from_custom, BlockCachedReader, ChainedSliceReader, SliceReader};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Packet<'a> {
+        #[serde(borrow)]
+        name: Cow<'a, str>,
+        #[serde(borrow, with = "serde_bytes")]
+        data: Cow<'a, [u8]>,
+    }
+
+    fn borrowed_from(packet: &Packet, input: &[u8]) -> bool {
+        let range = input.as_ptr() as usize..input.as_ptr() as usize + input.len();
+        match (&packet.name, &packet.data) {
+            (&Cow::Borrowed(name), &Cow::Borrowed(data)) => {
+                range.contains(&(name.as_ptr() as usize)) && range.contains(&(data.as_ptr() as usize))
+            }
+            _ => false,
+        }
+    }
+
+    let packet = Packet { name: Cow::Borrowed("sensor"), data: Cow::Borrowed(&[1, 2, 3]) };
+    let bytes = serialize(&packet).unwrap();
+
+    let decoded: Packet = deserialize(&bytes).unwrap();
+    assert_eq!(decoded, packet);
+    assert!(borrowed_from(&decoded, &bytes));
+
+    let decoded: Packet = deserialize_from_custom(SliceReader::new(&bytes)).unwrap();
+    assert!(borrowed_from(&decoded, &bytes));
+
+    let decoded: Packet = deserialize_from_custom(BlockCachedReader::<16>::new(&bytes)).unwrap();
+    assert!(borrowed_from(&decoded, &bytes));
+
+    let (head, tail) = bytes.split_at(10);
+    let decoded: Packet = deserialize_from_custom(ChainedSliceReader::new(head, tail)).unwrap();
+    assert_eq!(decoded, packet);
+    match decoded.name {
+        Cow::Owned(ref name) => assert_eq!(name, "sensor"),
+        Cow::Borrowed(_) => panic!("a string across the seam can't be borrowed"),
+    }
+
+    // Without `#[serde(borrow)]` serde always builds an owned `Cow`.
+    let decoded: Cow<str> = deserialize(&serialize("sensor").unwrap()).unwrap();
+    assert!(match decoded {
+        Cow::Owned(_) => true,
+        Cow::Borrowed(_) => false,
+    });
+}
+
+#[test]
+fn length_prefixed_frames() {
+    use bincode::{frame, DefaultOptions, OptionsExt};
+
+    let mut buf: ArrayVec<[u8; 64]> = ArrayVec::new();
+    assert_eq!(frame::encode(&(1u16, "ab"), &mut buf).unwrap(), 4 + 12);
+    assert_eq!(&buf[..4], &[12, 0, 0, 0]);
+    assert_eq!(&buf[4..], &serialize(&(1u16, "ab")).unwrap()[..]);
+
+    let mut big = config();
+    big.big_endian();
+    frame::encode_with_config(&7u32, &mut buf, &big).unwrap();
+    assert_eq!(&buf[16..], &[0, 0, 0, 4, 0, 0, 0, 7]);
+
+    let (value, rest): ((u16, &str), _) = frame::decode(&buf).unwrap();
+    assert_eq!(value, (1, "ab"));
+    let (value, rest): (u32, _) = frame::decode_with_config(rest, &big).unwrap();
+    assert_eq!((value, rest.len()), (7, 0));
+
+    // Options chosen by type write the same frame.
+    let options = || DefaultOptions::new().with_big_endian();
+    let mut typed: ArrayVec<[u8; 64]> = ArrayVec::new();
+    assert_eq!(frame::encode_with_options(&7u32, &mut typed, options()).unwrap(), 8);
+    assert_eq!(&typed[..], &buf[16..]);
+    let (value, rest): (u32, _) = frame::decode_with_options(&typed, options()).unwrap();
+    assert_eq!((value, rest.len()), (7, 0));
+
+    // Incomplete frames report how much is missing, in the prefix or the body.
+    for (cut, missing) in [(0, 4), (3, 1), (4, 12), (15, 1)].iter().cloned() {
+        match frame::decode::<(u16, &str)>(&buf[..cut]) {
+            Err(ErrorKind::UnexpectedEof { needed: Some(n), .. }) => assert_eq!(n, missing),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    // A prefix that doesn't match the value is corrupt, not incomplete.
+    let mut corrupt = buf.clone();
+    corrupt[0] = 11;
+    match frame::decode::<(u16, &str)>(&corrupt) {
+        Err(ErrorKind::InvalidFrameLength(11)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    corrupt[0] = 13;
+    match frame::decode::<(u16, &str)>(&corrupt) {
+        Err(ErrorKind::InvalidFrameLength(13)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // A frame that doesn't fit leaves the buffer untouched.
+    let mut small: ArrayVec<[u8; 10]> = ArrayVec::new();
+    small.push(0xAA);
+    match frame::encode(&"toolong", &mut small) {
+        Err(ErrorKind::BufferTooSmall { needed: 19, available: 9, written: 4 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    small.extend([0xAA; 7].iter().cloned());
+    match frame::encode(&0u8, &mut small) {
+        Err(ErrorKind::BufferTooSmall { needed: 5, available: 2, written: 0 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(&small[..], &[0xAA; 8]);
+}
+
+#[test]
+fn fingerprint_shapes() {
+    use bincode::fingerprint::fingerprint;
+    use std::num::NonZeroU32;
+
+    #[derive(Deserialize)]
+    struct Telemetry<'a> {
+        id: u32,
+        name: &'a str,
+        samples: Vec<(i16, f32)>,
+        mode: Option<u8>,
+    }
+
+    // Same fields, renamed.
+    #[derive(Deserialize)]
+    struct Renamed<'a> {
+        ident: u32,
+        label: &'a str,
+        points: Vec<(i16, f32)>,
+        state: Option<u8>,
+    }
+
+    // Same fields, two swapped.
+    #[derive(Deserialize)]
+    struct Reordered<'a> {
+        name: &'a str,
+        id: u32,
+        samples: Vec<(i16, f32)>,
+        mode: Option<u8>,
+    }
+
+    // Same fields, one retyped.
+    #[derive(Deserialize)]
+    struct Retyped<'a> {
+        id: u64,
+        name: &'a str,
+        samples: Vec<(i16, f32)>,
+        mode: Option<u8>,
+    }
+
+    #[derive(Deserialize)]
+    struct Extended<'a> {
+        id: u32,
+        name: &'a str,
+        samples: Vec<(i16, f32)>,
+        mode: Option<u8>,
+        extra: bool,
+    }
+
+    let base = fingerprint::<Telemetry>();
+    assert_eq!(fingerprint::<Telemetry>(), base);
+    assert_eq!(fingerprint::<Renamed>(), base);
+    assert_ne!(fingerprint::<Reordered>(), base);
+    assert_ne!(fingerprint::<Retyped>(), base);
+    assert_ne!(fingerprint::<Extended>(), base);
+
+    // Element types count, signedness and widths included.
+    assert_ne!(fingerprint::<Vec<u16>>(), fingerprint::<Vec<i16>>());
+    assert_ne!(fingerprint::<Vec<u16>>(), fingerprint::<Vec<u32>>());
+    assert_ne!(fingerprint::<(u8, u8)>(), fingerprint::<Vec<u8>>());
+    assert_ne!(fingerprint::<f32>(), fingerprint::<u32>());
+    assert_ne!(fingerprint::<String>(), fingerprint::<char>());
+    assert_ne!(fingerprint::<Option<u8>>(), fingerprint::<u8>());
+    assert_eq!(fingerprint::<NonZeroU32>(), fingerprint::<u32>());
+
+    // Every variant is described, whichever one a value holds.
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum Command {
+        Stop,
+        Move(i32),
+        Turn { degrees: i16 },
+    }
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum Reshaped {
+        Stop,
+        Move(i32),
+        Turn { degrees: i32 },
+    }
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum Shorter {
+        Stop,
+        Move(i32),
+    }
+    assert_ne!(fingerprint::<Reshaped>(), fingerprint::<Command>());
+    assert_ne!(fingerprint::<Shorter>(), fingerprint::<Command>());
+    assert_ne!(fingerprint::<(Shorter, Command)>(), fingerprint::<(Command, Shorter)>());
+
+    // Enums nested in variants are described as well.
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum Outer<T> {
+        Idle,
+        Busy(Option<T>),
+    }
+    assert_ne!(fingerprint::<Outer<Command>>(), fingerprint::<Outer<Reshaped>>());
+
+    // Recursive types end.
+    #[derive(Deserialize)]
+    struct Tree {
+        label: u8,
+        children: Vec<Tree>,
+        parent: Option<Box<Tree>>,
+    }
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum List {
+        Cons(u16, Box<List>),
+        Nil,
+    }
+    assert_eq!(fingerprint::<Tree>(), fingerprint::<Tree>());
+    assert_ne!(fingerprint::<List>(), fingerprint::<Tree>());
+
+    // The hash is fixed, so both ends of a link compute the same one.
+    assert_eq!(fingerprint::<()>(), 0x3192_275c_d9c2_9c10);
+    assert_eq!(fingerprint::<(u8, String)>(), 0x51ef_017d_2dbf_8d0a);
+}
+
+#[test]
+fn fingerprint_ignores_values() {
+    use bincode::fingerprint::fingerprint;
+
+    #[derive(Serialize, Deserialize)]
+    enum Mode {
+        Off,
+        Level(u8),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Setting {
+        mode: Mode,
+        limit: Option<u32>,
+        history: Vec<u16>,
+    }
+
+    // Peers with different example values still agree, since only the type
+    // is hashed, while the encodings differ.
+    let quiet = Setting { mode: Mode::Off, limit: None, history: vec![] };
+    let loud = Setting { mode: Mode::Level(9), limit: Some(5), history: vec![1, 2, 3] };
+    assert_ne!(serialize(&quiet).unwrap(), serialize(&loud).unwrap());
+    assert_eq!(fingerprint::<Setting>(), fingerprint::<Setting>());
+    assert_ne!(fingerprint::<Setting>(), fingerprint::<Mode>());
+}
+
+#[test]
+fn max_size_bounds_encoding() {
+    use bincode::MaxSize;
+
+    #[derive(Serialize)]
+    struct Sample {
+        channel: u8,
+        value: Option<i64>,
+        marker: char,
+        history: [u16; 3],
+    }
+    impl_max_size!(struct Sample { u8, Option<i64>, char, [u16; 3] });
+
+    #[derive(Serialize)]
+    enum Event {
+        Idle,
+        Sample(Sample),
+        Pair(bool, f64),
+        Fault { code: u32, retry: Option<bool> },
+    }
+    impl_max_size!(enum Event { (), (Sample,), (bool, f64), (u32, Option<bool>) });
+
+    assert_eq!(<(u8, u16, Option<u32>)>::MAX_SIZE, 8);
+    assert_eq!(<[char; 2]>::MAX_SIZE, 8);
+    assert_eq!(Sample::MAX_SIZE, 1 + 9 + 4 + 6);
+    assert_eq!(Event::MAX_SIZE, 4 + Sample::MAX_SIZE);
+
+    // xorshift, so every run checks the same values.
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut next = move |bound: u64| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state % bound
+    };
+    let chars = ['a', 'ü', '€', '😀'];
+
+    let mut configs = Vec::new();
+    for &big in &[false, true] {
+        let mut config = config();
+        if big {
+            config.big_endian();
+        }
+        configs.push(config);
+    }
+
+    for _ in 0..200 {
+        let event = match next(4) {
+            0 => Event::Idle,
+            1 => Event::Sample(Sample {
+                channel: next(256) as u8,
+                value: if next(2) == 0 { None } else { Some(next(u64::max_value()) as i64) },
+                marker: chars[next(4) as usize],
+                history: [next(65536) as u16, 0, 1],
+            }),
+            2 => Event::Pair(next(2) == 0, next(1000) as f64),
+            _ => Event::Fault {
+                code: next(1 << 32) as u32,
+                retry: match next(3) {
+                    0 => None,
+                    n => Some(n == 1),
+                },
+            },
+        };
+
+        for config in &configs {
+            assert!(config.serialized_size(&event).unwrap() <= Event::MAX_SIZE as u64);
+            let mut buf: ArrayVec<[u8; Event::MAX_SIZE]> = ArrayVec::new();
+            config.serialize_into(&mut buf, &event).unwrap();
+        }
+    }
+}
+
+#[test]
+fn field_spans() {
+    use bincode::{serialize_into_with_spans, FieldSpan};
+
+    // Written with `collect_str`, whose length is only known once formatted.
+    struct Shown(u32);
+
+    impl serde::Serialize for Shown {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            serializer.collect_str(&self.0)
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Inner {
+        a: u8,
+        b: u16,
+    }
+
+    #[derive(Serialize)]
+    struct Record {
+        tag: u8,
+        label: Shown,
+        inner: Inner,
+        value: u32,
+    }
+
+    #[derive(Serialize)]
+    struct Wrapper(Record);
+
+    let record = Record { tag: 1, label: Shown(12345), inner: Inner { a: 2, b: 3 }, value: 4 };
+
+    let mut buf = Buffer::new();
+    buf.extend([0xAA, 0xBB].iter().cloned());
+    let mut spans = [FieldSpan::default(); 4];
+    assert_eq!(serialize_into_with_spans(&mut buf, &record, &mut spans).unwrap(), (21, 4));
+    assert_eq!(
+        spans,
+        [
+            FieldSpan { name: "tag", offset: 2, len: 1 },
+            FieldSpan { name: "label", offset: 3, len: 8 + 5 },
+            FieldSpan { name: "inner", offset: 16, len: 3 },
+            FieldSpan { name: "value", offset: 19, len: 4 },
+        ]
+    );
+    assert_eq!(&buf[2..], &serialize(&record).unwrap()[..]);
+    for span in &spans[1..] {
+        let previous = spans.iter().find(|s| s.offset + s.len == span.offset);
+        assert!(previous.is_some(), "{:?} doesn't follow another field", span);
+    }
+
+    // Newtypes are looked through, and only the fields that fit are recorded.
+    let mut buf = Buffer::new();
+    let mut spans = [FieldSpan::default(); 2];
+    assert_eq!(serialize_into_with_spans(&mut buf, &Wrapper(record), &mut spans).unwrap(), (21, 4));
+    assert_eq!(spans[1], FieldSpan { name: "label", offset: 1, len: 13 });
+
+    // Padding before a field belongs to it.
+    let record = Record { tag: 1, label: Shown(7), inner: Inner { a: 2, b: 3 }, value: 4 };
+    let mut buf = Buffer::new();
+    let mut spans = [FieldSpan::default(); 4];
+    config().align(true).serialize_into_with_spans(&mut buf, &record, &mut spans).unwrap();
+    assert_eq!(spans[1], FieldSpan { name: "label", offset: 1, len: 7 + 8 + 1 });
+    assert_eq!(spans[2], FieldSpan { name: "inner", offset: 17, len: 1 + 2 });
+    assert_eq!(spans[3], FieldSpan { name: "value", offset: 20, len: 4 });
+
+    // Anything but a struct has no fields.
+    let mut buf = Buffer::new();
+    assert_eq!(serialize_into_with_spans(&mut buf, &(1u8, 2u8), &mut spans).unwrap(), (2, 0));
+
+    // A failed value leaves the buffer as it was.
+    let mut small: ArrayVec<[u8; 8]> = ArrayVec::new();
+    small.push(9);
+    match serialize_into_with_spans(&mut small, &record, &mut spans) {
+        Err(ErrorKind::BufferTooSmall { needed: 17, available: 7, written: 1 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(&small[..], &[9]);
+}
+
+#[test]
+fn patch_in_place() {
+    use bincode::{patch, patch_with_options, DefaultOptions, FieldSpan, OptionsExt};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Header {
+        seq: u16,
+        stamp: u64,
+        ack: Option<u32>,
+        note: String,
+    }
+
+    let header = Header { seq: 0x0102, stamp: 0x0304_0506_0708_090A, ack: None, note: "hi".to_string() };
+    let mut spans = [FieldSpan::default(); 4];
+
+    // Big-endian on both ends, with the rest of the buffer left alone.
+    let mut buf = Buffer::new();
+    buf.push(0xEE);
+    config().big_endian().serialize_into_with_spans(&mut buf, &header, &mut spans).unwrap();
+    let before = buf.clone();
+    patch_with_options(&mut buf, spans[1].offset, &0x1112_1314_1516_1718u64, DefaultOptions::new().with_big_endian()).unwrap();
+    assert_eq!(&buf[spans[1].offset..spans[1].offset + 8], &[0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18]);
+    assert_eq!(&buf[..spans[1].offset], &before[..spans[1].offset]);
+    assert_eq!(&buf[spans[2].offset..], &before[spans[2].offset..]);
+    let patched: Header = config().big_endian().deserialize(&buf[1..]).unwrap();
+    assert_eq!(patched.stamp, 0x1112_1314_1516_1718);
+
+    // Little-endian, through the default configuration.
+    let mut buf = Buffer::new();
+    serialize_into(&mut buf, &header).unwrap();
+    patch(&mut buf, 0, &0xA1B2u16).unwrap();
+    assert_eq!(&buf[..2], &[0xB2, 0xA1]);
+    assert_eq!(deserialize::<Header>(&buf).unwrap().seq, 0xA1B2);
+
+    // Changing the length is refused, and nothing is written.
+    let before = buf.clone();
+    match patch(&mut buf, 10, &Some(1u32)) {
+        Err(ErrorKind::PatchLengthMismatch { region: 1, encoded: 5 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    match patch(&mut buf, 11, &"hey".to_string()) {
+        Err(ErrorKind::PatchLengthMismatch { region: 10, encoded: 11 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    patch(&mut buf, 11, &"yo".to_string()).unwrap();
+    assert_eq!(deserialize::<Header>(&buf).unwrap().note, "yo");
+    patch(&mut buf, 11, &"hi".to_string()).unwrap();
+    assert_eq!(buf, before);
+
+    // The old value has to be there to be replaced.
+    let len = buf.len();
+    match patch(&mut buf, len - 1, &0u16) {
+        Err(ErrorKind::UnexpectedEof { .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    match patch(&mut buf, len + 2, &0u8) {
+        Err(ErrorKind::UnexpectedEof { remaining: 0, needed: Some(2), .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(buf, before);
+}
+
+#[test]
+fn unknown_length_backpatched() {
+    use bincode::LengthEncoding;
+    use serde::ser::{SerializeMap, SerializeSeq};
+
+    // Hides its length from the serializer, like a filtering iterator would.
+    struct Unsized<T>(Vec<T>);
+
+    impl<T: serde::Serialize> serde::Serialize for Unsized<T> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            let mut seq = serializer.serialize_seq(None)?;
+            for element in &self.0 {
+                seq.serialize_element(element)?;
+            }
+            seq.end()
+        }
+    }
+
+    struct UnsizedMap(BTreeMap<u8, Unsized<u16>>);
+
+    impl serde::Serialize for UnsizedMap {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            let mut map = serializer.serialize_map(None)?;
+            for (key, value) in &self.0 {
+                map.serialize_entry(key, value)?;
+            }
+            map.end()
+        }
+    }
+
+    let nested = Unsized(vec![Unsized(vec![1u8, 2]), Unsized(vec![]), Unsized(vec![3])]);
+    let known = vec![vec![1u8, 2], vec![], vec![3]];
+    let mut map = BTreeMap::new();
+    map.insert(1u8, Unsized(vec![7u16, 8]));
+    map.insert(2u8, Unsized(vec![]));
+    let map = UnsizedMap(map);
+    let mut known_map = BTreeMap::new();
+    known_map.insert(1u8, vec![7u16, 8]);
+    known_map.insert(2u8, vec![]);
+
+    // The same bytes as with the length up front, whatever the length
+    // encoding and alignment.
+    for &encoding in &[LengthEncoding::U16, LengthEncoding::U32, LengthEncoding::U64] {
+        for &align in &[false, true] {
+            let mut config = config();
+            config.length_encoding(encoding).align(align);
+            let value = (1u8, &nested, 2u8, &map);
+            let expected = (1u8, &known, 2u8, &known_map);
+
+            let mut buf = Buffer::new();
+            let len = config.serialize_into(&mut buf, &value).unwrap();
+            let mut known_buf = Buffer::new();
+            config.serialize_into(&mut known_buf, &expected).unwrap();
+            assert_eq!(buf, known_buf);
+            assert_eq!(len, buf.len());
+            assert_eq!(config.serialized_size(&value).unwrap(), buf.len() as u64);
+        }
+    }
+
+    // Too many elements for the length encoding is caught at the end.
+    let long = Unsized(vec![0u8; 300]);
+    let mut buf = Buffer::new();
+    match config().length_encoding(LengthEncoding::U16).serialize_into(&mut buf, &long) {
+        Ok(_) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    let too_long = Unsized(vec![(); 70_000]);
+    match config().length_encoding(LengthEncoding::U16).serialize_into(&mut buf, &too_long) {
+        Err(ErrorKind::LengthOverflow { len: 70_000, max: 65_535 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(buf.len(), 302);
+
+    // Running out of room leaves the buffer as it was.
+    let mut small: ArrayVec<[u8; 16]> = ArrayVec::new();
+    small.push(0xEE);
+    match serialize_into(&mut small, &nested) {
+        Err(ErrorKind::BufferTooSmall { needed: 35, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(&small[..], &[0xEE]);
+
+    // Outputs that can't go back keep refusing.
+    match bincode::serialize_with_sink(&nested, |_: &[u8]| Ok(())) {
+        Err(ErrorKind::SequenceMustHaveLength) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    match bincode::serialize_with_sink(&map, |_: &[u8]| Ok(())) {
+        Err(ErrorKind::SequenceMustHaveLength) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn versioned_envelope() {
+    use bincode::{
+        decode_versioned, decode_versioned_with, decode_versioned_with_options, encode_versioned,
+        encode_versioned_with_options, DefaultOptions, OptionsExt,
+    };
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Reading {
+        sensor: u8,
+        value: u64,
+    }
+
+    let reading = Reading { sensor: 3, value: 0x0102 };
+
+    // The version comes first, in the configured endianness, then the value
+    // as if it were serialized on its own.
+    let mut buf = Buffer::new();
+    assert_eq!(encode_versioned(0x0A0B_0C0D, &reading, &mut buf).unwrap(), 13);
+    assert_eq!(&buf[..], &[0x0D, 0x0C, 0x0B, 0x0A, 3, 0x02, 0x01, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(decode_versioned::<Reading>(&buf).unwrap(), (0x0A0B_0C0D, reading));
+
+    let reading = Reading { sensor: 3, value: 0x0102 };
+    let mut buf = Buffer::new();
+    config().big_endian().encode_versioned(2, &reading, &mut buf).unwrap();
+    assert_eq!(&buf[..], &[0, 0, 0, 2, 3, 0, 0, 0, 0, 0, 0, 0x01, 0x02]);
+    assert_eq!(config().big_endian().decode_versioned::<Reading>(&buf).unwrap(), (2, reading));
+    let mut typed = Buffer::new();
+    let options = || DefaultOptions::new().with_big_endian();
+    encode_versioned_with_options(2, &Reading { sensor: 3, value: 0x0102 }, &mut typed, options()).unwrap();
+    assert_eq!(&typed[..], &buf[..]);
+    assert_eq!(decode_versioned_with_options::<Reading, _>(&typed, options()).unwrap().0, 2);
+
+    // Padding is counted from the start of the value, not of the envelope.
+    let mut buf = Buffer::new();
+    config().align(true).encode_versioned(1, &(1u8, 2u16), &mut buf).unwrap();
+    assert_eq!(&buf[..], &[1, 0, 0, 0, 1, 0, 2, 0]);
+
+    // Dispatching on the version before picking a type.
+    let mut buf = Buffer::new();
+    encode_versioned(1, &7u16, &mut buf).unwrap();
+    encode_versioned(2, &(7u32, "seven"), &mut buf).unwrap();
+    let decode = |bytes| {
+        decode_versioned_with(bytes, |version, rest| match version {
+            1 => deserialize::<u16>(rest).map(|n| (n as u32, "")),
+            2 => deserialize(rest),
+            version => Err(ErrorKind::InvalidTagEncoding(version as usize)),
+        })
+    };
+    assert_eq!(decode(&buf[..6]).unwrap(), (7, ""));
+    assert_eq!(decode(&buf[6..]).unwrap(), (7, "seven"));
+    match decode(&[3, 0, 0, 0]) {
+        Err(ErrorKind::InvalidTagEncoding(3)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // A truncated version is an ordinary end of input, and `f` isn't called.
+    for len in 0..4 {
+        match decode_versioned_with(&buf[..len], |_, _| -> Result<()> { panic!("called") }) {
+            Err(ErrorKind::UnexpectedEof { remaining, needed: Some(needed), .. }) => {
+                assert_eq!((remaining, needed), (len, 4 - len));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+    match decode_versioned::<u16>(&buf[..5]) {
+        Err(ErrorKind::UnexpectedEof { .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Running out of room leaves the buffer as it was.
+    let mut small: ArrayVec<[u8; 8]> = ArrayVec::new();
+    small.push(0xEE);
+    match encode_versioned(1, &0u64, &mut small) {
+        Err(ErrorKind::BufferTooSmall { needed: 12, available: 7, written: 4 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    small.extend([0; 5].iter().cloned());
+    match encode_versioned(1, &0u8, &mut small) {
+        Err(ErrorKind::BufferTooSmall { needed: 5, available: 2, written: 0 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(&small[..], &[0xEE, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn magic_frames() {
+    use bincode::{frame, DefaultOptions, OptionsExt};
+
+    const MAGIC: [u8; 4] = [0xB1, 0x0C, 0xC0, 0xDE];
+
+    let mut buf = Buffer::new();
+    assert_eq!(frame::encode_with_magic(MAGIC, &(1u8, 2u16), &mut buf).unwrap(), 11);
+    assert_eq!(&buf[..], &[0xB1, 0x0C, 0xC0, 0xDE, 3, 0, 0, 0, 1, 2, 0]);
+    let mut big = Buffer::new();
+    frame::encode_with_magic_and_config(MAGIC, &(1u8, 2u16), &mut big, config().big_endian()).unwrap();
+    assert_eq!(&big[..], &[0xB1, 0x0C, 0xC0, 0xDE, 0, 0, 0, 3, 1, 0, 2]);
+    let (value, rest): ((u8, u16), _) = frame::decode_with_magic_and_config(MAGIC, &big, config().big_endian()).unwrap();
+    assert_eq!((value, rest.len()), ((1, 2), 0));
+    let mut typed = Buffer::new();
+    let options = || DefaultOptions::new().with_big_endian();
+    frame::encode_with_magic_and_options(MAGIC, &(1u8, 2u16), &mut typed, options()).unwrap();
+    assert_eq!(&typed[..], &big[..]);
+    let (value, rest): ((u8, u16), _) = frame::decode_with_magic_and_options(MAGIC, &typed, options()).unwrap();
+    assert_eq!((value, rest.len()), ((1, 2), 0));
+
+    // Garbage, including a stray magic number with a bogus frame behind it,
+    // then two good frames.
+    let mut stream = Buffer::new();
+    stream.extend([0x00, 0xB1, 0x0C, 0xFF, 0x13].iter().cloned());
+    stream.extend(MAGIC.iter().cloned());
+    stream.extend([200, 0, 0, 0, 0xAA].iter().cloned());
+    frame::encode_with_magic(MAGIC, &"first", &mut stream).unwrap();
+    frame::encode_with_magic(MAGIC, &"second", &mut stream).unwrap();
+
+    match frame::decode_with_magic::<&str>(MAGIC, &stream) {
+        Err(ErrorKind::MagicMismatch { expected, found: [0x00, 0xB1, 0x0C, 0xFF] }) => assert_eq!(expected, MAGIC),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let mut decoded = Vec::new();
+    let mut rest = &stream[..];
+    while let Some(start) = frame::scan_for_magic(MAGIC, rest) {
+        match frame::decode_with_magic::<&str>(MAGIC, &rest[start..]) {
+            Ok((value, after)) => {
+                decoded.push(value);
+                rest = after;
+            }
+            Err(_) => rest = &rest[start + 1..],
+        }
+    }
+    assert_eq!(decoded, ["first", "second"]);
+    assert!(rest.is_empty());
+
+    // Only whole magic numbers are found.
+    assert_eq!(frame::scan_for_magic(MAGIC, &MAGIC[..3]), None);
+    assert_eq!(frame::scan_for_magic(MAGIC, &[]), None);
+    assert_eq!(frame::scan_for_magic(MAGIC, &[0xB1, 0xB1, 0x0C, 0xC0, 0xDE]), Some(1));
+
+    // A cut-off magic number is an ordinary end of input.
+    match frame::decode_with_magic::<u8>(MAGIC, &MAGIC[..1]) {
+        Err(ErrorKind::UnexpectedEof { remaining: 1, needed: Some(3), .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Running out of room leaves the buffer as it was.
+    let mut small: ArrayVec<[u8; 10]> = ArrayVec::new();
+    small.push(0xEE);
+    match frame::encode_with_magic(MAGIC, &0u64, &mut small) {
+        Err(ErrorKind::BufferTooSmall { needed: 16, available: 9, written: 8 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    small.extend([0; 6].iter().cloned());
+    match frame::encode_with_magic(MAGIC, &0u64, &mut small) {
+        Err(ErrorKind::BufferTooSmall { needed: 16, available: 3, written: 0 }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(small.len(), 7);
+}
+
+#[test]
+fn error_codes() {
+    use bincode::arrayvec::CapacityError;
+    use std::collections::BTreeSet;
+
+    // The documented codes. Spelled out without a catch-all, so a new kind of
+    // error doesn't compile until it has one.
+    fn documented(e: &ErrorKind) -> u8 {
+        match e {
+            #[cfg(feature = "std")]
+            ErrorKind::Io(_) => 1,
+            #[cfg(feature = "embedded-io")]
+            ErrorKind::EmbeddedIo(_) => 2,
+            ErrorKind::Fmt(_) => 3,
+            ErrorKind::BufferTooSmall { .. } => 4,
+            ErrorKind::InvalidUtf8Encoding(e) if e.error_len().is_some() => 5,
+            ErrorKind::InvalidUtf8Encoding(_) => 6,
+            ErrorKind::InvalidBoolEncoding(_) => 7,
+            ErrorKind::InvalidCharEncoding => 8,
+            ErrorKind::InvalidTagEncoding(_) => 9,
+            ErrorKind::DeserializeAnyNotSupported => 10,
+            ErrorKind::SizeLimit(Direction::Serialize) => 11,
+            ErrorKind::SizeLimit(Direction::Deserialize) => 12,
+            ErrorKind::UnexpectedEof { .. } => 13,
+            ErrorKind::SeamTooLong { .. } => 14,
+            ErrorKind::ScratchTooSmall { .. } => 15,
+            ErrorKind::MissingNulTerminator { .. } => 16,
+            ErrorKind::SequenceMustHaveLength => 17,
+            ErrorKind::U128Unsupported => 18,
+            ErrorKind::CapacityError(_) => 19,
+            ErrorKind::Serde => 20,
+            ErrorKind::LengthOverflow { .. } => 21,
+            ErrorKind::TrailingBytes(_) => 22,
+            ErrorKind::DirtyPadding { .. } => 23,
+            ErrorKind::AmbiguousEncoding => 24,
+            ErrorKind::FlushFailed => 25,
+            ErrorKind::InvalidCobsEncoding => 26,
+            ErrorKind::InvalidFrameLength(_) => 27,
+            ErrorKind::MagicMismatch { .. } => 28,
+            ErrorKind::PatchLengthMismatch { .. } => 29,
+            ErrorKind::ChecksumMismatch { .. } => 30,
+            ErrorKind::InvalidVarintEncoding => 31,
+            ErrorKind::FillOverrun { .. } => 32,
+            ErrorKind::ForeignCheckpoint => 33,
+            ErrorKind::LookAheadUnsupported => 34,
+            ErrorKind::EqualOptionTags => 35,
+            ErrorKind::NoCandidates => 36,
+        }
+    }
+
+    fn utf8_error(bytes: &[u8]) -> std::str::Utf8Error {
+        std::str::from_utf8(bytes).unwrap_err()
+    }
+
+    // Only the features that are on add more.
+    #[allow(unused_mut)]
+    let mut samples = vec![
+        ErrorKind::Fmt(std::fmt::Error),
+        ErrorKind::BufferTooSmall { needed: 9, available: 8, written: 3 },
+        ErrorKind::InvalidUtf8Encoding(utf8_error(&[b'a', 0xFF])),
+        ErrorKind::InvalidUtf8Encoding(utf8_error(&[b'a', 0xE2, 0x82])),
+        ErrorKind::InvalidBoolEncoding(2),
+        ErrorKind::InvalidCharEncoding,
+        ErrorKind::InvalidTagEncoding(7),
+        ErrorKind::DeserializeAnyNotSupported,
+        ErrorKind::SizeLimit(Direction::Serialize),
+        ErrorKind::SizeLimit(Direction::Deserialize),
+        ErrorKind::UnexpectedEof { remaining: 1, budget: None, needed: Some(3) },
+        ErrorKind::SeamTooLong { len: 10, max: 4 },
+        ErrorKind::ScratchTooSmall { len: 10, max: 4 },
+        ErrorKind::MissingNulTerminator { max: 8 },
+        ErrorKind::SequenceMustHaveLength,
+        ErrorKind::U128Unsupported,
+        ErrorKind::CapacityError(CapacityError::new(0)),
+        ErrorKind::Serde,
+        ErrorKind::LengthOverflow { len: 70_000, max: 65_535 },
+        ErrorKind::TrailingBytes(2),
+        ErrorKind::DirtyPadding { offset: 3, found: 1 },
+        ErrorKind::AmbiguousEncoding,
+        ErrorKind::FlushFailed,
+        ErrorKind::InvalidCobsEncoding,
+        ErrorKind::InvalidFrameLength(12),
+        ErrorKind::MagicMismatch { expected: *b"BNC1", found: [0; 4] },
+        ErrorKind::PatchLengthMismatch { region: 1, encoded: 5 },
+        ErrorKind::ChecksumMismatch { expected: 1, actual: 2 },
+        ErrorKind::InvalidVarintEncoding,
+        ErrorKind::FillOverrun { written: 9, room: 8 },
+        ErrorKind::ForeignCheckpoint,
+        ErrorKind::LookAheadUnsupported,
+        ErrorKind::EqualOptionTags,
+        ErrorKind::NoCandidates,
+    ];
+    #[cfg(feature = "std")]
+    samples.push(ErrorKind::Io(std::io::Error::new(std::io::ErrorKind::Other, "gone")));
+    #[cfg(feature = "embedded-io")]
+    samples.push(ErrorKind::EmbeddedIo(bincode::embedded_io::ErrorKind::Other));
+
+    // Every code is used by exactly one sample, and matches the table.
+    let mut codes = BTreeSet::new();
+    for sample in &samples {
+        assert_eq!(sample.code(), documented(sample), "{:?}", sample);
+        assert!(codes.insert(sample.code()), "{:?}", sample);
+    }
+    let mut expected: BTreeSet<u8> = (3..=36).collect();
+    if cfg!(feature = "std") {
+        expected.insert(1);
+    }
+    if cfg!(feature = "embedded-io") {
+        expected.insert(2);
+    }
+    assert_eq!(codes, expected);
+
+    // Errors that are nothing but their kind come back from their code.
+    let mut rebuilt = 0;
+    for code in 0..=255u8 {
+        if let Some(e) = ErrorKind::from_code(code) {
+            assert_eq!(e.code(), code);
+            rebuilt += 1;
+        }
+    }
+    assert_eq!(rebuilt, 16);
+    assert!(ErrorKind::from_code(0).is_none());
+    assert!(ErrorKind::from_code(13).is_none());
+    match ErrorKind::from_code(12) {
+        Some(ErrorKind::SizeLimit(Direction::Deserialize)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Codes of errors from actual (de)serialization.
+    assert_eq!(deserialize::<u32>(&[1, 2]).unwrap_err().code(), 13);
+    assert_eq!(deserialize::<bool>(&[2]).unwrap_err().code(), 7);
+    assert_eq!(deserialize::<&str>(&[1, 0, 0, 0, 0, 0, 0, 0, 0xFF]).unwrap_err().code(), 5);
+}
+
+#[test]
+fn varint_lengths() {
+    use bincode::LengthEncoding;
+    use std::fmt;
+
+    // Claims a length without having the elements, to reach lengths that
+    // don't fit into memory.
+    struct Claimed(usize);
+
+    impl serde::Serialize for Claimed {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            serde::ser::SerializeSeq::end(serializer.serialize_seq(Some(self.0))?)
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for Claimed {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Claimed, D::Error> {
+            struct LenVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for LenVisitor {
+                type Value = Claimed;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("a sequence")
+                }
+
+                fn visit_seq<A: serde::de::SeqAccess<'de>>(self, seq: A) -> std::result::Result<Claimed, A::Error> {
+                    Ok(Claimed(seq.size_hint().unwrap()))
+                }
+            }
+
+            deserializer.deserialize_seq(LenVisitor)
+        }
+    }
+
+    let mut varint = config();
+    varint.length_encoding(LengthEncoding::Varint);
+    let fixtures: &[(u64, &[u8])] = &[
+        (0, &[0x00]),
+        (127, &[0x7F]),
+        (128, &[0x80, 0x01]),
+        (300, &[0xAC, 0x02]),
+        (1 << 32, &[0x80, 0x80, 0x80, 0x80, 0x10]),
+        (1 << 60, &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x10]),
+        (u64::max_value(), &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]),
+    ];
+    for &(len, bytes) in fixtures {
+        let len = len as usize;
+        for config in &[varint, *varint.clone().big_endian().align(true)] {
+            let mut buf = Buffer::new();
+            config.serialize_into(&mut buf, &Claimed(len)).unwrap();
+            assert_eq!(&buf[..], bytes, "{}", len);
+            assert_eq!(config.serialized_size(&Claimed(len)).unwrap(), bytes.len() as u64);
+            assert_eq!(config.deserialize::<Claimed>(bytes).unwrap().0, len);
+        }
+    }
+
+    // Strings, bytes, sequences and maps all take a varint.
+    struct Collected<'a>(&'a str);
+
+    impl<'a> serde::Serialize for Collected<'a> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            serializer.collect_str(&format_args!("{}{}", self.0, self.0))
+        }
+    }
+
+    let long = "x".repeat(100);
+    let mut map = BTreeMap::new();
+    map.insert(1u8, vec![2u16; 128]);
+    let value = ("ab", serde_bytes::Bytes::new(&[7; 127]), map, long.clone());
+    let mut buf = Buffer::new();
+    varint.serialize_into(&mut buf, &value).unwrap();
+    assert_eq!(&buf[..3], &[2, b'a', b'b']);
+    assert_eq!(buf[3], 127);
+    assert_eq!(&buf[131..135], &[1, 1, 0x80, 0x01]);
+    assert_eq!(&buf[391..393], &[100, b'x']);
+    assert_eq!(buf.len(), 492);
+    assert_eq!(varint.serialized_size(&value).unwrap(), 492);
+    let decoded: (String, serde_bytes::ByteBuf, BTreeMap<u8, Vec<u16>>, String) = varint.deserialize(&buf).unwrap();
+    assert_eq!((&decoded.0[..], &decoded.1[..], &decoded.2, &decoded.3), (value.0, &value.1[..], &value.2, &long));
+
+    let mut buf = Buffer::new();
+    varint.serialize_into(&mut buf, &Collected(&long)).unwrap();
+    assert_eq!(&buf[..3], &[0xC8, 0x01, b'x']);
+    assert_eq!(varint.serialized_size(&Collected(&long)).unwrap(), 202);
+    assert_eq!(varint.deserialize::<String>(&buf).unwrap(), "x".repeat(200));
+
+    // Lengths are never padded.
+    let mut buf = Buffer::new();
+    varint.clone().align(true).serialize_into(&mut buf, &(1u8, "abc", 3u16)).unwrap();
+    assert_eq!(&buf[..], &[1, 3, b'a', b'b', b'c', 0, 3, 0]);
+
+    // Only the shortest form of a length is accepted.
+    for bytes in &[&[0x80, 0x00][..], &[0xFF, 0x80, 0x00], &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00]] {
+        match varint.deserialize::<Claimed>(bytes) {
+            Err(ErrorKind::InvalidVarintEncoding) => {}
+            other => panic!("{:?}: unexpected {:?}", bytes, other.map(|c| c.0)),
+        }
+    }
+    // Nor can it run past ten bytes, or past 64 bits.
+    for bytes in &[&[0xFF; 11][..], &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x02]] {
+        match varint.deserialize::<Claimed>(bytes) {
+            Err(ErrorKind::InvalidVarintEncoding) => {}
+            other => panic!("{:?}: unexpected {:?}", bytes, other.map(|c| c.0)),
+        }
+    }
+    match varint.deserialize::<&str>(&[0x80, 0x80]) {
+        Err(ErrorKind::UnexpectedEof { remaining: 0, .. }) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // There is no telling how much room a length of unknown size will need.
+    struct Unsized;
+
+    impl serde::Serialize for Unsized {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            serde::ser::SerializeSeq::end(serializer.serialize_seq(None)?)
+        }
+    }
+
+    let mut buf = Buffer::new();
+    match varint.serialize_into(&mut buf, &Unsized) {
+        Err(ErrorKind::SequenceMustHaveLength) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    match varint.serialized_size(&Unsized) {
+        Err(ErrorKind::SequenceMustHaveLength) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}